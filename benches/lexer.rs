@@ -0,0 +1,85 @@
+// lexing time should grow linearly with the size of input,
+// so the 10k lines source takes about ten times as long as the 1k lines one,
+// and likewise for a single literal holding that many lines or words
+//
+// run with `cargo bench --bench lexer`
+
+#![feature(test)]
+
+extern crate test;
+extern crate ruby_on_rust;
+
+use test::Bencher;
+
+use ruby_on_rust::lexer::tokenize;
+
+const LINES: [&'static str; 3] = [
+    "foo = [1, 2.5, :bar, baz] # comment\n",
+    "foo.bar + 1 if baz\n",
+    "\"qux #{foo}\"\n",
+];
+
+fn source(lines: usize) -> String {
+    LINES.iter().cycle().take(lines).map(|line| *line).collect()
+}
+
+// a heredoc with the given number of lines in its body
+fn heredoc_source(lines: usize) -> String {
+    let body: String = LINES.iter().cycle().take(lines).map(|line| *line).collect();
+    format!("foo = <<~SQL\n{}SQL\n", body)
+}
+
+// a %w[] with the given number of words
+fn words_source(words: usize) -> String {
+    let words: Vec<String> = (0..words).map(|i| format!("w{}", i)).collect();
+    format!("foo = %w[{}]\n", words.join(" "))
+}
+
+fn lex(source: &str) -> usize {
+    let (tokens, diagnostics) = tokenize(String::from(source));
+    assert!(diagnostics.is_empty());
+
+    tokens.len()
+}
+
+#[bench]
+fn lex_1k_lines(b: &mut Bencher) {
+    let source = source(1_000);
+    b.bytes = source.len() as u64;
+    b.iter(|| lex(&source));
+}
+
+#[bench]
+fn lex_10k_lines(b: &mut Bencher) {
+    let source = source(10_000);
+    b.bytes = source.len() as u64;
+    b.iter(|| lex(&source));
+}
+
+#[bench]
+fn lex_1k_lines_heredoc(b: &mut Bencher) {
+    let source = heredoc_source(1_000);
+    b.bytes = source.len() as u64;
+    b.iter(|| lex(&source));
+}
+
+#[bench]
+fn lex_10k_lines_heredoc(b: &mut Bencher) {
+    let source = heredoc_source(10_000);
+    b.bytes = source.len() as u64;
+    b.iter(|| lex(&source));
+}
+
+#[bench]
+fn lex_1k_words(b: &mut Bencher) {
+    let source = words_source(1_000);
+    b.bytes = source.len() as u64;
+    b.iter(|| lex(&source));
+}
+
+#[bench]
+fn lex_10k_words(b: &mut Bencher) {
+    let source = words_source(10_000);
+    b.bytes = source.len() as u64;
+    b.iter(|| lex(&source));
+}
@@ -0,0 +1,166 @@
+// NOTE
+// whitequark's nodes carry their own source maps, ours don't (yet),
+// so the parser builds a tree of these along with the nodes, with the `expression` range of every node,
+// see Parser::locate
+
+use source::range::Range;
+use ast::node::Node;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocatedNode {
+    pub node: Node,
+    pub expression: Range,
+    // located children, in source order,
+    // children which can't be located are left out, like those without a location in whitequark
+    pub children: Vec<LocatedNode>,
+}
+
+// child nodes, in source order,
+// except for the modifier forms like `bar if foo`, whose condition comes first, see Parser::locate_built
+pub fn children(node: &Node) -> Vec<&Node> {
+    match *node {
+        Node::Nodes(ref nodes) |
+        Node::DStr(ref nodes) |
+        Node::XStr(ref nodes) |
+        Node::Array(ref nodes) |
+        Node::Hash(ref nodes) |
+        Node::Begin(ref nodes) |
+        Node::KwBegin(ref nodes) |
+        Node::Args(ref nodes) |
+        Node::Mlhs(ref nodes) |
+        Node::LVasgn(_, ref nodes) => nodes.iter().collect(),
+
+        Node::OptArg(_, ref value) |
+        Node::KwOptArg(_, ref value) => vec![value],
+
+        Node::Complex(ref value) |
+        Node::Kwsplat(ref value) |
+        Node::BlockPass(ref value) |
+        Node::Procarg0(ref value) |
+        Node::MatchCurrentLine(ref value) => vec![value],
+        Node::Splat(ref value) => value.iter().map(|value| &**value).collect(),
+
+        Node::Regexp(ref parts, ref options) => {
+            let mut children: Vec<&Node> = parts.iter().collect();
+            children.push(options);
+            children
+        },
+
+        Node::Pair { ref key, ref value } => vec![key, value],
+        Node::IRange(ref lhs, ref rhs) |
+        Node::ERange(ref lhs, ref rhs) => lhs.iter().chain(rhs.iter()).map(|value| &**value).collect(),
+        Node::Assign(ref lhs, _, ref rhs) => vec![lhs, rhs],
+
+        Node::Const { ref scope, .. } => scope.iter().map(|scope| &**scope).collect(),
+
+        Node::Send { ref receiver, ref args, .. } => {
+            let mut children: Vec<&Node> = receiver.iter().map(|receiver| &**receiver).collect();
+            children.extend(args.iter());
+            children
+        },
+        Node::CSend { ref receiver, ref args, .. } => {
+            let mut children: Vec<&Node> = vec![receiver];
+            children.extend(args.iter());
+            children
+        },
+
+        Node::Block { ref call, ref args, ref body } => {
+            let mut children: Vec<&Node> = vec![call, args];
+            children.extend(body.iter().map(|body| &**body));
+            children
+        },
+
+        Node::NumBlock { ref call, ref body, .. } => {
+            let mut children: Vec<&Node> = vec![call];
+            children.extend(body.iter().map(|body| &**body));
+            children
+        },
+
+        Node::If { ref cond, ref if_true, ref if_false } => {
+            let mut children: Vec<&Node> = vec![cond];
+            children.extend(if_true.iter().map(|if_true| &**if_true));
+            children.extend(if_false.iter().map(|if_false| &**if_false));
+            children
+        },
+        Node::While { ref cond, ref body } |
+        Node::Until { ref cond, ref body } => {
+            let mut children: Vec<&Node> = vec![cond];
+            children.extend(body.iter().map(|body| &**body));
+            children
+        },
+        Node::WhilePost { ref cond, ref body } |
+        Node::UntilPost { ref cond, ref body } => vec![cond, body],
+        Node::For { ref iterator, ref iteratee, ref body } => {
+            let mut children: Vec<&Node> = vec![iterator, iteratee];
+            children.extend(body.iter().map(|body| &**body));
+            children
+        },
+
+        Node::Case { ref expr, ref when_bodies, ref else_body } => {
+            let mut children: Vec<&Node> = expr.iter().map(|expr| &**expr).collect();
+            children.extend(when_bodies.iter());
+            children.extend(else_body.iter().map(|else_body| &**else_body));
+            children
+        },
+        Node::When { ref patterns, ref body } => {
+            let mut children: Vec<&Node> = patterns.iter().collect();
+            children.extend(body.iter().map(|body| &**body));
+            children
+        },
+
+        Node::CaseMatch { ref expr, ref in_bodies, ref else_body } => {
+            let mut children: Vec<&Node> = vec![expr];
+            children.extend(in_bodies.iter());
+            children.extend(else_body.iter().map(|else_body| &**else_body));
+            children
+        },
+        Node::InPattern { ref pattern, ref guard, ref body } => {
+            let mut children: Vec<&Node> = vec![pattern];
+            children.extend(guard.iter().map(|guard| &**guard));
+            children.extend(body.iter().map(|body| &**body));
+            children
+        },
+        Node::IfGuard(ref value) |
+        Node::UnlessGuard(ref value) |
+        Node::MatchWithTrailingComma(ref value) |
+        Node::Pin(ref value) => vec![value],
+        Node::MatchRest(ref value) => value.iter().map(|value| &**value).collect(),
+        Node::MatchAs { ref value, ref as_ } => vec![value, as_],
+        Node::MatchAlt(ref left, ref right) => vec![left, right],
+        Node::ArrayPattern(ref nodes) |
+        Node::ArrayPatternWithTail(ref nodes) |
+        Node::FindPattern(ref nodes) |
+        Node::HashPattern(ref nodes) => nodes.iter().collect(),
+        Node::ConstPattern { ref const_, ref pattern } => vec![const_, pattern],
+
+        Node::Class { ref name, ref superclass, ref body } => {
+            let mut children: Vec<&Node> = vec![name];
+            children.extend(superclass.iter().map(|superclass| &**superclass));
+            children.extend(body.iter().map(|body| &**body));
+            children
+        },
+        Node::SClass { ref expr, ref body } => {
+            let mut children: Vec<&Node> = vec![expr];
+            children.extend(body.iter().map(|body| &**body));
+            children
+        },
+        Node::Module { ref name, ref body } => {
+            let mut children: Vec<&Node> = vec![name];
+            children.extend(body.iter().map(|body| &**body));
+            children
+        },
+
+        Node::Def { ref args, ref body, .. } => {
+            let mut children: Vec<&Node> = vec![args];
+            children.extend(body.iter().map(|body| &**body));
+            children
+        },
+        Node::Defs { ref definee, ref args, ref body, .. } => {
+            let mut children: Vec<&Node> = vec![definee, args];
+            children.extend(body.iter().map(|body| &**body));
+            children
+        },
+
+        _ => vec![],
+    }
+}
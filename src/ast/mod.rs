@@ -1 +1,3 @@
 pub mod node;
+pub mod located;
+mod regexp;
@@ -1,6 +1,13 @@
 // https://raw.githubusercontent.com/whitequark/parser/2a73841d6da04a5ab9bd270561165fd766722d43/lib/parser/builders/default.rb
 
+use num_rational::Ratio;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
 use parser::token::Token;
+use lexer::dedenter::Dedenter;
+use shared::static_env::StaticEnv;
+use ast::regexp;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Node {
@@ -17,20 +24,45 @@ pub enum Node {
     False,
 
     Int(isize),
+    // integer which doesn't fit in isize
+    BigInt(BigInt),
+    Float(f64),
+    Rational(Ratio<isize>),
+    // rational whose numerator or denominator doesn't fit in isize
+    BigRational(Ratio<BigInt>),
+    // Complex(0, value), value being one of Int, BigInt, Float, Rational and BigRational
+    Complex(Box<Node>),
 
     Str(String),
     DStr(Vec<Node>),
+    XStr(Vec<Node>),
 
     Sym(String),
 
+    // parts, RegOpt
+    Regexp(Vec<Node>, Box<Node>),
+    RegOpt(Vec<char>),
+
     Array(Vec<Node>),
+    // NOTE the splat of a bare `*` is None
+    Splat(Option<Box<Node>>),
 
     Pair { key: Box<Node>, value: Box<Node> },
+    Kwsplat(Box<Node>),
     Hash(Vec<Node>), // TODO Hash(Vec<Node::Pair>) after enum variants become types
 
+    // NOTE the missing end of an endless or beginless range is None, where whitequark has nil
+    IRange(Option<Box<Node>>, Option<Box<Node>>),
+    ERange(Option<Box<Node>>, Option<Box<Node>>),
+
     NSelf,
     LVar(String),
 
+    // `::` of `::Foo`
+    CBase,
+    // NOTE the scope of an unscoped constant is None, where whitequark has nil
+    Const { scope: Option<Box<Node>>, name: String },
+
     Ident(String),
     Assign(Box<Node>, Token, Box<Node>), // TODO a dummy NodeType for builder.assign
 
@@ -38,6 +70,89 @@ pub enum Node {
     LVasgn(String, Vec<Node>),
 
     Begin(Vec<Node>),
+    KwBegin(Vec<Node>),
+
+    // NOTE the receiver of a call without one is None, where whitequark has nil
+    Send { receiver: Option<Box<Node>>, selector: String, args: Vec<Node> },
+    CSend { receiver: Box<Node>, selector: String, args: Vec<Node> },
+    BlockPass(Box<Node>),
+    // NOTE the call is Send, CSend or Lambda, the args are Args
+    Block { call: Box<Node>, args: Box<Node>, body: Option<Box<Node>> },
+    // `->` of a stabby lambda, the call of its Block
+    Lambda,
+    // a block using numbered parameters, with the largest one used, `foo { _1 + _2 }` being 2
+    NumBlock { call: Box<Node>, numargs: usize, body: Option<Box<Node>> },
+    // the args of such a block, made into NumBlock by block
+    Numargs(usize),
+
+    // NOTE a missing branch or body is None, where whitequark has nil
+    If { cond: Box<Node>, if_true: Option<Box<Node>>, if_false: Option<Box<Node>> },
+    // a regexp literal as a condition
+    MatchCurrentLine(Box<Node>),
+    While { cond: Box<Node>, body: Option<Box<Node>> },
+    Until { cond: Box<Node>, body: Option<Box<Node>> },
+    // `begin ... end while cond`, the body is KwBegin
+    WhilePost { cond: Box<Node>, body: Box<Node> },
+    UntilPost { cond: Box<Node>, body: Box<Node> },
+    For { iterator: Box<Node>, iteratee: Box<Node>, body: Option<Box<Node>> },
+
+    // NOTE the expr of `case` alone is None, the whens are When
+    Case { expr: Option<Box<Node>>, when_bodies: Vec<Node>, else_body: Option<Box<Node>> },
+    When { patterns: Vec<Node>, body: Option<Box<Node>> },
+
+    // pattern matching, from a later whitequark/parser, the in_bodies are InPattern
+    CaseMatch { expr: Box<Node>, in_bodies: Vec<Node>, else_body: Option<Box<Node>> },
+    // the guard is IfGuard or UnlessGuard
+    InPattern { pattern: Box<Node>, guard: Option<Box<Node>>, body: Option<Box<Node>> },
+    IfGuard(Box<Node>),
+    UnlessGuard(Box<Node>),
+    // `else` of case_match without a body, where a missing else is None
+    EmptyElse,
+    MatchVar(String),
+    // NOTE the MatchVar of a bare `*` or `**` is None
+    MatchRest(Option<Box<Node>>),
+    MatchAs { value: Box<Node>, as_: Box<Node> },
+    MatchAlt(Box<Node>, Box<Node>),
+    // `**nil`
+    MatchNilPattern,
+    // an element followed by a comma, made into ArrayPatternWithTail by array_pattern
+    MatchWithTrailingComma(Box<Node>),
+    ArrayPattern(Vec<Node>),
+    // an array pattern ending with a comma, like `in a,`
+    ArrayPatternWithTail(Vec<Node>),
+    // `[*, x, *]`
+    FindPattern(Vec<Node>),
+    HashPattern(Vec<Node>),
+    // the pattern is ArrayPattern or HashPattern
+    ConstPattern { const_: Box<Node>, pattern: Box<Node> },
+    Pin(Box<Node>),
+
+    Class { name: Box<Node>, superclass: Option<Box<Node>>, body: Option<Box<Node>> },
+    SClass { expr: Box<Node>, body: Option<Box<Node>> },
+    Module { name: Box<Node>, body: Option<Box<Node>> },
+
+    // NOTE a missing body is None, where whitequark has nil
+    Def { name: String, args: Box<Node>, body: Option<Box<Node>> },
+    Defs { definee: Box<Node>, name: String, args: Box<Node>, body: Option<Box<Node>> },
+
+    // formal arguments
+    Args(Vec<Node>),
+    Arg(String),
+    OptArg(String, Box<Node>),
+    RestArg(Option<String>),
+    KwArg(String),
+    KwOptArg(String, Box<Node>),
+    KwRestArg(Option<String>),
+    BlockArg(String),
+    ShadowArg(String),
+    // `...` of `def foo(...)`
+    ForwardArg,
+    // `...` of `bar(...)`
+    ForwardedArgs,
+    // NOTE the Arg or Mlhs made into it, whitequark keeps the children of either
+    Procarg0(Box<Node>),
+
+    Mlhs(Vec<Node>),
 }
 
 
@@ -58,20 +173,79 @@ pub enum Node {
 //         operator_loc,
 //         operator_loc.join(numeric.loc.expression)))
 // end
-// TODO INCOMPLETE
-// DUMMY ALWAYS Node::Int
 pub fn unary_num(t_unary: Token, n_simple_numeric: Node) -> Node {
-    let mut numeric_value = if let Node::Int(int_value) = n_simple_numeric { int_value } else { panic!(); };
-
     if let Token::T_UNARY_NUM(polarty) = t_unary {
         match polarty.as_ref() {
-            "+" => (),
-            "-" => { numeric_value = 0 - numeric_value; },
+            "+" => n_simple_numeric,
+            "-" => negate_numeric(n_simple_numeric),
             _ => { panic!(); }
         }
     } else { panic!(); }
+}
 
-    return Node::Int(numeric_value);
+fn negate_numeric(n_numeric: Node) -> Node {
+    match n_numeric {
+        Node::Int(int_value) => match int_value.checked_neg() {
+            Some(int_value) => Node::Int(int_value),
+            None => Node::BigInt(-BigInt::from(int_value))
+        },
+        // -9223372036854775808 fits in isize again
+        Node::BigInt(big_int_value) => {
+            let big_int_value = -big_int_value;
+            match big_int_value.to_isize() {
+                Some(int_value) => Node::Int(int_value),
+                None => Node::BigInt(big_int_value)
+            }
+        },
+        Node::Float(float_value) => Node::Float(-float_value),
+        Node::Rational(rational_value) => Node::Rational(-rational_value),
+        // like BigInt, -9223372036854775808r fits again
+        Node::BigRational(big_rational_value) => {
+            let big_rational_value = -big_rational_value;
+            match (big_rational_value.numer().to_isize(), big_rational_value.denom().to_isize()) {
+                (Some(numer), Some(denom)) => Node::Rational(Ratio::new_raw(numer, denom)),
+                _ => Node::BigRational(big_rational_value)
+            }
+        },
+        Node::Complex(n_imaginary) => Node::Complex(box negate_numeric(*n_imaginary)),
+        _ => { panic!("can't negate non-numeric node"); }
+    }
+}
+
+// # Numerics
+
+// def integer(integer_t)
+//   numeric(:int, integer_t)
+// end
+
+// def float(float_t)
+//   numeric(:float, float_t)
+// end
+
+// def rational(rational_t)
+//   numeric(:rational, rational_t)
+// end
+
+// def complex(complex_t)
+//   numeric(:complex, complex_t)
+// end
+
+// def numeric(kind, token)
+//   n(kind, [ value(token) ],
+//     Source::Map::Operator.new(nil, loc(token)))
+// end
+// 
+// NOTE integer, float, rational and complex are merged into one
+pub fn numeric(t_numeric: Token) -> Node {
+    match t_numeric {
+        Token::T_INTEGER(int_value) => Node::Int(int_value),
+        Token::T_BIG_INTEGER(big_int_value) => Node::BigInt(big_int_value),
+        Token::T_FLOAT(float_value) => Node::Float(float_value),
+        Token::T_RATIONAL(rational_value) => Node::Rational(rational_value),
+        Token::T_BIG_RATIONAL(big_rational_value) => Node::BigRational(big_rational_value),
+        Token::T_IMAGINARY(t_imaginary) => Node::Complex(box numeric(*t_imaginary)),
+        _ => { panic!("can't build numeric node from token {:?}", t_numeric); }
+    }
 }
 
 // # Strings
@@ -99,25 +273,27 @@ pub fn unary_num(t_unary: Token, n_simple_numeric: Node) -> Node {
 //       string_map(begin_t, parts, end_t))
 //   end
 // end
-// TODO INCOMPLETE
+// NOTE begin_t and end_t only matter for the source map, which isn't ported yet
 pub fn string_compose(parts: Node) -> Node {
-    if is_collapse_string_parts(&parts) {}
+    let parts = extract_parts(parts);
 
-    // TODO DUMMY
-    if let Node::Str(string_value) = parts { return Node::Str(string_value); }
-    if let Node::Nodes(str_nodes) = parts {
-        if let Node::Str(str_value) = (*str_nodes.get(0).unwrap()).clone() {
-            return Node::Str(str_value);
-        }
+    if is_collapse_string_parts(&parts) {
+        parts.into_iter().next().unwrap()
+    } else {
+        Node::DStr(parts)
     }
-
-    panic!("string_compose UNIMPL");
 }
 
 // def character(char_t)
 //   n(:str, [ string_value(char_t) ],
 //     prefix_string_map(char_t))
 // end
+pub fn character(t_character: Token) -> Node {
+    match t_character {
+        Token::T_CHARACTER(value) => Node::Str(value),
+        _ => { panic!("can't build character node from token {:?}", t_character); }
+    }
+}
 
 // def __FILE__(__FILE__t)
 //   n0(:__FILE__,
@@ -156,6 +332,9 @@ pub fn string_compose(parts: Node) -> Node {
 //   n(:xstr, [ *parts ],
 //     string_map(begin_t, parts, end_t))
 // end
+pub fn xstring_compose(parts: Node) -> Node {
+    Node::XStr(extract_parts(parts))
+}
 
 // # Indented (interpolated, noninterpolated, executable) strings
 
@@ -180,6 +359,30 @@ pub fn string_compose(parts: Node) -> Node {
 
 //   node
 // end
+pub fn dedent_string(node: Node, dedent_level: Option<usize>) -> Node {
+    let dedent_level = match dedent_level {
+        Some(dedent_level) => dedent_level,
+        None => { return node; }
+    };
+
+    let mut dedenter = Dedenter::new(dedent_level);
+
+    match node {
+        Node::Str(string_value) => Node::Str(dedenter.dedent(&string_value)),
+        Node::DStr(parts) => Node::DStr(dedent_parts(&mut dedenter, parts)),
+        Node::XStr(parts) => Node::XStr(dedent_parts(&mut dedenter, parts)),
+        _ => node
+    }
+}
+
+fn dedent_parts(dedenter: &mut Dedenter, parts: Vec<Node>) -> Vec<Node> {
+    parts.into_iter().map(|part| {
+        match part {
+            Node::Str(string_value) => Node::Str(dedenter.dedent(&string_value)),
+            _ => { dedenter.interrupt(); part }
+        }
+    }).collect()
+}
 
 // # Regular expressions
 
@@ -191,6 +394,15 @@ pub fn string_compose(parts: Node) -> Node {
 //   n(:regopt, options,
 //     token_map(regopt_t))
 // end
+pub fn regexp_options(t_regopt: Token) -> Node {
+    if let Token::T_REGEXP_OPT(options) = t_regopt {
+        let mut options: Vec<char> = options.chars().collect();
+        options.sort();
+        options.dedup();
+
+        Node::RegOpt(options)
+    } else { panic!("can't build regopt node from token {:?}", t_regopt); }
+}
 
 // def regexp_compose(begin_t, parts, end_t, options)
 //   begin
@@ -203,6 +415,11 @@ pub fn string_compose(parts: Node) -> Node {
 //   n(:regexp, (parts << options),
 //     regexp_map(begin_t, end_t, options))
 // end
+//
+// NOTE the builder doesn't report diagnostics, the parser checks the result with static_regexp_node
+pub fn regexp_compose(parts: Node, options: Node) -> Node {
+    Node::Regexp(extract_parts(parts), box options)
+}
 
 // # Arrays
 
@@ -220,6 +437,9 @@ pub fn string_compose(parts: Node) -> Node {
 //       unary_op_map(star_t, arg))
 //   end
 // end
+pub fn splat(arg: Option<Node>) -> Node {
+    Node::Splat(arg.map(|arg| box arg))
+}
 
 // def word(parts)
 //   if collapse_string_parts?(parts)
@@ -229,6 +449,10 @@ pub fn string_compose(parts: Node) -> Node {
 //       collection_map(nil, parts, nil))
 //   end
 // end
+// NOTE same as string_compose, without begin_t and end_t
+pub fn word(parts: Node) -> Node {
+    string_compose(parts)
+}
 
 // def words_compose(begin_t, parts, end_t)
 //   n(:array, [ *parts ],
@@ -258,6 +482,9 @@ pub fn string_compose(parts: Node) -> Node {
 //   n(:pair, [ key, value ],
 //     binary_op_map(key, assoc_t, value))
 // end
+pub fn pair(key: Node, value: Node) -> Node {
+    Node::Pair { key: box key, value: box value }
+}
 
 // def pair_list_18(list)
 //   if list.size % 2 != 0
@@ -278,6 +505,32 @@ pub fn string_compose(parts: Node) -> Node {
 
 //   n(:pair, [ key, value ], pair_map)
 // end
+pub fn pair_keyword(key: String, value: Node) -> Node {
+    pair(Node::Sym(key), value)
+}
+
+// NOTE from a later whitequark/parser, where `{x:}` came with 3.1
+// def pair_label(key_t)
+//   key_l = loc(key_t)
+//   value_l = key_l.adjust(end_pos: -1)
+//
+//   label = value(key_t)
+//   value =
+//     if label =~ /\A[[:lower:]]/
+//       n(:ident, [ label.to_sym ], Source::Map::Variable.new(value_l))
+//     else
+//       n(:const, [ nil, label.to_sym ], Source::Map::Constant.new(nil, value_l, value_l))
+//     end
+//   pair_keyword(key_t, accessible(value))
+// end
+pub fn pair_label(label: String, static_env: &StaticEnv) -> Node {
+    let value = if label.starts_with(|c: char| c.is_lowercase() || c == '_') {
+        Node::Ident(label.clone())
+    } else {
+        constant(label.clone())
+    };
+    pair_keyword(label, accessible(value, static_env))
+}
 
 // def pair_quoted(begin_t, parts, end_t, value)
 //   end_t, pair_map = pair_quoted_map(begin_t, end_t, value)
@@ -291,11 +544,17 @@ pub fn string_compose(parts: Node) -> Node {
 //   n(:kwsplat, [ arg ],
 //     unary_op_map(dstar_t, arg))
 // end
+pub fn kwsplat(arg: Node) -> Node {
+    Node::Kwsplat(box arg)
+}
 
 // def associate(begin_t, pairs, end_t)
 //   n(:hash, [ *pairs ],
 //     collection_map(begin_t, pairs, end_t))
 // end
+pub fn associate(pairs: Vec<Node>) -> Node {
+    Node::Hash(pairs)
+}
 
 // # Ranges
 
@@ -303,11 +562,18 @@ pub fn string_compose(parts: Node) -> Node {
 //   n(:irange, [ lhs, rhs ],
 //     binary_op_map(lhs, dot2_t, rhs))
 // end
+// NOTE a missing end of a range, which came with 2.6 and 2.7, is Node::Null
+pub fn range_inclusive(lhs: Node, rhs: Node) -> Node {
+    Node::IRange(optional_body(lhs), optional_body(rhs))
+}
 
 // def range_exclusive(lhs, dot3_t, rhs)
 //   n(:erange, [ lhs, rhs ],
 //     binary_op_map(lhs, dot3_t, rhs))
 // end
+pub fn range_exclusive(lhs: Node, rhs: Node) -> Node {
+    Node::ERange(optional_body(lhs), optional_body(rhs))
+}
 
 // #
 // # Access
@@ -386,23 +652,28 @@ pub fn string_compose(parts: Node) -> Node {
 //   end
 // end
 // TODO INCOMPLETE
-pub fn accessible(node: Node) -> Node {
-    println!("building node:accessible, node: {:?}", node);
-    return match node {
-        Node::Ident(n_ident_value) => {
-            // TODO DUMMY
-            // TODO handle static_env
-            let node = Node::LVar(n_ident_value);
-            return node;
-        }
+// NOTE the parser hands its static_env over, see Parser::static_env
+pub fn accessible(node: Node, static_env: &StaticEnv) -> Node {
+    match node {
+        Node::Ident(name) => {
+            if static_env.has_declared(name.clone()) {
+                Node::LVar(name)
+            } else {
+                Node::Send { receiver: None, selector: name, args: vec![] }
+            }
+        },
         _ => node
-    };
+    }
 }
 
 // def const(name_t)
 //   n(:const, [ nil, value(name_t).to_sym ],
 //     constant_map(nil, nil, name_t))
 // end
+// NOTE `const` is a keyword
+pub fn constant(name: String) -> Node {
+    Node::Const { scope: None, name }
+}
 
 // def const_global(t_colon3, name_t)
 //   cbase = n0(:cbase, token_map(t_colon3))
@@ -410,11 +681,17 @@ pub fn accessible(node: Node) -> Node {
 //   n(:const, [ cbase, value(name_t).to_sym ],
 //     constant_map(cbase, t_colon3, name_t))
 // end
+pub fn const_global(name: String) -> Node {
+    Node::Const { scope: Some(box Node::CBase), name }
+}
 
 // def const_fetch(scope, t_colon2, name_t)
 //   n(:const, [ scope, value(name_t).to_sym ],
 //     constant_map(scope, t_colon2, name_t))
 // end
+pub fn const_fetch(scope: Node, name: String) -> Node {
+    Node::Const { scope: Some(box scope), name }
+}
 
 // def __ENCODING__(__ENCODING__t)
 //   n0(:__ENCODING__,
@@ -459,7 +736,6 @@ pub fn accessible(node: Node) -> Node {
 // end
 // TODO INCOMPLETE
 pub fn assignable(node: Node) -> Node {
-    println!("DEBUGGING node::assignable: {:?}", node);
     match node {
         Node::Ident(ident) => {
             //     name, = *node
@@ -468,7 +744,7 @@ pub fn assignable(node: Node) -> Node {
             //     node.updated(:lVasgn)
 
             // TODO handle appending nodes list
-            // TODO handle static_env
+            // NOTE declared by parser, see p_arg
             return Node::LVasgn(ident, vec![]);
         },
         _ => { panic!("node::assignable: UNIMPL branch"); }
@@ -492,6 +768,14 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
             nodes.push(rhs_node);
             return Node::LVasgn(var_str, nodes);
         },
+        Node::Send { receiver, selector, mut args } => {
+            args.push(rhs_node);
+            Node::Send { receiver, selector, args }
+        },
+        Node::CSend { receiver, selector, mut args } => {
+            args.push(rhs_node);
+            Node::CSend { receiver, selector, args }
+        },
         _ => { panic!("node::assign UNIMPL"); }
     }
 }
@@ -522,6 +806,13 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //   n(:mlhs, [ *items ],
 //     collection_map(begin_t, items, end_t))
 // end
+// NOTE items may be an mlhs as well, whose children `*items` takes
+pub fn multi_lhs(items: Node) -> Node {
+    match items {
+        Node::Mlhs(items) => Node::Mlhs(items),
+        _ => Node::Mlhs(extract_parts(items))
+    }
+}
 
 // def multi_assign(lhs, eql_t, rhs)
 //   n(:masgn, [ lhs, rhs ],
@@ -538,18 +829,28 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //   n(:class, [ name, superclass, body ],
 //     module_definition_map(class_t, name, lt_t, end_t))
 // end
+// NOTE a missing superclass is Node::Null, like a missing body
+pub fn def_class(name: Node, superclass: Node, body: Node) -> Node {
+    Node::Class { name: box name, superclass: optional_body(superclass), body: optional_body(body) }
+}
 
 // def def_sclass(class_t, lshft_t, expr,
 //                body, end_t)
 //   n(:sclass, [ expr, body ],
 //     module_definition_map(class_t, nil, lshft_t, end_t))
 // end
+pub fn def_sclass(expr: Node, body: Node) -> Node {
+    Node::SClass { expr: box expr, body: optional_body(body) }
+}
 
 // def def_module(module_t, name,
 //                body, end_t)
 //   n(:module, [ name, body ],
 //     module_definition_map(module_t, name, nil, end_t))
 // end
+pub fn def_module(name: Node, body: Node) -> Node {
+    Node::Module { name: box name, body: optional_body(body) }
+}
 
 // #
 // # Method (un)definition
@@ -560,6 +861,10 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //   n(:def, [ value(name_t).to_sym, args, body ],
 //     definition_map(def_t, nil, name_t, end_t))
 // end
+// NOTE the name is the source of name_t, since operator tokens have no value
+pub fn def_method(name: String, args: Node, body: Node) -> Node {
+    Node::Def { name, args: box args, body: optional_body(body) }
+}
 
 // def def_singleton(def_t, definee, dot_t,
 //                   name_t, args,
@@ -575,6 +880,26 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //       definition_map(def_t, dot_t, name_t, end_t))
 //   end
 // end
+// NOTE the parser checks the definee with is_singleton_literal, see regexp_compose
+pub fn def_singleton(definee: Node, name: String, args: Node, body: Node) -> Node {
+    Node::Defs { definee: box definee, name, args: box args, body: optional_body(body) }
+}
+
+pub fn is_singleton_literal(definee: &Node) -> bool {
+    match *definee {
+        Node::Int(_) | Node::BigInt(_) | Node::Float(_) | Node::Rational(_) | Node::BigRational(_) | Node::Complex(_) |
+        Node::Str(_) | Node::DStr(_) | Node::Sym(_) |
+        Node::Regexp(_, _) | Node::Array(_) | Node::Hash(_) => true,
+        _ => false
+    }
+}
+
+fn optional_body(body: Node) -> Option<Box<Node>> {
+    match body {
+        Node::Null => None,
+        body => Some(box body)
+    }
+}
 
 // def undef_method(undef_t, names)
 //   n(:undef, [ *names ],
@@ -595,11 +920,20 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //   n(:args, args,
 //     collection_map(begin_t, args, end_t))
 // end
+// NOTE
+// the names are passed to the builders below, instead of name_t,
+// duplicated names are checked by the parser, which knows where they are, see Parser::check_duplicate_args
+pub fn args(args: Node) -> Node {
+    Node::Args(extract_parts(args))
+}
 
 // def arg(name_t)
 //   n(:arg, [ value(name_t).to_sym ],
 //     variable_map(name_t))
 // end
+pub fn arg(name: String) -> Node {
+    Node::Arg(name)
+}
 
 // def optarg(name_t, eql_t, value)
 //   n(:optarg, [ value(name_t).to_sym, value ],
@@ -607,6 +941,9 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //       with_operator(loc(eql_t)).
 //       with_expression(loc(name_t).join(value.loc.expression)))
 // end
+pub fn optarg(name: String, value: Node) -> Node {
+    Node::OptArg(name, box value)
+}
 
 // def restarg(star_t, name_t=nil)
 //   if name_t
@@ -617,16 +954,25 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //       arg_prefix_map(star_t))
 //   end
 // end
+pub fn restarg(name: Option<String>) -> Node {
+    Node::RestArg(name)
+}
 
 // def kwarg(name_t)
 //   n(:kwarg, [ value(name_t).to_sym ],
 //     kwarg_map(name_t))
 // end
+pub fn kwarg(name: String) -> Node {
+    Node::KwArg(name)
+}
 
 // def kwoptarg(name_t, value)
 //   n(:kwoptarg, [ value(name_t).to_sym, value ],
 //     kwarg_map(name_t, value))
 // end
+pub fn kwoptarg(name: String, value: Node) -> Node {
+    Node::KwOptArg(name, box value)
+}
 
 // def kwrestarg(dstar_t, name_t=nil)
 //   if name_t
@@ -637,16 +983,52 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //       arg_prefix_map(dstar_t))
 //   end
 // end
+pub fn kwrestarg(name: Option<String>) -> Node {
+    Node::KwRestArg(name)
+}
 
 // def shadowarg(name_t)
 //   n(:shadowarg, [ value(name_t).to_sym ],
 //     variable_map(name_t))
 // end
+pub fn shadowarg(name: String) -> Node {
+    Node::ShadowArg(name)
+}
 
 // def blockarg(amper_t, name_t)
 //   n(:blockarg, [ value(name_t).to_sym ],
 //     arg_prefix_map(amper_t, name_t))
 // end
+pub fn blockarg(name: String) -> Node {
+    Node::BlockArg(name)
+}
+
+// NOTE from a later whitequark/parser, where `...` came with 2.7
+// def forward_arg(dots_t)
+//   n(:forward_arg, [], token_map(dots_t))
+// end
+pub fn forward_arg() -> Node {
+    Node::ForwardArg
+}
+
+//     def arg_name_collides?(this_name, that_name)
+//       case @parser.version
+//       when 18
+//         this_name == that_name
+//       when 19
+//         # Ignore underscore.
+//         this_name != :_ &&
+//           this_name == that_name
+//       else
+//         # Ignore everything beginning with underscore.
+//         this_name && this_name[0] != '_' &&
+//           this_name == that_name
+//       end
+//     end
+// NOTE nameless ones like `*` aren't passed in
+pub fn arg_name_collides(this_name: &str, that_name: &str) -> bool {
+    !this_name.starts_with('_') && this_name == that_name
+}
 
 // def procarg0(arg)
 //   if self.class.emit_procarg0
@@ -655,6 +1037,10 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //     arg
 //   end
 // end
+// NOTE emit_procarg0 is always on
+pub fn procarg0(arg: Node) -> Node {
+    Node::Procarg0(box arg)
+}
 
 // # Ruby 1.8 block arguments
 
@@ -734,6 +1120,14 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //     :send
 //   end
 // end
+// NOTE builds the call of that type, t_dot being the token of call_op or tCOLON2
+fn call_for_dot(receiver: Option<Node>, t_dot: Option<Token>, selector: String, args: Vec<Node>) -> Node {
+    if t_dot == Some(Token::T_ANDDOT) {
+        Node::CSend { receiver: box receiver.expect("csend without receiver"), selector, args }
+    } else {
+        Node::Send { receiver: receiver.map(|receiver| box receiver), selector, args }
+    }
+}
 
 // def call_method(receiver, dot_t, selector_t,
 //                 lparen_t=nil, args=[], rparen_t=nil)
@@ -746,6 +1140,10 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //       send_map(receiver, dot_t, selector_t, lparen_t, args, rparen_t))
 //   end
 // end
+pub fn call_method(receiver: Option<Node>, t_dot: Option<Token>, selector: Option<String>, args: Vec<Node>) -> Node {
+    let selector = selector.unwrap_or_else(|| String::from("call"));
+    call_for_dot(receiver, t_dot, selector, args)
+}
 
 // def call_lambda(lambda_t)
 //   if self.class.emit_lambda
@@ -755,6 +1153,10 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //       send_map(nil, nil, lambda_t))
 //   end
 // end
+// NOTE emit_lambda is always on
+pub fn call_lambda() -> Node {
+    Node::Lambda
+}
 
 // def block(method_call, begin_t, args, body, end_t)
 //   _receiver, _selector, *call_args = *method_call
@@ -783,11 +1185,48 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //       method_call.loc.with_expression(join_exprs(method_call, block)))
 //   end
 // end
+// NOTE
+// the diagnostics are reported by the parser, which knows where the tokens are, see Parser::check_block_and_blockarg
+// TODO INCOMPLETE yield and the keyword commands like `return foo 1 do end`, which aren't parsed yet
+// 
+// NOTE
+// a later whitequark/parser builds a numblock for numbered parameters, which came with 2.7,
+//   if args.type == :numargs
+//     block_type = :numblock
+//     args = args.children[0]
+//   else
+//     block_type = :block
+//   end
+pub fn block(method_call: Node, args: Node, body: Node) -> Node {
+    match args {
+        Node::Numargs(numargs) => Node::NumBlock { call: box method_call, numargs, body: optional_body(body) },
+        args => Node::Block { call: box method_call, args: box args, body: optional_body(body) }
+    }
+}
+
+// NOTE from a later whitequark/parser, like forward_arg
+// def forwarded_args(dots_t)
+//   n(:forwarded_args, [], token_map(dots_t))
+// end
+pub fn forwarded_args() -> Node {
+    Node::ForwardedArgs
+}
+
+// NOTE from a later whitequark/parser, like block
+// def numargs(max_numparam)
+//   n(:numargs, [ max_numparam ], nil)
+// end
+pub fn numargs(max_numparam: usize) -> Node {
+    Node::Numargs(max_numparam)
+}
 
 // def block_pass(amper_t, arg)
 //   n(:block_pass, [ arg ],
 //     unary_op_map(amper_t, arg))
 // end
+pub fn block_pass(arg: Node) -> Node {
+    Node::BlockPass(box arg)
+}
 
 // def objc_varargs(pair, rest_of_varargs)
 //   value, first_vararg = *pair
@@ -806,17 +1245,26 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //   n(type, [ receiver, method_name ],
 //     send_map(receiver, dot_t, selector_t))
 // end
+pub fn attr_asgn(receiver: Node, t_dot: Token, selector: String) -> Node {
+    call_for_dot(Some(receiver), Some(t_dot), selector + "=", vec![])
+}
 
 // def index(receiver, lbrack_t, indexes, rbrack_t)
 //   n(:send, [ receiver, :[], *indexes ],
 //     send_index_map(receiver, lbrack_t, rbrack_t))
 // end
+pub fn index(receiver: Node, indexes: Vec<Node>) -> Node {
+    Node::Send { receiver: Some(box receiver), selector: String::from("[]"), args: indexes }
+}
 
 // def index_asgn(receiver, lbrack_t, indexes, rbrack_t)
 //   # Incomplete method call.
 //   n(:send, [ receiver, :[]=, *indexes ],
 //     send_index_map(receiver, lbrack_t, rbrack_t))
 // end
+pub fn index_asgn(receiver: Node, indexes: Vec<Node>) -> Node {
+    Node::Send { receiver: Some(box receiver), selector: String::from("[]="), args: indexes }
+}
 
 // def binary_op(receiver, operator_t, arg)
 //   source_map = send_binary_op_map(receiver, operator_t, arg)
@@ -899,21 +1347,86 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 
 // # Conditionals
 
+//     def check_condition(cond)
+//       case cond.type
+//       when :masgn
+//         diagnostic :error, :masgn_as_condition, nil, cond.loc.expression
+// 
+//       when :begin
+//         if cond.children.count == 1
+//           cond.updated(nil, [
+//             check_condition(cond.children.last)
+//           ])
+//         else
+//           cond
+//         end
+// 
+//       when :and, :or, :irange, :erange
+//         lhs, rhs = *cond
+// 
+//         type = case cond.type
+//         when :irange then :iflipflop
+//         when :erange then :eflipflop
+//         end
+// 
+//         if [:and, :or].include?(cond.type) &&
+//                @parser.version == 18
+//           cond
+//         else
+//           cond.updated(type, [
+//             check_condition(lhs),
+//             check_condition(rhs)
+//           ])
+//         end
+// 
+//       when :regexp
+//         n(:match_current_line, [ cond ], expr_map(cond.loc.expression))
+// 
+//       else
+//         cond
+//       end
+//     end
+// TODO INCOMPLETE masgn, and, or and ranges, which aren't parsed yet
+fn check_condition(cond: Node) -> Node {
+    match cond {
+        Node::Begin(mut statements) => {
+            if statements.len() == 1 {
+                let statement = statements.pop().unwrap();
+                Node::Begin(vec![check_condition(statement)])
+            } else {
+                Node::Begin(statements)
+            }
+        },
+        Node::Regexp(..) => Node::MatchCurrentLine(box cond),
+        _ => cond
+    }
+}
+
 // def condition(cond_t, cond, then_t,
 //               if_true, else_t, if_false, end_t)
 //   n(:if, [ check_condition(cond), if_true, if_false ],
 //     condition_map(cond_t, cond, then_t, if_true, else_t, if_false, end_t))
 // end
+// NOTE a missing branch is Node::Null
+pub fn condition(cond: Node, if_true: Node, if_false: Node) -> Node {
+    Node::If { cond: box check_condition(cond), if_true: optional_body(if_true), if_false: optional_body(if_false) }
+}
 
 // def condition_mod(if_true, if_false, cond_t, cond)
 //   n(:if, [ check_condition(cond), if_true, if_false ],
 //     keyword_mod_map(if_true || if_false, cond_t, cond))
 // end
+pub fn condition_mod(if_true: Node, if_false: Node, cond: Node) -> Node {
+    condition(cond, if_true, if_false)
+}
 
 // def ternary(cond, question_t, if_true, colon_t, if_false)
 //   n(:if, [ check_condition(cond), if_true, if_false ],
 //     ternary_map(cond, question_t, if_true, colon_t, if_false))
 // end
+pub fn ternary(cond: Node, if_true: Node, if_false: Node) -> Node {
+    Node::If { cond: box check_condition(cond), if_true: Some(box if_true), if_false: Some(box if_false) }
+}
 
 // # Case matching
 
@@ -922,11 +1435,223 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //   n(:when, children,
 //     keyword_map(when_t, then_t, children, nil))
 // end
+pub fn when(patterns: Node, body: Node) -> Node {
+    Node::When { patterns: extract_parts(patterns), body: optional_body(body) }
+}
 
 // def case(case_t, expr, when_bodies, else_t, else_body, end_t)
 //   n(:case, [ expr, *(when_bodies << else_body)],
 //     condition_map(case_t, expr, nil, nil, else_t, else_body, end_t))
 // end
+// NOTE a missing expr or else_body is Node::Null
+pub fn case(expr: Node, when_bodies: Vec<Node>, else_body: Node) -> Node {
+    Node::Case { expr: optional_body(expr), when_bodies, else_body: optional_body(else_body) }
+}
+
+// # Pattern matching
+// NOTE from a later whitequark/parser, where pattern matching came with 2.7
+
+// def case_match(case_t, expr, in_bodies, else_t, else_body, end_t)
+//   else_body = n(:empty_else, nil, token_map(else_t)) if else_t && !else_body
+//   n(:case_match, [ expr, *(in_bodies << else_body)],
+//     condition_map(case_t, expr, nil, nil, else_t, else_body, end_t))
+// end
+pub fn case_match(expr: Node, in_bodies: Vec<Node>, else_t: Option<Token>, else_body: Node) -> Node {
+    let else_body = match (else_t, else_body) {
+        (Some(_), Node::Null) => Some(box Node::EmptyElse),
+        (_, else_body) => optional_body(else_body)
+    };
+    Node::CaseMatch { expr: box expr, in_bodies, else_body }
+}
+
+// def in_pattern(in_t, pattern, guard, then_t, body)
+//   children = [pattern, guard, body]
+//   n(:in_pattern, children,
+//     keyword_map(in_t, then_t, children.compact, nil))
+// end
+pub fn in_pattern(pattern: Node, guard: Node, body: Node) -> Node {
+    Node::InPattern { pattern: box pattern, guard: optional_body(guard), body: optional_body(body) }
+}
+
+// def if_guard(if_t, if_body)
+//   n(:if_guard, [ if_body ], guard_map(if_t, if_body))
+// end
+pub fn if_guard(if_body: Node) -> Node {
+    Node::IfGuard(box if_body)
+}
+
+// def unless_guard(unless_t, unless_body)
+//   n(:unless_guard, [ unless_body ], guard_map(unless_t, unless_body))
+// end
+pub fn unless_guard(unless_body: Node) -> Node {
+    Node::UnlessGuard(box unless_body)
+}
+
+// def match_var(name_t)
+//   name = value(name_t).to_sym
+//   name_l = loc(name_t)
+// 
+//   check_lvar_name(name, name_l)
+//   check_duplicate_pattern_variable(name, name_l)
+//   @parser.static_env.declare(name)
+// 
+//   n(:match_var, [ name ],
+//     variable_map(name_t))
+// end
+// NOTE
+// the name is checked and declared by the parser, which knows where it is, see Parser::declare_pattern_variable,
+// so this is match_hash_var as well
+pub fn match_var(name: String) -> Node {
+    Node::MatchVar(name)
+}
+
+// def match_rest(star_t, name_t = nil)
+//   if name_t.nil?
+//     n0(:match_rest,
+//       unary_op_map(star_t))
+//   else
+//     name = match_var(name_t)
+//     n(:match_rest, [ name ],
+//       unary_op_map(star_t, name))
+//   end
+// end
+pub fn match_rest(name: Option<String>) -> Node {
+    Node::MatchRest(name.map(|name| box match_var(name)))
+}
+
+// def match_as(value, assoc_t, as)
+//   source_map = binary_op_map(value, assoc_t, as)
+// 
+//   n(:match_as, [ value, as ],
+//     source_map)
+// end
+pub fn match_as(value: Node, as_: Node) -> Node {
+    Node::MatchAs { value: box value, as_: box as_ }
+}
+
+// def match_alt(left, pipe_t, right)
+//   source_map = binary_op_map(left, pipe_t, right)
+// 
+//   n(:match_alt, [ left, right ],
+//     source_map)
+// end
+pub fn match_alt(left: Node, right: Node) -> Node {
+    Node::MatchAlt(box left, box right)
+}
+
+// def match_nil_pattern(dstar_t, nil_t)
+//   n0(:match_nil_pattern,
+//     match_nil_pattern_map(dstar_t, nil_t))
+// end
+pub fn match_nil_pattern() -> Node {
+    Node::MatchNilPattern
+}
+
+// def match_pair(label_type, label, value)
+//   if label_type == :label
+//     check_duplicate_pattern_key(label[0], label[1])
+//     pair_keyword(label, value)
+//   else
+//     ...
+//   end
+// end
+// NOTE the key is checked by the parser, see match_var, and quoted labels aren't parsed yet
+pub fn match_pair(key: String, value: Node) -> Node {
+    pair_keyword(key, value)
+}
+
+// def match_label(label_type, label)
+//   if label_type == :label
+//     match_hash_var(label)
+//   else
+//     ...
+//   end
+// end
+pub fn match_label(name: String) -> Node {
+    match_var(name)
+}
+
+// def match_with_trailing_comma(match, comma_t)
+//   n(:match_with_trailing_comma, [ match ], expr_map(match.loc.expression.join(loc(comma_t))))
+// end
+pub fn match_with_trailing_comma(match_: Node) -> Node {
+    Node::MatchWithTrailingComma(box match_)
+}
+
+// def array_pattern(lbrack_t, elements, rbrack_t)
+//   return n(:array_pattern, nil, collection_map(lbrack_t, [], rbrack_t)) if elements.nil?
+// 
+//   trailing_comma = false
+// 
+//   nodes = elements.map do |element|
+//     if element.type == :match_with_trailing_comma
+//       trailing_comma = true
+//       element.children.first
+//     else
+//       trailing_comma = false
+//       element
+//     end
+//   end
+// 
+//   node_type = trailing_comma ? :array_pattern_with_tail : :array_pattern
+// 
+//   n(node_type, nodes,
+//     collection_map(lbrack_t, elements, rbrack_t))
+// end
+pub fn array_pattern(elements: Node) -> Node {
+    let mut trailing_comma = false;
+
+    let nodes = extract_parts(elements).into_iter().map(|element| {
+        match element {
+            Node::MatchWithTrailingComma(element) => {
+                trailing_comma = true;
+                *element
+            },
+            element => {
+                trailing_comma = false;
+                element
+            }
+        }
+    }).collect();
+
+    if trailing_comma { Node::ArrayPatternWithTail(nodes) } else { Node::ArrayPattern(nodes) }
+}
+
+// def find_pattern(lbrack_t, elements, rbrack_t)
+//   n(:find_pattern, elements,
+//     collection_map(lbrack_t, elements, rbrack_t))
+// end
+pub fn find_pattern(elements: Node) -> Node {
+    Node::FindPattern(extract_parts(elements))
+}
+
+// def hash_pattern(lbrace_t, kwargs, rbrace_t)
+//   n(:hash_pattern, kwargs,
+//     collection_map(lbrace_t, kwargs, rbrace_t))
+// end
+pub fn hash_pattern(kwargs: Node) -> Node {
+    Node::HashPattern(extract_parts(kwargs))
+}
+
+// def const_pattern(const, ldelim_t, pattern, rdelim_t)
+//   n(:const_pattern, [const, pattern],
+//     Source::Map::Collection.new(
+//       loc(ldelim_t), loc(rdelim_t),
+//       const.loc.expression.join(loc(rdelim_t))
+//     )
+//   )
+// end
+pub fn const_pattern(const_: Node, pattern: Node) -> Node {
+    Node::ConstPattern { const_: box const_, pattern: box pattern }
+}
+
+// def pin(pin_t, var)
+//   n(:pin, [ var ],
+//     send_unary_op_map(pin_t, var))
+// end
+pub fn pin(var: Node) -> Node {
+    Node::Pin(box var)
+}
 
 // # Loops
 
@@ -934,6 +1659,17 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //   n(type, [ check_condition(cond), body ],
 //     keyword_map(keyword_t, do_t, nil, end_t))
 // end
+// NOTE the type is told by keyword_t, which is kWHILE or kUNTIL
+pub fn loop_(keyword_t: Token, cond: Node, body: Node) -> Node {
+    let cond = box check_condition(cond);
+    let body = optional_body(body);
+
+    match keyword_t {
+        Token::K_WHILE => Node::While { cond, body },
+        Token::K_UNTIL => Node::Until { cond, body },
+        _ => panic!("node::loop_: unknown keyword {:?}", keyword_t)
+    }
+}
 
 // def loop_mod(type, body, keyword_t, cond)
 //   if body.type == :kwbegin
@@ -943,12 +1679,28 @@ pub fn assign(lhs_node: Node, token: Token, rhs_node: Node) -> Node {
 //   n(type, [ check_condition(cond), body ],
 //     keyword_mod_map(body, keyword_t, cond))
 // end
+// NOTE the type is told by keyword_t, which is kWHILE_MOD or kUNTIL_MOD
+pub fn loop_mod(body: Node, keyword_t: Token, cond: Node) -> Node {
+    let cond = box check_condition(cond);
+    let is_post = match body { Node::KwBegin(_) => true, _ => false };
+
+    match (keyword_t, is_post) {
+        (Token::K_WHILE_MOD, false) => Node::While { cond, body: Some(box body) },
+        (Token::K_WHILE_MOD, true) => Node::WhilePost { cond, body: box body },
+        (Token::K_UNTIL_MOD, false) => Node::Until { cond, body: Some(box body) },
+        (Token::K_UNTIL_MOD, true) => Node::UntilPost { cond, body: box body },
+        (keyword_t, _) => panic!("node::loop_mod: unknown keyword {:?}", keyword_t)
+    }
+}
 
 // def for(for_t, iterator, in_t, iteratee,
 //         do_t, body, end_t)
 //   n(:for, [ iterator, iteratee, body ],
 //     for_map(for_t, in_t, do_t, end_t))
 // end
+pub fn for_(iterator: Node, iteratee: Node, body: Node) -> Node {
+    Node::For { iterator: box iterator, iteratee: box iteratee, body: optional_body(body) }
+}
 
 // # Keywords
 
@@ -1073,6 +1825,15 @@ pub fn compstmt(nodes: Node) -> Node {
 //       collection_map(begin_t, [ body ], end_t))
 //   end
 // end
+pub fn begin(body: Node) -> Node {
+    match body {
+        Node::Null => Node::Begin(vec![]),
+        // NOTE a Begin here is always synthesized by compstmt, since it has no begin/end tokens
+        Node::Begin(statements) => Node::Begin(statements),
+        Node::Mlhs(items) => Node::Mlhs(items),
+        _ => Node::Begin(vec![body])
+    }
+}
 
 // def begin_keyword(begin_t, body, end_t)
 //   if body.nil?
@@ -1089,6 +1850,14 @@ pub fn compstmt(nodes: Node) -> Node {
 //       collection_map(begin_t, [ body ], end_t))
 //   end
 // end
+// TODO a Begin of parentheses is taken as synthesized as well, see begin
+pub fn begin_keyword(body: Node) -> Node {
+    match body {
+        Node::Null => Node::KwBegin(vec![]),
+        Node::Begin(statements) => Node::KwBegin(statements),
+        _ => Node::KwBegin(vec![body])
+    }
+}
 
 //     #
 //     # HELPERS
@@ -1113,6 +1882,19 @@ pub fn compstmt(nodes: Node) -> Node {
 //         end
 //       end.join
 //     end
+fn static_string(nodes: &Vec<Node>) -> Option<String> {
+    let mut string = String::new();
+
+    for node in nodes {
+        match *node {
+            Node::Str(ref string_value) => { string.push_str(string_value); },
+            Node::Begin(ref children) => { string.push_str(&static_string(children)?); },
+            _ => { return None; }
+        }
+    }
+
+    Some(string)
+}
 
 //     def static_regexp(parts, options)
 //       source = static_string(parts)
@@ -1133,6 +1915,14 @@ pub fn compstmt(nodes: Node) -> Node {
 
 //       Regexp.new(source, (Regexp::EXTENDED if options.children.include?(:x)))
 //     end
+//
+// NOTE only checks the source, Err being the message of RegexpError
+fn static_regexp(parts: &Vec<Node>, options: &Vec<char>) -> Result<(), String> {
+    match static_string(parts) {
+        None => Ok(()),
+        Some(source) => regexp::check(&source, options.contains(&'x')),
+    }
+}
 
 //     def static_regexp_node(node)
 //       if node.type == :regexp
@@ -1140,13 +1930,31 @@ pub fn compstmt(nodes: Node) -> Node {
 //         static_regexp(parts, options)
 //       end
 //     end
+pub fn static_regexp_node(node: &Node) -> Result<(), String> {
+    if let Node::Regexp(ref parts, ref options) = *node {
+        if let Node::RegOpt(ref options) = **options {
+            return static_regexp(parts, options);
+        }
+    }
+
+    Ok(())
+}
 
 //     def collapse_string_parts?(parts)
 //       parts.one? &&
 //           [:str, :dstr].include?(parts.first.type)
 //     end
-fn is_collapse_string_parts(parts: &Node) -> bool {
-    true
+fn is_collapse_string_parts(parts: &Vec<Node>) -> bool {
+    parts.len() == 1 &&
+        match parts[0] { Node::Str(_) | Node::DStr(_) => true, _ => false }
+}
+
+// string parts come in as a Node::Nodes, or a single node
+fn extract_parts(parts: Node) -> Vec<Node> {
+    match parts {
+        Node::Nodes(nodes) => nodes,
+        _ => vec![parts]
+    }
 }
 
 //     def value(token)
@@ -0,0 +1,205 @@
+// NOTE
+// whitequark relies on Regexp.new to tell whether a static regexp is valid, see static_regexp in node.rs,
+// there's no onigmo here, so the common syntax errors are detected by hand,
+// messages are the ones onigmo reports, formatted like RegexpError#message
+//
+// TODO detect the rest of onigmo errors, like invalid backrefs and bad escapes
+
+// what a repeat operator would apply to
+enum Target {
+    // at the beginning of pattern, group or alternative
+    Nothing,
+    // ^, $, \A, etc.
+    Anchor,
+    Atom,
+}
+
+// check the source of a regexp like Regexp.new(source, (Regexp::EXTENDED if extended)) would
+pub fn check(source: &str, extended: bool) -> Result<(), String> {
+    match check_syntax(source, extended) {
+        Ok(()) => Ok(()),
+        Err(message) => Err(format!("{}: /{}/", message, source)),
+    }
+}
+
+fn check_syntax(source: &str, extended: bool) -> Result<(), &'static str> {
+    let chars: Vec<char> = source.chars().collect();
+
+    let mut i = 0;
+    let mut depth = 0;
+    let mut target = Target::Nothing;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                match chars.get(i + 1) {
+                    None => { return Err("too short escape sequence"); },
+                    Some(&'A') | Some(&'z') | Some(&'Z') | Some(&'b') | Some(&'B') | Some(&'G') => { target = Target::Anchor; },
+                    Some(_) => { target = Target::Atom; },
+                }
+                i += 2;
+                continue;
+            },
+
+            '(' => {
+                if chars.get(i + 1) == Some(&'?') {
+                    let (next_i, opens_group) = check_group_extension(&chars, i + 2)?;
+                    i = next_i;
+                    if opens_group { depth += 1; }
+                } else {
+                    i += 1;
+                    depth += 1;
+                }
+                target = Target::Nothing;
+                continue;
+            },
+
+            ')' => {
+                if depth == 0 { return Err("unmatched close parenthesis"); }
+                depth -= 1;
+                target = Target::Atom;
+            },
+
+            '|' => { target = Target::Nothing; },
+
+            '^' | '$' => { target = Target::Anchor; },
+
+            '*' | '+' | '?' => {
+                check_repeat_target(&target)?;
+            },
+
+            '{' => {
+                if let Some((lower, upper, len)) = scan_interval(&chars, i) {
+                    check_repeat_target(&target)?;
+                    if let (Some(lower), Some(upper)) = (lower, upper) {
+                        if lower > upper { return Err("upper is smaller than lower in repeat range"); }
+                    }
+                    i += len;
+                    continue;
+                }
+                target = Target::Atom;
+            },
+
+            '[' => {
+                i = skip_char_class(&chars, i)?;
+                target = Target::Atom;
+                continue;
+            },
+
+            '#' if extended => {
+                while i < chars.len() && chars[i] != '\n' { i += 1; }
+            },
+
+            ' ' | '\t' | '\n' | '\r' | '\x0c' | '\x0b' if extended => {},
+
+            _ => { target = Target::Atom; },
+        }
+
+        i += 1;
+    }
+
+    if depth > 0 { return Err("end pattern with unmatched parenthesis"); }
+
+    Ok(())
+}
+
+fn check_repeat_target(target: &Target) -> Result<(), &'static str> {
+    match *target {
+        Target::Nothing => Err("target of repeat operator is not specified"),
+        Target::Anchor => Err("target of repeat operator is invalid"),
+        Target::Atom => Ok(()),
+    }
+}
+
+// check what follows `(?`, returns the position after the extension,
+// and whether it opens a group, (?#comment) and (?imx-imx) don't
+fn check_group_extension(chars: &Vec<char>, i: usize) -> Result<(usize, bool), &'static str> {
+    match chars.get(i) {
+        None => Err("end pattern in group"),
+        Some(&':') | Some(&'=') | Some(&'!') | Some(&'>') | Some(&'~') => Ok((i + 1, true)),
+        Some(&'<') => {
+            match chars.get(i + 1) {
+                Some(&'=') | Some(&'!') => Ok((i + 2, true)),
+                _ => {
+                    // named group
+                    match chars[i..].iter().position(|c| *c == '>') {
+                        Some(len) if len > 1 => Ok((i + len + 1, true)),
+                        _ => Err("invalid group name <>"),
+                    }
+                }
+            }
+        },
+        Some(&'#') => {
+            match chars[i..].iter().position(|c| *c == ')') {
+                Some(len) => Ok((i + len + 1, false)),
+                None => Err("end pattern in group"),
+            }
+        },
+        Some(&c) if is_option(c) => {
+            let len = chars[i..].iter().take_while(|c| is_option(**c)).count();
+            match chars.get(i + len) {
+                Some(&':') => Ok((i + len + 1, true)),
+                Some(&')') => Ok((i + len + 1, false)),
+                None => Err("end pattern in group"),
+                Some(_) => Err("undefined group option"),
+            }
+        },
+        Some(_) => Err("undefined group option"),
+    }
+}
+
+fn is_option(c: char) -> bool {
+    c == 'i' || c == 'm' || c == 'x' || c == '-'
+}
+
+// `{n}`, `{n,}`, `{,n}` or `{n,m}` starting at i, returns lower, upper and length,
+// anything else is a literal `{`
+fn scan_interval(chars: &Vec<char>, i: usize) -> Option<(Option<usize>, Option<usize>, usize)> {
+    let close = i + chars[i..].iter().position(|c| *c == '}')?;
+    let interval: String = chars[(i + 1)..close].iter().collect();
+
+    let parse = |bound: &str| -> Result<Option<usize>, ()> {
+        if bound.is_empty() { return Ok(None); }
+        if !bound.chars().all(|c| c.is_ascii_digit()) { return Err(()); }
+        bound.parse::<usize>().map(Some).map_err(|_| ())
+    };
+
+    let (lower, upper) = match interval.find(',') {
+        None => {
+            let bound = parse(&interval).ok()?;
+            (bound, bound)
+        },
+        Some(comma) => (parse(&interval[..comma]).ok()?, parse(&interval[(comma + 1)..]).ok()?),
+    };
+
+    if lower.is_none() && upper.is_none() { return None; }
+
+    Some((lower, upper, close - i + 1))
+}
+
+// skip a (possibly nested) char class starting at i, returns the position after its closing `]`
+fn skip_char_class(chars: &Vec<char>, i: usize) -> Result<usize, &'static str> {
+    let mut i = i + 1;
+    let mut depth = 1;
+
+    if chars.get(i) == Some(&'^') { i += 1; }
+    if chars.get(i) == Some(&']') { return Err("empty char-class"); }
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => { i += 1; },
+            '[' => {
+                depth += 1;
+                if chars.get(i + 1) == Some(&'^') { i += 1; }
+            },
+            ']' => {
+                depth -= 1;
+                if depth == 0 { return Ok(i + 1); }
+            },
+            _ => {}
+        }
+        i += 1;
+    }
+
+    Err("premature end of char-class")
+}
@@ -0,0 +1,147 @@
+// based on
+// https://github.com/whitequark/parser/blob/2a73841d6da04a5ab9bd270561165fd766722d43/lib/parser/messages.rb
+
+// module Parser
+//   ##
+//   # Diagnostic messages (errors, warnings and notes) that can be generated.
+//   #
+//   # @see Diagnostic
+//   #
+//   # @api public
+//   #
+//   MESSAGES = {
+//     # Lexer errors
+//     :unicode_point_too_large => 'invalid Unicode codepoint (too large)',
+//     :invalid_escape          => 'invalid escape character syntax',
+//     :incomplete_escape       => 'incomplete character syntax',
+//     :invalid_hex_escape      => 'invalid hex escape',
+//     :invalid_unicode_escape  => 'invalid Unicode escape',
+//     :unterminated_unicode    => 'unterminated Unicode escape',
+//     :escape_eof              => 'escape sequence meets end of file',
+//     :string_eof              => 'unterminated string meets end of file',
+//     :regexp_options          => 'unknown regexp options: %{options}',
+//     :cvar_name               => "`%{name}' is not allowed as a class variable name",
+//     :ivar_name               => "`%{name}' is not allowed as an instance variable name",
+//     :trailing_in_number      => "trailing `%{character}' in number",
+//     :empty_numeric           => 'numeric literal without digits',
+//     :invalid_octal           => 'invalid octal digit',
+//     :no_dot_digit_literal    => 'no .<digit> floating literal anymore; put 0 before dot',
+//     :bare_backslash          => 'bare backslash only allowed before newline',
+//     :unexpected              => "unexpected `%{character}'",
+//     :embedded_document       => 'embedded document meets end of file (and they\'re disallowed in a command line)',
+// 
+//     # Lexer warnings
+//     :invalid_escape_use      => 'invalid character syntax; use ?%{escape}',
+//     :ambiguous_literal       => 'ambiguous first argument; put parentheses or a space even after the operator',
+//     :ambiguous_prefix        => "`%{prefix}' interpreted as argument prefix",
+// 
+//     # Parser errors
+//     :nth_ref_alias           => 'cannot define an alias for a back-reference variable',
+//     :begin_in_method         => 'BEGIN in method',
+//     :backref_assignment      => 'cannot assign to a back-reference variable',
+//     :invalid_assignment      => 'cannot assign to a keyword',
+//     :module_name_const       => 'class or module name must be a constant literal',
+//     :unexpected_token        => 'unexpected token %{token}',
+//     :argument_const          => 'formal argument cannot be a constant',
+//     :argument_ivar           => 'formal argument cannot be an instance variable',
+//     :argument_gvar           => 'formal argument cannot be a global variable',
+//     :argument_cvar           => 'formal argument cannot be a class variable',
+//     :duplicate_argument      => 'duplicate argument name',
+//     :empty_symbol            => 'empty symbol literal',
+//     :odd_hash                => 'odd number of entries for a hash',
+//     :singleton_literal       => 'cannot define a singleton method for a literal',
+//     :dynamic_const           => 'dynamic constant assignment',
+//     :const_reassignment      => 'constant re-assignment',
+//     :module_in_def           => 'module definition in method body',
+//     :class_in_def            => 'class definition in method body',
+//     :unexpected_percent_str  => '%{type}: unknown type of percent-literal',
+//     :block_and_blockarg      => 'both block argument and literal block are passed',
+//     :masgn_as_condition      => 'multiple assignment in conditional context',
+//     :block_given_to_yield    => 'block given to yield',
+//     :invalid_regexp          => '%{message}',
+//     :invalid_return          => 'Invalid return in class/module body',
+// 
+//     # Parser warnings
+//     :useless_else            => 'else without rescue is useless',
+// 
+//     # Parser errors that are not Ruby errors
+//     :invalid_encoding        => 'literal contains escape sequences incompatible with UTF-8',
+// 
+//     # Parser warnings that are not Ruby warnings
+//     :ambiguous_range         => 'ambiguous range literal',
+//   }.freeze
+// end
+// 
+// NOTE
+// only reasons actually being reported are listed here,
+// add new ones along with the code reporting them
+pub fn message_for(reason: &str) -> &'static str {
+    match reason {
+        // # Lexer errors
+        "unicode_point_too_large" => "invalid Unicode codepoint (too large)",
+        "invalid_escape"         => "invalid escape character syntax",
+        "incomplete_escape"      => "incomplete character syntax",
+        "invalid_hex_escape"     => "invalid hex escape",
+        "invalid_unicode_escape" => "invalid Unicode escape",
+        "unterminated_unicode"   => "unterminated Unicode escape",
+        "escape_eof"             => "escape sequence meets end of file",
+        "string_eof"             => "unterminated string meets end of file",
+        "regexp_options"         => "unknown regexp options: %{options}",
+        "trailing_in_number"     => "trailing `%{character}' in number",
+        "empty_numeric"          => "numeric literal without digits",
+        "invalid_octal"          => "invalid octal digit",
+        "no_dot_digit_literal"   => "no .<digit> floating literal anymore; put 0 before dot",
+        "unexpected"             => "unexpected `%{character}'",
+        "embedded_document"      => "embedded document meets end of file (and they're disallowed in a command line)",
+
+        // # Lexer warnings
+        "invalid_escape_use"     => "invalid character syntax; use ?%{escape}",
+        "ambiguous_literal"      => "ambiguous first argument; put parentheses or a space even after the operator",
+        "ambiguous_prefix"       => "`%{prefix}' interpreted as argument prefix",
+
+        // NOTE not in this version of whitequark/parser, added along with the heredoc identifier checks
+        "heredoc_id_has_newline" => "here document identifier across newlines, never found",
+        "heredoc_id_ends_with_nl" => "here document identifier ends with a newline",
+
+        // # Parser errors
+        "module_name_const"      => "class or module name must be a constant literal",
+        "unexpected_token"       => "unexpected token %{token}",
+        "argument_const"         => "formal argument cannot be a constant",
+        "argument_ivar"          => "formal argument cannot be an instance variable",
+        "argument_gvar"          => "formal argument cannot be a global variable",
+        "duplicate_argument"     => "duplicate argument name",
+        "singleton_literal"      => "cannot define a singleton method for a literal",
+        "module_in_def"          => "module definition in method body",
+        "class_in_def"           => "class definition in method body",
+        "block_and_blockarg"     => "both block argument and literal block are passed",
+        "invalid_regexp"         => "%{message}",
+
+        // NOTE not in this version of whitequark/parser, added along with endless def and numbered parameters
+        "endless_setter"         => "setter method cannot be defined in an endless method definition",
+        "ordinary_param_defined" => "ordinary parameter is defined",
+        "numparam_used_in_outer_scope" => "numbered parameter is already used in an outer scope",
+
+        // NOTE not in this version of whitequark/parser, added along with pattern matching
+        "lvar_name"              => "`%{name}' is not allowed as a local variable name",
+        "duplicate_variable_name" => "duplicate variable name %{name}",
+        "duplicate_pattern_key"  => "duplicate hash pattern key %{name}",
+
+        // NOTE reported by the lexer, see Literal::buffer_string
+        "invalid_encoding"       => "literal contains escape sequences incompatible with UTF-8",
+
+        // NOTE not in whitequark/parser, which raises EncodingError or leaves these to ruby
+        "unknown_encoding"       => "unknown encoding name: %{name}",
+        "invalid_byte_sequence"  => "invalid byte sequence in %{encoding}",
+        "read_error"             => "cannot read source: %{message}",
+        "magic_comment_ignored"  => "`%{name}' is ignored after any tokens",
+        "invalid_magic_value"    => "invalid value for %{name}: %{value}",
+
+        // NOTE not in whitequark/parser, which has a parser for each ruby version instead, see ParserOptions
+        "unavailable_in_version" => "%{feature} is not available in Ruby %{version}",
+
+        // NOTE not in whitequark/parser, reported by the parts not ported yet
+        "unimplemented"          => "%{feature} is not implemented yet",
+
+        _ => { panic!("no message for diagnostic reason {:?}", reason); }
+    }
+}
@@ -0,0 +1,149 @@
+// based on
+// https://github.com/whitequark/parser/blob/2a73841d6da04a5ab9bd270561165fd766722d43/lib/parser/diagnostic.rb
+
+use std::collections::HashMap;
+use std::fmt;
+
+use source::range::Range;
+use source::buffer::SourceBuffer;
+
+pub mod messages;
+
+//     ##
+//     # Collection of the available diagnostic levels.
+//     #
+//     # @return [Array]
+//     #
+//     LEVELS = [:note, :warning, :error, :fatal].freeze
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Note,
+    Warning,
+    Error,
+    Fatal,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let level = match *self {
+            Level::Note => "note",
+            Level::Warning => "warning",
+            Level::Error => "error",
+            Level::Fatal => "fatal",
+        };
+        write!(f, "{}", level)
+    }
+}
+
+//   class Diagnostic
+//     attr_reader :level
+//     attr_reader :reason
+//     attr_reader :arguments
+//     attr_reader :location
+//     attr_reader :highlights
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub reason: &'static str,
+    pub arguments: HashMap<&'static str, String>,
+    pub range: Range,
+}
+
+impl Diagnostic {
+    //     def initialize(level, reason, arguments, location, highlights=[])
+    //       unless LEVELS.include?(level)
+    //         raise ArgumentError,
+    //               "Diagnostic#level must be one of #{LEVELS.join(', ')}; " \
+    //               "#{level.inspect} provided."
+    //       end
+    //       raise 'Expected a location' unless location
+    //
+    //       @level       = level
+    //       @reason      = reason
+    //       @arguments   = (arguments || {}).dup.freeze
+    //       @location    = location
+    //       @highlights  = highlights.dup.freeze
+    //
+    //       freeze
+    //     end
+    pub fn new(level: Level, reason: &'static str, arguments: HashMap<&'static str, String>, range: Range) -> Diagnostic {
+        Diagnostic { level, reason, arguments, range }
+    }
+
+    //     def message
+    //       MESSAGES[@reason] % @arguments
+    //     end
+    pub fn message(&self) -> String {
+        let mut message = String::from(messages::message_for(self.reason));
+
+        for (name, value) in self.arguments.iter() {
+            message = message.replace(&format!("%{{{}}}", name), value);
+        }
+
+        message
+    }
+
+    //     def render
+    //       if @location.line == @location.last_line || @location.is?("\n")
+    //         ["#{@location}: #{@level}: #{message}"] + render_line(@location)
+    //       else
+    //         ...
+    //       end
+    //     end
+    //
+    // e.g.
+    //   (string):1:5: error: unexpected token $end
+    //   (string):1: foo +
+    //   (string):1:     ^
+    //
+    // TODO multi-line ranges are rendered as if they end at the end of the first line
+    pub fn render(&self, source_buffer: &SourceBuffer) -> Vec<String> {
+        let (line, column) = source_buffer.decompose_position(self.range.begin_pos);
+
+        let mut rendered = vec![
+            format!("{}:{}:{}: {}: {}", source_buffer.name, line, column, self.level, self.message())
+        ];
+        rendered.append(&mut self.render_line(source_buffer, line, column));
+
+        rendered
+    }
+
+    //     def render_line(range, ellipsis=false, range_end=false)
+    //       source_line    = range.source_line
+    //       highlight_line = ' ' * source_line.length
+    //       ...
+    //       if range.is?("\n")
+    //         highlight_line += "^"
+    //       else
+    //         if !range_end && range.size >= 1
+    //           highlight_line[range.column_range] = '^' + '~' * (range.size - 1)
+    //         else
+    //           highlight_line[range.column_range] = '~' * range.size
+    //         end
+    //       end
+    //       ...
+    //       [source_line, highlight_line].
+    //         map { |line| "#{range.source_buffer.name}:#{range.line}: #{line}" }
+    //     end
+    fn render_line(&self, source_buffer: &SourceBuffer, line: usize, column: usize) -> Vec<String> {
+        let source_line = source_buffer.source_line(line);
+        let line_range = source_buffer.line_range(line);
+
+        // clip the range to the current line, but always show at least one `^`
+        let end_pos = if self.range.end_pos > line_range.end_pos { line_range.end_pos } else { self.range.end_pos };
+
+        // NOTE column and range are in bytes, the highlight is aligned by chars
+        let chars_count = |begin_pos: usize, end_pos: usize| {
+            source_buffer.source().get(begin_pos..end_pos).map_or(end_pos - begin_pos, |slice| slice.chars().count())
+        };
+        let indent = chars_count(line_range.begin_pos, line_range.begin_pos + column - 1);
+        let size = if end_pos > self.range.begin_pos { chars_count(self.range.begin_pos, end_pos) } else { 1 };
+
+        let highlight_line = format!("{}^{}", " ".repeat(indent), "~".repeat(size - 1));
+
+        vec![
+            format!("{}:{}: {}", source_buffer.name, line, source_line),
+            format!("{}:{}: {}", source_buffer.name, line, highlight_line),
+        ]
+    }
+}
@@ -18,10 +18,11 @@ pub struct Action {
 //     }
 // }
 
+// NOTE wrap the pattern, so `^` anchors every alternative of patterns like `a|b`
 macro_rules! action_with_literal {
     ($pattern_literal:expr, $procedure:expr) => {
         box Action {
-            regex: Regex::new( &format!(r"^{}", $pattern_literal) ).unwrap(),
+            regex: Regex::new( &format!(r"^(?:{})", $pattern_literal) ).unwrap(),
             procedure: $procedure
         }
     };
@@ -0,0 +1,181 @@
+// NOTE
+// whitequark's parser is LALR, it never goes back to the tokens it has shifted,
+// ours backtracks, and a failed alternative may have made lexer lex tokens under the wrong state,
+// so lexer hands out a snapshot of everything the later tokens depend on, which it can go back to, see Parser::rewind
+// 
+// lexer may lex many tokens at once, like the words of a %w[], the ones before each of them share a snapshot, see next
+
+use std::rc::Rc;
+
+use lexer::Lexer;
+use lexer::LexingState;
+use lexer::stack_state::StackState;
+use lexer::literal::Literal;
+
+use shared::static_env::StaticEnv;
+
+use parser::token::TokenWithRange;
+
+use diagnostic::Diagnostic;
+
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    state: Rc<State>,
+    // how many of the tokens lexed at once had been handed out
+    tokens_p: usize,
+}
+
+#[derive(Debug)]
+struct State {
+    current_state: LexingState,
+    next_state: Option<LexingState>,
+    calling_state: Option<LexingState>,
+    stack: Vec<LexingState>,
+
+    p: isize,
+    ts: Option<usize>,
+    te: Option<usize>,
+    tm: usize,
+    entering_machine: bool,
+
+    cond: StackState,
+    cmdarg: StackState,
+    cond_stack: Vec<StackState>,
+    cmdarg_stack: Vec<StackState>,
+
+    literal_stack: Vec<Literal>,
+
+    paren_nest: usize,
+    lambda_stack: Vec<usize>,
+
+    herebody_s: Option<usize>,
+    newline_s: usize,
+    eq_begin_s: usize,
+    dedent_level: Option<usize>,
+    command_state: bool,
+    in_kwarg: bool,
+
+    // NOTE a failed alternative may have declared variables, see Parser::update_static_env
+    static_env_log_len: Option<usize>,
+
+    data_s: Option<usize>,
+
+    tokens: Rc<Vec<TokenWithRange>>,
+
+    // comments and diagnostics are only ever appended, how many there were is enough
+    comments_len: usize,
+    diagnostics_len: usize,
+}
+
+impl Checkpoint {
+    // the checkpoint before the token after the next one, when that has been lexed along with the next one,
+    // and lexer isn't changed in between
+    pub fn next(&self) -> Checkpoint {
+        Checkpoint { state: self.state.clone(), tokens_p: self.tokens_p + 1 }
+    }
+}
+
+impl Lexer {
+    pub fn checkpoint(&self) -> Checkpoint {
+        let state = State {
+            current_state: self.current_state.clone(),
+            next_state: self.next_state.clone(),
+            calling_state: self.calling_state.clone(),
+            stack: self.stack.clone(),
+
+            p: self.input_stream.p,
+            ts: self.input_stream.ts,
+            te: self.input_stream.te,
+            tm: self.input_stream.tm,
+            entering_machine: self.input_stream.entering_machine,
+
+            cond: self.cond.clone(),
+            cmdarg: self.cmdarg.clone(),
+            cond_stack: self.cond_stack.clone(),
+            cmdarg_stack: self.cmdarg_stack.clone(),
+
+            literal_stack: self.literal_stack.clone(),
+
+            paren_nest: self.paren_nest,
+            lambda_stack: self.lambda_stack.clone(),
+
+            herebody_s: self.herebody_s,
+            newline_s: self.newline_s,
+            eq_begin_s: self.eq_begin_s,
+            dedent_level: self.dedent_level,
+            command_state: self.command_state,
+            in_kwarg: self.in_kwarg,
+
+            static_env_log_len: self.static_env.as_ref().map(StaticEnv::log_len),
+
+            data_s: self.data_s,
+
+            tokens: self.tokens.clone(),
+
+            comments_len: self.comments.len(),
+            diagnostics_len: self.diagnostics.len(),
+        };
+
+        Checkpoint { state: Rc::new(state), tokens_p: self.tokens_p }
+    }
+
+    // go back to where the checkpoint was taken, tokens from there on will be lexed again,
+    // returns the diagnostics reported since
+    //
+    // NOTE
+    // a lexer reading from a reader may have dropped the source there already, see InputStream::discard_before,
+    // it can only go back as far as it holds
+    pub fn rewind(&mut self, checkpoint: &Checkpoint) -> Vec<Diagnostic> {
+        let tokens_p = checkpoint.tokens_p;
+        let checkpoint = &checkpoint.state;
+
+        let heredoc_positions = checkpoint.literal_stack.iter()
+            .flat_map(|literal| literal.heredoc_e.into_iter().chain(literal.saved_herebody_s))
+            .chain(checkpoint.herebody_s);
+        let earliest_pos = heredoc_positions.fold(checkpoint.p.max(0) as usize, |earliest_pos, pos| earliest_pos.min(pos));
+        assert!(self.input_stream.holds(earliest_pos), "can't rewind lexer to the source it has dropped");
+
+        self.current_state = checkpoint.current_state.clone();
+        self.next_state = checkpoint.next_state.clone();
+        self.calling_state = checkpoint.calling_state.clone();
+        self.stack = checkpoint.stack.clone();
+
+        self.input_stream.p = checkpoint.p;
+        self.input_stream.ts = checkpoint.ts;
+        self.input_stream.te = checkpoint.te;
+        self.input_stream.tm = checkpoint.tm;
+        self.input_stream.entering_machine = checkpoint.entering_machine;
+
+        self.cond = checkpoint.cond.clone();
+        self.cmdarg = checkpoint.cmdarg.clone();
+        self.cond_stack = checkpoint.cond_stack.clone();
+        self.cmdarg_stack = checkpoint.cmdarg_stack.clone();
+
+        self.literal_stack = checkpoint.literal_stack.clone();
+
+        self.paren_nest = checkpoint.paren_nest;
+        self.lambda_stack = checkpoint.lambda_stack.clone();
+
+        self.herebody_s = checkpoint.herebody_s;
+        self.newline_s = checkpoint.newline_s;
+        self.eq_begin_s = checkpoint.eq_begin_s;
+        self.dedent_level = checkpoint.dedent_level;
+        self.command_state = checkpoint.command_state;
+        self.in_kwarg = checkpoint.in_kwarg;
+
+        if let (Some(static_env), Some(log_len)) = (self.static_env.as_mut(), checkpoint.static_env_log_len) {
+            static_env.rewind(log_len);
+        }
+
+        self.data_s = checkpoint.data_s;
+
+        self.tokens = checkpoint.tokens.clone();
+        self.tokens_p = tokens_p;
+
+        self.comments.truncate(checkpoint.comments_len);
+        let dropped_diagnostics = self.diagnostics.split_off(checkpoint.diagnostics_len);
+        self.fatal_diagnostic_index = self.fatal_diagnostic_index.filter(|&index| index < checkpoint.diagnostics_len);
+
+        dropped_diagnostics
+    }
+}
@@ -0,0 +1,99 @@
+// based on
+// https://github.com/whitequark/parser/blob/2a73841d6da04a5ab9bd270561165fd766722d43/lib/parser/lexer/dedenter.rb
+
+// module Parser
+//   class Lexer::Dedenter
+//     def initialize(dedent_level)
+//       @dedent_level = dedent_level
+//       @at_line_begin = true
+//       @indent_level  = 0
+//     end
+pub struct Dedenter {
+    dedent_level: usize,
+    at_line_begin: bool,
+    indent_level: usize,
+}
+
+impl Dedenter {
+    pub fn new(dedent_level: usize) -> Dedenter {
+        Dedenter {
+            dedent_level,
+            at_line_begin: true,
+            indent_level: 0,
+        }
+    }
+
+    //     def dedent(string)
+    //       space_begin = space_end = offset = 0
+    //       last_index  = string.length - 1
+    //       string.chars.each_with_index do |char, index|
+    //         if @at_line_begin
+    //           if char == ?\n || @indent_level >= @dedent_level
+    //             string.slice!(space_begin...space_end)
+    //             offset += space_end - space_begin
+    //             @at_line_begin = false
+    //           end
+    //
+    //           case char
+    //           when ?\s
+    //             @indent_level += 1
+    //             space_end += 1
+    //           when ?\t
+    //             @indent_level += 8 - @indent_level % 8
+    //             space_end += 1
+    //           end
+    //         end
+    //
+    //         if char == ?\n && index == last_index
+    //           @at_line_begin = true
+    //           @indent_level  = 0
+    //           space_begin = space_end = index - offset + 1
+    //         end
+    //       end
+    //
+    //       if @at_line_begin
+    //         string.slice!(space_begin, space_end)
+    //       end
+    //
+    //       nil
+    //     end
+    //
+    // NOTE builds a new string instead of slicing in place
+    // 
+    // NOTE a newline only starts a line at the end of the chunk,
+    // the ones before it come from escapes like `\n`, whose next line isn't dedented
+    pub fn dedent(&mut self, string: &str) -> String {
+        let mut dedented = String::new();
+
+        let last_index = string.chars().count().saturating_sub(1);
+        for (index, char) in string.chars().enumerate() {
+            if self.at_line_begin {
+                if char == '\n' || self.indent_level >= self.dedent_level {
+                    self.at_line_begin = false;
+                } else {
+                    match char {
+                        ' ' => { self.indent_level += 1; continue; },
+                        '\t' => { self.indent_level += 8 - self.indent_level % 8; continue; },
+                        _ => { self.at_line_begin = false; }
+                    }
+                }
+            }
+
+            dedented.push(char);
+
+            if char == '\n' && index == last_index {
+                self.at_line_begin = true;
+                self.indent_level = 0;
+            }
+        }
+
+        dedented
+    }
+
+    //     def interrupt
+    //       @at_line_begin = false
+    //     end
+    pub fn interrupt(&mut self) {
+        self.at_line_begin = false;
+    }
+}
@@ -1,11 +1,34 @@
-use regex::Regex;
+use std::collections::HashMap;
+use std::io::{Read, ErrorKind};
+use std::str;
 
 use lexer::action::Action;
+use lexer::machines::Machine;
 
 use parser::token::TokenString;
+use source::range::Range;
+use diagnostic::{Diagnostic, Level};
+
+// bytes asked from the reader at a time
+const CHUNK_SIZE: usize = 8192;
+
+// how far beyond the current position the window reaches before matching, see longest_matching_action
+const LOOKAHEAD: usize = 8192;
 
 pub struct InputStream {
+    // NOTE
+    // the window of source from byte offset `offset` on, which is the whole source unless it's read from a reader,
+    // positions (p, ts, te, etc.) are always offsets into the whole source, see local_pos
     string: String,
+    offset: usize,
+
+    // None once it's exhausted, or if there's none at all
+    reader: Option<Box<Read>>,
+    is_streamed: bool,
+    // bytes of a char which the last chunk cut in half
+    pending_bytes: Vec<u8>,
+    // the fatal diagnostic from reading, for lexer to pick up, see take_error
+    error: Option<Diagnostic>,
 
     // ripped from whitequark/parser
     // 
@@ -24,6 +47,11 @@ pub struct InputStream {
     // 
     // TODO NOTE p, ts, te are actually more complex
     // 
+    // NOTE
+    // p, ts, te are byte offsets into string, ts and te always lie on char boundaries,
+    // so ranges made of them can slice the source directly,
+    // p + 1 is where the next matching starts, see starting_pos
+    // 
     pub p: isize,
     pub ts: Option<usize>,
     pub te: Option<usize>,
@@ -37,6 +65,12 @@ impl InputStream {
     pub fn new(string: String) -> InputStream {
         InputStream {
             string,
+            offset: 0,
+
+            reader: None,
+            is_streamed: false,
+            pending_bytes: vec![],
+            error: None,
 
             p: 0,
             ts: None,
@@ -47,69 +81,95 @@ impl InputStream {
         }
     }
 
+    // the source is read chunk by chunk, and decoded as UTF-8
+    pub fn from_reader(reader: Box<Read>) -> InputStream {
+        let mut input_stream = InputStream::new(String::new());
+        input_stream.reader = Some(reader);
+        input_stream.is_streamed = true;
+        input_stream.fill_to(LOOKAHEAD);
+        input_stream
+    }
+
     // starting from pos
-    pub fn longest_matching_action(&mut self, actions: &Vec<Box<Action>>) -> Option<Box<Action>> {
+    // 
+    // the longest match wins, the first listed action wins a tie, like ragel does
+    // 
+    // NOTE
+    // when reading from a reader, the window reaches at least LOOKAHEAD bytes beyond starting_pos,
+    // and whenever a candidate match runs into the end of window, which may be where `$` matched,
+    // the window is extended and the matching starts over,
+    // so a token is only cut short if some longer alternative of it exceeds LOOKAHEAD
+    pub fn longest_matching_action<'a>(&mut self, machine: &'a Machine) -> Option<&'a Action> {
 
-        println!("finding longest matching action..., current p: {}", self.p);
-        // println!("actions: {:?}", actions);
+        let starting_pos = self.starting_pos();
+        self.fill_to(starting_pos + LOOKAHEAD);
 
         // TODO not that elegant, use Option<Action> instead of
-        let mut longest_matched_action_i: Option<usize> = None;
-        let mut longest_matched_action_len = -1; // init as -1, since there will be matching result with len 0 (c_eof)
+        let mut longest_matched_action_i: Option<usize>;
+        let mut longest_matched_action_len: Option<usize>; // there will be matching result with len 0 (c_eof)
 
-        let starting_pos = ( if self.entering_machine { self.p } else { self.p + 1 } ) as usize;
-        let sliced_string: String = self.string.chars().skip(starting_pos).collect();
+        loop {
+            let local_starting_pos = self.local_pos(starting_pos);
+            let sliced_string = &self.string[local_starting_pos..];
+            let mut reaches_window_end = false;
 
-        for (i, action) in actions.iter().enumerate() {
+            longest_matched_action_i = None;
+            longest_matched_action_len = None;
 
-            // println!("matching action with regex {:?}", &action.regex);
+            // NOTE matches() lists the matching actions in the order of the machine
+            for i in machine.regex_set.matches(sliced_string).iter() {
+                let len = match machine.actions[i].regex.find(sliced_string) {
+                    Some(match_) => match_.end(),
+                    None => { continue; }
+                };
 
-            match self.match_action_starting_from_pos(&sliced_string, &action.regex) {
-                None => {},
-                Some(len) => {
-                    let len = len as isize;
+                if len == sliced_string.len() { reaches_window_end = true; }
 
-                    // println!("matched something, length: {}, regex: {:?}", len, &action.regex);
-
-                    if ( len > longest_matched_action_len ) {
-                        longest_matched_action_len = len as isize;
-                        longest_matched_action_i = Some(i);
-                    }
+                if longest_matched_action_len.map_or(true, |longest_len| len > longest_len) {
+                    longest_matched_action_len = Some(len);
+                    longest_matched_action_i = Some(i);
                 }
             };
-        };
 
-        println!("longest_matched_action_len: {}", longest_matched_action_len);
-        // println!("longest_matched_action_i: {:?}", longest_matched_action_i);
+            if !reaches_window_end || !self.fill() { break; }
+        }
+
+        if let Some(len) = longest_matched_action_len {
+            self.fill_to(starting_pos + len + LOOKAHEAD);
+        }
+
+        match ( longest_matched_action_i, longest_matched_action_len ) {
+            ( Some(i), Some(len) ) => {
+                let len = len as isize;
 
-        match longest_matched_action_i {
-            None => { return None; },
-            Some(i) => {
                 // update p, ts, te
                 if self.entering_machine {
                     self.ts = Some(self.p as usize);
-                    self.p += longest_matched_action_len - 1;
+                    self.p += len - 1;
                     self.te = Some((self.p + 1) as usize);
                 } else {
                     self.ts = Some((self.p + 1) as usize);
-                    self.p += longest_matched_action_len;
+                    self.p += len;
                     self.te = Some((self.p + 1) as usize);
                 }
 
-                println!("matched token: {:?}", self.current_token() );
-                // println!("current ts {} p {} te {}", self.ts.unwrap(), self.p, self.te.unwrap() );
-
-                return Some(actions.get(i).unwrap().clone());
-            }
+                return Some(&machine.actions[i]);
+            },
+            _ => { return None; }
         }
     }
 
+    // where the next matching will start from
+    pub fn starting_pos(&self) -> usize {
+        ( if self.entering_machine { self.p } else { self.p + 1 } ) as usize
+    }
+
     // TODO maybe dont need to use a Option for current_token, ts, te
     // TODO renaming current_token -> current_slice
     pub fn current_token(&self) -> Option<String> {
         match ( self.ts, self.te ) {
             ( Some(ts), Some(te) ) => {
-                Some( self.string.chars().skip(ts).take(te - ts).collect() )
+                Some( self.slice_from_range(ts, te) )
             },
             _ => None
         }
@@ -120,8 +180,26 @@ impl InputStream {
         TokenString::from(self.current_token().unwrap())
     }
 
+    // NOTE
+    // positions are byte offsets, while slices like `tok(p, p + 1)` are meant to hold whole chars,
+    // so the end is moved forward to the next char boundary, and both ends are clipped to the window
     pub fn slice_from_range(&self, start: usize, end: usize) -> String {
-        self.string.chars().skip(start).take(end - start).collect()
+        let start = self.local_pos(start);
+        let mut end = self.local_pos(end);
+        if end < start { end = start; }
+        while !self.string.is_char_boundary(end) { end += 1; }
+
+        String::from(&self.string[start..end])
+    }
+
+    // length in bytes of the char at pos, 1 beyond the string, like the c_eof ranges
+    pub fn char_len_at(&self, pos: usize) -> usize {
+        if pos < self.offset { return 1; }
+
+        match self.string.get(pos - self.offset..).and_then(|rest| rest.chars().next()) {
+            Some(c) => c.len_utf8(),
+            None => 1
+        }
     }
 
     pub fn token_string_from_range(&self, ts: usize, te: usize) -> TokenString {
@@ -136,7 +214,14 @@ impl InputStream {
         // println!("=== debugging fhold invoking: p {:?} ts {:?} te {:?}", self.p, self.ts, self.te );
 
         // NOTE assuming original p is never 0
-        self.p -= 1;
+        // 
+        // the held char, i.e. the one before p + 1, may take more than one byte
+        let next_pos = self.local_pos(( self.p + 1 ) as usize);
+        let char_len = match self.string.get(..next_pos).and_then(|before| before.chars().next_back()) {
+            Some(c) => c.len_utf8(),
+            None => 1
+        };
+        self.p -= char_len as isize;
 
         // println!("=== debugging fhold invoking: p {:?} ts {:?} te {:?}", self.p, self.ts, self.te );
     }
@@ -163,27 +248,131 @@ impl InputStream {
         }
     }
 
-    fn match_action_starting_from_pos(&mut self, current_slice: &String, regex: &Regex) -> Option<usize> {
+    // position in string of the given position in source, clipped to the window
+    fn local_pos(&self, pos: usize) -> usize {
+        if pos < self.offset { return 0; }
 
-        // println!("\n===\n    matching action starting from pos");
+        let pos = pos - self.offset;
+        if pos > self.string.len() { self.string.len() } else { pos }
+    }
+
+    pub fn starts_with(&self, c: char) -> bool {
+        self.offset == 0 && self.string.starts_with(c)
+    }
 
-        // println!("    current entering machine: {}", self.entering_machine);
-        // println!("    current starting pos: {}", starting_pos);
-        // println!("    current sliced string: {}, (len: {})", sliced_string, sliced_string.len());
-        // println!("    regex: {:?}", regex);
+    // nothing but the byte order mark before pos on its line
+    // NOTE the char before pos is kept, see discard_before
+    pub fn is_line_begin(&self, pos: usize) -> bool {
+        if pos == 0 { return true; }
+        if pos == '\u{feff}'.len_utf8() && self.starts_with('\u{feff}') { return true; }
 
-        let captures = regex.captures(current_slice);
-        match captures {
-            None => None,
-            Some(capture) => {
-                let match_ = capture.get(0).unwrap();
-                let matched_str = String::from(match_.as_str());
-                // println!("    ***** matched str: {:?}", matched_str);
-                // println!("    DEBUGGING CAPTURE: capture: {:?}", capture);
-                return Some(matched_str.len());
+        pos > self.offset && self.string.as_bytes().get(pos - self.offset - 1) == Some(&b'\n')
+    }
+
+    pub fn holds(&self, pos: usize) -> bool {
+        pos >= self.offset
+    }
+
+    // how many bytes of source are held now
+    pub fn window_len(&self) -> usize {
+        self.string.len()
+    }
+
+    // read chunks until the window reaches pos, or the reader is exhausted
+    fn fill_to(&mut self, pos: usize) {
+        while self.offset + self.string.len() < pos {
+            if !self.fill() { break; }
+        }
+    }
+
+    // read one more chunk, returns false if there's nothing left to read
+    // 
+    // NOTE
+    // like SourceBuffer::from_bytes, an invalid byte or a failed read is fatal,
+    // the source ends there
+    fn fill(&mut self) -> bool {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let read_len = match self.reader {
+            None => { return false; },
+            Some(ref mut reader) => {
+                loop {
+                    match reader.read(&mut chunk) {
+                        Ok(read_len) => { break Ok(read_len); },
+                        Err(ref error) if error.kind() == ErrorKind::Interrupted => { continue; },
+                        Err(error) => { break Err(error); }
+                    }
+                }
+            }
+        };
+
+        let end_pos = self.offset + self.string.len();
+
+        let read_len = match read_len {
+            Ok(read_len) => read_len,
+            Err(error) => {
+                let mut arguments = HashMap::new();
+                arguments.insert("message", error.to_string());
+                self.stop_reading(Diagnostic::new(Level::Fatal, "read_error", arguments, Range::new(end_pos, end_pos)));
+                return false;
+            }
+        };
+
+        let is_exhausted = read_len == 0;
+
+        let mut bytes = ::std::mem::replace(&mut self.pending_bytes, vec![]);
+        bytes.extend_from_slice(&chunk[..read_len]);
+
+        let valid_len = match str::from_utf8(&bytes) {
+            Ok(_) => bytes.len(),
+            // a char cut in half, the rest of it comes with the next chunk
+            Err(ref error) if error.error_len().is_none() && !is_exhausted => error.valid_up_to(),
+            Err(ref error) => {
+                let valid_len = error.valid_up_to();
+                self.string.push_str(str::from_utf8(&bytes[..valid_len]).unwrap());
+
+                let position = end_pos + valid_len;
+                let mut arguments = HashMap::new();
+                arguments.insert("encoding", String::from("UTF-8"));
+                self.stop_reading(Diagnostic::new(Level::Fatal, "invalid_byte_sequence", arguments, Range::new(position, position + 1)));
+                return valid_len > 0;
             }
+        };
+
+        self.string.push_str(str::from_utf8(&bytes[..valid_len]).unwrap());
+        self.pending_bytes = bytes[valid_len..].to_vec();
+
+        if is_exhausted {
+            self.reader = None;
+            return false;
         }
 
+        true
     }
 
+    fn stop_reading(&mut self, diagnostic: Diagnostic) {
+        self.reader = None;
+        self.pending_bytes = vec![];
+        self.error = Some(diagnostic);
+    }
+
+    // the error, once everything read before it is consumed
+    pub fn take_error(&mut self) -> Option<Diagnostic> {
+        if self.starting_pos() < self.offset + self.string.len() { return None; }
+
+        self.error.take()
+    }
+
+    // drop the source before pos, which lexer will never come back to,
+    // along with the char right before it, for hold_current_char
+    // 
+    // NOTE only once there's a chunk worth of it, not to move the rest of string over and over
+    pub fn discard_before(&mut self, pos: usize) {
+        if !self.is_streamed || pos < self.offset + CHUNK_SIZE { return; }
+
+        let mut discard_len = self.local_pos(pos).saturating_sub(4);
+        while !self.string.is_char_boundary(discard_len) { discard_len -= 1; }
+
+        self.string.drain(..discard_len);
+        self.offset += discard_len;
+    }
 }
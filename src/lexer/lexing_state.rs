@@ -3,6 +3,7 @@ use std::str::FromStr;
 // ORIGINAL
 // 
 // :line_begin    => lex_en_line_begin,
+// :line_comment  => lex_en_line_comment,
 // :expr_dot      => lex_en_expr_dot,
 // :expr_fname    => lex_en_expr_fname,
 // :expr_value    => lex_en_expr_value,
@@ -23,6 +24,7 @@ use std::str::FromStr;
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum LexingState {
     LineBegin,
+    LineComment,
     ExprDot,
     ExprFname,
     ExprValue,
@@ -47,6 +49,7 @@ pub enum LexingState {
     PlainBackslashDelimited,
     InterpBackslashDelimitedWords,
     PlainBackslashDelimitedWords,
+    RegexpModifiers,
 
     // TODO
     // not original states in lexer.rl,
@@ -61,6 +64,7 @@ impl FromStr for LexingState {
     fn from_str(s: &str) -> Result<LexingState, ()> {
         match s {
             "line_begin" => Ok(LexingState::LineBegin),
+            "line_comment" => Ok(LexingState::LineComment),
             "expr_dot" => Ok(LexingState::ExprDot),
             "expr_fname" => Ok(LexingState::ExprFname),
             "expr_value" => Ok(LexingState::ExprValue),
@@ -82,6 +86,7 @@ impl FromStr for LexingState {
             "plain_backslash_delimited" => Ok(LexingState::PlainBackslashDelimited),
             "interp_backslash_delimited_words" => Ok(LexingState::InterpBackslashDelimitedWords),
             "plain_backslash_delimited_words" => Ok(LexingState::PlainBackslashDelimitedWords),
+            "regexp_modifiers" => Ok(LexingState::RegexpModifiers),
 
             "expr_variable" => Ok(LexingState::ExprVariable),
             "leading_dot" => Ok(LexingState::LeadingDot),
@@ -6,29 +6,41 @@
 // module Parser
 
 //   class Lexer::Literal
+//     attr_reader   :heredoc_e, :str_s, :dedent_level
 //     attr_accessor :saved_herebody_s
 
 // end
 
+use std::collections::HashMap;
+
 use lexer::Lexer;
 use lexer::LexingState;
-use parser::token::Token;
+use source::range::Range;
+use parser::token::{Token, TokenWithRange};
+use diagnostic::Level;
 
 #[derive(Debug, Clone)]
 pub struct Literal {
     nesting: usize,
 
     start_tok: Token,
-    interpolate: bool,
+    pub interpolate: bool,
 
     start_delim: Option<String>,
     end_delim: Option<String>,
 
-    heredoc_e: Option<usize>,
+    pub heredoc_e: Option<usize>,
     indent: bool,
     label_allowed: bool,
 
     dedent_body: bool,
+    pub dedent_level: Option<usize>,
+
+    // @herebody_s of lexer, saved when entering an interpolation
+    pub saved_herebody_s: Option<usize>,
+    // where the bodies of the heredocs opened in the interpolations on the current line of a heredoc end,
+    // which is where lexing goes on after that line
+    pub interp_herebody_s: Option<usize>,
 
     space_emitted: bool,
 
@@ -39,18 +51,23 @@ pub struct Literal {
 
     // # Start of the string type specifier.
     // starting point of the literal, normarlly related to @ts
-    str_s: usize,
+    pub str_s: usize,
 
     interp_braces: usize,
 
-    buffer: String,
+    // NOTE
+    // a byte string, since escapes like \xff aren't valid utf-8 by themselves,
+    // see buffer_string
+    buffer: Vec<u8>,
     buffer_s: Option<usize>,
     buffer_e: Option<usize>,
 
     // TODO NOTE
-    tokens_to_emit: Vec<Token>,
+    tokens_to_emit: Vec<TokenWithRange>,
+    // ranges of emitted strings which aren't valid utf-8, see buffer_string
+    invalid_encodings: Vec<Range>,
 
-    is_words: bool,
+    pub is_words: bool,
 }
 
 impl Literal {
@@ -63,12 +80,11 @@ impl Literal {
         str_type: String,
         delimiter: String,
         str_s: usize,
-        heredoc_e: Option<usize>, // TODO
+        heredoc_e: Option<usize>,
         indent: bool,
-        dedent_body: bool, // TODO
+        dedent_body: bool,
         label_allowed: bool
     ) -> Literal {
-        println!("creating new literal with: str_type: {:?}", str_type);
         // TODO
         //       # DELIMITERS and TYPES are hashes with keys encoded in binary.
         //       # Coerce incoming data to the same encoding.
@@ -139,8 +155,7 @@ impl Literal {
         //       @monolithic  = (@start_tok == :tSTRING_BEG  &&
         //                       %w(' ").include?(str_type) &&
         //                       !heredoc?)
-        // TODO handle heredoc
-        let monolithic = ( start_tok.clone() == Token::T_STRING_BEG && ( &str_type == "'" || &str_type == "\"" ) );
+        let monolithic = ( start_tok.clone() == Token::T_STRING_BEG && ( &str_type == "'" || &str_type == "\"" ) && heredoc_e.is_none() );
 
         let mut literal = Literal {
             nesting: 1,
@@ -158,9 +173,9 @@ impl Literal {
             end_delim: match delimiter.as_ref() {
                 "(" => Some(String::from(")")),
                 "[" => Some(String::from("]")),
-                "{" => Some(String::from("}}")),
+                "{" => Some(String::from("}")),
                 "<" => Some(String::from(">")),
-                _ => Some(delimiter)
+                _ => Some(delimiter.clone())
             },
 
             heredoc_e,
@@ -168,20 +183,27 @@ impl Literal {
             label_allowed,
 
             dedent_body,
+            dedent_level: None,
+
+            saved_herebody_s: None,
+            interp_herebody_s: None,
 
             space_emitted: true,
             monolithic,
 
-            str_type,
+            //       # Capture opening delimiter in percent-literals.
+            //       @str_type += delimiter if @str_type.start_with?('%'.freeze)
+            str_type: if str_type.starts_with("%") { str_type.clone() + &delimiter } else { str_type.clone() },
             str_s,
 
             interp_braces: 0,
 
-            buffer: String::from(""),
+            buffer: vec![],
             buffer_s: None,
             buffer_e: None,
 
             tokens_to_emit: vec![],
+            invalid_encodings: vec![],
 
             is_words:   start_tok.clone() == Token::T_WORDS_BEG ||
                         start_tok.clone() == Token::T_QWORDS_BEG ||
@@ -189,8 +211,6 @@ impl Literal {
                         start_tok.clone() == Token::T_QSYMBOLS_BEG,
         };
 
-        println!("creating new literal: {:?}", literal.clone());
-
         // emit_start_tok unless @monolithic
         if !monolithic { literal.emit_start_tok(); }
 
@@ -211,11 +231,12 @@ impl Literal {
     //     def regexp?
     //       type == :tREGEXP_BEG
     //     end
+    pub fn is_regexp(&self) -> bool { self.start_tok == Token::T_REGEXP_BEG }
 
     //     def heredoc?
     //       !!@heredoc_e
     //     end
-    fn is_heredoc(&self) -> bool { self.heredoc_e.is_some() }
+    pub fn is_heredoc(&self) -> bool { self.heredoc_e.is_some() }
 
     //     def backslash_delimited?
     //       @end_delim == '\\'.freeze
@@ -235,6 +256,15 @@ impl Literal {
     //         ['\\'.freeze, @start_delim, @end_delim].include?(character)
     //       end
     //     end
+    pub fn munge_escape(&self, character: &str) -> bool {
+        if self.is_words && [" ", "\t", "\x0b", "\r", "\x0c", "\n"].contains(&character) {
+            return true;
+        }
+
+        character == "\\" ||
+            self.start_delim.as_ref().map_or(false, |delim| delim == character) ||
+            self.end_delim.as_ref().map_or(false, |delim| delim == character)
+    }
 
     //     def nest_and_try_closing(delimiter, ts, te, lookahead=nil)
     //       delimiter = coerce_encoding(delimiter)
@@ -276,20 +306,17 @@ impl Literal {
     // original method includes emitting token (and return the token),
     // now we will emit the returned token after invoking
     // and since we dont want to save lexer in Literal,
-    // we have to maintain a `tokens_to_emit : Vec<Token>`,
+    // we have to maintain a `tokens_to_emit : Vec<TokenWithRange>`,
     // 
     // this function return the final_token only
     // and after every time lexer called `nest_and_try_closing`, lexer have to
-    // 1. call `literal.consume_tokens_to_emit` and emit every tokens in manually, and
+    // 1. call `lexer.emit_literal_tokens`, which emits every tokens in manually, and
     // 2. use final_token_to_emit just like the origin return value
     // 
-    pub fn nest_and_try_closing(&mut self, delimiter: String, ts: usize, te: usize, lookahead: Option<String>) -> Option<Token> {
+    pub fn nest_and_try_closing(&mut self, delimiter: String, ts: usize, te: usize, lookahead: Option<String>) -> Option<TokenWithRange> {
         // Some("") -> None
         let lookahead = if (lookahead.is_some() && !lookahead.clone().unwrap().is_empty()) { lookahead } else { None };
 
-        println!("### invoking `nest_and_try_closing`, delimiter: {:?}", delimiter);
-        println!("### lookahead: {:?}", lookahead);
-
         if self.start_delim.is_some() && self.start_delim.clone().unwrap() == delimiter {
             self.nesting += 1;
         } else {
@@ -298,8 +325,6 @@ impl Literal {
             }
         }
 
-        println!("### self.nesting: {}", self.nesting);
-
         if self.nesting == 0 {
             // if words?
             //   extend_space(ts, ts)
@@ -326,17 +351,18 @@ impl Literal {
             // end
             if  lookahead.is_some() &&
                 self.label_allowed &&
-                ( lookahead.clone().unwrap().chars().nth(0).unwrap() == ':' ) &&
-                ( lookahead.clone().unwrap().chars().nth(1).unwrap() != ':' ) {
+                ( lookahead.as_ref().unwrap().starts_with(':') ) &&
+                ( lookahead.as_ref().unwrap().get(1..2) != Some(":") ) {
                     //   # This is a quoted label.
                     self.flush_string();
 
-                    return Some(Token::T_LABEL_END);
+                    return Some((Token::T_LABEL_END, Range::new(ts, te + 1)));
             } else {
                 if self.monolithic {
                     //   # Emit the string as a single token.
                     // let token = );
-                    return Some(Token::T_STRING(self.buffer.clone()));
+                    let range = Range::new(self.str_s, te);
+                    return Some((Token::T_STRING(self.buffer_string(range)), range));
                 } else {
                     //   # If this is a heredoc, @buffer contains the sentinel now.
                     //   # Just throw it out. Lexer flushes the heredoc after each
@@ -345,7 +371,7 @@ impl Literal {
                         self.flush_string();
                     }
 
-                    return(Some(Token::T_STRING_END));
+                    return Some((Token::T_STRING_END, Range::new(ts, te)));
                 }
             }
         }
@@ -353,15 +379,8 @@ impl Literal {
         None
     }
 
-    pub fn consume_tokens_to_emit(&mut self) -> Vec<Token> {
-        let mut tokens_to_emit = vec![];
-
-        loop {
-            if self.tokens_to_emit.len() == 0 { break; }
-            tokens_to_emit.push(self.tokens_to_emit.remove(0));
-        }
-
-        tokens_to_emit
+    pub fn consume_tokens_to_emit(&mut self) -> Vec<TokenWithRange> {
+        self.tokens_to_emit.drain(..).collect()
     }
 
     //     def infer_indent_level(line)
@@ -382,6 +401,23 @@ impl Literal {
     //         end
     //       end
     //     end
+    pub fn infer_indent_level(&mut self, line: &str) {
+        if !self.dedent_body { return; }
+
+        let mut indent_level = 0;
+        for c in line.chars() {
+            match c {
+                ' ' => { indent_level += 1; },
+                '\t' => { indent_level += 8 - indent_level % 8; },
+                _ => {
+                    if self.dedent_level.is_none() || self.dedent_level.unwrap() > indent_level {
+                        self.dedent_level = Some(indent_level);
+                    }
+                    break;
+                }
+            }
+        }
+    }
 
     //     def start_interp_brace
     //       @interp_braces += 1
@@ -408,14 +444,20 @@ impl Literal {
     //       @buffer << string
     //     end
     pub fn extend_string(&mut self, string: String, ts: usize, te: usize) {
-        println!("invoking literal.extend_string, string: {:?}", string);
-
         if self.buffer_s.is_none() { self.buffer_s = Some(ts); }
         self.buffer_e = Some(te);
 
-        self.buffer += &string;
+        self.buffer.extend(string.as_bytes());
+    }
+
+    // NOTE
+    // extend_string for the value of an escape sequence, which may not be valid utf-8 on its own,
+    // e.g. "\xe2\x98\xba" is only valid as a whole
+    pub fn extend_escape(&mut self, escape: Vec<u8>, ts: usize, te: usize) {
+        if self.buffer_s.is_none() { self.buffer_s = Some(ts); }
+        self.buffer_e = Some(te);
 
-        println!("invoked literal.extend_string, now buffer: {:?}", self.buffer);
+        self.buffer.extend(escape);
     }
 
     //     def flush_string
@@ -442,7 +484,9 @@ impl Literal {
         }
 
         if !self.buffer.is_empty() {
-            self.tokens_to_emit.push(Token::T_STRING_CONTENT(self.buffer.clone()));
+            let range = Range::new(self.buffer_s.unwrap(), self.buffer_e.unwrap());
+            let string = self.buffer_string(range);
+            self.tokens_to_emit.push((Token::T_STRING_CONTENT(string), range));
 
             self.clear_buffer();
             self.extend_content();
@@ -474,7 +518,7 @@ impl Literal {
     pub fn extend_space(&mut self, ts: usize, te: usize) {
         self.flush_string();
         if !self.space_emitted {
-            self.tokens_to_emit.push(Token::T_SPACE);
+            self.tokens_to_emit.push((Token::T_SPACE, Range::new(ts, te)));
             self.space_emitted = true;
         }
     }
@@ -512,23 +556,37 @@ impl Literal {
     //       @buffer_e = nil
     //     end
     fn clear_buffer(&mut self) {
-        self.buffer = String::from("");
+        self.buffer = vec![];
 
         self.buffer_s = None;
         self.buffer_e = None;
     }
 
+    // NOTE
+    // whitequark keeps the bytes in the token and reports :invalid_encoding in the builder (string_value),
+    // tokens here carry Strings, so the range is saved and reported by the lexer instead, see emit_literal_tokens
+    fn buffer_string(&mut self, range: Range) -> String {
+        match String::from_utf8(self.buffer.clone()) {
+            Ok(string) => string,
+            Err(error) => {
+                self.invalid_encodings.push(range);
+                String::from_utf8_lossy(error.as_bytes()).into_owned()
+            }
+        }
+    }
+
     //     def emit_start_tok
     //       str_e = @heredoc_e || @str_s + @str_type.length
     //       emit(@start_tok, @str_type, @str_s, str_e)
     //     end
     fn emit_start_tok(&mut self) {
-        // TODO DUMMY haven't handle heredoc_e
-
-        // let str_e = self.str_s + self.str_type.len();
+        let str_e = match self.heredoc_e {
+            Some(heredoc_e) => heredoc_e,
+            None => self.str_s + self.str_type.len()
+        };
         let token = self.start_tok.clone();
-    
-        self.tokens_to_emit.push(token);
+
+        self.tokens_to_emit.push((token, Range::new(self.str_s, str_e)));
     }
 
     //     def emit(token, type, s, e)
@@ -608,6 +666,16 @@ impl Lexer {
         }
     }
 
+    // emit the tokens a literal has queued, see notes of nest_and_try_closing,
+    // along with the :invalid_encoding errors, see buffer_string
+    pub fn emit_literal_tokens(&mut self, literal: &mut Literal) {
+        for (token, range) in literal.consume_tokens_to_emit() { self.emit_token_with_range(token, range); }
+
+        for range in literal.invalid_encodings.drain(..) {
+            self.diagnostic(Level::Error, "invalid_encoding", HashMap::new(), range);
+        }
+    }
+
     //   def literal
     //     @literal_stack.last
     //   end
@@ -629,9 +697,16 @@ impl Lexer {
     //       self.class.lex_en_expr_endarg
     //     end
     //   end
-    // TODO DUMMY
+    // NOTE ignored ruby < 24 stuff
     pub fn pop_literal(&mut self) -> LexingState {
         let old_literal = self.literal_stack.pop().unwrap();
-        state!("expr_endarg")
+
+        self.dedent_level = old_literal.dedent_level;
+
+        if old_literal.start_tok == Token::T_REGEXP_BEG {
+            state!("regexp_modifiers")
+        } else {
+            state!("expr_endarg")
+        }
     }
 }
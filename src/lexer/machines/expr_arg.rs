@@ -2,6 +2,8 @@
 // # is consumed; the current expression is a command or method call.
 // #
 
+use std::collections::HashMap;
+
 use regex::Regex;
 
 use lexer::Lexer;
@@ -9,9 +11,14 @@ use lexer::LexingState;
 use lexer::action::{Action};
 use lexer::matching_patterns::TMatchingPatterns;
 use lexer::shared_actions::TSharedActions;
+use lexer::shared_functions;
 
 use parser::token::Token;
 
+use source::range::Range;
+
+use diagnostic::Level;
+
 pub fn construct_machine_expr_arg( patterns: &TMatchingPatterns, shared_actions: &TSharedActions ) -> Vec<Box<Action>> {
     let (pattern_literals, pattern_regexs) = (*patterns).clone();
 
@@ -24,6 +31,12 @@ pub fn construct_machine_expr_arg( patterns: &TMatchingPatterns, shared_actions:
         };
     }
 
+    macro_rules! pattern_lit {
+        ($pattern_name:expr) => {
+            pattern_literals.get($pattern_name).unwrap()
+        };
+    }
+
     macro_rules! get_shared_action {
         ( $action_name:expr ) => {
             shared_actions.get($action_name).unwrap().clone()
@@ -52,7 +65,7 @@ pub fn construct_machine_expr_arg( patterns: &TMatchingPatterns, shared_actions:
         //         e_lparen
         // 
         box Action {
-            regex: Regex::new(r"^[ \t\r\f\v]+\[").unwrap(),
+            regex: Regex::new(r"^[ \t\r\f\v]+\(").unwrap(),
             //                   ^         ^
             //                   w_space
             procedure: |lexer: &mut Lexer| {
@@ -61,7 +74,8 @@ pub fn construct_machine_expr_arg( patterns: &TMatchingPatterns, shared_actions:
                 // NOTE ignored version18
 
                 // TODO NOTE originally the token `tLPAREN2` contains a value '('
-                lexer.emit_token(Token::T_LPAREN_ARG);
+                let te = lexer.input_stream.te.unwrap();
+                lexer.emit_token_with_range(Token::T_LPAREN_ARG, Range::new(te - 1, te));
 
                 lexer.set_next_state(state!("expr_beg"));
                 lexer.flag_breaking();
@@ -78,6 +92,7 @@ pub fn construct_machine_expr_arg( patterns: &TMatchingPatterns, shared_actions:
         //
         action!("e_lparen", |lexer: &mut Lexer| {
             lexer.invoke_proc("e_lparen");
+            lexer.emit_token(Token::T_LPAREN2);
             lexer.set_next_state(state!("expr_beg"));
             lexer.flag_breaking();
         }),
@@ -94,6 +109,8 @@ pub fn construct_machine_expr_arg( patterns: &TMatchingPatterns, shared_actions:
             procedure: |lexer: &mut Lexer| {
                 lexer.invoke_proc("e_lbrack");
                 // TODO NOTE originally the token `tLBRACK` contains a value '['
+                let te = lexer.input_stream.te.unwrap();
+                lexer.emit_token_with_range(Token::T_LBRACK, Range::new(te - 1, te));
                 lexer.set_next_state(state!("expr_beg"));
                 lexer.flag_breaking();
             }
@@ -114,18 +131,18 @@ pub fn construct_machine_expr_arg( patterns: &TMatchingPatterns, shared_actions:
         // with embedded
         //     e_lbrace
         box Action {
-            regex: Regex::new(r"^[ \t\r\f\v]*\(").unwrap(),
+            regex: Regex::new(r"^[ \t\r\f\v]*\{").unwrap(),
             procedure: |lexer: &mut Lexer| {
-
-                lexer.invoke_proc("e_lbrace");
-
-                // TODO maybe unwrap()
-                if lexer.lambda_stack.is_empty() || ( lexer.lambda_stack.last().unwrap() == &lexer.paren_nest ) {
+                if lexer.lambda_stack.last() == Some(&lexer.paren_nest) {
+                    // NOTE e_lbrace is left to expr_end, which lexes `{` again
                     lexer.input_stream.hold_current_token();
                     lexer.set_next_state(state!("expr_end"))
                 } else {
+                    lexer.invoke_proc("e_lbrace");
+
                     // TODO NOTE originally the token `tLCURLY` contains a value '{'
-                    lexer.emit_token(Token::T_LCURLY);
+                    let te = lexer.input_stream.te.unwrap();
+                    lexer.emit_token_with_range(Token::T_LCURLY, Range::new(te - 1, te));
                     lexer.set_next_state(state!("expr_value"));
                     lexer.flag_breaking();
                 }
@@ -188,15 +205,13 @@ pub fn construct_machine_expr_arg( patterns: &TMatchingPatterns, shared_actions:
 
                 // handle w_space+ %{ tm = p }
                 let current_slice = lexer.input_stream.current_token().unwrap();
-                let w_space_regex = Regex::new(r"^[ \t\r\f\v]+").unwrap();
-                let w_space_len = w_space_regex.captures(&current_slice).unwrap().get(0).unwrap().as_str().chars().count();
+                let w_space_len = current_slice.chars().take_while(|c| shared_functions::is_c_space(*c)).count();
                 let tm = lexer.input_stream.ts.unwrap() + w_space_len;
 
                 if lexer.input_stream.slice_from_range(tm, tm + 1) == String::from("/") {
                     // # Ambiguous regexp literal.
                     // diagnostic :warning, :ambiguous_literal, nil, range(tm, tm + 1)
-                    // TODO
-                    panic!("TODO");
+                    lexer.diagnostic(Level::Warning, "ambiguous_literal", HashMap::new(), Range::new(tm, tm + 1));
                 }
 
                 lexer.input_stream.p = ( tm - 1 ) as isize;
@@ -220,11 +235,13 @@ pub fn construct_machine_expr_arg( patterns: &TMatchingPatterns, shared_actions:
 
                 // handle w_space+ %{ tm = p }
                 let current_slice = lexer.input_stream.current_token().unwrap();
-                let w_space_regex = Regex::new(r"^[ \t\r\f\v]+").unwrap();
-                let w_space_len = w_space_regex.captures(&current_slice).unwrap().get(0).unwrap().as_str().chars().count();
+                let w_space_len = current_slice.chars().take_while(|c| shared_functions::is_c_space(*c)).count();
                 let tm = lexer.input_stream.ts.unwrap() + w_space_len;
 
-                // TODO handle diagnostic
+                let te = lexer.input_stream.te.unwrap();
+                let mut arguments = HashMap::new();
+                arguments.insert("prefix", lexer.input_stream.token_string_from_range(tm, te));
+                lexer.diagnostic(Level::Warning, "ambiguous_prefix", arguments, Range::new(tm, te));
 
                 lexer.input_stream.p = ( tm - 1 ) as isize;
                 lexer.set_next_state(state!("expr_beg"));
@@ -258,13 +275,13 @@ pub fn construct_machine_expr_arg( patterns: &TMatchingPatterns, shared_actions:
 
         //     w_space+ label
         //     => { p = @ts - 1; fgoto expr_beg; };
-        box Action {
-            regex: Regex::new(r"^[ \t\r\f\v]+[[:alpha:]][[:alnum:]]*[\?!]?:").unwrap(),
-            procedure: |lexer: &mut Lexer| {
+        action_with_literal!(
+            format!(r"{}+{}", pattern_lit!("c_space"), pattern_lit!("label")),
+            |lexer: &mut Lexer| {
                 lexer.input_stream.hold_current_token();
                 lexer.set_next_state(state!("expr_beg"));
             }
-        },
+        ),
 
         //     #
         //     # AMBIGUOUS TOKENS RESOLVED VIA EXPR_END
@@ -279,8 +296,7 @@ pub fn construct_machine_expr_arg( patterns: &TMatchingPatterns, shared_actions:
             procedure: |lexer: &mut Lexer| {
                 // handle w_space+ %{ tm = p }
                 let current_slice = lexer.input_stream.current_token().unwrap();
-                let w_space_regex = Regex::new(r"^[ \t\r\f\v]+").unwrap();
-                let w_space_len = w_space_regex.captures(&current_slice).unwrap().get(0).unwrap().as_str().chars().count();
+                let w_space_len = current_slice.chars().take_while(|c| shared_functions::is_c_space(*c)).count();
                 let tm = lexer.input_stream.ts.unwrap() + w_space_len;
 
                 lexer.input_stream.p = ( tm - 1 ) as isize;
@@ -327,15 +343,24 @@ pub fn construct_machine_expr_arg( patterns: &TMatchingPatterns, shared_actions:
 
         //     w_comment
         //     => { fgoto expr_end; };
-        action!("w_space", |lexer: &mut Lexer| { lexer.set_next_state(state!("expr_end")) }),
+        action!("w_comment", |lexer: &mut Lexer| {
+            lexer.invoke_proc("emit_comment");
+            lexer.set_next_state(state!("expr_end"));
+        }),
 
         //     w_newline
         //     => { fhold; fgoto expr_end; };
-        action!("w_newline", |lexer: &mut Lexer| { lexer.set_next_state(state!("expr_end")) }),
+        action!("w_newline", |lexer: &mut Lexer| {
+            lexer.input_stream.hold_current_char();
+            lexer.set_next_state(state!("expr_end"));
+        }),
 
         //     c_any
         //     => { fhold; fgoto expr_beg; };
-        action!("c_any", |lexer: &mut Lexer| { lexer.set_next_state(state!("expr_beg")); }),
+        action!("c_any", |lexer: &mut Lexer| {
+            lexer.input_stream.hold_current_char();
+            lexer.set_next_state(state!("expr_beg"));
+        }),
 
         //     c_eof => do_eof;
         action!("c_eof", get_shared_action!("do_eof")),
@@ -6,6 +6,8 @@
 //   # explodes.
 //   #
 
+use std::collections::HashMap;
+
 use regex::Regex;
 
 use lexer::Lexer;
@@ -16,6 +18,17 @@ use lexer::shared_actions::TSharedActions;
 use lexer::literal::Literal;
 
 use parser::token::Token;
+use parser::options::RubyVersion;
+
+use source::range::Range;
+
+use diagnostic::Level;
+
+// NOTE compiled once instead of on every invoking of the actions below
+lazy_static! {
+    static ref HEREDOC_ID_PREFIX_RE: Regex = Regex::new(r#"^<<(-?)(~?)(["'`]?)"#).unwrap();
+    static ref MULTIPLE_CODEPOINTS_RE: Regex = Regex::new(r"^u\{[[:xdigit:]]+([ \t]+[[:xdigit:]]+)+\}$").unwrap();
+}
 
 pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions: &TSharedActions ) -> Vec<Box<Action>> {
     let (pattern_literals, pattern_regexs) = (*patterns).clone();
@@ -54,8 +67,9 @@ pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions:
                 pattern_lit!("w_any*"),
                 r"[[:digit:]]"
             ), |lexer: &mut Lexer| {
-                let token = Token::T_UNARY_NUM(lexer.input_stream.token_string_from_range(lexer.input_stream.ts.unwrap(), lexer.input_stream.ts.unwrap() + 1));
-                lexer.emit_token(token);
+                let ts = lexer.input_stream.ts.unwrap();
+                let token = Token::T_UNARY_NUM(lexer.input_stream.token_string_from_range(ts, ts + 1));
+                lexer.emit_token_with_range(token, Range::new(ts, ts + 1));
                 lexer.input_stream.hold_current_char();
                 lexer.set_next_state(state!("expr_end"));
                 lexer.flag_breaking();
@@ -91,7 +105,7 @@ pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions:
 
                 let ts = lexer.input_stream.ts.unwrap();
                 let mut literal = Literal::new(lit_type, lit_delimiter, ts, None, false, false, false );
-                for token in literal.consume_tokens_to_emit() { lexer.emit_token(token); }
+                lexer.emit_literal_tokens(&mut literal);
 
                 lexer.input_stream.hold_current_char();
 
@@ -117,7 +131,7 @@ pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions:
 
             let ts = lexer.input_stream.ts.unwrap();
             let mut literal = Literal::new(lit_type, lit_delimiter, ts, None, false, false, false );
-            for token in literal.consume_tokens_to_emit() { lexer.emit_token(token); }
+            lexer.emit_literal_tokens(&mut literal);
 
             let next_state = lexer.push_literal(literal);
             lexer.set_next_state(next_state);
@@ -138,7 +152,7 @@ pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions:
 
                 let ts = lexer.input_stream.ts.unwrap();
                 let mut literal = Literal::new(lit_type, lit_delimiter, ts, None, false, false, false );
-                for token in literal.consume_tokens_to_emit() { lexer.emit_token(token); }
+                lexer.emit_literal_tokens(&mut literal);
 
                 let next_state = lexer.push_literal(literal);
                 lexer.set_next_state(next_state);
@@ -196,7 +210,62 @@ pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions:
         //           p = @herebody_s - 1
         //         end
         //       };
-        // TODO UNIMPL
+        // 
+        // NOTE <<~ always starts a heredoc, which ruby22 and below don't have, see Lexer::check_version
+        action_with_literal!(
+            format!(r#"<<[~-]?("[^"]*"|'[^']*'|`[^`]*`|{}){}*{}"#,
+                pattern_lit!("bareword"), pattern_lit!("c_line"), pattern_lit!("c_nl")
+            ),
+            |lexer: &mut Lexer| {
+                let ts = lexer.input_stream.ts.unwrap();
+                let te = lexer.input_stream.te.unwrap();
+                let current_slice = lexer.input_stream.current_token().unwrap();
+
+                let captures = HEREDOC_ID_PREFIX_RE.captures(&current_slice).unwrap();
+                let indent = !captures[1].is_empty() || !captures[2].is_empty();
+                let dedent_body = !captures[2].is_empty();
+                let quote = String::from(&captures[3]);
+
+                // % { heredoc_e = p }
+                let id_s = captures[0].len();
+                let rest = String::from(&current_slice[id_s..]);
+                let (mut delimiter, heredoc_e) = if quote.is_empty() {
+                    let id: String = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '_' || !c.is_ascii()).collect();
+                    let id_len = id.len();
+                    (id, ts + id_s + id_len)
+                } else {
+                    let id: String = rest.chars().take_while(|c| c.to_string() != quote).collect();
+                    let id_len = id.len();
+                    (id, ts + id_s + id_len + 1)
+                };
+                let lit_type = if quote.is_empty() { String::from("<<\"") } else { format!("<<{}", quote) };
+
+                if lexer.version() >= RubyVersion::Ruby24 && delimiter.contains('\n') {
+                    if delimiter.ends_with('\n') {
+                        lexer.diagnostic(Level::Warning, "heredoc_id_ends_with_nl", HashMap::new(), Range::new(ts, ts + 1));
+                        delimiter = String::from(delimiter.trim_right());
+                    } else {
+                        lexer.diagnostic(Level::Fatal, "heredoc_id_has_newline", HashMap::new(), Range::new(ts, ts + 1));
+                        return;
+                    }
+                }
+
+                if dedent_body {
+                    lexer.check_version("squiggly heredoc", RubyVersion::Ruby23, Range::new(ts, ts + 3));
+                }
+
+                let mut literal = Literal::new(lit_type, delimiter, ts, Some(heredoc_e), indent, dedent_body, false);
+                lexer.emit_literal_tokens(&mut literal);
+
+                let next_state = lexer.push_literal(literal);
+                lexer.set_next_state(next_state);
+
+                // c_line* c_nl % { new_herebody_s = p }
+                let new_herebody_s = te;
+                if lexer.herebody_s.is_none() { lexer.herebody_s = Some(new_herebody_s); }
+                lexer.input_stream.p = lexer.herebody_s.unwrap() as isize - 1;
+            }
+        ),
 
         //       #
         //       # SYMBOL LITERALS
@@ -218,7 +287,7 @@ pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions:
 
                 let ts = lexer.input_stream.ts.unwrap();
                 let mut literal = Literal::new(lit_type, lit_delimiter, ts, None, false, false, false );
-                for token in literal.consume_tokens_to_emit() { lexer.emit_token(token); }
+                lexer.emit_literal_tokens(&mut literal);
 
                 let next_state = lexer.push_literal(literal);
                 lexer.set_next_state(next_state);
@@ -239,8 +308,10 @@ pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions:
             |lexer: &mut Lexer| {
                 lexer.invoke_proc("ambiguous_suffix");
 
-                let token = Token::T_SYMBOL(lexer.input_stream.current_token_string());
-                lexer.emit_token(token);
+                let ts = lexer.input_stream.ts.unwrap();
+                let token = Token::T_SYMBOL(lexer.input_stream.token_string_from_range(ts + 1, lexer.input_stream.tm));
+                let range = Range::new(ts, lexer.input_stream.tm);
+                lexer.emit_token_with_range(token, range);
 
                 lexer.input_stream.p = lexer.input_stream.tm as isize - 1;
                 lexer.set_next_state(state!("expr_end"));
@@ -288,7 +359,44 @@ pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions:
 
         //         fnext expr_end; fbreak;
         //       };
-        // TODO UNIMPL
+        // NOTE ignored ruby18 stuff
+        action_with_literal!(
+            format!(r"\?(?:{}{}|[^ \n\t\r\f\v\\])", pattern_lit!("e_bs"), pattern_lit!("escape")),
+            |lexer: &mut Lexer| {
+                let ts = lexer.input_stream.ts.unwrap();
+                let tok = lexer.input_stream.current_token().unwrap();
+
+                let value = if tok.starts_with(r"?\") {
+                    // NOTE
+                    // there's no regex for `escape - ( ... )`, a \u{...} with multiple codepoints is
+                    // handed over like this rule didn't match, i.e. to the `c_any` rule below
+                    if MULTIPLE_CODEPOINTS_RE.is_match(&tok[2..]) {
+                        lexer.input_stream.hold_current_token();
+                        lexer.set_next_state(state!("expr_end"));
+                        return;
+                    }
+
+                    let escape = lexer.unescape(ts + 2, &tok[2..]);
+
+                    // NOTE whitequark reports this in the builder, see Literal::buffer_string
+                    match String::from_utf8(escape) {
+                        Ok(value) => value,
+                        Err(error) => {
+                            let range = lexer.current_range();
+                            lexer.diagnostic(Level::Error, "invalid_encoding", HashMap::new(), range);
+                            String::from_utf8_lossy(error.as_bytes()).into_owned()
+                        }
+                    }
+                } else {
+                    String::from(&tok[1..])
+                };
+
+                lexer.emit_token(Token::T_CHARACTER(value));
+
+                lexer.set_next_state(state!("expr_end"));
+                lexer.flag_breaking();
+            }
+        ),
 
         //       '?' c_space_nl
         //       => {
@@ -302,7 +410,19 @@ pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions:
         action_with_literal!(
             format!(r"\?{}", pattern_lit!("c_space_nl")),
             |lexer: &mut Lexer| {
-                panic!("UNIMPL");
+                let ts = lexer.input_stream.ts.unwrap();
+                let escape = match lexer.input_stream.slice_from_range(ts + 1, ts + 2).as_ref() {
+                    " " => r"\s", "\r" => r"\r", "\n" => r"\n", "\t" => r"\t",
+                    "\x0b" => r"\v", "\x0c" => r"\f",
+                    _ => ""
+                };
+                let mut arguments = HashMap::new();
+                arguments.insert("escape", String::from(escape));
+                let range = lexer.current_range();
+                lexer.diagnostic(Level::Warning, "invalid_escape_use", arguments, range);
+
+                lexer.input_stream.p = ts as isize - 1;
+                lexer.set_next_state(state!("expr_end"));
             }
         ),
 
@@ -313,7 +433,8 @@ pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions:
         action_with_literal!(
             format!(r"\?{}", pattern_lit!("c_eof")),
             |lexer: &mut Lexer| {
-                panic!("UNIMPL");
+                let ts = lexer.input_stream.ts.unwrap();
+                lexer.diagnostic(Level::Fatal, "incomplete_escape", HashMap::new(), Range::new(ts, ts + 1));
             }
         ),
 
@@ -384,7 +505,7 @@ pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions:
         //       => { emit_table(PUNCTUATION_BEGIN)
         //            fbreak; };
         action!("punctuation_begin", |lexer: &mut Lexer| {
-            lexer.emit_token_from_table("puncuation_begin");
+            lexer.emit_token_from_table("punctuation_begin");
             lexer.flag_breaking();
         }),
 
@@ -433,10 +554,19 @@ pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions:
 
         //         fbreak;
         //       };
+        // NOTE ignored version18, see expr_endfn for the offsets
         action_with_literal!(
             format!("{}[^:]", pattern_lit!("label")),
             |lexer: &mut Lexer| {
-                panic!("UNIMPL");
+                let last_char_len = lexer.input_stream.current_token().unwrap().chars().last().unwrap().len_utf8();
+                let label_e = lexer.input_stream.te.unwrap() - last_char_len;
+
+                let slice = lexer.input_stream.token_string_from_range( lexer.input_stream.ts.unwrap(), label_e - 1 );
+                let range = Range::new(lexer.input_stream.ts.unwrap(), label_e);
+                lexer.emit_token_with_range(Token::T_LABLE(slice), range);
+                lexer.input_stream.hold_current_char();
+                lexer.set_next_state(LexingState::ExprLabelarg);
+                lexer.flag_breaking();
             }
         ),
 
@@ -492,7 +622,7 @@ pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions:
                 pattern_lit!("w_space")
             ),
             |lexer: &mut Lexer| {
-                panic!("UNIMPL");
+                lexer.unimplemented("method call with space before parenthesis");
             }
         ),
 
@@ -501,7 +631,7 @@ pub fn construct_machine_expr_beg( patterns: &TMatchingPatterns, shared_actions:
         //       #
 
         //       w_any;
-        action!("w_any", get_shared_action!("noop")),
+        action!("w_any", get_shared_action!("emit_comment")),
 
         //       e_heredoc_nl '=begin' ( c_space | c_nl_zlen )
         //       => { p = @ts - 1
@@ -14,6 +14,7 @@ use lexer::matching_patterns::TMatchingPatterns;
 use lexer::shared_actions::TSharedActions;
 
 use parser::token::Token;
+use source::range::Range;
 
 pub fn construct_machine_expr_cmdarg( patterns: &TMatchingPatterns, shared_actions: &TSharedActions ) -> Vec<Box<Action>> {
     let (pattern_literals, pattern_regexs) = (*patterns).clone();
@@ -79,9 +80,13 @@ pub fn construct_machine_expr_cmdarg( patterns: &TMatchingPatterns, shared_actio
         //       end
         //     };
         action_with_literal!(format!(r"{}+{}", pattern_lit!("w_space"), pattern_lit!("e_lparen")), |lexer: &mut Lexer| {
-            lexer.emit_token(Token::T_LPAREN_ARG);
+            lexer.invoke_proc("e_lparen");
+
+            let te = lexer.input_stream.te.unwrap();
+            lexer.emit_token_with_range(Token::T_LPAREN_ARG, Range::new(te - 1, te));
             // NOTE ignored version 18
             lexer.set_next_state(state!("expr_beg"));
+            lexer.flag_breaking();
         }),
 
         //     w_space* 'do'
@@ -111,10 +116,13 @@ pub fn construct_machine_expr_cmdarg( patterns: &TMatchingPatterns, shared_actio
         //     => { p = @ts - 1
         //           fgoto expr_arg; };
         // TODO use w_space_*
-        action_with_literal!(format!(r"({})|({}*{})|({}*{})",
-            pattern_lit!("c_any"),
-            pattern_lit!("w_space"), pattern_lit!("bareword"),
+        // NOTE
+        // the alternation of a regex takes the first alternative matching instead of the longest one,
+        // so c_any goes last, or `foo double` would be lexed as `foo do uble`
+        action_with_literal!(format!(r"({}*{})|({}*{})|({})",
             pattern_lit!("w_space"), pattern_lit!("label"),
+            pattern_lit!("w_space"), pattern_lit!("bareword"),
+            pattern_lit!("c_any"),
             )
         , |lexer: &mut Lexer| {
             lexer.input_stream.hold_current_token();
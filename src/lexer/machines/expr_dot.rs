@@ -13,6 +13,8 @@ use lexer::shared_actions::TSharedActions;
 
 use parser::token::Token;
 
+use source::range::Range;
+
 pub fn construct_machine_expr_dot( patterns: &TMatchingPatterns, shared_actions: &TSharedActions ) -> Vec<Box<Action>> {
     let (pattern_literals, pattern_regexs) = (*patterns).clone();
 
@@ -25,6 +27,12 @@ pub fn construct_machine_expr_dot( patterns: &TMatchingPatterns, shared_actions:
         };
     }
 
+    macro_rules! pattern_lit {
+        ($pattern_name:expr) => {
+            pattern_literals.get($pattern_name).unwrap()
+        };
+    }
+
     macro_rules! get_shared_action {
         ( $action_name:expr ) => {
             shared_actions.get($action_name).unwrap().clone()
@@ -79,47 +87,41 @@ pub fn construct_machine_expr_dot( patterns: &TMatchingPatterns, shared_actions:
         //   # to sheer ambiguity.
         // and we haven't do anything about `non-longest-match scanning`,
         // still don't know if that will cause any issue
-        // 
-        // TODO use pattern_lit!
 
-        box Action {
-            regex: Regex::new(r"^[[:alpha:]][[:alnum:]]*[?!]").unwrap(),
-            //                   ^                     ^
-            //                   bareword
-            procedure: |lexer: &mut Lexer| {
-                let tm = lexer.input_stream.p;
+        action_with_literal!(
+            format!(r"{}[?!]", pattern_lit!("bareword")),
+            |lexer: &mut Lexer| {
+                let tm = lexer.input_stream.te.unwrap() as isize;
 
                 let token = Token::T_FID(lexer.input_stream.token_string_from_range( lexer.input_stream.ts.unwrap(), tm as usize ));
-                lexer.emit_token(token);
+                let range = Range::new(lexer.input_stream.ts.unwrap(), tm as usize);
+                lexer.emit_token_with_range(token, range);
 
                 let next_state = lexer.arg_or_cmdarg();
                 lexer.set_next_state(next_state);
 
-                // TODO NOTE
-                // original p = tm - 1
-                lexer.input_stream.p = tm;
+                lexer.input_stream.p = tm - 1;
 
                 lexer.flag_breaking();
             }
-        },
-        box Action {
-            regex: Regex::new(r"^[[:alpha:]][[:alnum:]]*[?!]=").unwrap(),
-            procedure: |lexer: &mut Lexer| {
-                let tm = lexer.input_stream.p - 2;
+        ),
+        action_with_literal!(
+            format!(r"{}[?!]=", pattern_lit!("bareword")),
+            |lexer: &mut Lexer| {
+                let tm = lexer.input_stream.te.unwrap() as isize - 2;
 
                 let token = Token::T_FID(lexer.input_stream.token_string_from_range( lexer.input_stream.ts.unwrap(), tm as usize ));
-                lexer.emit_token(token);
+                let range = Range::new(lexer.input_stream.ts.unwrap(), tm as usize);
+                lexer.emit_token_with_range(token, range);
 
                 let next_state = lexer.arg_or_cmdarg();
                 lexer.set_next_state(next_state);
 
-                // TODO NOTE
-                // original p = tm - 1
-                lexer.input_stream.p = tm;
+                lexer.input_stream.p = tm - 1;
 
                 lexer.flag_breaking();
             }
-        },
+        ),
 
         //     # See the comment in `expr_fname`.
         //     operator_fname      |
@@ -127,21 +129,17 @@ pub fn construct_machine_expr_dot( patterns: &TMatchingPatterns, shared_actions:
         //     operator_rest
         //     => { emit_table(PUNCTUATION)
         //           fnext expr_arg; fbreak; };
-        // TODO use pattern_lit!
-        box Action {
-            // TODO impl pattern_literals, for operator_fname, etc. and build pattern like this
-            regex: Regex::new(r"^(\[\])|(\[\]=)|`|(-@)|(\+@)|(~@)|(!@)|(&)|(\|)|(&&)|(\|\|)|(\^)|(\+)|(-)|(\*)|(/)|(\*\*)|(~)|(<<)|(>>)|(%)|(=~)|(!~)|(==)|(!=)|(!)|(===)|(<)|(<=)|(>)|(>=)|(<=>)|(=>)").unwrap(),
-            //                   ^                                   ^ ^                                                                  ^ ^                                                        ^
-            //                   operator_fname                        _arithmetic                                                          _rest
-            procedure: |lexer: &mut Lexer| {
+        action_with_literal!(
+            format!(r"({})|({})|({})", pattern_lit!("operator_fname"), pattern_lit!("operator_arithmetic"), pattern_lit!("operator_rest")),
+            |lexer: &mut Lexer| {
                 lexer.emit_token_from_table("punctuation");
                 lexer.set_next_state(LexingState::ExprArg);
                 lexer.flag_breaking();
             }
-        },
+        ),
 
         //     w_any;
-        action!("w_any", get_shared_action!("noop")),
+        action!("w_any", get_shared_action!("emit_comment")),
 
         //     c_any
         //     => { fhold; fgoto expr_end; };
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use regex::Regex;
 
 use lexer::Lexer;
@@ -5,8 +7,15 @@ use lexer::LexingState;
 use lexer::action::{Action};
 use lexer::matching_patterns::TMatchingPatterns;
 use lexer::shared_actions::TSharedActions;
+use lexer::shared_functions::integer_token;
+use lexer::literal::Literal;
 
 use parser::token::Token;
+use parser::options::RubyVersion;
+
+use source::range::Range;
+
+use diagnostic::Level;
 
 pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions: &TSharedActions ) -> Vec<Box<Action>> {
     let (pattern_literals, pattern_regexs) = (*patterns).clone();
@@ -47,7 +56,8 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
         //       };
 
         action_with_literal!("->", |lexer: &mut Lexer| {
-            lexer.emit_token(Token::T_LAMBDA);
+            let ts = lexer.input_stream.ts.unwrap();
+            lexer.emit_token_with_range(Token::T_LAMBDA, Range::new(ts, ts + 2));
 
             lexer.lambda_stack.push(lexer.paren_nest);
             lexer.set_next_state(state!("expr_endfn"));
@@ -94,7 +104,7 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
                     if lexer.input_stream.current_token().unwrap() == String::from("{") {
                         lexer.emit_token(Token::T_LCURLY);
                     } else {
-                        panic!("UNIMPL emit_do");
+                        lexer.emit_do(false);
                     }
                 }
                 lexer.set_next_state(state!("expr_value"));
@@ -122,8 +132,10 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
         action_with_literal!(
             format!(r"class{}<<", pattern_lit!("w_any*")),
             |lexer: &mut Lexer| {
-                lexer.emit_token(Token::K_CLASS);
-                lexer.emit_token(Token::T_LSHFT); // TODO originally has value `<<`
+                let ts = lexer.input_stream.ts.unwrap();
+                let te = lexer.input_stream.te.unwrap();
+                lexer.emit_token_with_range(Token::K_CLASS, Range::new(ts, ts + 5));
+                lexer.emit_token_with_range(Token::T_LSHFT, Range::new(te - 2, te)); // TODO originally has value `<<`
                 lexer.set_next_state(state!("expr_value"));
                 lexer.flag_breaking();
             }
@@ -238,16 +250,46 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
         //         end
         //         fbreak;
         //       };
-        // 
-        // TODO IMCOMPLETE
-        //     @num_base stuff
-        // 
         action_with_literal!(
-            format!(r"[1-9][[:digit:]]*_?{}", pattern_lit!("int_dec")),
+            format!(r"(0[xX]{}|0[dD]{}|0[oO]{}|0[bB]{}|[1-9][[:digit:]]*_?{}|0[[:digit:]]*_?{}){}",
+                pattern_lit!("int_hex"), pattern_lit!("int_dec"), pattern_lit!("int_dec"), pattern_lit!("int_bin"),
+                pattern_lit!("int_dec"), pattern_lit!("int_dec"),
+                pattern_lit!("int_suffix")
+            ),
             |lexer: &mut Lexer| {
-                // TODO DUMMY
-                let int = lexer.input_stream.current_token().unwrap().parse::<isize>().unwrap();
-                lexer.emit_token(Token::T_INTEGER(int));
+                let slice = lexer.input_stream.current_token().unwrap();
+                let ts = lexer.input_stream.ts.unwrap();
+                let te = lexer.input_stream.te.unwrap();
+
+                // NOTE offsets relative to @ts
+                let prefix: String = slice.chars().take(2).collect::<String>().to_lowercase();
+                let (num_base, num_digits_s) = match prefix.as_ref() {
+                    "0x" => (16, 2),
+                    "0d" => (10, 2),
+                    "0o" => (8, 2),
+                    "0b" => (2, 2),
+                    _ => if slice.starts_with("0") { (8, 0) } else { (10, 0) }
+                };
+                let num_suffix_s = slice.len() - slice.chars().rev().take_while(|c| *c == 'r' || *c == 'i').count();
+
+                let digits = String::from(&slice[num_digits_s..num_suffix_s]);
+                let suffix = String::from(&slice[num_suffix_s..]);
+
+                if digits.ends_with("_") {
+                    let mut arguments = HashMap::new();
+                    arguments.insert("character", String::from("_"));
+                    lexer.diagnostic(Level::Error, "trailing_in_number", arguments, Range::new(te - 1, te));
+                } else if digits.is_empty() {
+                    let range = lexer.current_range();
+                    lexer.diagnostic(Level::Error, "empty_numeric", HashMap::new(), range);
+                } else if num_base == 8 {
+                    if let Some(invalid_idx) = digits.find(|c| c == '8' || c == '9') {
+                        let invalid_s = ts + num_digits_s + invalid_idx;
+                        lexer.diagnostic(Level::Error, "invalid_octal", HashMap::new(), Range::new(invalid_s, invalid_s + 1));
+                    }
+                }
+
+                lexer.emit_num(&digits.replace("_", ""), num_base, &suffix);
                 lexer.flag_breaking();
             }
         ),
@@ -256,9 +298,13 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
         //       => {
         //         diagnostic :error, :no_dot_digit_literal
         //       };
-        // 
-        // TODO UNIMPL
-        // 
+        action_with_literal!(
+            format!(r"{}{}?", pattern_lit!("flo_frac"), pattern_lit!("flo_pow")),
+            |lexer: &mut Lexer| {
+                let range = lexer.current_range();
+                lexer.diagnostic(Level::Error, "no_dot_digit_literal", HashMap::new(), range);
+            }
+        ),
 
         //       flo_int [eE]
         //       => {
@@ -271,9 +317,17 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
         //           fhold; fbreak;
         //         end
         //       };
-        // 
-        // TODO UNIMPL
-        // 
+        action_with_literal!(
+            format!(r"({})[eE]", pattern_lit!("flo_int")),
+            |lexer: &mut Lexer| {
+                let ts = lexer.input_stream.ts.unwrap();
+                let te = lexer.input_stream.te.unwrap();
+                let digits = lexer.input_stream.slice_from_range(ts, te - 1).replace("_", "");
+                lexer.emit_token_with_range(integer_token(&digits, 10), Range::new(ts, te - 1));
+                lexer.input_stream.hold_current_char();
+                lexer.flag_breaking();
+            }
+        ),
 
         //       flo_int flo_frac [eE]
         //       => {
@@ -286,9 +340,17 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
         //           fhold; fbreak;
         //         end
         //       };
-        // 
-        // TODO UNIMPL
-        // 
+        action_with_literal!(
+            format!(r"({}){}[eE]", pattern_lit!("flo_int"), pattern_lit!("flo_frac")),
+            |lexer: &mut Lexer| {
+                let ts = lexer.input_stream.ts.unwrap();
+                let te = lexer.input_stream.te.unwrap();
+                let digits = lexer.input_stream.slice_from_range(ts, te - 1).replace("_", "");
+                lexer.emit_token_with_range(Token::T_FLOAT(digits.parse::<f64>().unwrap()), Range::new(ts, te - 1));
+                lexer.input_stream.hold_current_char();
+                lexer.flag_breaking();
+            }
+        ),
 
         //       flo_int
         //       ( flo_frac? flo_pow %{ @num_suffix_s = p } flo_pow_suffix
@@ -305,9 +367,18 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
         //         end
         //         fbreak;
         //       };
-        // 
-        // TODO UNIMPL
-        // 
+        action_with_literal!(
+            format!(r"({})(({})?{}{}|{}{})",
+                pattern_lit!("flo_int"),
+                pattern_lit!("flo_frac"), pattern_lit!("flo_pow"), pattern_lit!("flo_pow_suffix"),
+                pattern_lit!("flo_frac"), pattern_lit!("flo_suffix")
+            ),
+            |lexer: &mut Lexer| {
+                let (digits, suffix) = split_num_suffix(lexer.input_stream.current_token().unwrap());
+                lexer.emit_num(&digits, 10, &suffix);
+                lexer.flag_breaking();
+            }
+        ),
 
         //       #
         //       # STRING AND XSTRING LITERALS
@@ -320,8 +391,22 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
         //         fgoto *push_literal(type, delimiter, @ts, nil, false, false, true);
         //       };
         // 
-        // TODO UNIMPL
+        // TODO '`', which is still reported as unexpected
         // 
+        action_with_literal!(
+            "['\"]",
+            |lexer: &mut Lexer| {
+                let lit_type = lexer.input_stream.current_token().unwrap();
+                let lit_delimiter = lexer.input_stream.current_token().unwrap();
+
+                let ts = lexer.input_stream.ts.unwrap();
+                let mut literal = Literal::new( lit_type, lit_delimiter, ts, None, false, false, true );
+                lexer.emit_literal_tokens(&mut literal);
+
+                let next_state = lexer.push_literal(literal);
+                lexer.set_next_state(next_state);
+            }
+        ),
 
         //       #
         //       # CONSTANTS AND VARIABLES
@@ -331,6 +416,9 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
         //       => { emit(:tCONSTANT)
         //            fnext *arg_or_cmdarg; fbreak; };
         action!("constant", |lexer: &mut Lexer| {
+            let token = Token::T_CONSTANT(lexer.input_stream.current_token_string());
+            lexer.emit_token(token);
+
             let next_state = lexer.arg_or_cmdarg();
             lexer.set_next_state(next_state);
             lexer.flag_breaking();
@@ -362,6 +450,10 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
         //       => { emit_table(PUNCTUATION)
         //            fnext expr_dot; fbreak; };
         action_with_literal!( r"(\.)|(&\.)|(::)", |lexer: &mut Lexer| {
+            if lexer.input_stream.current_token().unwrap() == "&." {
+                let range = lexer.current_range();
+                lexer.check_version("safe navigation operator", RubyVersion::Ruby23, range);
+            }
             lexer.emit_token_from_table("punctuation");
             lexer.set_next_state(state!("expr_dot"));
             lexer.flag_breaking();
@@ -383,10 +475,30 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
         //         end
         //         fnext expr_arg; fbreak;
         //       };
-        // 
-        // TODO UNIMPL
-        // TODO EMBEDDED ACTIONS
-        // 
+        // NOTE separated into 2 actions like the one of expr_dot, see ambiguous_fid_suffix there
+        action_with_literal!(
+            format!(r"{}[?!]", pattern_lit!("bareword")),
+            |lexer: &mut Lexer| {
+                let token = Token::T_FID(lexer.input_stream.current_token_string());
+                lexer.emit_token(token);
+                lexer.set_next_state(state!("expr_arg"));
+                lexer.flag_breaking();
+            }
+        ),
+        action_with_literal!(
+            format!(r"{}[?!]=", pattern_lit!("bareword")),
+            |lexer: &mut Lexer| {
+                let ts = lexer.input_stream.ts.unwrap();
+                let tm = lexer.input_stream.te.unwrap() - 2;
+
+                let token = Token::T_IDENTIFIER(lexer.input_stream.token_string_from_range(ts, tm));
+                lexer.emit_token_with_range(token, Range::new(ts, tm));
+                lexer.input_stream.p = tm as isize - 1;
+
+                lexer.set_next_state(state!("expr_arg"));
+                lexer.flag_breaking();
+            }
+        ),
 
         //       #
         //       # OPERATORS
@@ -444,6 +556,9 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
                     "]" | _ => ()
                 };
 
+                // NOTE e_rbrace closed an interpolation and fbreak-ed
+                if lexer.is_breaking { return; }
+
                 lexer.emit_token_from_table("punctuation");
                 lexer.cond.lexpop();
                 lexer.cmdarg.lexpop();
@@ -505,11 +620,12 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
         //       #
 
         //       w_space_comment;
-        action!("w_space_comment", get_shared_action!("noop")),
+        action!("w_space_comment", get_shared_action!("emit_comment")),
 
         //       w_newline
         //       => { fgoto leading_dot; };
         action!("w_newline", |lexer: &mut Lexer| {
+            lexer.invoke_proc("e_heredoc_nl");
             lexer.set_next_state(state!("leading_dot"));
         }),
 
@@ -534,10 +650,21 @@ pub fn construct_machine_expr_end( patterns: &TMatchingPatterns, shared_actions:
         //       => {
         //         diagnostic :fatal, :unexpected, { :character => tok.inspect[1..-2] }
         //       };
-        action!("c_any", |lexer: &mut Lexer| {panic!("UNIMPL");}),
+        action!("c_any", |lexer: &mut Lexer| {
+            let range = lexer.current_range();
+            lexer.unexpected_char(range);
+        }),
 
         //       c_eof => do_eof;
         action!("c_eof", get_shared_action!("do_eof")),
 
     ]
 }
+
+// split `1_000ri` into (`1000`, `ri`)
+fn split_num_suffix(slice: String) -> (String, String) {
+    let suffix_len = slice.chars().rev().take_while(|c| *c == 'r' || *c == 'i').count();
+    let digits_len = slice.len() - suffix_len;
+
+    ( slice[..digits_len].replace("_", ""), String::from(&slice[digits_len..]) )
+}
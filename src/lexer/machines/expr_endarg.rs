@@ -85,11 +85,11 @@ pub fn construct_machine_expr_endarg( patterns: &TMatchingPatterns, shared_actio
         }),
 
         //     w_space_comment;
-        action!("w_space_comment", get_shared_action!("noop")),
+        action!("w_space_comment", get_shared_action!("emit_comment")),
 
         //     c_any
         //     => { fhold; fgoto expr_end; };
-        action_with_literal!("c_any", |lexer: &mut Lexer| {
+        action!("c_any", |lexer: &mut Lexer| {
             lexer.input_stream.hold_current_char();
             lexer.set_next_state(state!("expr_end"));
         }),
@@ -14,6 +14,8 @@ use lexer::shared_actions::TSharedActions;
 
 use parser::token::Token;
 
+use source::range::Range;
+
 pub fn construct_machine_expr_endfn( patterns: &TMatchingPatterns, shared_actions: &TSharedActions ) -> Vec<Box<Action>> {
     let (pattern_literals, pattern_regexs) = (*patterns).clone();
 
@@ -26,6 +28,12 @@ pub fn construct_machine_expr_endfn( patterns: &TMatchingPatterns, shared_action
         };
     }
 
+    macro_rules! pattern_lit {
+        ($pattern_name:expr) => {
+            pattern_literals.get($pattern_name).unwrap()
+        };
+    }
+
     macro_rules! get_shared_action {
         ( $action_name:expr ) => {
             shared_actions.get($action_name).unwrap().clone()
@@ -44,20 +52,25 @@ pub fn construct_machine_expr_endfn( patterns: &TMatchingPatterns, shared_action
         // 
         // tok(@ts, @te-2) -> `foo:`
 
-        box Action {
-            regex: Regex::new(r"^[[:alpha:]][[:alnum:]]*[\\?!]?:[^:]").unwrap(),
-            procedure: |lexer: &mut Lexer| {
-                let slice = lexer.input_stream.token_string_from_range( lexer.input_stream.ts.unwrap(), lexer.input_stream.te.unwrap() - 2 );
+        action_with_literal!(
+            format!(r"{}[^:]", pattern_lit!("label")),
+            |lexer: &mut Lexer| {
+                // NOTE the char after `:` may take more than one byte
+                let last_char_len = lexer.input_stream.current_token().unwrap().chars().last().unwrap().len_utf8();
+                let label_e = lexer.input_stream.te.unwrap() - last_char_len;
+
+                let slice = lexer.input_stream.token_string_from_range( lexer.input_stream.ts.unwrap(), label_e - 1 );
                 let token = Token::T_LABLE(slice);
-                lexer.emit_token(token);
+                let range = Range::new(lexer.input_stream.ts.unwrap(), label_e);
+                lexer.emit_token_with_range(token, range);
                 lexer.input_stream.hold_current_char();
                 lexer.set_next_state(LexingState::ExprLabelarg);
                 lexer.flag_breaking();
             }
-        },
+        ),
 
         //     w_space_comment;
-        action!("w_space_comment", get_shared_action!("noop")),
+        action!("w_space_comment", get_shared_action!("emit_comment")),
 
         //     c_any
         //     => { fhold; fgoto expr_end; };
@@ -31,6 +31,12 @@ pub fn construct_machine_expr_fname( patterns: &TMatchingPatterns, shared_action
         };
     }
 
+    macro_rules! pattern_lit {
+        ($pattern_name:expr) => {
+            pattern_literals.get($pattern_name).unwrap()
+        };
+    }
+
     macro_rules! get_shared_action {
         ( $action_name:expr ) => {
             shared_actions.get($action_name).unwrap().clone()
@@ -43,6 +49,7 @@ pub fn construct_machine_expr_fname( patterns: &TMatchingPatterns, shared_action
         //     fnext expr_endfn; fbreak; };
         action!("keyword", |lexer: &mut Lexer| {
             lexer.emit_token_from_table("keywords_begin");
+            lexer.set_next_state(LexingState::ExprEndfn);
             lexer.flag_breaking();
         }),
 
@@ -59,15 +66,15 @@ pub fn construct_machine_expr_fname( patterns: &TMatchingPatterns, shared_action
         //     bareword [?=!]?
         //     => { emit(:tIDENTIFIER)
         //          fnext expr_endfn; fbreak; };
-        box Action {
-            regex: Regex::new(r"^[[:alpha:]][[:alnum:]]*[?=!]?").unwrap(),
-            procedure: |lexer: &mut Lexer| {
+        action_with_literal!(
+            format!(r"{}[?=!]?", pattern_lit!("bareword")),
+            |lexer: &mut Lexer| {
                 let token =  Token::T_IDENTIFIER( lexer.input_stream.current_token_string() );
                 lexer.emit_token(token);
                 lexer.set_next_state(LexingState::ExprEndfn);
                 lexer.flag_breaking();
             }
-        },
+        ),
 
         //     global_var
         //     => { p = @ts - 1
@@ -89,9 +96,9 @@ pub fn construct_machine_expr_fname( patterns: &TMatchingPatterns, shared_action
         // NOTE
         // separated into 3 actions,
         // since we dont have RegexGroup solution for calculation matching_patterns
-        action!("operator_fname", |lexer: &mut Lexer|{ lexer.emit_token_from_table("punctation"); lexer.flag_breaking(); }),
-        action!("operator_arithmetic", |lexer: &mut Lexer|{ lexer.emit_token_from_table("punctation"); lexer.flag_breaking(); }),
-        action!("operator_rest", |lexer: &mut Lexer|{ lexer.emit_token_from_table("punctation"); lexer.flag_breaking(); }),
+        action!("operator_fname", |lexer: &mut Lexer|{ lexer.emit_token_from_table("punctuation"); lexer.set_next_state(LexingState::ExprEndfn); lexer.flag_breaking(); }),
+        action!("operator_arithmetic", |lexer: &mut Lexer|{ lexer.emit_token_from_table("punctuation"); lexer.set_next_state(LexingState::ExprEndfn); lexer.flag_breaking(); }),
+        action!("operator_rest", |lexer: &mut Lexer|{ lexer.emit_token_from_table("punctuation"); lexer.set_next_state(LexingState::ExprEndfn); lexer.flag_breaking(); }),
 
         //     '::'
         //     => { fhold; fhold; fgoto expr_end; };
@@ -110,7 +117,7 @@ pub fn construct_machine_expr_fname( patterns: &TMatchingPatterns, shared_action
             regex: Regex::new(r"^:").unwrap(),
             procedure: |lexer: &mut Lexer| {
                 lexer.input_stream.hold_current_char();
-                lexer.set_next_state(state!("expr_end"))
+                lexer.set_next_state(state!("expr_beg"))
             }
         },
 
@@ -135,7 +142,7 @@ pub fn construct_machine_expr_fname( patterns: &TMatchingPatterns, shared_action
         },
 
         //     w_any;
-        action!("w_any", get_shared_action!("noop")),
+        action!("w_any", get_shared_action!("emit_comment")),
 
         //     c_any
         //     => { fhold; fgoto expr_end; };
@@ -38,7 +38,7 @@ pub fn construct_machine_expr_labelarg( patterns: &TMatchingPatterns, shared_act
     vec![
 
         //   w_space_comment;
-        action!("w_space_comment", get_shared_action!("noop")),
+        action!("w_space_comment", get_shared_action!("emit_comment")),
 
         //   w_newline
         //   => {
@@ -53,6 +53,7 @@ pub fn construct_machine_expr_labelarg( patterns: &TMatchingPatterns, shared_act
                 lexer.input_stream.hold_current_char();
                 lexer.set_next_state(state!("expr_end"));
             } else {
+                lexer.invoke_proc("e_heredoc_nl");
                 lexer.set_next_state(state!("line_begin"));
             }
         }),
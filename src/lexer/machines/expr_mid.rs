@@ -59,7 +59,7 @@ pub fn construct_machine_expr_mid( patterns: &TMatchingPatterns, shared_actions:
         }),
 
         //     w_space_comment;
-        action!("w_space_comment", get_shared_action!("noop")),
+        action!("w_space_comment", get_shared_action!("emit_comment")),
 
         //     w_newline
         //     => { fhold; fgoto expr_end; };
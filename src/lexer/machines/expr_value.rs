@@ -57,13 +57,12 @@ pub fn construct_machine_expr_value( patterns: &TMatchingPatterns, shared_action
         action_with_literal!(
             "['\"]",
             |lexer: &mut Lexer| {
-                println!("WTF current_token {:?}", lexer.input_stream.current_token());
                 let lit_type = lexer.input_stream.current_token().unwrap();
                 let lit_delimiter = lexer.input_stream.current_token().unwrap();
 
                 let ts = lexer.input_stream.ts.unwrap();
                 let mut literal = Literal::new( lit_type, lit_delimiter, ts, None, false, false, false );
-                for token in literal.consume_tokens_to_emit() { lexer.emit_token(token); }
+                lexer.emit_literal_tokens(&mut literal);
 
                 let next_state = lexer.push_literal(literal);
                 lexer.set_next_state(next_state);
@@ -71,11 +70,14 @@ pub fn construct_machine_expr_value( patterns: &TMatchingPatterns, shared_action
         ),
 
         //       w_space_comment;
-        action!("w_space_comment", get_shared_action!("noop")),
+        action!("w_space_comment", get_shared_action!("emit_comment")),
 
         //       w_newline
         //       => { fgoto line_begin; };
-        action!("w_newline", |lexer: &mut Lexer| { lexer.set_next_state(state!("line_begin")); }),
+        action!("w_newline", |lexer: &mut Lexer| {
+            lexer.invoke_proc("e_heredoc_nl");
+            lexer.set_next_state(state!("line_begin"));
+        }),
 
         //       c_any
         //       => { fhold; fgoto expr_beg; };
@@ -46,8 +46,8 @@ pub fn construct_machine_expr_variable( patterns: &TMatchingPatterns, shared_act
             let token = Token::T_GVAR( lexer.input_stream.current_token_string() );
             lexer.emit_token(token);
 
-            let last_state = lexer.last_state.clone().unwrap();
-            lexer.set_next_state(last_state);
+            let next_state = lexer.stack_pop();
+            lexer.set_next_state(next_state);
 
             lexer.flag_breaking();
         }),
@@ -59,7 +59,7 @@ pub fn construct_machine_expr_variable( patterns: &TMatchingPatterns, shared_act
         //     end
         // TODO INCOMPLETE
         action!("class_var_v", |lexer: &mut Lexer| {
-            panic!("class_var_v UNIMPL");
+            lexer.unimplemented("class variable");
         }),
 
         //     instance_var_v
@@ -76,8 +76,8 @@ pub fn construct_machine_expr_variable( patterns: &TMatchingPatterns, shared_act
             let token = Token::T_IVAR(lexer.input_stream.current_token_string());
             lexer.emit_token(token);
 
-            let last_state = lexer.last_state.clone().unwrap();
-            lexer.set_next_state(last_state);
+            let next_state = lexer.stack_pop();
+            lexer.set_next_state(next_state);
 
             lexer.flag_breaking();
         }),
@@ -1,35 +1,4 @@
-// TODO
-//   regexp_modifiers := |*
-//       [A-Za-z]+
-//       => {
-//         unknown_options = tok.scan(/[^imxouesn]/)
-//         if unknown_options.any?
-//           diagnostic :error, :regexp_options,
-//                      { :options => unknown_options.join }
-//         end
-// 
-//         emit(:tREGEXP_OPT)
-// 
-//         if @version < 24
-//           fnext expr_end;
-//         else
-//           fnext expr_endarg;
-//         end
-// 
-//         fbreak;
-//       };
-// 
-//       any
-//       => {
-//         emit(:tREGEXP_OPT, tok(@ts, @te - 1), @ts, @te - 1)
-//         fhold;
-//         if @version < 24
-//           fgoto expr_end;
-//         else
-//           fgoto expr_endarg;
-//         end
-//       };
-//   *|;
+use std::collections::HashMap;
 
 use regex::Regex;
 
@@ -40,6 +9,8 @@ use lexer::matching_patterns::TMatchingPatterns;
 use lexer::shared_actions::TSharedActions;
 
 use parser::token::Token;
+use source::range::Range;
+use diagnostic::Level;
 
 pub fn construct_machine_interp_words( patterns: &TMatchingPatterns, shared_actions: &TSharedActions ) -> Vec<Box<Action>> {
     let (pattern_literals, pattern_regexs) = (*patterns).clone();
@@ -69,8 +40,6 @@ pub fn construct_machine_interp_words( patterns: &TMatchingPatterns, shared_acti
 //       interp_code => extend_interp_code;
 //       interp_var  => extend_interp_var;
 //       e_bs escape => extend_string_escaped;
-//           TODO NOTE e_bs embedded action
-//           TODO NOTE escape embedded action
 //       c_space+    => extend_string_space;
 //       c_eol       => extend_string_eol;
 //       c_any       => extend_string;
@@ -78,14 +47,10 @@ pub fn construct_machine_interp_words( patterns: &TMatchingPatterns, shared_acti
     vec![
         action!("interp_code", get_shared_action!("extend_interp_code")),
         action!("interp_var", get_shared_action!("extend_interp_var")),
-        // TODO
-        // action_with_literal!(
-        //     format!(r"{}{}",
-        //         pattern_lit!("e_bs"), pattern_lit!("escape"),
-        //     ), |lexer: &mut Lexer| {
-        //         panic!("UNIMPL");
-        //     }
-        // ),
+        action_with_literal!(
+            format!(r"{}{}", pattern_lit!("e_bs"), pattern_lit!("escape")),
+            get_shared_action!("extend_string_escaped")
+        ),
         action_with_literal!(format!(r"{}+", pattern_lit!("c_space")), get_shared_action!("extend_string_space")),
         action!("c_eol", get_shared_action!("extend_string_eol")),
         action!("c_any", get_shared_action!("extend_string")),
@@ -120,21 +85,16 @@ pub fn construct_machine_interp_string( patterns: &TMatchingPatterns, shared_act
 //       interp_code => extend_interp_code;
 //       interp_var  => extend_interp_var;
 //       e_bs escape => extend_string_escaped;
-//           TODO NOTE e_bs embedded action
 //       c_eol       => extend_string_eol;
 //       c_any       => extend_string;
 //   *|;
     vec![
         action!("interp_code", get_shared_action!("extend_interp_code")),
         action!("interp_var", get_shared_action!("extend_interp_var")),
-        // TODO
-        // action_with_literal!(
-        //     format!(r"{}{}",
-        //         pattern_lit!("e_bs"), pattern_lit!("escape"),
-        //     ), |lexer: &mut Lexer| {
-        //         panic!("UNIMPL");
-        //     }
-        // ),
+        action_with_literal!(
+            format!(r"{}{}", pattern_lit!("e_bs"), pattern_lit!("escape")),
+            get_shared_action!("extend_string_escaped")
+        ),
         action!("c_eol", get_shared_action!("extend_string_eol")),
         action!("c_any", get_shared_action!("extend_string")),
     ]
@@ -166,21 +126,15 @@ pub fn construct_machine_plain_words( patterns: &TMatchingPatterns, shared_actio
 
 //   plain_words := |*
 //       e_bs c_any  => extend_string_escaped;
-//           TODO NOTE e_bs embedded action
 //       c_space+    => extend_string_space;
 //       c_eol       => extend_string_eol;
 //       c_any       => extend_string;
 //   *|;
     vec![
-        // TODO
-        // action_with_literal!(
-        //     format!(r"{}{}",
-        //         pattern_lit!("e_bs"), pattern_lit!("c_any"),
-        //     ), |lexer: &mut Lexer| {
-        //         // TODO e_bs embedded action
-        //         panic!("UNIMPL");
-        //     }
-        // ),
+        action_with_literal!(
+            format!(r"{}{}", pattern_lit!("e_bs"), pattern_lit!("c_any")),
+            get_shared_action!("extend_string_escaped")
+        ),
         action_with_literal!(format!(r"{}+", pattern_lit!("c_space")), get_shared_action!("extend_string_space")),
         action!("c_eol", get_shared_action!("extend_string_eol")),
         action!("c_any", get_shared_action!("extend_string")),
@@ -215,23 +169,16 @@ pub fn construct_machine_plain_string( patterns: &TMatchingPatterns, shared_acti
 //   plain_string := |*
 //       '\\' c_nl   => extend_string_eol;
 //       e_bs c_any  => extend_string_escaped;
-//           TODO NOTE e_bs embedded action
 //       c_eol       => extend_string_eol;
 //       c_any       => extend_string;
 //   *|;
     vec![
-        // TODO
-        //       '\\' c_nl   => extend_string_eol;
-
-        // TODO
-        // action_with_literal!(
-        //     format!(r"{}{}",
-        //         pattern_lit!("e_bs"), pattern_lit!("c_any"),
-        //     ), |lexer: &mut Lexer| {
-        //         panic!("UNIMPL");
-        //     }
-        // ),
-
+        // NOTE listed first, to win over `e_bs c_any` which matches as long
+        action_with_literal!(format!(r"\\{}", pattern_lit!("c_nl")), get_shared_action!("extend_string_eol")),
+        action_with_literal!(
+            format!(r"{}{}", pattern_lit!("e_bs"), pattern_lit!("c_any")),
+            get_shared_action!("extend_string_escaped")
+        ),
         action!("c_eol", get_shared_action!("extend_string_eol")),
         action!("c_any", get_shared_action!("extend_string")),
     ]
@@ -387,3 +334,82 @@ pub fn construct_machine_plain_backslash_delimited_words( patterns: &TMatchingPa
         action!("c_any", get_shared_action!("extend_string")),
     ]
 }
+
+pub fn construct_machine_regexp_modifiers( patterns: &TMatchingPatterns, shared_actions: &TSharedActions ) -> Vec<Box<Action>> {
+    let (pattern_literals, pattern_regexs) = (*patterns).clone();
+
+    macro_rules! action {
+        ($pattern_name:expr, $procedure:expr) => {
+            box Action {
+                regex: pattern_regexs.get($pattern_name).expect(&format!("no matching_pattern: {:?}", $pattern_name)).clone(),
+                procedure: $procedure
+            }
+        };
+    }
+
+//   regexp_modifiers := |*
+//       [A-Za-z]+
+//       => {
+//         unknown_options = tok.scan(/[^imxouesn]/)
+//         if unknown_options.any?
+//           diagnostic :error, :regexp_options,
+//                      { :options => unknown_options.join }
+//         end
+// 
+//         emit(:tREGEXP_OPT)
+// 
+//         if @version < 24
+//           fnext expr_end;
+//         else
+//           fnext expr_endarg;
+//         end
+// 
+//         fbreak;
+//       };
+// 
+//       any
+//       => {
+//         emit(:tREGEXP_OPT, tok(@ts, @te - 1), @ts, @te - 1)
+//         fhold;
+//         if @version < 24
+//           fgoto expr_end;
+//         else
+//           fgoto expr_endarg;
+//         end
+//       };
+//   *|;
+    vec![
+        action_with_literal!(r"[A-Za-z]+", |lexer: &mut Lexer| {
+            let unknown_options: String = lexer.input_stream.current_token().unwrap().chars().filter(|c| !"imxouesn".contains(*c)).collect();
+            if !unknown_options.is_empty() {
+                let mut arguments = HashMap::new();
+                arguments.insert("options", unknown_options);
+                let range = lexer.current_range();
+                lexer.diagnostic(Level::Error, "regexp_options", arguments, range);
+            }
+
+            let token = Token::T_REGEXP_OPT(lexer.input_stream.current_token_string());
+            lexer.emit_token(token);
+
+            // NOTE ignored ruby < 24 stuff
+            lexer.set_next_state(state!("expr_endarg"));
+            lexer.flag_breaking();
+        }),
+
+        // NOTE
+        // whitequark's `any` also matches the trailing \0 of source at eof,
+        // separated into c_any and c_eof, since we have no such \0 and there's nothing to fhold at eof
+        action!("c_any", |lexer: &mut Lexer| {
+            let ts = lexer.input_stream.ts.unwrap();
+            lexer.emit_token_with_range(Token::T_REGEXP_OPT(String::from("")), Range::new(ts, ts));
+            lexer.input_stream.hold_current_char();
+            lexer.set_next_state(state!("expr_endarg"));
+        }),
+        action!("c_eof", |lexer: &mut Lexer| {
+            let ts = lexer.input_stream.ts.unwrap();
+            lexer.emit_token_with_range(Token::T_REGEXP_OPT(String::from("")), Range::new(ts, ts));
+            lexer.set_next_state(state!("expr_endarg"));
+            lexer.flag_breaking();
+        }),
+    ]
+}
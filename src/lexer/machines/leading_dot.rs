@@ -7,6 +7,7 @@ use lexer::matching_patterns::TMatchingPatterns;
 use lexer::shared_actions::TSharedActions;
 
 use parser::token::Token;
+use source::range::Range;
 
 pub fn construct_machine_leading_dot( patterns: &TMatchingPatterns, shared_actions: &TSharedActions ) -> Vec<Box<Action>> {
     let (pattern_literals, pattern_regexs) = (*patterns).clone();
@@ -41,7 +42,12 @@ pub fn construct_machine_leading_dot( patterns: &TMatchingPatterns, shared_actio
         action_with_literal!(
             format!(r"{}*(\.|(&\.))", pattern_lit!("c_space")),
             |lexer: &mut Lexer| {
-                panic!("UNIMPL");
+                let current_slice = lexer.input_stream.current_token().unwrap();
+                let c_space_len = current_slice.chars().take_while(|c| *c != '.' && *c != '&').count();
+                let tm = lexer.input_stream.ts.unwrap() + c_space_len;
+
+                lexer.input_stream.p = tm as isize - 1;
+                lexer.set_next_state(state!("expr_end"));
             }
         ),
 
@@ -49,8 +55,10 @@ pub fn construct_machine_leading_dot( patterns: &TMatchingPatterns, shared_actio
         //       => { emit(:tNL, nil, @newline_s, @newline_s + 1)
         //            fhold; fnext line_begin; fbreak; };
         action!("any", |lexer: &mut Lexer| {
-            lexer.emit_token(Token::T_NL);
-            lexer.input_stream.hold_current_char();
+            let newline_s = lexer.newline_s;
+            lexer.emit_token_with_range(Token::T_NL, Range::new(newline_s, newline_s + 1));
+            // NOTE any matches nothing at EOF, where fhold would step back into the last token
+            lexer.input_stream.hold_current_token();
             lexer.set_next_state(state!("line_begin"));
             lexer.flag_breaking();
         })
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use regex::Regex;
 
 use lexer::Lexer;
@@ -6,6 +8,9 @@ use lexer::action::{Action};
 use lexer::matching_patterns::TMatchingPatterns;
 use lexer::shared_actions::TSharedActions;
 
+use source::range::Range;
+use diagnostic::Level;
+
 pub fn construct_machine_line_begin( patterns: &TMatchingPatterns, shared_actions: &TSharedActions ) -> Vec<Box<Action>> {
     let (pattern_literals, pattern_regexs) = (*patterns).clone();
 
@@ -32,7 +37,7 @@ pub fn construct_machine_line_begin( patterns: &TMatchingPatterns, shared_action
 
     vec![
         //       w_any;
-        action!("w_any", get_shared_action!("noop")),
+        action!("w_any", get_shared_action!("emit_comment")),
 
         //       '=begin' ( c_space | c_nl_zlen )
         //       => { @eq_begin_s = @ts
@@ -40,18 +45,31 @@ pub fn construct_machine_line_begin( patterns: &TMatchingPatterns, shared_action
         action_with_literal!(
             format!(r"=begin({}|{})", pattern_lit!("c_space"), pattern_lit!("c_nl_zlen")),
             |lexer: &mut Lexer| {
-                // TODO @eq_begin_s
+                lexer.eq_begin_s = lexer.input_stream.ts.unwrap();
                 lexer.set_next_state(state!("line_comment"));
             }
         ),
 
         //       '__END__' ( c_eol - zlen )
         //       => { p = pe - 3 };
+        // NOTE
+        // whitequark jumps to the end of source, we stop lexing and keep where the data starts, see data_s,
+        // a streamed lexer doesn't know where the end is
+        // 
+        // `\r\n` is already `\n` in a source buffer but not in a stream,
+        // and line_begin is entered again after the whitespaces before `__END__`, which ruby doesn't allow
         action_with_literal!(
-            // TODO c_eol - zlen
-            r"__END__\n",
+            r"__END__(\r?\n|\z)",
             |lexer: &mut Lexer| {
-                panic!("UNIMPL");
+                let ts = lexer.input_stream.ts.unwrap();
+                if !lexer.input_stream.is_line_begin(ts) {
+                    lexer.input_stream.hold_current_token();
+                    lexer.set_next_state(state!("expr_value"));
+                    return;
+                }
+
+                lexer.data_s = Some(lexer.input_stream.te.unwrap());
+                lexer.flag_breaking();
             }
         ),
 
@@ -66,3 +84,58 @@ pub fn construct_machine_line_begin( patterns: &TMatchingPatterns, shared_action
         action!("c_eof", get_shared_action!("do_eof")),
     ]
 }
+
+//   #
+//   # === EMBEDDED DOCUMENT (aka BLOCK COMMENT) PARSING ===
+//   #
+
+pub fn construct_machine_line_comment( patterns: &TMatchingPatterns, shared_actions: &TSharedActions ) -> Vec<Box<Action>> {
+    let (pattern_literals, _) = (*patterns).clone();
+
+    macro_rules! pattern_lit {
+        ($pattern_name:expr) => {
+            pattern_literals.get($pattern_name).unwrap()
+        };
+    }
+
+    macro_rules! get_shared_action {
+        ( $action_name:expr ) => {
+            shared_actions.get($action_name).unwrap().clone()
+        };
+    }
+
+    vec![
+        //       '=end' c_line* c_nl_zlen
+        //       => {
+        //         emit_comment(@eq_begin_s, @te)
+        //         fgoto line_begin;
+        //       };
+        action_with_literal!(
+            format!(r"=end{}*({})", pattern_lit!("c_line"), pattern_lit!("c_nl_zlen")),
+            |lexer: &mut Lexer| {
+                let range = Range::new(lexer.eq_begin_s, lexer.input_stream.te.unwrap());
+                lexer.emit_comment(range);
+                lexer.set_next_state(state!("line_begin"));
+            }
+        ),
+
+        //       c_line* c_nl;
+        action_with_literal!(
+            format!(r"{}*{}", pattern_lit!("c_line"), pattern_lit!("c_nl")),
+            get_shared_action!("noop")
+        ),
+
+        //       c_line* zlen
+        //       => {
+        //         diagnostic :fatal, :embedded_document, nil,
+        //                    range(@eq_begin_s, @eq_begin_s + '=begin'.length)
+        //       };
+        action_with_literal!(
+            format!(r"{}*$", pattern_lit!("c_line")),
+            |lexer: &mut Lexer| {
+                let range = Range::new(lexer.eq_begin_s, lexer.eq_begin_s + "=begin".len());
+                lexer.diagnostic(Level::Fatal, "embedded_document", HashMap::new(), range);
+            }
+        ),
+    ]
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use regex::RegexSet;
+
 use lexer::lexing_state::LexingState;
 use lexer::action::{Action};
 use lexer::matching_patterns;
@@ -23,7 +25,27 @@ mod interpolation; use self::interpolation::*;
 mod expr_variable; use self::expr_variable::*;
 mod leading_dot; use self::leading_dot::*;
 
-pub fn construct(shared_actions: &shared_actions::TSharedActions) -> HashMap<LexingState, Vec<Box<Action>>> {
+// NOTE
+// ragel compiles every machine into a single DFA, we get close to that by combining
+// the patterns of a machine into a RegexSet, which tells in one pass which actions match,
+// only these actions are then matched alone to get their length, see InputStream::longest_matching_action
+// 
+// every pattern is anchored with `^`, so the scanning stops where the longest token would end,
+// instead of going through the rest of input
+pub struct Machine {
+    pub actions: Vec<Box<Action>>,
+    pub regex_set: RegexSet,
+}
+
+impl Machine {
+    pub fn new(actions: Vec<Box<Action>>) -> Machine {
+        let regex_set = RegexSet::new(actions.iter().map(|action| action.regex.as_str())).expect("can't build RegexSet for machine");
+
+        Machine { actions, regex_set }
+    }
+}
+
+pub fn construct(shared_actions: &shared_actions::TSharedActions) -> HashMap<LexingState, Machine> {
     let patterns = matching_patterns::construct();
 
     let mut machines = HashMap::new();
@@ -31,11 +53,12 @@ pub fn construct(shared_actions: &shared_actions::TSharedActions) -> HashMap<Lex
     macro_rules! machine {
         ( $state:expr, $actions:expr ) => {
             let state = $state.parse::<LexingState>().expect("can't parse LexingState");
-            machines.insert(state, $actions);
+            machines.insert(state, Machine::new($actions));
         };
     }
 
     machine!("line_begin", construct_machine_line_begin(&patterns, &shared_actions));
+    machine!("line_comment", construct_machine_line_comment(&patterns, &shared_actions));
     machine!("expr_dot", construct_machine_expr_dot(&patterns, &shared_actions));
     machine!("expr_fname", construct_machine_expr_fname(&patterns, &shared_actions));
     machine!("expr_value", construct_machine_expr_value(&patterns, &shared_actions));
@@ -56,6 +79,7 @@ pub fn construct(shared_actions: &shared_actions::TSharedActions) -> HashMap<Lex
     machine!("plain_backslash_delimited", construct_machine_plain_backslash_delimited(&patterns, &shared_actions));
     machine!("interp_backslash_delimited_words", construct_machine_interp_backslash_delimited_words(&patterns, &shared_actions));
     machine!("plain_backslash_delimited_words", construct_machine_plain_backslash_delimited_words(&patterns, &shared_actions));
+    machine!("regexp_modifiers", construct_machine_regexp_modifiers(&patterns, &shared_actions));
 
     machine!("expr_variable", construct_machine_expr_variable(&patterns, &shared_actions));
     machine!("leading_dot", construct_machine_leading_dot(&patterns, &shared_actions));
@@ -29,6 +29,12 @@ pub fn construct() -> TMatchingPatterns {
         };
     }
 
+    macro_rules! pattern_lit {
+        ($pattern_name:expr) => {
+            pattern_literals.get($pattern_name).unwrap()
+        };
+    }
+
     // 
     // NATIVE
     // 
@@ -55,22 +61,34 @@ pub fn construct() -> TMatchingPatterns {
     pattern!("c_any", r"(?s)."); // TODO NOT CORRESPONDING
 
     //   c_nl_zlen  = c_nl | zlen;
-    pattern!("c_nl_zlen", r"\n"); // TODO NOT CORRESPONDING
+    pattern!("c_nl_zlen", r"\n|$");
 
     //   c_line     = any - c_nl_zlen;
     pattern!("c_line", r"[^\n]"); // TODO NOT CORRESPONDING
 
-    // TODO
+    // NOTE
+    // the POSIX classes like [[:alpha:]] are ASCII only and leave out `_`,
+    // every non-ASCII char counts as a lowercase letter instead, like in lexer.rl
+
     //   c_unicode  = c_any - 0x00..0x7f;
+    pattern!("c_unicode", r"[\x{80}-\x{10FFFF}]");
     //   c_upper    = [A-Z];
+    pattern!("c_upper", r"[A-Z]");
     //   c_lower    = [a-z_]  | c_unicode;
+    pattern!("c_lower", r"[a-z_\x{80}-\x{10FFFF}]");
     //   c_alpha    = c_lower | c_upper;
+    pattern!("c_alpha", r"[A-Za-z_\x{80}-\x{10FFFF}]");
     //   c_alnum    = c_alpha | [0-9];
+    pattern!("c_alnum", r"[0-9A-Za-z_\x{80}-\x{10FFFF}]");
 
     // 
     // TOKEN DEFINITIONS
     // 
 
+    // NOTE
+    // ragel picks the longest alternative, regex picks the leftmost one,
+    // so longer alternatives are listed before their prefixes, like `**` before `*`
+
     // # All operators are punctuation. There is more to punctuation
     // # than just operators. Operators can be overridden by user;
     // # punctuation can not.
@@ -78,17 +96,17 @@ pub fn construct() -> TMatchingPatterns {
     // # A list of operators which are valid in the function name context, but
     // # have different semantics in others.
     // operator_fname      = '[]' | '[]=' | '`'  | '-@' | '+@' | '~@'  | '!@' ;
-    pattern!("operator_fname", r"(\[\])|(\[\]=)|`|(-@)|(\+@)|(~@)|(!@)");
+    pattern!("operator_fname", r"(\[\]=)|(\[\])|`|(-@)|(\+@)|(~@)|(!@)");
 
     // # A list of operators which can occur within an assignment shortcut (+ → +=).
     // operator_arithmetic = '&'  | '|'   | '&&' | '||' | '^'  | '+'   | '-'  |
     //                       '*'  | '/'   | '**' | '~'  | '<<' | '>>'  | '%'  ;
-    pattern!("operator_arithmetic", r"(&)|(\|)|(&&)|(\|\|)|(\^)|(\+)|(-)|(\*)|(/)|(\*\*)|(~)|(<<)|(>>)|(%)");
+    pattern!("operator_arithmetic", r"(&&)|(&)|(\|\|)|(\|)|(\^)|(\+)|(-)|(\*\*)|(\*)|(/)|(~)|(<<)|(>>)|(%)");
 
     // # A list of all user-definable operators not covered by groups above.
     // operator_rest       = '=~' | '!~' | '==' | '!=' | '!'   | '===' |
     //                       '<'  | '<=' | '>'  | '>=' | '<=>' | '=>'  ;
-    pattern!("operator_rest", "(=~)|(!~)|(==)|(!=)|(!)|(===)|(<)|(<=)|(>)|(>=)|(<=>)|(=>)");
+    pattern!("operator_rest", "(=~)|(!~)|(===)|(==)|(!=)|(!)|(<=>)|(<=)|(<)|(>=)|(>)|(=>)");
 
     //   # Note that `{` and `}` need to be referred to as e_lbrace and e_rbrace,
     //   # as they are ambiguous with interpolation `#{}` and should be counted.
@@ -98,12 +116,12 @@ pub fn construct() -> TMatchingPatterns {
     //   # beginning of expression.
     //   punctuation_begin   = '-'  | '+'  | '::' | '('  | '['  |
     //                         '*'  | '**' | '&'  ;
-    pattern!("punctuation_begin", r"(-)|(\+)|(::)|(\()|(\[)|(\*)|(\*\*)|(&)");
+    pattern!("punctuation_begin", r"(-)|(\+)|(::)|(\()|(\[)|(\*\*)|(\*)|(&)");
 
     //   # A list of all punctuation except punctuation_begin.
     //   punctuation_end     = ','  | '='  | '->' | '('  | '['  | ']'   |
     //                         '::' | '?'  | ':'  | '.'  | '..' | '...' ;
-    pattern!("punctuation_end", r"(,)|(=)|(->)|(\()|(\[)|(\])|(::)|(\?)|(:)|(\.)|(\.\.)|(\.\.\.)");
+    pattern!("punctuation_end", r"(,)|(=)|(->)|(\()|(\[)|(\])|(::)|(\?)|(:)|(\.\.\.)|(\.\.)|(\.)");
 
     // # A list of keywords which have different meaning at the beginning of expression.
     // keyword_modifier    = 'if'     | 'unless' | 'while'  | 'until' | 'rescue' ;
@@ -113,7 +131,7 @@ pub fn construct() -> TMatchingPatterns {
     // # same post-processing as method calls or commands. Example: `yield 1`,
     // # `yield (1)`, `yield(1)`, are interpreted as if `yield` was a function.
     // keyword_with_arg    = 'yield'  | 'super'  | 'not'    | 'defined?' ;
-    pattern!("keyword_with_arg", "(yield)|(super)|(not)|(defined?)");
+    pattern!("keyword_with_arg", "(yield)|(super)|(not)|(defined\\?)");
 
     // # A list of keywords which accept a literal function name as an argument.
     // keyword_with_fname  = 'def'    | 'undef'  | 'alias'  ;
@@ -146,19 +164,19 @@ pub fn construct() -> TMatchingPatterns {
     //     pattern_literals.get("keyword_with_arg").unwrap(), pattern_literals.get("keyword_with_end").unwrap(),
     //     pattern_literals.get("keyword_with_fname").unwrap(), pattern_literals.get("keyword_modifier").unwrap()
     // );
-    pattern!("keyword", "(else)|(case)|(ensure)|(module)|(elsif)|(then)|(for)|(in)|(do)|(when)|(begin)|(class)|(and)|(or)|(rescue)|(return)|(break)|(next)|(end)|(self)|(true)|(false)|(retry)|(redo)|(nil)|(BEGIN)|(END)|(__FILE__)|(__LINE__)|(__ENCODING__)|(yield)|(super)|(not)|(defined?)|(def)|(undef)|(alias)|(if)|(unless)|(while)|(until)|(rescue)");
+    pattern!("keyword", "(else)|(case)|(ensure)|(module)|(elsif)|(then)|(for)|(in)|(do)|(when)|(begin)|(class)|(and)|(or)|(rescue)|(return)|(break)|(next)|(end)|(self)|(true)|(false)|(retry)|(redo)|(nil)|(BEGIN)|(END)|(__FILE__)|(__LINE__)|(__ENCODING__)|(yield)|(super)|(not)|(defined\\?)|(def)|(undef)|(alias)|(if)|(unless)|(while)|(until)|(rescue)");
 
     //   constant       = c_upper c_alnum*;
-    pattern!("constant", "[[:upper:]][[:alnum:]]*");
+    pattern!("constant", format!("{}{}*", pattern_lit!("c_upper"), pattern_lit!("c_alnum")));
     //   bareword       = c_alpha c_alnum*;
-    pattern!("bareword", "[[:alpha:]][[:alnum:]]*");
+    pattern!("bareword", format!("{}{}*", pattern_lit!("c_alpha"), pattern_lit!("c_alnum")));
 
     //   call_or_var    = c_lower c_alnum*;
-    pattern!("call_or_var", "[[:lower:]][[:alnum:]]*");
+    pattern!("call_or_var", format!("{}{}*", pattern_lit!("c_lower"), pattern_lit!("c_alnum")));
     //   class_var      = '@@' bareword;
-    pattern!("class_var", "@@[[:alpha:]][[:alnum:]]*");
+    pattern!("class_var", format!("@@{}", pattern_lit!("bareword")));
     //   instance_var   = '@' bareword;
-    pattern!("instance_var", "@[[:alpha:]][[:alnum:]]*");
+    pattern!("instance_var", format!("@{}", pattern_lit!("bareword")));
     //   global_var     = '$'
     //       ( bareword | digit+
     //       | [`'+~*$&?!@/\\;,.=:<>"] # `
@@ -168,50 +186,62 @@ pub fn construct() -> TMatchingPatterns {
     // TODO use macro to combine complex pattern
     pattern!("global_var",
         format!(r"\$(({})|({})|({})|({}))",
-            r"[[:alpha:]][[:alnum:]]*",
+            pattern_lit!("bareword"),
             r"[[:digit:]]+",
             r#"[`'\+~\*$&\?!@/\\;,\.=:<>"]"#,
-            r"-[[:alnum:]]"
+            format!("-{}", pattern_lit!("c_alnum"))
         )
     );
 
     //   # Ruby accepts (and fails on) variables with leading digit
     //   # in literal context, but not in unquoted symbol body.
     //   class_var_v    = '@@' c_alnum+;
-    pattern!("class_var_v", "@@[:alnum:]+");
+    pattern!("class_var_v", format!("@@{}+", pattern_lit!("c_alnum")));
     //   instance_var_v = '@' c_alnum+;
-    pattern!("instance_var_v", "@[:alnum:]+");
+    pattern!("instance_var_v", format!("@{}+", pattern_lit!("c_alnum")));
 
     //   label          = bareword [?!]? ':';
-    pattern!("label", r"[[:alpha:]][[:alnum:]]*[\?!]?:");
+    pattern!("label", format!(r"{}[\?!]?:", pattern_lit!("bareword")));
 
     //   #
     //   # === NUMERIC PARSING ===
     //   #
 
     //   int_hex  = ( xdigit+ '_' )* xdigit* '_'? ;
+    pattern!("int_hex", "([[:xdigit:]]+_)*[[:xdigit:]]*_?");
     //   int_dec  = ( digit+ '_' )* digit* '_'? ;
     pattern!("int_dec", "([[:digit:]]+_)*[[:digit:]]*_?");
     //   int_bin  = ( [01]+ '_' )* [01]* '_'? ;
+    pattern!("int_bin", "([01]+_)*[01]*_?");
 
     //   flo_int  = [1-9] [0-9]* ( '_' digit+ )* | '0';
+    pattern!("flo_int", r"[1-9][[:digit:]]*(_[[:digit:]]+)*|0");
     //   flo_frac = '.' ( digit+ '_' )* digit+;
+    pattern!("flo_frac", r"\.([[:digit:]]+_)*[[:digit:]]+");
     //   flo_pow  = [eE] [+\-]? ( digit+ '_' )* digit+;
+    pattern!("flo_pow", r"[eE][+\-]?([[:digit:]]+_)*[[:digit:]]+");
+
+    // NOTE
+    // @num_xfrm is not set here, see Lexer#emit_num
+    // and `ri` must come before `r` since regex alternation is not longest-match
 
     //   int_suffix =
     //     ''   % { @num_xfrm = lambda { |chars| emit(:tINTEGER,   chars) } }
     //   | 'r'  % { @num_xfrm = lambda { |chars| emit(:tRATIONAL,  Rational(chars)) } }
     //   | 'i'  % { @num_xfrm = lambda { |chars| emit(:tIMAGINARY, Complex(0, chars)) } }
     //   | 'ri' % { @num_xfrm = lambda { |chars| emit(:tIMAGINARY, Complex(0, Rational(chars))) } };
+    pattern!("int_suffix", r"(ri|r|i)?");
 
     //   flo_pow_suffix =
     //     ''   % { @num_xfrm = lambda { |chars| emit(:tFLOAT,     Float(chars)) } }
     //   | 'i'  % { @num_xfrm = lambda { |chars| emit(:tIMAGINARY, Complex(0, Float(chars))) } };
+    pattern!("flo_pow_suffix", r"i?");
 
     //   flo_suffix =
     //     flo_pow_suffix
     //   | 'r'  % { @num_xfrm = lambda { |chars| emit(:tRATIONAL,  Rational(chars)) } }
     //   | 'ri' % { @num_xfrm = lambda { |chars| emit(:tIMAGINARY, Complex(0, Rational(chars))) } };
+    pattern!("flo_suffix", r"(ri|r|i)?");
 
 
     //   #
@@ -226,6 +256,7 @@ pub fn construct() -> TMatchingPatterns {
     //   # The amount of code required to parse \M\C stuff correctly is ridiculous.
 
     //   escaped_nl = "\\" c_nl;
+    pattern!("escaped_nl", r"\\\n");
 
     //   action unicode_points {
     //     @escape = ""
@@ -368,7 +399,38 @@ pub fn construct() -> TMatchingPatterns {
     //       diagnostic :fatal, :escape_eof, nil, range(p - 1, p)
     //     }
     //   );
-    // TODO
+    //
+    // NOTE
+    // this pattern only finds where an escape ends, the embedded actions are done by `unescape` in shared_functions,
+    // regex alternations are leftmost-first instead of longest-match like ragel,
+    // so longer alternatives go first, e.g. \M-\C-x before \M-\Cx before \M-\C
+    let maybe_escaped_char = r"(?:\\(?s:.)|[^\\])";
+    let maybe_escaped_ctrl_char = r"(?:\\(?s:.)|\?|[^\\?])";
+    let escape_alternatives = vec![
+        // \377
+        String::from(r"[0-7]{1,3}"),
+        // \xff, %q[\x]
+        String::from(r"x[[:xdigit:]]{1,2}"),
+        String::from(r"x(?:[^[:xdigit:]]|\z)"),
+        // u{not hex} or u{}
+        String::from(r"u\{[^[:xdigit:] \t}]*\}"),
+        // \u{  \t  123  \t 456   \t\t }, or an unterminated one
+        String::from(r"u\{[ \t]*(?:[[:xdigit:]]{1,6}[ \t]+)*(?:[[:xdigit:]]{1,6}[ \t]*\}|[[:xdigit:]]*[^[:xdigit:] \t}]+\}|[^ \t}]*\z|[[:xdigit:]]{7,})"),
+        // \u263a, \u123
+        String::from(r"u[[:xdigit:]]{4}"),
+        String::from(r"u[[:xdigit:]]{0,3}"),
+        // \C-\M-f \M-\cf \c\M-f
+        format!(r"(?:(?:C-|c)(?:\\\n)?\\M-|M-\\(?:\\\n)?(?:C-|c))(?:\\\n)?{}", maybe_escaped_ctrl_char),
+        String::from(r"(?:M-\\C|C-\\M)(?s:.)"),
+        // \C-\a \cx
+        format!(r"(?:C-|c)(?:\\\n)?{}", maybe_escaped_ctrl_char),
+        // \M-a
+        format!(r"M-(?:\\\n)?{}", maybe_escaped_char),
+        String::from(r"[CM](?s:.)"),
+        String::from(r"[^0-7xuCMc]"),
+        String::from(r"\z"),
+    ];
+    pattern!("escape", escape_alternatives.join("|"));
 
     //   # Use rules in form of `e_bs escape' when you need to parse a sequence.
     //   e_bs = '\\' % {
@@ -493,15 +555,16 @@ pub fn construct() -> TMatchingPatterns {
     //     # the way Ragel handles EOF.
     //     c_line* %{ emit_comment(@sharp_s, p == pe ? p - 2 : p) }
     //     ;
-    // TODO INCOMPLETE
-    // patterns.insert("w_comment", r"^#.*");
+    // NOTE embedded action is the shared action `emit_comment`
+    pattern!("w_comment", r"#[^\n]*");
 
     // w_space_comment =
     //     w_space
     //     | w_comment
     //     ;
     // TODO INCOMPLETE
-    pattern!("w_space_comment", r"[ \t\r\f\v]+");
+    // NOTE embedded action is the shared action `emit_comment`
+    pattern!("w_space_comment", r"[ \t\r\f\v]+|#[^\n]*");
 
     // # A newline in non-literal context always interoperates with
     // # here document logic and can always be escaped by a backslash,
@@ -526,7 +589,8 @@ pub fn construct() -> TMatchingPatterns {
     //     | w_comment
     //     | w_newline
     //     ;
-    pattern!("w_any", r"[ \t\r\f\v]+"); // TODO INCOMPLETE
+    // NOTE embedded action is the shared action `emit_comment`, which runs e_heredoc_nl for w_newline
+    pattern!("w_any", r"[ \t\r\f\v]+|#[^\n]*|\n");
 
     //   #
     //   # === EXPRESSION PARSING ===
@@ -541,7 +605,7 @@ pub fn construct() -> TMatchingPatterns {
     //       [?!]'=' %{ tm = p - 2 }    # a!=b      a != b
     //   ;
     // NOTE embedded action is `ambiguous_suffix`
-    pattern!("ambiguous_ident_suffix", r"[\?!]=?");
+    pattern!("ambiguous_fid_suffix", r"[\?!]=?");
 
     //   ambiguous_ident_suffix =       # actual    parsed
     //       ambiguous_fid_suffix     |
@@ -552,14 +616,15 @@ pub fn construct() -> TMatchingPatterns {
     //       '==='   %{ tm = p - 3 }    # a===b     a === b
     //   ;
     // NOTE embedded action is `ambiguous_suffix`
-    pattern!("ambiguous_ident_suffix", r"([\?!]=?)|=|(==)|(=~)|(=>)|(===)");
+    // NOTE regex alternation takes the leftmost alternative rather than the longest one, so the longer suffixes go first
+    pattern!("ambiguous_ident_suffix", r"(===)|(==)|(=~)|(=>)|([\?!]=?)|=");
 
     //   ambiguous_symbol_suffix =      # actual    parsed
     //       ambiguous_ident_suffix |
     //       '==>'   %{ tm = p - 2 }    # :a==>b    :a= => b
     //   ;
     // NOTE embedded action is `ambiguous_suffix`
-    pattern!("ambiguous_symbol_suffix", r"([\?!]=?)|=|(==)|(=~)|(=>)|(===)|(==>)");
+    pattern!("ambiguous_symbol_suffix", r"(===)|(==>)|(==)|(=~)|(=>)|([\?!]=?)|=");
 
     //   # Ambiguous with 1.9 hash labels.
     //   ambiguous_const_suffix =       # actual    parsed
@@ -7,47 +7,70 @@
 //
 
 use std::collections::HashMap;
+use std::io::Read;
+use std::rc::Rc;
 
-use parser::token::Token;
+use parser::token::{Token, TokenWithRange};
+use parser::options::RubyVersion;
+
+use source::range::Range;
+use source::buffer::SourceBuffer;
+use source::comment::Comment;
+
+use diagnostic::{Diagnostic, Level};
 
 use shared::static_env::StaticEnv;
 
+use trace::{TraceEvent, Tracer};
+
 #[macro_use]
 pub mod lexing_state;  use self::lexing_state::LexingState;
 #[macro_use]
-mod action;            use self::action::Action;
+mod action;
 mod input_stream;      use self::input_stream::InputStream;
 mod shared_actions;    use self::shared_actions::TSharedActions;
-mod machines;
+mod machines;          use self::machines::Machine;
 mod matching_patterns;
 mod tokens_tables;
 mod shared_functions;
-mod stack_state;       use self::stack_state::StackState;
+pub mod stack_state;   use self::stack_state::StackState;
 mod literal;           use self::literal::Literal;
+mod checkpoint;        pub use self::checkpoint::Checkpoint;
+pub mod dedenter;
+
+// NOTE
+// machines only depend on the patterns and shared actions, which never change,
+// so every lexer shares them instead of compiling hundreds of regexes on its own
+lazy_static! {
+    static ref MACHINES: HashMap<LexingState, Machine> = machines::construct(&shared_actions::construct());
+}
 
 pub struct Lexer {
     current_state: LexingState, // NOTE like the @cs somehow
     next_state: Option<LexingState>,
     // TODO NOTE simulate fcall
     calling_state: Option<LexingState>,
-    // TODO NOTE simulate *stack_pop
-    last_state: Option<LexingState>,
+    // NOTE simulate @stack, states to return to from fcall, see stack_pop
+    stack: Vec<LexingState>,
     is_breaking: bool,
 
     tokens_tables: HashMap<&'static str, HashMap<&'static str, Token>>,
     shared_actions: TSharedActions,
-    machines: HashMap<LexingState, Vec<Box<Action>>>,
+    machines: &'static HashMap<LexingState, Machine>,
 
+    // NOTE None for a lexer reading from a reader, which never holds the whole source
+    source_buffer: Option<SourceBuffer>,
     input_stream: InputStream,
 
     // stack: Vec<usize>,
     // top: usize,
 
+    // NOTE the parser drives them as well, see Parser::update_lexer
     pub cond: StackState,
     pub cmdarg: StackState,
-    // TODO
-    // cond_stack: Vec<StackState>,
-    // cmdarg_stack: Vec<StackState>,
+    // the ones saved by push_cond and push_cmdarg
+    cond_stack: Vec<StackState>,
+    cmdarg_stack: Vec<StackState>,
 
     literal_stack: Vec<Literal>,
 
@@ -55,6 +78,13 @@ pub struct Lexer {
     paren_nest: usize,
     lambda_stack: Vec<usize>,
 
+    // @herebody_s, see comments of e_heredoc_nl in shared_actions
+    herebody_s: Option<usize>,
+    // @newline_s, position of the last newline, for tNL
+    newline_s: usize,
+    // @eq_begin_s, position of the =begin of current embedded document
+    eq_begin_s: usize,
+
     // # After encountering the closing line of <<~SQUIGGLY_HEREDOC,
     // # we store the indentation level and give it out to the parser
     // # on request. It is not possible to infer indentation level just
@@ -62,6 +92,7 @@ pub struct Lexer {
     // # expanded inside the lexer, but count as non-whitespace for
     // # indentation purposes.
     // @dedent_level  = nil
+    dedent_level: Option<usize>,
 
     // # If the lexer is in `command state' (aka expr_value)
     // # at the entry to #advance, it will transition to expr_cmdarg
@@ -75,35 +106,81 @@ pub struct Lexer {
 
     static_env: Option<StaticEnv>,
 
-    pub tokens: Vec<Token>,
+    // where the data after `__END__` starts, nothing is lexed from there on
+    data_s: Option<usize>,
+
+    // the tokens emitted by the last exec, and how many of them advance has handed out
+    // NOTE behind Rc, since the checkpoints before them share these, see Checkpoint::next
+    tokens: Rc<Vec<TokenWithRange>>,
+    tokens_p: usize,
+
+    // see set_error_tolerant
+    is_error_tolerant: bool,
+
+    // @version, see check_version
+    version: RubyVersion,
+
+    // NOTE whitequark collects comments only when asked to, we always do
+    pub comments: Vec<Comment>,
+
+    pub diagnostics: Vec<Diagnostic>,
+    // the index of the first fatal one in diagnostics, so that lexer doesn't look through them after every action
+    fatal_diagnostic_index: Option<usize>,
+
+    tracer: Option<Tracer>,
 }
 
 impl Lexer {
     pub fn new(input_string: String) -> Lexer {
-        let shared_actions = shared_actions::construct();
+        Lexer::with_source_buffer(SourceBuffer::new("(string)", input_string))
+    }
+
+    pub fn with_source_buffer(source_buffer: SourceBuffer) -> Lexer {
+        let input_stream = InputStream::new(source_buffer.source().clone());
+        Lexer::with_input_stream(input_stream, Some(source_buffer))
+    }
+
+    // lex the UTF-8 source from reader as it's read,
+    // holding only the part of it which lexer may still look at, see InputStream::discard_before
+    // 
+    // NOTE
+    // a heredoc is held as a whole until it's closed, since lexer comes back to the line of its `<<ID` afterwards,
+    // so is an embedded document
+    pub fn from_reader<R: Read + 'static>(reader: R) -> Lexer {
+        Lexer::with_input_stream(InputStream::from_reader(Box::new(reader)), None)
+    }
 
-        Lexer {
+    fn with_input_stream(input_stream: InputStream, source_buffer: Option<SourceBuffer>) -> Lexer {
+        let mut lexer = Lexer {
             current_state: LexingState::LineBegin, // NOTE setting value here is no use actually, since every time will pop one from states_stack
             next_state: None,
             calling_state: None,
-            last_state: None,
+            stack: vec![],
             is_breaking: false,
 
             tokens_tables: tokens_tables::construct(),
 
-            shared_actions: shared_actions.clone(),
-            machines: machines::construct(&shared_actions),
+            shared_actions: shared_actions::construct(),
+            machines: &MACHINES,
 
-            input_stream: InputStream::new(input_string),
+            input_stream,
+            source_buffer,
 
             // stack: vec![],
             // top: 0,
 
             cond: StackState::new(),
             cmdarg: StackState::new(),
+            cond_stack: vec![],
+            cmdarg_stack: vec![],
 
             literal_stack: vec![],
 
+            herebody_s: None,
+            newline_s: 0,
+            eq_begin_s: 0,
+            dedent_level: None,
+
             paren_nest: 0,
             lambda_stack: vec![],
 
@@ -113,31 +190,76 @@ impl Lexer {
 
             static_env: None,
 
-            tokens: Vec::new(),
+            data_s: None,
+
+            tokens: Rc::new(vec![]),
+            tokens_p: 0,
+
+            is_error_tolerant: false,
+
+            version: RubyVersion::latest(),
+
+            comments: Vec::new(),
+
+            diagnostics: Vec::new(),
+            fatal_diagnostic_index: None,
+
+            tracer: None,
+        };
+
+        //     if @source_pts.size > 1 && @source_pts[0] == 0xfeff
+        //       # Skip byte order mark.
+        //       @p = 1
+        //     end
+        // 
+        // NOTE p is a byte offset, the byte order mark takes 3 bytes
+        if lexer.input_stream.starts_with('\u{feff}') {
+            lexer.input_stream.p = '\u{feff}'.len_utf8() as isize;
         }
+
+        lexer
     }
 
-    // return one token
+    // return one token, along with its range in source
     // 
     // TODO MAYBE wrap in a Result, instead of Option
     // 
-    pub fn advance(&mut self) -> Option<Token> {
-        println!("--- lexer: advance ---");
+    pub fn advance(&mut self) -> Option<TokenWithRange> {
+        if let Some(token) = self.next_pending_token() {
+            return Some(token);
+        }
+
+        // NOTE whitequark raises a SyntaxError on fatal diagnostics,
+        // we just stop lexing and leave the diagnostic to the caller
+        if self.fatal_diagnostic().is_some() {
+            return None;
+        }
 
-        if !self.tokens.is_empty() {
-            return Some(self.tokens.remove(0));
+        if self.data_s.is_some() {
+            return None;
         }
 
         self.command_state = ( self.current_state == LexingState::ExprValue ) || 
                              ( self.current_state == LexingState::LineBegin );
 
+        // NOTE checkpoints may hold the ones handed out, so new ones go to a new vec instead of a copy
+        self.tokens = Rc::new(vec![]);
+        self.tokens_p = 0;
+
         self.exec();
 
-        if self.tokens.is_empty() {
-            return None;
-        } else {
-            return Some( self.tokens.remove(0) );
-        }
+        self.next_pending_token()
+    }
+
+    fn next_pending_token(&mut self) -> Option<TokenWithRange> {
+        let token = self.tokens.get(self.tokens_p).cloned();
+        if token.is_some() { self.tokens_p += 1; }
+        token
+    }
+
+    // whether there are tokens lexed but not handed out yet, which advance hands out without lexing
+    pub fn has_pending_tokens(&self) -> bool {
+        self.tokens_p < self.tokens.len()
     }
 
     // match-state-invoke-action loop
@@ -156,8 +278,9 @@ impl Lexer {
             }
 
             // handle state transition
-            self.last_state = Some(self.current_state.clone());
+            let previous_state = self.current_state.clone();
             if let Some(calling_state) = self.calling_state.clone() {
+                self.stack.push(self.current_state.clone());
                 self.current_state = calling_state.clone();
                 self.calling_state = None;
             } else {
@@ -167,27 +290,209 @@ impl Lexer {
                 }
             }
 
-            println!("\n--- exec looping\ncurrent_state: {:?}\nnext_state: {:?}\ncalling_state: {:?}\nis_breaking: {:?}\n---", self.current_state, self.next_state, self.calling_state, self.is_breaking);
-            // println!("state trans-ed; current_state: {:?}, next_state: {:?}, calling_state: {:?}, is_breaking: {:?} ---", self.current_state, self.next_state, self.calling_state, self.is_breaking);
-
-            // get actions
-            let actions = self.machines.get(&self.current_state).unwrap().clone();
-
-            // find matching action
-            let action = self.input_stream.longest_matching_action(&actions).expect("cant match any action");
-            // println!("matched action: {:?}", action.regex);
+            if self.current_state != previous_state {
+                let current_state = self.current_state.clone();
+                self.trace(|| TraceEvent::StateTransition { from: previous_state, to: current_state });
+            }
 
+            self.discard_input();
+
+            if self.input_stream_error() { break; }
+
+            // find matching action of current machine
+            let procedure = match self.input_stream.longest_matching_action(self.machines.get(&self.current_state).unwrap()) {
+                Some(action) => {
+                    self.trace(|| TraceEvent::ActionMatched {
+                        state: self.current_state.clone(),
+                        pattern: String::from(action.regex.as_str()),
+                        slice: self.input_stream.current_token().unwrap(),
+                        range: self.current_range(),
+                    });
+
+                    action.procedure
+                },
+                None => {
+                    // NOTE
+                    // every machine should end with a `c_any` action, so this means the machine is incomplete,
+                    // report it the way c_any does
+                    let pos = self.input_stream.starting_pos();
+                    let char_len = self.input_stream.char_len_at(pos);
+                    self.unexpected_char(Range::new(pos, pos + char_len));
+
+                    if self.fatal_diagnostic().is_some() { break; }
+                    continue;
+                }
+            };
             // invoke proc
-            let procedure = action.procedure;
             procedure(self);
 
             self.input_stream.entering_machine = false;
+
+            if self.fatal_diagnostic().is_some() {
+                break;
+            }
         }
     }
 
+    pub fn source_buffer(&self) -> &SourceBuffer {
+        self.source_buffer.as_ref().expect("a lexer reading from a reader has no source buffer")
+    }
+
+    // how many bytes of source lexer holds now
+    pub fn buffered_len(&self) -> usize {
+        self.input_stream.window_len()
+    }
+
+    // drop the source before the earliest position lexer may still come back to
+    fn discard_input(&mut self) {
+        let mut keep_pos = self.input_stream.starting_pos();
+
+        let heredoc_positions = self.literal_stack.iter()
+            .flat_map(|literal| literal.heredoc_e.into_iter().chain(literal.saved_herebody_s))
+            .chain(self.herebody_s);
+        for pos in heredoc_positions {
+            if pos < keep_pos { keep_pos = pos; }
+        }
+
+        if self.current_state == LexingState::LineComment && self.eq_begin_s < keep_pos {
+            keep_pos = self.eq_begin_s;
+        }
+
+        self.input_stream.discard_before(keep_pos);
+    }
+
+    // report the fatal diagnostic from reading source, once lexer reaches where it happened
+    fn input_stream_error(&mut self) -> bool {
+        match self.input_stream.take_error() {
+            Some(diagnostic) => {
+                self.push_diagnostic(diagnostic);
+                true
+            },
+            None => false
+        }
+    }
+
+    //   def dedent_level
+    //     # We erase @dedent_level as a precaution to avoid accidentally
+    //     # using a stale value.
+    //     dedent_level, @dedent_level = @dedent_level, nil
+    //     dedent_level
+    //   end
+    pub fn dedent_level(&mut self) -> Option<usize> {
+        self.dedent_level.take()
+    }
+
+    // see data_s
+    pub fn data_s(&self) -> Option<usize> {
+        self.data_s
+    }
+
     // parser will use this method to set lexer's state directly
     pub fn set_state(&mut self, state: LexingState) {
+        if self.current_state != state {
+            let previous_state = self.current_state.clone();
+            let current_state = state.clone();
+            self.trace(|| TraceEvent::StateTransition { from: previous_state, to: current_state });
+        }
+
+        // NOTE the state an action asked for is not taken until next exec, it would override this one
         self.current_state = state;
+        self.next_state = None;
+    }
+
+    //   def push_cmdarg
+    //     @cmdarg_stack.push(@cmdarg)
+    //     @cmdarg = StackState.new("cmdarg.#{@cmdarg_stack.count}")
+    //   end
+    pub fn push_cmdarg(&mut self) {
+        let cmdarg = ::std::mem::replace(&mut self.cmdarg, StackState::new());
+        self.cmdarg_stack.push(cmdarg);
+    }
+
+    //   def pop_cmdarg
+    //     @cmdarg = @cmdarg_stack.pop
+    //   end
+    pub fn pop_cmdarg(&mut self) {
+        self.cmdarg = self.cmdarg_stack.pop().expect("pop_cmdarg without push_cmdarg");
+    }
+
+    //   def push_cond
+    //     @cond_stack.push(@cond)
+    //     @cond = StackState.new("cond.#{@cond_stack.count}")
+    //   end
+    pub fn push_cond(&mut self) {
+        let cond = ::std::mem::replace(&mut self.cond, StackState::new());
+        self.cond_stack.push(cond);
+    }
+
+    //   def pop_cond
+    //     @cond = @cond_stack.pop
+    //   end
+    pub fn pop_cond(&mut self) {
+        self.cond = self.cond_stack.pop().expect("pop_cond without push_cond");
+    }
+
+    //   attr_accessor :in_kwarg
+    pub fn in_kwarg(&self) -> bool {
+        self.in_kwarg
+    }
+
+    pub fn set_in_kwarg(&mut self, in_kwarg: bool) {
+        self.in_kwarg = in_kwarg;
+    }
+
+    //   attr_accessor :static_env
+    // NOTE the parser sets one, see Parser::update_static_env, then identifiers declared in it lex as local variables
+    pub fn static_env(&self) -> Option<&StaticEnv> {
+        self.static_env.as_ref()
+    }
+
+    pub fn set_static_env(&mut self, static_env: Option<StaticEnv>) {
+        self.static_env = static_env;
+    }
+
+    pub fn static_env_mut(&mut self) -> Option<&mut StaticEnv> {
+        self.static_env.as_mut()
+    }
+
+    // NOTE
+    // an unexpected char is fatal to whitequark's lexer,
+    // an error-tolerant one emits it as T_ERROR along with an error and goes on,
+    // other fatal diagnostics still stop it
+    pub fn set_error_tolerant(&mut self, is_error_tolerant: bool) {
+        self.is_error_tolerant = is_error_tolerant;
+    }
+
+    pub fn set_version(&mut self, version: RubyVersion) {
+        self.version = version;
+    }
+
+    pub fn version(&self) -> RubyVersion {
+        self.version
+    }
+
+    // report the syntax which came with a later ruby than the target one, and tell if it's available
+    // NOTE it's still lexed as usual, whitequark's lexer for an older ruby lexes it as something else
+    pub fn check_version(&mut self, feature: &str, since: RubyVersion, range: Range) -> bool {
+        if self.version >= since { return true; }
+
+        let mut arguments = HashMap::new();
+        arguments.insert("feature", String::from(feature));
+        arguments.insert("version", self.version.to_string());
+        self.diagnostic(Level::Error, "unavailable_in_version", arguments, range);
+
+        false
+    }
+
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = Some(tracer);
+    }
+
+    // NOTE the event is only built when there's a tracer
+    pub fn trace<F: FnOnce() -> TraceEvent>(&self, event: F) {
+        if let Some(ref tracer) = self.tracer {
+            tracer(&event());
+        }
     }
 
     fn flag_breaking(&mut self) {
@@ -203,10 +508,36 @@ impl Lexer {
         self.calling_state = Some(state);
     }
 
+    //   def stack_pop
+    //     @top -= 1
+    //     @stack[@top]
+    //   end
+    fn stack_pop(&mut self) -> LexingState {
+        self.stack.pop().expect("stack_pop without fcall")
+    }
+
+    //   def range(s = @ts, e = @te)
+    //     Parser::Source::Range.new(@source_buffer, s, e)
+    //   end
+    fn current_range(&self) -> Range {
+        Range::new(self.input_stream.ts.unwrap(), self.input_stream.te.unwrap())
+    }
+
+    // emit token with range of current slice, i.e. @ts...@te
     fn emit_token(&mut self, token: Token) {
-        println!(">>> emitting token: {:?}", token);
+        let range = self.current_range();
+
+        self.emit_token_with_range(token, range);
+    }
+
+    //   def emit(type, value = tok, s = @ts, e = @te)
+    //     token = [ type, [ value, range(s, e) ] ]
+    //     ...
+    //   end
+    fn emit_token_with_range(&mut self, token: Token, range: Range) {
+        self.trace(|| TraceEvent::TokenEmitted { token: token.clone(), range });
 
-        self.tokens.push(token);
+        Rc::make_mut(&mut self.tokens).push((token, range));
     }
 
     // emit current slice as token from table
@@ -217,9 +548,84 @@ impl Lexer {
         let tokens_table = self.tokens_tables.get(table_name).unwrap();
         let token = tokens_table.get(token_str.as_str()).expect(&format!("no token {} from tokens_table {}", token_str, table_name));
 
-        println!(">>> emitting token (from table): {:?}", token);
+        let token = (*token).clone();
 
-        self.tokens.push((*token).clone());
+        self.emit_token(token);
+    }
+
+    //   def emit_comment(s = @ts, e = @te)
+    //     if @comments
+    //       @comments.push(Parser::Source::Comment.new(range(s, e)))
+    //     end
+    //
+    //     if @tokens
+    //       @tokens.push([ :tCOMMENT, [ tok(s, e), range(s, e) ] ])
+    //     end
+    //
+    //     nil
+    //   end
+    // 
+    // NOTE tokens here are the ones to be handed to the parser, tCOMMENT is not one of them
+    fn emit_comment(&mut self, range: Range) {
+        let text = self.input_stream.slice_from_range(range.begin_pos, range.end_pos);
+        self.comments.push(Comment::new(range, text));
+    }
+
+    //   def diagnostic(type, reason, arguments=nil, location=range, highlights=[])
+    //     @diagnostics.process(
+    //         Parser::Diagnostic.new(type, reason, arguments, location, highlights))
+    // 
+    //     if type == :fatal
+    //       raise Parser::SyntaxError, ...
+    //     end
+    //   end
+    // 
+    // NOTE a fatal diagnostic stops lexing, see `advance` and `exec`
+    fn diagnostic(&mut self, level: Level, reason: &'static str, arguments: HashMap<&'static str, String>, range: Range) {
+        let diagnostic = Diagnostic::new(level, reason, arguments, range);
+        self.push_diagnostic(diagnostic);
+    }
+
+    fn push_diagnostic(&mut self, diagnostic: Diagnostic) {
+        self.trace(|| TraceEvent::Diagnostic(diagnostic.clone()));
+
+        if diagnostic.level == Level::Fatal && self.fatal_diagnostic_index.is_none() {
+            self.fatal_diagnostic_index = Some(self.diagnostics.len());
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    //   diagnostic :fatal, :unexpected, { :character => tok.inspect[1..-2] }
+    // 
+    // see set_error_tolerant
+    fn unexpected_char(&mut self, range: Range) {
+        let character = self.input_stream.slice_from_range(range.begin_pos, range.end_pos);
+        let mut arguments = HashMap::new();
+        arguments.insert("character", character.clone());
+
+        if self.is_error_tolerant {
+            self.diagnostic(Level::Error, "unexpected", arguments, range);
+            self.emit_token_with_range(Token::T_ERROR(character), range);
+
+            // go on right after the char
+            self.input_stream.p = range.end_pos as isize - 1;
+            self.flag_breaking();
+        } else {
+            self.diagnostic(Level::Fatal, "unexpected", arguments, range);
+        }
+    }
+
+    // NOTE for the unported parts of lexer.rl, instead of panicking
+    fn unimplemented(&mut self, feature: &str) {
+        let mut arguments = HashMap::new();
+        arguments.insert("feature", String::from(feature));
+        let range = self.current_range();
+
+        self.diagnostic(Level::Fatal, "unimplemented", arguments, range);
+    }
+
+    pub fn fatal_diagnostic(&self) -> Option<&Diagnostic> {
+        self.fatal_diagnostic_index.map(|index| &self.diagnostics[index])
     }
 
     fn invoke_proc(&mut self, proc_name: &str) {
@@ -228,3 +634,20 @@ impl Lexer {
     }
 
 }
+
+// NOTE tokens along with their ranges, until lexing is done or stopped by a fatal diagnostic
+impl Iterator for Lexer {
+    type Item = TokenWithRange;
+
+    fn next(&mut self) -> Option<TokenWithRange> {
+        self.advance()
+    }
+}
+
+// lex the whole source at once, returns every token and the diagnostics reported meanwhile
+pub fn tokenize(source: String) -> (Vec<TokenWithRange>, Vec<Diagnostic>) {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.by_ref().collect();
+
+    (tokens, lexer.diagnostics)
+}
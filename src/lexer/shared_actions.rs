@@ -1,13 +1,13 @@
 use std::collections::HashMap;
 
-use regex::Regex;
-
 use lexer::LexingState;
 use lexer::Lexer;
 use lexer::action::{ActionProc};
 
 use parser::token::Token;
 
+use source::range::Range;
+
 pub type TSharedActions = HashMap<&'static str, ActionProc>;
 
 pub fn construct() -> TSharedActions {
@@ -22,6 +22,20 @@ pub fn construct() -> TSharedActions {
 
     action!("noop", |lexer: &mut Lexer|{});
 
+    // NOTE
+    // simulates the embedded action of w_comment, for w_comment, w_space_comment and w_any,
+    // does nothing if the matched slice is a whitespace,
+    // and the one of w_newline within w_any, see e_heredoc_nl
+    action!("emit_comment", |lexer: &mut Lexer| {
+        let slice = lexer.input_stream.current_token().unwrap();
+        if slice.starts_with('#') {
+            let range = lexer.current_range();
+            lexer.emit_comment(range);
+        } else if slice == "\n" {
+            lexer.invoke_proc("e_heredoc_nl");
+        }
+    });
+
     // original do_eof
     action!("do_eof", |lexer: &mut Lexer| {
         // println!("action invoked for c_eof");
@@ -189,9 +203,9 @@ pub fn construct() -> TSharedActions {
     //     @escape_s = p
     //     @escape   = nil
     //   };
-    action!("e_bs", |lexer: &mut Lexer| {
-        panic!("UNIMPL");
-    });
+    // NOTE
+    // not an action, the embedded action is done by extend_string_escaped and the `?` rule of expr_beg,
+    // @escape_s being @ts + 1, and @escape the value `unescape` returns
 
     // // #
     // // # === STRING AND HEREDOC PARSING ===
@@ -236,9 +250,19 @@ pub fn construct() -> TSharedActions {
     // //     @herebody_s = nil
     // // end
     // // };
-    // // TODO INCOMPLETE
-    // // e_heredoc_nl embedded proc
-    // pattern!("e_heredoc_nl", r"\n");
+    // 
+    // NOTE
+    // invoked by the w_newline actions which consume the newline,
+    // lexing continues from @herebody_s after the action
+    // NOTE also records @newline_s, which is done by do_nl of c_nl in whitequark
+    action!("e_heredoc_nl", |lexer: &mut Lexer| {
+        lexer.newline_s = lexer.input_stream.ts.unwrap();
+
+        if let Some(herebody_s) = lexer.herebody_s {
+            lexer.input_stream.p = herebody_s as isize - 1;
+            lexer.herebody_s = None;
+        }
+    });
 
     //   action extend_string {
     //     string = tok
@@ -263,50 +287,44 @@ pub fn construct() -> TSharedActions {
     //       current_literal.extend_string(string, @ts, @te)
     //     end
     //   }
-    // TODO INCOMPLETE
     action!("extend_string", |lexer: &mut Lexer| {
-        // println!("action `extend_string` invoking. current_token: {:?}", lexer.input_stream.current_token());
-
-        // TODO NAMING
-        let current_string = lexer.input_stream.current_token().unwrap();
+        let string = lexer.input_stream.current_token().unwrap();
+        let ts = lexer.input_stream.ts.unwrap();
+        let te = lexer.input_stream.te.unwrap();
 
         // NOTE ignored ruby22-and-below case
-        let lookahead = lexer.input_stream.slice_from_range(lexer.input_stream.te.clone().unwrap(), lexer.input_stream.te.clone().unwrap() + 2);
+        let lookahead = lexer.input_stream.slice_from_range(te, te + 2);
 
-        let mut current_literal = lexer.literal().expect("cant fetch current_literal").clone();
-        if true { // TODO heredoc?
+        // NOTE
+        // due to limitations of borrowing in rust, we have to
+        // 1 take current_literal from the stack
+        // 2 modify it
+        // 3 re-save it to the stack
+        let mut current_literal = lexer.literal_stack.pop().expect("cant fetch current_literal");
 
+        if !current_literal.is_heredoc() {
             // calling literal.nest_and_try_closing is kinda complex, see notes before that fn for more detail
-            let final_token_emitted = current_literal.nest_and_try_closing(current_string, lexer.input_stream.ts.unwrap(), lexer.input_stream.te.unwrap(), Some(lookahead));
-            for token_to_emit in current_literal.consume_tokens_to_emit().iter() { lexer.emit_token(token_to_emit.clone()); }
+            let final_token = current_literal.nest_and_try_closing(string.clone(), ts, te, Some(lookahead));
+            lexer.emit_literal_tokens(&mut current_literal);
 
-            if let Some(token) = final_token_emitted {
-                lexer.emit_token(token);
+            if let Some((token, range)) = final_token {
+                lexer.literal_stack.push(current_literal);
+                let is_label_end = token == Token::T_LABEL_END;
+                lexer.emit_token_with_range(token, range);
 
-                // TODO handle t_label_end
-
-                // TODO DUMMY
                 let next_state = lexer.pop_literal();
-                lexer.set_next_state(next_state);
+                if is_label_end {
+                    lexer.input_stream.p += 1;
+                    lexer.set_next_state(state!("expr_labelarg"));
+                } else {
+                    lexer.set_next_state(next_state);
+                }
                 lexer.flag_breaking();
+                return;
             }
-            // (token = current_literal.nest_and_try_closing(string, @ts, @te, lookahead)
         }
 
-        // TODO DUMMY
-
-        // current_literal.extend_string(string, @ts, @te)
-        current_literal.extend_string(
-            lexer.input_stream.current_token().unwrap(),
-            lexer.input_stream.ts.unwrap(), lexer.input_stream.te.unwrap());
-
-        // NOTE
-        // due to limitations of borrowing in rust, we have to
-        // 1 clone current_literal
-        // 2 modify it 
-        // 3 re-save it to the stack
-        // TODO need some helper fns on literal
-        lexer.literal_stack.pop();
+        current_literal.extend_string(string, ts, te);
         lexer.literal_stack.push(current_literal);
     });
 
@@ -345,7 +363,53 @@ pub fn construct() -> TSharedActions {
     //     end
     //   }
     action!("extend_string_escaped", |lexer: &mut Lexer| {
-        panic!("UNIMPL");
+        let ts = lexer.input_stream.ts.unwrap();
+        let te = lexer.input_stream.te.unwrap();
+        let tok = lexer.input_stream.current_token().unwrap();
+
+        let escape_s = ts + 1;
+        // # Get the first character after the backslash.
+        let escaped_char = lexer.input_stream.slice_from_range(escape_s, escape_s + 1);
+
+        // NOTE
+        // @escape is only set by `e_bs escape` of interpolating literals,
+        // plain ones use `e_bs c_any`, leaving @escape nil
+        let escape = if lexer.literal().expect("can't fetch current literal").interpolate {
+            Some(lexer.unescape(escape_s, &tok[1..]))
+        } else {
+            None
+        };
+
+        let mut current_literal = lexer.literal_stack.pop().expect("can't fetch current literal");
+
+        if current_literal.munge_escape(&escaped_char) {
+            // # If this particular literal uses this character as an opening
+            // # or closing delimiter, it is an escape sequence for that
+            // # particular character. Write it without the backslash.
+
+            if current_literal.is_regexp() && is_regexp_meta_character(&escaped_char) {
+                // # Regular expressions should include escaped delimiters in their
+                // # escaped form, except when the escaped character is
+                // # a closing delimiter but not a regexp metacharacter.
+                current_literal.extend_string(tok, ts, te);
+            } else {
+                current_literal.extend_string(escaped_char, ts, te);
+            }
+        } else {
+            // # It does not. So this is an actual escape sequence, yay!
+            if current_literal.is_regexp() {
+                // # Regular expressions should include escape sequences in their
+                // # escaped form. On the other hand, escaped newlines are removed.
+                current_literal.extend_string(tok.replace("\\\n", ""), ts, te);
+            } else {
+                match escape {
+                    Some(escape) => { current_literal.extend_escape(escape, ts, te); },
+                    None => { current_literal.extend_string(tok, ts, te); },
+                }
+            }
+        }
+
+        lexer.literal_stack.push(current_literal);
     });
 
     //   # Extend a string with a newline or a EOF character.
@@ -412,8 +476,95 @@ pub fn construct() -> TSharedActions {
     //       current_literal.flush_string
     //     end
     //   }
+    // NOTE
+    // c_eol matches an empty string at the end of input, that's how `@te == pe` is checked here,
+    // also a heredoc whose closing line immediately precedes EOF closes before reporting string_eof
     action!("extend_string_eol", |lexer: &mut Lexer| {
-        panic!("UNIMPL");
+        let ts = lexer.input_stream.ts.unwrap();
+        let te = lexer.input_stream.te.unwrap();
+        let is_eof = ts == te;
+
+        let mut current_literal = lexer.literal_stack.pop().expect("cant fetch current_literal");
+
+        if current_literal.is_heredoc() {
+            let herebody_s = lexer.herebody_s.unwrap();
+            let line = String::from(lexer.input_stream.slice_from_range(herebody_s, ts).trim_right_matches('\r'));
+
+            // # Try ending the heredoc with the complete most recently
+            // # scanned line. @herebody_s always refers to the start of such line.
+            if let Some((token, range)) = current_literal.nest_and_try_closing(line.clone(), herebody_s, ts, None) {
+                lexer.emit_token_with_range(token, range);
+
+                // # Adjust @herebody_s to point to the next line.
+                lexer.herebody_s = Some(te);
+
+                // # Continue regular lexing after the heredoc reference (<<END).
+                lexer.input_stream.p = current_literal.heredoc_e.unwrap() as isize - 1;
+
+                lexer.literal_stack.push(current_literal);
+                let next_state = lexer.pop_literal();
+                lexer.set_next_state(next_state);
+                lexer.flag_breaking();
+                return;
+            }
+
+            if is_eof { lexer.string_eof(&current_literal); return; }
+
+            // # Calculate indentation level for <<~HEREDOCs.
+            current_literal.infer_indent_level(&line);
+
+            // # Ditto.
+            lexer.herebody_s = Some(te);
+
+            // NOTE skip the bodies of the heredocs opened in the interpolations on this line, see e_rbrace
+            if let Some(interp_herebody_s) = current_literal.interp_herebody_s.take() {
+                lexer.herebody_s = Some(interp_herebody_s);
+                lexer.input_stream.p = interp_herebody_s as isize - 1;
+            }
+        } else {
+            // # Try ending the literal with a newline.
+            let string = lexer.input_stream.current_token().unwrap();
+            let final_token = current_literal.nest_and_try_closing(string, ts, te, None);
+            lexer.emit_literal_tokens(&mut current_literal);
+
+            if let Some((token, range)) = final_token {
+                lexer.literal_stack.push(current_literal);
+                lexer.emit_token_with_range(token, range);
+
+                let next_state = lexer.pop_literal();
+                lexer.set_next_state(next_state);
+                lexer.flag_breaking();
+                return;
+            }
+
+            if is_eof { lexer.string_eof(&current_literal); return; }
+
+            if let Some(herebody_s) = lexer.herebody_s {
+                // # This is a regular literal intertwined with a heredoc. Like:
+                // #
+                // #     p <<-foo+"1
+                // #     bar
+                // #     foo
+                // #     2"
+                // #
+                // # which, incidentally, evaluates to "bar\n1\n2".
+                lexer.input_stream.p = herebody_s as isize - 1;
+                lexer.herebody_s = None;
+            }
+        }
+
+        if current_literal.is_words {
+            current_literal.extend_space(ts, te);
+        } else {
+            // # A literal newline is appended if the heredoc was _not_ closed
+            // # this time (see fbreak above). See also Literal#nest_and_try_closing
+            // # for rationale of calling #flush_string here.
+            current_literal.extend_string(lexer.input_stream.current_token().unwrap(), ts, te);
+            current_literal.flush_string();
+        }
+        lexer.emit_literal_tokens(&mut current_literal);
+
+        lexer.literal_stack.push(current_literal);
     });
 
     //   action extend_string_space {
@@ -447,8 +598,9 @@ pub fn construct() -> TSharedActions {
         current_literal.flush_string();
         current_literal.extend_content();
 
-        for token_to_emit in current_literal.consume_tokens_to_emit().iter() { lexer.emit_token(token_to_emit.clone()); }
-        lexer.emit_token(Token::T_STRING_DVAR);
+        lexer.emit_literal_tokens(&mut current_literal);
+        let ts = lexer.input_stream.ts.unwrap();
+        lexer.emit_token_with_range(Token::T_STRING_DVAR, Range::new(ts, ts + 1));
 
         lexer.literal_stack.push(current_literal);
 
@@ -478,16 +630,18 @@ pub fn construct() -> TSharedActions {
         let mut current_literal = lexer.literal_stack.pop().unwrap().clone();
 
         current_literal.flush_string();
-        for token_to_emit in current_literal.consume_tokens_to_emit().iter() { lexer.emit_token(token_to_emit.clone()); }
+        lexer.emit_literal_tokens(&mut current_literal);
         current_literal.extend_content();
 
         lexer.emit_token(Token::T_STRING_DBEG);
 
-        // TODO handle heredoc
-        //     if current_literal.heredoc?
-        //       current_literal.saved_herebody_s = @herebody_s
-        //       @herebody_s = nil
-        //     end
+        // NOTE
+        // a heredoc opened in an earlier interpolation on the same line has its body right after the line,
+        // a heredoc opened in this one goes after that
+        if current_literal.is_heredoc() {
+            current_literal.saved_herebody_s = lexer.herebody_s;
+            lexer.herebody_s = current_literal.interp_herebody_s;
+        }
 
         current_literal.start_interp_brace();
 
@@ -526,18 +680,27 @@ pub fn construct() -> TSharedActions {
     //   ;
 
     // NOTE shared action for `ambiguous_fid_suffix` `ambiguous_ident_suffix` `ambiguous_symbol_suffix` `ambiguous_const_suffix`
+    // 
+    // NOTE
+    // the suffix is at the end of the matched slice, and p in ragel's leaving action is te here,
+    // longer suffixes are checked first since `===` ends with `==` as well
     action!("ambiguous_suffix", |lexer: &mut Lexer| {
         let current_slice = lexer.input_stream.current_token().unwrap();
 
-        if let Some(capture) = Regex::new(r"^===").unwrap().captures(&current_slice) { lexer.input_stream.tm = ( lexer.input_stream.p - 3) as usize; return; }
-        if let Some(capture) = Regex::new(r"^==>").unwrap().captures(&current_slice) { lexer.input_stream.tm = ( lexer.input_stream.p - 2) as usize; return; }
-        if let Some(capture) = Regex::new(r"^[?!]=").unwrap().captures(&current_slice) { lexer.input_stream.tm = ( lexer.input_stream.p - 2) as usize; return; }
-        if let Some(capture) = Regex::new(r"^==").unwrap().captures(&current_slice) { lexer.input_stream.tm = ( lexer.input_stream.p - 2) as usize; return; }
-        if let Some(capture) = Regex::new(r"^=~").unwrap().captures(&current_slice) { lexer.input_stream.tm = ( lexer.input_stream.p - 2) as usize; return; }
-        if let Some(capture) = Regex::new(r"^=>").unwrap().captures(&current_slice) { lexer.input_stream.tm = ( lexer.input_stream.p - 2) as usize; return; }
-        if let Some(capture) = Regex::new(r"^::").unwrap().captures(&current_slice) { lexer.input_stream.tm = ( lexer.input_stream.p - 2) as usize; return; }
-        if let Some(capture) = Regex::new(r"^[?!]").unwrap().captures(&current_slice) { lexer.input_stream.tm = ( lexer.input_stream.p as usize ); return; }
-        if let Some(capture) = Regex::new(r"^=").unwrap().captures(&current_slice) { lexer.input_stream.tm = ( lexer.input_stream.p as usize ); return; }
+        let te = lexer.input_stream.te.unwrap();
+
+        let tm = if current_slice.ends_with("===") { te - 3 }
+            else if current_slice.ends_with("==>") { te - 2 }
+            else if current_slice.ends_with("?=") || current_slice.ends_with("!=") { te - 2 }
+            else if current_slice.ends_with("==") { te - 2 }
+            else if current_slice.ends_with("=~") { te - 2 }
+            else if current_slice.ends_with("=>") { te - 2 }
+            else if current_slice.ends_with("::") { te - 2 }
+            else if current_slice.ends_with('?') || current_slice.ends_with('!') { te }
+            else if current_slice.ends_with('=') { te }
+            else { return; };
+
+        lexer.input_stream.tm = tm;
     });
 
     // # Resolving kDO/kDO_COND/kDO_BLOCK ambiguity requires embedding
@@ -567,8 +730,10 @@ pub fn construct() -> TSharedActions {
     // e_rparen = ')' % {
     //   @paren_nest -= 1
     // };
+    // 
+    // NOTE paren_nest is unsigned, an unbalanced `)` leaves it at 0
     action!("e_rparen", |lexer: &mut Lexer| {
-        lexer.paren_nest -= 1;
+        lexer.paren_nest = lexer.paren_nest.saturating_sub(1);
     });
 
     //   e_lbrace = '{' % {
@@ -612,8 +777,6 @@ pub fn construct() -> TSharedActions {
     //     end
     //   };
     action!("e_rbrace", |lexer: &mut Lexer| {
-        println!("action e_rbrace invoked");
-
         if lexer.literal().is_none() { return; }
 
         let mut current_literal = lexer.literal_stack.pop().unwrap().clone();
@@ -622,17 +785,23 @@ pub fn construct() -> TSharedActions {
             // NOTE ignored ruby-18, ruby-19 stuff
             lexer.emit_token(Token::T_STRING_DEND);
 
-            // TODO HANDLE saved_herebody_s
-
-            lexer.input_stream.hold_current_char();
+            // NOTE
+            // herebody_s is past the bodies of the heredocs opened in the interpolation if any,
+            // the heredoc goes on from there once its line ends, see extend_string_eol
+            if current_literal.saved_herebody_s.is_some() {
+                current_literal.interp_herebody_s = lexer.herebody_s;
+                lexer.herebody_s = current_literal.saved_herebody_s;
+            }
 
-            let last_state = lexer.last_state.clone().unwrap();
-            lexer.set_next_state(last_state);
+            // NOTE no fhold here, e_rbrace is a leaving action in whitequark, p is already past the `}` there,
+            // while p is still at `}` for us
+            let next_state = lexer.stack_pop();
+            lexer.set_next_state(next_state);
 
             lexer.flag_breaking();
-
-            lexer.literal_stack.push(current_literal);
         }
+
+        lexer.literal_stack.push(current_literal);
     });
 
     // # Ruby is context-sensitive wrt/ local identifiers.
@@ -670,3 +839,8 @@ pub fn construct() -> TSharedActions {
 
     actions
 }
+
+//   REGEXP_META_CHARACTERS = Regexp.union(*"\\$()*+.<>?[]^{|}".chars).freeze
+fn is_regexp_meta_character(character: &str) -> bool {
+    character.len() == 1 && "\\$()*+.<>?[]^{|}".contains(character)
+}
@@ -1,7 +1,19 @@
+use std::collections::HashMap;
+
 use lexer::Lexer;
 use lexer::LexingState;
+use lexer::literal::Literal;
+
+use num_rational::Ratio;
+use num_bigint::BigInt;
+use num_traits::{pow, ToPrimitive};
 
 use parser::token::Token;
+use parser::options::RubyVersion;
+
+use source::range::Range;
+
+use diagnostic::Level;
 
 impl Lexer {
     // def arg_or_cmdarg
@@ -19,6 +31,16 @@ impl Lexer {
         }
     }
 
+    // part of `action extend_string_eol`
+    //     if @te == pe
+    //       diagnostic :fatal, :string_eof, nil,
+    //                  range(current_literal.str_s, current_literal.str_s + 1)
+    //     end
+    pub fn string_eof(&mut self, literal: &Literal) {
+        let range = Range::new(literal.str_s, literal.str_s + 1);
+        self.diagnostic(Level::Fatal, "string_eof", HashMap::new(), range);
+    }
+
     // def emit_do(do_block=false)
     //     if @cond.active?
     //     emit(:kDO_COND, 'do'.freeze)
@@ -39,4 +61,380 @@ impl Lexer {
             }
         }
     }
+
+    // NOTE
+    // whitequark sets @num_xfrm in leaving actions of int_suffix/flo_suffix/flo_pow_suffix,
+    // here the suffix is passed in along with the digits
+    // 
+    //   int_suffix / flo_suffix / flo_pow_suffix
+    //     ''   => emit(:tINTEGER,   chars) | emit(:tFLOAT, Float(chars))
+    //     'r'  => emit(:tRATIONAL,  Rational(chars))
+    //     'i'  => emit(:tIMAGINARY, Complex(0, chars))
+    //     'ri' => emit(:tIMAGINARY, Complex(0, Rational(chars)))
+    // 
+    // digits are without underscores, like `15`, `1.5` or `1.5e-3`, or `ff` for base 16
+    pub fn emit_num(&mut self, digits: &str, base: u32, suffix: &str) {
+        let is_float = base == 10 && digits.contains(|c| c == '.' || c == 'e' || c == 'E');
+
+        let numeric = if is_float {
+            Token::T_FLOAT(digits.parse::<f64>().unwrap())
+        } else {
+            integer_token(digits, base)
+        };
+
+        // NOTE ruby20 lexes the suffix as an identifier
+        if suffix.contains('r') {
+            let range = self.current_range();
+            self.check_version("rational literal", RubyVersion::Ruby21, range);
+        }
+        if suffix.contains('i') {
+            let range = self.current_range();
+            self.check_version("imaginary literal", RubyVersion::Ruby21, range);
+        }
+
+        let token = match suffix {
+            ""   => numeric,
+            "i"  => Token::T_IMAGINARY(box numeric),
+            "r" | "ri" => {
+                let rational = rational_token(numeric, digits);
+                if suffix == "r" { rational } else { Token::T_IMAGINARY(box rational) }
+            },
+            _ => { panic!("unknown numeric suffix {:?}", suffix); }
+        };
+
+        self.emit_token(token);
+    }
+
+    // NOTE
+    // the embedded actions of `escape` in matching_patterns, i.e. what sets @escape,
+    // escape is the sequence after the backslash, starting at escape_s (@escape_s),
+    // the value is returned as bytes since \xff or \M-a aren't valid utf-8 by themselves
+    // 
+    // TODO the `@version < 24` checks of unicode_points
+    pub fn unescape(&mut self, escape_s: usize, escape: &str) -> Vec<u8> {
+        let chars: Vec<char> = escape.chars().collect();
+        let escape_e = escape_s + escape.len();
+
+        match chars.first() {
+            //     | c_eof % {
+            //       diagnostic :fatal, :escape_eof, nil, range(p - 1, p)
+            //     }
+            None => {
+                self.diagnostic(Level::Fatal, "escape_eof", HashMap::new(), Range::new(escape_s - 1, escape_s));
+                vec![]
+            },
+
+            //       # \377
+            //       [0-7]{1,3}
+            //       % { @escape = encode_escape(tok(@escape_s, p).to_i(8) % 0x100) }
+            Some(c) if c.is_digit(8) => {
+                encode_escape(u32::from_str_radix(escape, 8).unwrap() % 0x100)
+            },
+
+            //       # \xff
+            //     | 'x' xdigit{1,2}
+            //         % { @escape = encode_escape(tok(@escape_s + 1, p).to_i(16)) }
+            //
+            //       # %q[\x]
+            //     | 'x' ( c_any - xdigit )
+            //       % {
+            //         diagnostic :fatal, :invalid_hex_escape, nil, range(@escape_s - 1, p + 2)
+            //       }
+            // NOTE the range only covers `\x` here
+            Some(&'x') => {
+                match u8::from_str_radix(&escape[1..], 16) {
+                    Ok(byte) => encode_escape(byte as u32),
+                    Err(_) => {
+                        self.diagnostic(Level::Fatal, "invalid_hex_escape", HashMap::new(), Range::new(escape_s - 1, escape_s + 1));
+                        vec![]
+                    }
+                }
+            },
+
+            Some(&'u') => {
+                if chars.get(1) == Some(&'{') {
+                    self.unescape_unicode_points(escape_s, &chars)
+                } else if chars.len() == 5 {
+                    //       # \u263a
+                    //     | 'u' xdigit{4}
+                    //       % { @escape = tok(@escape_s + 1, p).to_i(16).chr(Encoding::UTF_8) }
+                    let codepoint = u32::from_str_radix(&escape[1..], 16).unwrap();
+                    self.encode_codepoint(codepoint, Range::new(escape_s + 1, escape_e))
+                } else {
+                    //       # \u123
+                    //     | 'u' xdigit{0,3}
+                    //       % {
+                    //         diagnostic :fatal, :invalid_unicode_escape, nil, range(@escape_s - 1, p)
+                    //       }
+                    self.diagnostic(Level::Fatal, "invalid_unicode_escape", HashMap::new(), Range::new(escape_s - 1, escape_e));
+                    vec![]
+                }
+            },
+
+            Some(&'C') | Some(&'M') | Some(&'c') => {
+                match unescape_ctrl_meta(&chars) {
+                    Some(escape) => escape,
+                    None => {
+                        //   action invalid_complex_escape {
+                        //     diagnostic :fatal, :invalid_escape
+                        //   }
+                        let range = self.current_range();
+                        self.diagnostic(Level::Fatal, "invalid_escape", HashMap::new(), range);
+                        vec![]
+                    }
+                }
+            },
+
+            //     | ( c_any - [0-7xuCMc] ) %unescape_char
+            // NOTE an escaped newline is a line continuation, i.e. "a\
+            // b" is "ab"
+            Some(&'\n') => vec![],
+            Some(&c) => unescape_char(c).to_string().into_bytes(),
+        }
+    }
+
+    //     # \u{  \t  123  \t 456   \t\t }
+    //   | 'u{' [ \t]* ( xdigit{1,6} [ \t]+ )*
+    //     (
+    //       ( xdigit{1,6} [ \t]* '}'
+    //         %unicode_points
+    //       )
+    //       |
+    //       ( xdigit* ( c_any - xdigit - [ \t}] )+ '}'
+    //         | ( c_any - [ \t}] )* c_eof
+    //         | xdigit{7,}
+    //       ) % {
+    //         diagnostic :fatal, :unterminated_unicode, nil, range(p - 1, p)
+    //       }
+    //     )
+    fn unescape_unicode_points(&mut self, escape_s: usize, chars: &Vec<char>) -> Vec<u8> {
+        let escape_e = escape_s + chars.iter().map(|c| c.len_utf8()).sum::<usize>();
+        let is_blank = |c: &char| *c == ' ' || *c == '\t';
+
+        let terminated = chars.len() > 2 && chars[chars.len() - 1] == '}';
+        let codepoints: Vec<char> = if terminated { chars[2..(chars.len() - 1)].to_vec() } else { vec![] };
+
+        //       # u{not hex} or u{}
+        //     | 'u{' ( c_any - xdigit - [ \t}] )* '}'
+        //       % {
+        //         diagnostic :fatal, :invalid_unicode_escape, nil, range(@escape_s - 1, p)
+        //       }
+        if terminated && codepoints.iter().all(|c| !c.is_ascii_hexdigit() && !is_blank(c)) {
+            self.diagnostic(Level::Fatal, "invalid_unicode_escape", HashMap::new(), Range::new(escape_s - 1, escape_e));
+            return vec![];
+        }
+
+        let is_valid = terminated &&
+            codepoints.iter().all(|c| c.is_ascii_hexdigit() || is_blank(c)) &&
+            codepoints.split(is_blank).all(|codepoint| codepoint.len() <= 6);
+        if !is_valid {
+            self.diagnostic(Level::Fatal, "unterminated_unicode", HashMap::new(), Range::new(escape_e - 1, escape_e));
+            return vec![];
+        }
+
+        //   action unicode_points {
+        //     @escape = ""
+        //
+        //     codepoints  = tok(@escape_s + 2, p - 1)
+        //     codepoint_s = @escape_s + 2
+        //
+        //     ...
+        //
+        //     codepoints.scan(/([0-9a-fA-F]+)|([ \t]+)/).each do |(codepoint_str, spaces)|
+        //       if spaces
+        //         codepoint_s += spaces.length
+        //       else
+        //         codepoint = codepoint_str.to_i(16)
+        //
+        //         if codepoint >= 0x110000
+        //           diagnostic :error, :unicode_point_too_large, nil,
+        //                      range(codepoint_s, codepoint_s + codepoint_str.length)
+        //           break
+        //         end
+        //
+        //         @escape     += codepoint.chr(Encoding::UTF_8)
+        //         codepoint_s += codepoint_str.length
+        //       end
+        //     end
+        //   }
+        let mut escape = vec![];
+        let mut codepoint_s = escape_s + 2;
+        for codepoint_str in codepoints.split(is_blank) {
+            if !codepoint_str.is_empty() {
+                let codepoint_str: String = codepoint_str.iter().collect();
+                let codepoint = u32::from_str_radix(&codepoint_str, 16).unwrap();
+                let range = Range::new(codepoint_s, codepoint_s + codepoint_str.len());
+
+                if codepoint >= 0x110000 {
+                    self.diagnostic(Level::Error, "unicode_point_too_large", HashMap::new(), range);
+                    break;
+                }
+
+                let bytes = self.encode_codepoint(codepoint, range);
+                if bytes.is_empty() { break; }
+                escape.extend(bytes);
+
+                codepoint_s += codepoint_str.len();
+            }
+
+            // the separating space or tab
+            codepoint_s += 1;
+        }
+
+        escape
+    }
+
+    // codepoint.chr(Encoding::UTF_8)
+    // 
+    // NOTE Ruby raises RangeError for surrogates, reported as an invalid unicode escape here
+    fn encode_codepoint(&mut self, codepoint: u32, range: Range) -> Vec<u8> {
+        match ::std::char::from_u32(codepoint) {
+            Some(c) => c.to_string().into_bytes(),
+            None => {
+                self.diagnostic(Level::Error, "invalid_unicode_escape", HashMap::new(), range);
+                vec![]
+            }
+        }
+    }
+}
+
+//   ESCAPES = {
+//     ?a.ord => "\a", ?b.ord  => "\b", ?e.ord => "\e", ?f.ord => "\f",
+//     ?n.ord => "\n", ?r.ord  => "\r", ?s.ord => "\s", ?t.ord => "\t",
+//     ?v.ord => "\v", ?\\.ord => "\\"
+//   }.freeze
+// 
+//   action unescape_char {
+//     codepoint = @source_pts[p - 1]
+//     if (@escape = ESCAPES[codepoint]).nil?
+//       @escape = encode_escape(@source_buffer.slice(p - 1))
+//     end
+//   }
+// 
+fn unescape_char(c: char) -> char {
+    match c {
+        'a' => '\x07', 'b' => '\x08', 'e' => '\x1b', 'f' => '\x0c',
+        'n' => '\n',   'r' => '\r',   's' => ' ',    't' => '\t',
+        'v' => '\x0b', '\\' => '\\',
+        _ => c
+    }
+}
+
+//   def encode_escape(ord)
+//     ord.chr.force_encoding(@source_buffer.source.encoding)
+//   end
+fn encode_escape(ord: u32) -> Vec<u8> {
+    vec![ ord as u8 ]
+}
+
+//   action slash_c_char {
+//     @escape = encode_escape(@escape[0].ord & 0x9f)
+//   }
+// 
+//   action slash_m_char {
+//     @escape = encode_escape(@escape[0].ord | 0x80)
+//   }
+// 
+//   maybe_escaped_char = (
+//         '\\' c_any      %unescape_char
+//     | ( c_any - [\\] )  % { @escape = @source_buffer.slice(p - 1).chr }
+//   );
+// 
+//   maybe_escaped_ctrl_char = ( # why?!
+//         '\\' c_any      %unescape_char %slash_c_char
+//     |   '?'             % { @escape = "\x7f" }
+//     | ( c_any - [\\?] ) % { @escape = @source_buffer.slice(p - 1).chr } %slash_c_char
+//   );
+// 
+// \C-a \ca \M-a \C-\M-a \M-\C-a \c\M-a \M-\ca, with an optional escaped newline after each prefix,
+// returns None for the invalid ones, i.e. \Ca \Ma \M-\Ca \C-\Ma
+fn unescape_ctrl_meta(chars: &Vec<char>) -> Option<Vec<u8>> {
+    let (mut ctrl, mut meta) = (false, false);
+    let mut i = 0;
+
+    loop {
+        match (chars.get(i), chars.get(i + 1)) {
+            (Some(&'M'), Some(&'-')) if !meta => { meta = true; i += 2; },
+            (Some(&'C'), Some(&'-')) if !ctrl => { ctrl = true; i += 2; },
+            (Some(&'c'), _) if !ctrl => { ctrl = true; i += 1; },
+            // no prefix at all, or a backslash not followed by the other prefix
+            _ => { return None; }
+        }
+
+        // escaped_nl, unless it's the char itself
+        if chars.get(i) == Some(&'\\') && chars.get(i + 1) == Some(&'\n') && i + 2 < chars.len() {
+            i += 2;
+        }
+
+        // \C-\M-f, the other prefix comes after a backslash,
+        // otherwise the backslash escapes the char
+        if chars.get(i) == Some(&'\\') && i + 2 < chars.len() && !(ctrl && meta) {
+            i += 1;
+            continue;
+        }
+
+        break;
+    }
+
+    let (c, is_del) = match (chars.get(i), chars.get(i + 1)) {
+        (Some(&'\\'), Some(&c)) => (unescape_char(c), false),
+        (Some(&'?'), None) if ctrl => ('\x7f', true),
+        (Some(&c), None) => (c, false),
+        _ => { return None; }
+    };
+
+    let mut ord = c as u32;
+    if ctrl && !is_del { ord &= 0x9f; }
+    if meta { ord |= 0x80; }
+
+    Some(encode_escape(ord))
+}
+
+// NOTE flo_pow_suffix doesn't allow `r`, so a float here always has a dot
+// 
+// NOTE fall back to a big rational when it doesn't fit in isize, like integer_token
+fn rational_token(numeric: Token, digits: &str) -> Token {
+    match numeric {
+        Token::T_INTEGER(int_value) => Token::T_RATIONAL(Ratio::from_integer(int_value)),
+        Token::T_BIG_INTEGER(big_int_value) => Token::T_BIG_RATIONAL(Ratio::from_integer(big_int_value)),
+        Token::T_FLOAT(_) => {
+            // 1.25r -> 125/100
+            let frac_len = digits.len() - digits.find('.').unwrap() - 1;
+            let numer_digits = digits.replace(".", "");
+            if let (Ok(numer), Some(denom)) = (numer_digits.parse::<isize>(), 10isize.checked_pow(frac_len as u32)) {
+                return Token::T_RATIONAL(Ratio::new(numer, denom));
+            }
+
+            let numer = BigInt::parse_bytes(numer_digits.as_bytes(), 10).unwrap();
+            let denom = pow(BigInt::from(10), frac_len);
+            let big_rational_value = Ratio::new(numer, denom);
+
+            // reduced, it may fit again, like 0.50000000000000000000r
+            match (big_rational_value.numer().to_isize(), big_rational_value.denom().to_isize()) {
+                (Some(numer), Some(denom)) => Token::T_RATIONAL(Ratio::new_raw(numer, denom)),
+                _ => Token::T_BIG_RATIONAL(big_rational_value)
+            }
+        },
+        _ => { panic!("can't build rational token from {:?}", numeric); }
+    }
+}
+
+// like String#to_i(base), digits after the first invalid one are ignored
+// 
+// NOTE fall back to a big integer when it doesn't fit in isize
+pub fn integer_token(digits: &str, base: u32) -> Token {
+    let valid_digits: String = digits.chars().take_while(|c| c.is_digit(base)).collect();
+    if valid_digits.is_empty() {
+        return Token::T_INTEGER(0);
+    }
+
+    match isize::from_str_radix(&valid_digits, base) {
+        Ok(int_value) => Token::T_INTEGER(int_value),
+        Err(_) => Token::T_BIG_INTEGER(BigInt::parse_bytes(valid_digits.as_bytes(), base).unwrap())
+    }
+}
+
+//   c_space    = [ \t\r\f\v];
+pub fn is_c_space(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\r' || c == '\x0c' || c == '\x0b'
 }
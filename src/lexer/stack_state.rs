@@ -1,3 +1,4 @@
+#[derive(Debug, Clone)]
 pub struct StackState {
     stack: Vec<bool>,
 }
@@ -54,6 +55,13 @@ impl StackState {
         if self.stack.is_empty() { return false; } else { return *self.stack.last().unwrap(); }
     }
 
+    // def clear
+    //   @stack = 0
+    // end
+    pub fn clear(&mut self) {
+        self.stack.clear();
+    }
+
     // def active?
     //   @stack[0] == 1
     // end
@@ -2,9 +2,17 @@
 #![feature(box_syntax, box_patterns)]
 
 extern crate regex;
+#[macro_use]
+extern crate lazy_static;
+extern crate num_rational;
+extern crate num_bigint;
+extern crate num_traits;
 
-mod shared;
+pub mod shared;
+pub mod source;
+pub mod diagnostic;
 pub mod ast;
 pub mod lexer;
 pub mod parser;
+pub mod trace;
 // pub use parser::parser::{Parser, Token};
@@ -1,22 +1,74 @@
 #![feature(box_syntax, box_patterns)]
 
 extern crate regex;
+#[macro_use]
+extern crate lazy_static;
+extern crate num_rational;
+extern crate num_bigint;
+extern crate num_traits;
 
 use std::io::prelude::*;
 use std::fs::File;
+use std::rc::Rc;
 
 mod lexer;
 mod parser;
 mod shared;
+mod source;
+mod diagnostic;
 mod ast;
+mod trace;
 
+// usage: ruby-on-rust [--trace] [--ruby=VERSION] [FILE]
+// 
+// --trace prints every event of lexer and parser to stderr, see trace::TraceEvent
+// --ruby parses for the given ruby, like 2.3, the latest supported one by default
 fn main() {
-    let mut f = File::open("tmp/a.rb").expect("cant open file");
-    let mut file_content = String::new();
-    f.read_to_string(&mut file_content).expect("cant read file");
+    let mut is_tracing = false;
+    let mut options = parser::options::ParserOptions::default();
+    let mut file_name = String::from("tmp/a.rb");
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--trace" => { is_tracing = true; },
+            _ if arg.starts_with("--ruby=") => {
+                options.version = match arg["--ruby=".len()..].parse() {
+                    Ok(version) => version,
+                    Err(message) => {
+                        eprintln!("{}", message);
+                        std::process::exit(1);
+                    }
+                };
+            },
+            _ => { file_name = arg; }
+        }
+    }
+    let file_name = file_name.as_str();
 
-    let mut parser = parser::parser::Parser::new(file_content);
+    let mut f = File::open(file_name).expect("cant open file");
+    let mut file_content = vec![];
+    f.read_to_end(&mut file_content).expect("cant read file");
 
-    let node = parser.parse();
-    println!("====== parser parsed node:\n{:?}", node );
+    let source_buffer = match source::buffer::SourceBuffer::from_bytes(file_name, file_content) {
+        Ok(source_buffer) => source_buffer,
+        Err(diagnostic) => {
+            println!("{}: {}", diagnostic.level, diagnostic.message());
+            return;
+        }
+    };
+    let mut parser = parser::parser::Parser::with_source_buffer(source_buffer);
+    parser.set_options(options);
+    if is_tracing {
+        parser.set_tracer(Rc::new(|event: &trace::TraceEvent| eprintln!("{:?}", event)));
+    }
+
+    match parser.parse() {
+        Ok(node) => {
+            println!("====== parser parsed node:\n{:?}", node );
+        },
+        Err(diagnostic) => {
+            for line in diagnostic.render(parser.source_buffer()) {
+                println!("{}", line);
+            }
+        }
+    }
 }
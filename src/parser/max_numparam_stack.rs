@@ -0,0 +1,104 @@
+// NOTE
+// from a later whitequark/parser, where numbered parameters came with 2.7,
+// whitequark pushes a static scope for def, class and the like as well, so that a block doesn't look past them,
+// here a block's scope keeps how deep static_env was within it instead, see Parser::try_declare_numparam,
+// which tells a def or class within the block as well, and goes back along with static_env when the parser rewinds
+
+// module Parser
+//   # Holds p->max_numparam from parse.y
+//   #
+//   # @api private
+//   class MaxNumparamStack
+//     attr_reader :stack
+//
+//     ORDINARY_PARAMS = -1
+//
+//     def initialize
+//       @stack = []
+//     end
+const ORDINARY_PARAMS: isize = -1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumparamScope {
+    pub value: isize,
+    pub static_env_depth: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MaxNumparamStack {
+    stack: Vec<NumparamScope>,
+}
+
+impl MaxNumparamStack {
+    pub fn new() -> MaxNumparamStack {
+        MaxNumparamStack { stack: vec![] }
+    }
+
+    pub fn stack(&self) -> &Vec<NumparamScope> {
+        &self.stack
+    }
+
+    //     def has_ordinary_params!
+    //       set(ORDINARY_PARAMS)
+    //     end
+    pub fn set_has_ordinary_params(&mut self) {
+        self.set(ORDINARY_PARAMS);
+    }
+
+    //     def has_ordinary_params?
+    //       top == ORDINARY_PARAMS
+    //     end
+    pub fn has_ordinary_params(&self) -> bool {
+        self.top() == Some(ORDINARY_PARAMS)
+    }
+
+    //     def has_numparams?
+    //       top && top > 0
+    //     end
+    pub fn has_numparams(&self) -> bool {
+        self.top().unwrap_or(0) > 0
+    }
+
+    //     def register(numparam)
+    //       set( [top, numparam].max )
+    //     end
+    pub fn register(&mut self, numparam: usize) {
+        let value = self.top().map_or(numparam as isize, |top| top.max(numparam as isize));
+        self.set(value);
+    }
+
+    //     def top
+    //       @stack.last[:value]
+    //     end
+    pub fn top(&self) -> Option<isize> {
+        self.stack.last().map(|scope| scope.value)
+    }
+
+    //     def push(static:)
+    //       @stack.push(value: 0, static: static)
+    //     end
+    pub fn push(&mut self, static_env_depth: usize) {
+        self.stack.push(NumparamScope { value: 0, static_env_depth });
+    }
+
+    //     def pop
+    //       @stack.pop[:value]
+    //     end
+    pub fn pop(&mut self) -> Option<isize> {
+        self.stack.pop().map(|scope| scope.value)
+    }
+
+    //     private
+    //
+    //     def set(value)
+    //       @stack.last[:value] = value
+    //     end
+    fn set(&mut self, value: isize) {
+        if let Some(scope) = self.stack.last_mut() {
+            scope.value = value;
+        }
+    }
+}
+
+//   end
+// end
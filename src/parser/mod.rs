@@ -1,2 +1,4 @@
 pub mod token;
 pub mod parser;
+pub mod options;
+pub mod max_numparam_stack;
@@ -0,0 +1,86 @@
+// NOTE
+// whitequark has a parser class for each ruby version, Parser::Ruby20 through Parser::Ruby25, and lexer checks `@version`,
+// there's one grammar here, it accepts whatever the latest supported version does,
+// and the syntax a target version lacks is reported as an error, see Lexer::check_version and Parser::check_version,
+// except for numbered parameters, which are method calls before 2.7
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RubyVersion {
+    Ruby20,
+    Ruby21,
+    Ruby22,
+    Ruby23,
+    Ruby24,
+    Ruby25,
+    Ruby26,
+    Ruby27,
+    Ruby30,
+    Ruby31,
+    Ruby32,
+    Ruby33,
+}
+
+// NOTE `it` as the block parameter is 3.4, so it's an ordinary method call in all of these
+
+impl RubyVersion {
+    pub fn latest() -> RubyVersion { RubyVersion::Ruby33 }
+}
+
+impl fmt::Display for RubyVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let version = match *self {
+            RubyVersion::Ruby20 => "2.0",
+            RubyVersion::Ruby21 => "2.1",
+            RubyVersion::Ruby22 => "2.2",
+            RubyVersion::Ruby23 => "2.3",
+            RubyVersion::Ruby24 => "2.4",
+            RubyVersion::Ruby25 => "2.5",
+            RubyVersion::Ruby26 => "2.6",
+            RubyVersion::Ruby27 => "2.7",
+            RubyVersion::Ruby30 => "3.0",
+            RubyVersion::Ruby31 => "3.1",
+            RubyVersion::Ruby32 => "3.2",
+            RubyVersion::Ruby33 => "3.3",
+        };
+        write!(f, "{}", version)
+    }
+}
+
+// "2.7", or "2.7.8" with the patch level ignored
+impl FromStr for RubyVersion {
+    type Err = String;
+
+    fn from_str(version: &str) -> Result<RubyVersion, String> {
+        let minor_version = version.splitn(3, '.').take(2).collect::<Vec<_>>().join(".");
+        match minor_version.as_str() {
+            "2.0" => Ok(RubyVersion::Ruby20),
+            "2.1" => Ok(RubyVersion::Ruby21),
+            "2.2" => Ok(RubyVersion::Ruby22),
+            "2.3" => Ok(RubyVersion::Ruby23),
+            "2.4" => Ok(RubyVersion::Ruby24),
+            "2.5" => Ok(RubyVersion::Ruby25),
+            "2.6" => Ok(RubyVersion::Ruby26),
+            "2.7" => Ok(RubyVersion::Ruby27),
+            "3.0" => Ok(RubyVersion::Ruby30),
+            "3.1" => Ok(RubyVersion::Ruby31),
+            "3.2" => Ok(RubyVersion::Ruby32),
+            "3.3" => Ok(RubyVersion::Ruby33),
+            _ => Err(format!("unsupported ruby version {}", version))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParserOptions {
+    // the ruby the source is to run on
+    pub version: RubyVersion,
+}
+
+impl Default for ParserOptions {
+    fn default() -> ParserOptions {
+        ParserOptions { version: RubyVersion::latest() }
+    }
+}
@@ -7,16 +7,39 @@
 // eventually we should done such work automatically
 // 1. log the recursion path, check every rule
 
+use std::collections::HashMap;
+
 use lexer::lexing_state::LexingState;
-use lexer::Lexer;
-use parser::token::Token;
+use lexer::{Lexer, Checkpoint};
+use lexer::stack_state::StackState;
+use shared::static_env::StaticEnv;
+use source::buffer::SourceBuffer;
+use source::range::Range;
+use parser::token::{Token, TokenWithRange};
+use parser::options::{ParserOptions, RubyVersion};
+use parser::max_numparam_stack::MaxNumparamStack;
 use ast::node;
 use ast::node::Node;
+use ast::located::{self, LocatedNode};
+use source::comment::Comment;
+use source::magic_comment::{self, MagicComment};
+use diagnostic::{Diagnostic, Level};
+use trace::{TraceEvent, Tracer};
 
 // TODO dont rewrite this macro here
 macro_rules! state { ($state_name:expr) => { $state_name.parse::<LexingState>().unwrap() }; }
 
 // helpers
+
+// tIDENTIFIER-ish name of a token, like T_IDENTIFIER for T_IDENTIFIER("foo")
+fn token_name(token: &Token) -> String {
+    let token_debug = format!("{:?}", token);
+    match token_debug.find('(') {
+        Some(i) => String::from(&token_debug[..i]),
+        None => token_debug
+    }
+}
+
 fn extract_string_content(token: Token) -> String {
     match token {
         Token::T_STRING(content) | Token::T_STRING_CONTENT(content) => { return content; },
@@ -30,63 +53,769 @@ fn extract_nodes(node: Node) -> Vec<Node> {
     } else { panic!("can't extract nodes"); }
 }
 
+fn extract_ident(node: Node) -> String {
+    if let Node::Ident(name) = node {
+        return name;
+    } else { panic!("can't extract ident"); }
+}
+
+// the local variables an lhs or mlhs assigns to
+fn assigned_lvars(node: &Node) -> Vec<String> {
+    match *node {
+        Node::LVasgn(ref name, _) => vec![name.clone()],
+        Node::Splat(Some(ref node)) => assigned_lvars(node),
+        Node::Mlhs(ref nodes) => nodes.iter().flat_map(assigned_lvars).collect(),
+        _ => vec![]
+    }
+}
+
+// `name =~ /\A[[:lower:]_][[:alnum:]_]*\z/` of check_lvar_name, see Parser::declare_pattern_variable
+fn is_lvar_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_lowercase() || c == '_' => chars.all(|c| c.is_alphanumeric() || c == '_'),
+        _ => false
+    }
+}
+
+// the names remembered from token begin_p on, which are forgotten, see Parser::check_duplicate_pattern_variables
+fn take_names_from(names: &mut Vec<(usize, String, Range)>, begin_p: usize) -> Vec<(String, Range)> {
+    let taken = names.iter()
+        .filter(|&&(p, _, _)| p >= begin_p)
+        .map(|&(_, ref name, range)| (name.clone(), range))
+        .collect();
+    names.retain(|&(p, _, _)| p < begin_p);
+    taken
+}
+
+// op, see p_fname
+fn is_op(token: &Token) -> bool {
+    match *token {
+        Token::T_PIPE | Token::T_CARET | Token::T_AMPER2 | Token::T_CMP | Token::T_EQ | Token::T_EQQ |
+        Token::T_MATCH | Token::T_NMATCH | Token::T_GT | Token::T_GEQ | Token::T_LT | Token::T_LEQ |
+        Token::T_NEQ | Token::T_LSHFT | Token::T_RSHFT | Token::T_PLUS | Token::T_MINUS | Token::T_STAR2 |
+        Token::T_STAR | Token::T_DIVIDE | Token::T_PERCENT | Token::T_POW | Token::T_BANG | Token::T_TILDE |
+        Token::T_UPLUS | Token::T_UMINUS | Token::T_AREF | Token::T_ASET | Token::T_DSTAR | Token::T_BACK_REF2 => true,
+        _ => false
+    }
+}
+
+// operation, operation2 and operation3, see p_operation
+fn is_operation(token: &Token) -> bool {
+    match *token {
+        Token::T_IDENTIFIER(_) | Token::T_CONSTANT(_) | Token::T_FID(_) => true,
+        _ => false
+    }
+}
+
+fn is_operation2(token: &Token) -> bool {
+    is_operation(token) || is_op(token)
+}
+
+fn is_operation3(token: &Token) -> bool {
+    match *token {
+        Token::T_CONSTANT(_) => false,
+        ref token => is_operation2(token)
+    }
+}
+
+// reswords, see p_fname
+fn is_resword(token: &Token) -> bool {
+    match *token {
+        Token::K__LINE__ | Token::K__FILE__ | Token::K__ENCODING__ | Token::K_LBEGIN | Token::K_LEND |
+        Token::K_ALIAS | Token::K_AND | Token::K_BEGIN | Token::K_BREAK | Token::K_CASE |
+        Token::K_CLASS | Token::K_DEF | Token::K_DEFINED | Token::K_DO | Token::K_ELSE |
+        Token::K_ELSIF | Token::K_END | Token::K_ENSURE | Token::K_FALSE | Token::K_FOR |
+        Token::K_IN | Token::K_MODULE | Token::K_NEXT | Token::K_NIL | Token::K_NOT |
+        Token::K_OR | Token::K_REDO | Token::K_RESCUE | Token::K_RETRY | Token::K_RETURN |
+        Token::K_SELF | Token::K_SUPER | Token::K_THEN | Token::K_TRUE | Token::K_UNDEF |
+        Token::K_WHEN | Token::K_YIELD | Token::K_IF | Token::K_UNLESS | Token::K_WHILE |
+        Token::K_UNTIL => true,
+        _ => false
+    }
+}
+
+// (node, end_p, lexed_len, located), see Parser::primary_memo
+type PrimaryMemo = (Node, usize, usize, Option<LocatedNode>);
+
 pub struct Parser {
     lexer: Lexer,
-
-    tokens: Vec<Token>,
+    options: ParserOptions,
+
+    tokens: Vec<TokenWithRange>,
+    // lexer's checkpoint right before lexing each token, by token index, see rewind,
+    // None for the tokens before committed_p
+    lexer_checkpoints: Vec<Option<Checkpoint>>,
+    // parser never goes back before this token, see commit_tokens
+    committed_p: usize,
+    // whether lexer has been updated or rewound since the last checkpoint, see fetch_current_token
+    is_lexer_changed: bool,
     current_p: usize, // TODO NOTE
+    // the furthest token ever looked at, None for the end of source, where the error will be reported if parsing failed
+    // NOTE kept by value, since the tokens after it may be dropped by rewind
+    furthest_token: (Option<Token>, Range),
+    // @lexer.dedent_level right after lexing each tSTRING_END, by token index
+    // NOTE whitequark reads it when reducing string1 and xstring, we may backtrack and reduce them later than that
+    dedent_levels: HashMap<usize, usize>,
+    // what p_primary produced starting from each token under each def_level, along with where it ended,
+    // how many tokens had been lexed by then, which are all it may have looked at, and its location if recorded
+    // NOTE
+    // expr, arg and lhs all try primary again after giving up on it, which gets exponential with nesting like `f(f(f(1)))`,
+    // an entry is forgotten once any of its tokens is dropped, or static_env has changed, see drop_tokens_from
+    primary_memo: HashMap<(usize, usize), PrimaryMemo>,
+
+    // @def_level
+    def_level: usize,
+    // the names declared by f_norm_arg, f_label and so on, along with the index and the range of their tokens,
+    // see check_duplicate_args
+    arg_names: Vec<(usize, String, Range)>,
+    // @pattern_variables and @pattern_hash_keys, the names bound by a pattern and the keys of its hash patterns,
+    // along with the index and the range of their tokens, see check_duplicate_pattern_variables
+    pattern_variables: Vec<(usize, String, Range)>,
+    pattern_hash_keys: Vec<(usize, String, Range)>,
+    // where each block_arg ends, by the token index it begins from, see check_block_and_blockarg
+    block_arg_ends: HashMap<usize, usize>,
+    // @max_numparam_stack, see try_declare_numparam
+    max_numparam_stack: MaxNumparamStack,
 
     recursion_stack: Vec<String>, // TODO &str is enough
+    // the token index each rule in recursion_stack started from
+    rule_starts: Vec<usize>,
+
+    // the located nodes produced by rules and not yet taken by a parent as its children, see record_location
+    // NOTE only recorded by parse_with_comments, since that clones a lot
+    record_locations: bool,
+    locations: Vec<LocatedNode>,
+    // how many located nodes there were when each rule in recursion_stack started
+    location_starts: Vec<usize>,
+
+    magic_comments: Vec<MagicComment>,
+
+    diagnostics: Vec<Diagnostic>,
+    // the errors lexer reported for tokens dropped since, see drop_tokens_from
+    dropped_lexer_diagnostics: Vec<Diagnostic>,
 }
 
 impl Parser {
     pub fn new(input: String) -> Parser {
+        Parser::with_source_buffer(SourceBuffer::new("(string)", input))
+    }
+
+    // the source is decoded from the encoding its magic comment tells, UTF-8 by default,
+    // see SourceBuffer::from_bytes
+    pub fn from_bytes(input: Vec<u8>) -> Result<Parser, Diagnostic> {
+        Ok(Parser::with_source_buffer(SourceBuffer::from_bytes("(string)", input)?))
+    }
+
+    pub fn with_source_buffer(source_buffer: SourceBuffer) -> Parser {
+        //   @static_env  = StaticEnvironment.new
+        //   @lexer.static_env = @static_env
+        let mut lexer = Lexer::with_source_buffer(source_buffer);
+        lexer.set_static_env(Some(StaticEnv::new()));
+
         Parser {
-            lexer: Lexer::new(input),
+            lexer,
+            options: ParserOptions::default(),
 
             tokens: vec![],
+            lexer_checkpoints: vec![],
+            committed_p: 0,
+            is_lexer_changed: false,
             current_p: 0,
+            furthest_token: (None, Range::new(0, 0)),
+            dedent_levels: HashMap::new(),
+            primary_memo: HashMap::new(),
+
+            def_level: 0,
+            arg_names: vec![],
+            pattern_variables: vec![],
+            pattern_hash_keys: vec![],
+            block_arg_ends: HashMap::new(),
+            max_numparam_stack: MaxNumparamStack::new(),
 
             recursion_stack: vec![],
+            rule_starts: vec![],
+
+            record_locations: false,
+            locations: vec![],
+            location_starts: vec![],
+
+            magic_comments: vec![],
+
+            diagnostics: vec![],
+            dropped_lexer_diagnostics: vec![],
         }
     }
 
-    pub fn parse(&mut self) -> Node {
-        if let Some(n_program) = self.p_program() {
-            return n_program;
-        } else {
-            println!("PARSRE failed to parse");
-            println!("emitted tokens: {:?}", self.tokens);
-            panic!("parser: failed to parse");
+    pub fn parse(&mut self) -> Result<Node, Diagnostic> {
+        let n_program = self.p_program();
+
+        let first_token_pos = self.tokens.first().map(|&(_, range)| range.begin_pos);
+        self.magic_comments = magic_comment::recognize(&self.lexer.comments, first_token_pos, self.lexer.source_buffer(), &mut self.diagnostics);
+
+        // NOTE a fatal diagnostic from lexer means the tokens are incomplete
+        if let Some(diagnostic) = self.lexer.fatal_diagnostic() {
+            return Err(diagnostic.clone());
+        }
+
+        if let Some(diagnostic) = self.lexer.diagnostics.iter().find(|diagnostic| diagnostic.level >= Level::Error) {
+            return Err(diagnostic.clone());
+        }
+
+        if let Some(diagnostic) = self.diagnostics.iter().find(|diagnostic| diagnostic.level >= Level::Error) {
+            return Err(diagnostic.clone());
+        }
+
+        // NOTE backtracking may leave tokens unconsumed, that's an error as well
+        if let Some(n_program) = n_program {
+            if self.current_token().is_none() {
+                return Ok(n_program);
+            }
+        }
+
+        // NOTE
+        // an error lexer reported for tokens dropped since, like the end of source within a string,
+        // is what went wrong if parsing gave up before lexing that far again,
+        // since lexer stops on a fatal one, which leaves the alternatives with no tokens to go on
+        let lexed_begin_pos = self.tokens.last().map_or(0, |&(_, range)| range.begin_pos);
+        if let Some(diagnostic) = self.dropped_lexer_diagnostics.iter()
+            .filter(|diagnostic| diagnostic.range.begin_pos >= lexed_begin_pos)
+            .min_by_key(|diagnostic| diagnostic.range.begin_pos) {
+            return Err(diagnostic.clone());
+        }
+
+        //   def on_error(error_token_id, error_value, value_stack)
+        //     token_name = token_to_str(error_token_id)
+        //     _, location = error_value
+        // 
+        //     @diagnostics.process(Diagnostic.new(
+        //         :error, :unexpected_token, { :token => token_name }, location))
+        //   end
+        let (token_name, range) = match self.furthest_token {
+            (Some(ref token), range) => (token_name(token), range),
+            (None, range) => (String::from("$end"), range)
+        };
+        let mut arguments = HashMap::new();
+        arguments.insert("token", token_name);
+        self.diagnostic(Level::Error, "unexpected_token", arguments, range);
+
+        Err(self.diagnostics.last().unwrap().clone())
+    }
+
+    //   def parse_with_comments(source_buffer)
+    //     @lexer.comments = []
+    //
+    //     [ parse(source_buffer), @lexer.comments ]
+    //   ensure
+    //     @lexer.comments = nil
+    //   end
+    pub fn parse_with_comments(&mut self) -> Result<(Node, Vec<Comment>), Diagnostic> {
+        self.record_locations = true;
+
+        let node = self.parse()?;
+
+        Ok((node, self.lexer.comments.clone()))
+    }
+
+    // magic comments recognized by parse
+    pub fn magic_comments(&self) -> &Vec<MagicComment> {
+        &self.magic_comments
+    }
+
+    // the data section after `__END__`, what ruby reads from DATA, once parsed
+    // NOTE it's a range of the input as it was given, with `\r\n` kept, see SourceBuffer::original_source
+    pub fn data_range(&self) -> Option<Range> {
+        let source_buffer = self.source_buffer();
+        self.lexer.data_s().map(|data_s| Range::new(source_buffer.original_position(data_s), source_buffer.original_source().len()))
+    }
+
+    pub fn data(&self) -> Option<&str> {
+        self.data_range().map(|range| &self.source_buffer().original_source()[range.begin_pos..range.end_pos])
+    }
+
+    // the tree produced by parse_with_comments along with the range of every node, for Comment::associate,
+    // see record_location
+    pub fn locate(&self, node: &Node) -> Option<LocatedNode> {
+        self.locations.iter().rev().find(|located| located.node == *node).cloned()
+    }
+
+    // diagnostics reported by both lexer and parser
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = self.lexer.diagnostics.clone();
+        diagnostics.append(&mut self.diagnostics.clone());
+        diagnostics
+    }
+
+    pub fn source_buffer(&self) -> &SourceBuffer {
+        self.lexer.source_buffer()
+    }
+
+    // NOTE the tracer lives in lexer, so that both trace into one place
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.lexer.set_tracer(tracer);
+    }
+
+    // NOTE before parse, the version is lexer's as well
+    pub fn set_options(&mut self, options: ParserOptions) {
+        self.lexer.set_version(options.version);
+        self.options = options;
+    }
+
+    pub fn options(&self) -> &ParserOptions {
+        &self.options
+    }
+
+    //   attr_reader :static_env
+    // NOTE the one held by lexer, declare variables in it before parse to have them parsed as local variables
+    pub fn static_env_mut(&mut self) -> &mut StaticEnv {
+        self.lexer.static_env_mut().unwrap()
+    }
+
+    // NOTE a rule may be tried again after backtracking, the same diagnostic is reported once
+    fn diagnostic(&mut self, level: Level, reason: &'static str, arguments: HashMap<&'static str, String>, range: Range) {
+        let diagnostic = Diagnostic::new(level, reason, arguments, range);
+        if !self.diagnostics.contains(&diagnostic) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    //     def in_def?
+    //       @def_level > 0
+    //     end
+    fn in_def(&self) -> bool {
+        self.def_level > 0
+    }
+
+    // the source of token p, which is the value of tokens like tPLUS as well
+    fn token_source(&self, p: usize) -> String {
+        let range = self.tokens[p].1;
+        String::from(&self.source_buffer().source()[range.begin_pos..range.end_pos])
+    }
+
+    // `@static_env.declare val[0][0]` in f_norm_arg, f_label and so on, for the name token just matched,
+    // which is remembered for check_duplicate_args as well
+    fn declare_arg(&mut self, name: &str, range: Range) {
+        // NOTE the ones from the token on were declared by an alternative given up
+        let p = self.current_p - 1;
+        self.arg_names.retain(|&(arg_p, _, _)| arg_p < p);
+        self.arg_names.push((p, String::from(name), range));
+
+        self.update_static_env(|static_env| static_env.declare(name));
+    }
+
+    //     def check_duplicate_args(args, map={})
+    //       args.each do |this_arg|
+    //         case this_arg.type
+    //         when :arg, :optarg, :restarg, :blockarg,
+    //              :kwarg, :kwoptarg, :kwrestarg,
+    //              :shadowarg, :procarg0
+    // 
+    //           this_name, = *this_arg
+    // 
+    //           that_arg   = map[this_name]
+    //           that_name, = *that_arg
+    // 
+    //           if that_arg.nil?
+    //             map[this_name] = this_arg
+    //           elsif arg_name_collides?(this_name, that_name)
+    //             diagnostic :error, :duplicate_argument, nil,
+    //                        this_arg.loc.name, [ that_arg.loc.name ]
+    //           end
+    // 
+    //         when :mlhs
+    //           check_duplicate_args(this_arg.children, map)
+    //         end
+    //       end
+    //     end
+    // 
+    // NOTE
+    // nodes don't know where they are, so the names declared for the arglist from begin_p on are checked instead,
+    // they're in source order with mlhs flattened, like the args walked by whitequark,
+    // and they're forgotten afterwards, so that an arglist around this one doesn't check them again
+    fn check_duplicate_args(&mut self, begin_p: usize) {
+        let end_p = self.current_p;
+        let arg_names: Vec<(String, Range)> = self.arg_names.iter()
+            .filter(|&&(p, _, _)| p >= begin_p && p < end_p)
+            .map(|&(_, ref name, range)| (name.clone(), range))
+            .collect();
+        self.arg_names.retain(|&(p, _, _)| p < begin_p);
+
+        let mut declared_names: Vec<String> = vec![];
+        for (this_name, range) in arg_names {
+            if declared_names.iter().any(|that_name| node::arg_name_collides(&this_name, that_name)) {
+                self.diagnostic(Level::Error, "duplicate_argument", HashMap::new(), range);
+            } else {
+                declared_names.push(this_name);
+            }
+        }
+    }
+
+    // NOTE from a later whitequark/parser, where pattern matching came with 2.7
+    //   def match_var(name_t)
+    //     name = value(name_t).to_sym
+    //     name_l = loc(name_t)
+    // 
+    //     check_lvar_name(name, name_l)
+    //     check_duplicate_pattern_variable(name, name_l)
+    //     @parser.static_env.declare(name)
+    //     ...
+    //   end
+    // 
+    //   def check_lvar_name(name, loc)
+    //     if name =~ /\A[[:lower:]_][[:alnum:]_]*\z/
+    //       # OK
+    //     else
+    //       diagnostic :error, :lvar_name, { name: name }, loc
+    //     end
+    //   end
+    // for the name token just matched, whose name is at range,
+    // it's remembered for check_duplicate_pattern_variables, like declare_arg
+    fn declare_pattern_variable(&mut self, name: &str, range: Range) {
+        if !is_lvar_name(name) {
+            let mut arguments = HashMap::new();
+            arguments.insert("name", String::from(name));
+            self.diagnostic(Level::Error, "lvar_name", arguments, range);
+        }
+
+        // NOTE the ones from the token on were declared by an alternative given up
+        let p = self.current_p - 1;
+        self.pattern_variables.retain(|&(variable_p, _, _)| variable_p < p);
+        self.pattern_variables.push((p, String::from(name), range));
+
+        self.update_static_env(|static_env| static_env.declare(name));
+    }
+
+    //   def check_duplicate_pattern_variable(name, loc)
+    //     return if name.to_s.start_with?('_')
+    // 
+    //     if @parser.pattern_variables.declared?(name)
+    //       diagnostic :error, :duplicate_variable_name, { :name => name.to_s }, loc
+    //     end
+    // 
+    //     @parser.pattern_variables.declare(name)
+    //   end
+    // NOTE the names bound from begin_p on are checked once the pattern is parsed, then forgotten, like check_duplicate_args
+    fn check_duplicate_pattern_variables(&mut self, begin_p: usize) {
+        let names = take_names_from(&mut self.pattern_variables, begin_p);
+
+        let mut declared_names: Vec<String> = vec![];
+        for (name, range) in names {
+            if name.starts_with('_') { continue; }
+
+            if declared_names.contains(&name) {
+                let mut arguments = HashMap::new();
+                arguments.insert("name", name);
+                self.diagnostic(Level::Error, "duplicate_variable_name", arguments, range);
+            } else {
+                declared_names.push(name);
+            }
+        }
+    }
+
+    // the key of the label token just matched, see check_duplicate_pattern_hash_keys
+    fn declare_pattern_hash_key(&mut self, key: &str, range: Range) {
+        let p = self.current_p - 1;
+        self.pattern_hash_keys.retain(|&(key_p, _, _)| key_p < p);
+        self.pattern_hash_keys.push((p, String::from(key), range));
+    }
+
+    //   def check_duplicate_pattern_key(name, loc)
+    //     if @parser.pattern_hash_keys.declared?(name)
+    //       diagnostic :error, :duplicate_pattern_key, { :name => name.to_s }, loc
+    //     end
+    // 
+    //     @parser.pattern_hash_keys.declare(name)
+    //   end
+    // NOTE
+    // the keys from begin_p on are checked once p_kwargs has parsed them,
+    // then forgotten, so that a hash pattern around this one doesn't check them, like `@pattern_hash_keys.pop`
+    fn check_duplicate_pattern_hash_keys(&mut self, begin_p: usize) {
+        let keys = take_names_from(&mut self.pattern_hash_keys, begin_p);
+
+        let mut declared_keys: Vec<String> = vec![];
+        for (key, range) in keys {
+            if declared_keys.contains(&key) {
+                let mut arguments = HashMap::new();
+                arguments.insert("name", key);
+                self.diagnostic(Level::Error, "duplicate_pattern_key", arguments, range);
+            } else {
+                declared_keys.push(key);
+            }
+        }
+    }
+
+    //     def check_kwarg_name(name_t)
+    //       name, name_range = name_t
+    //       if name =~ /\A[[:upper:]]/
+    //         diagnostic :error, :argument_const, nil, name_range
+    //       end
+    //     end
+    fn check_kwarg_name(&mut self, name: &str, range: Range) {
+        if name.chars().next().map_or(false, |c| c.is_uppercase()) {
+            self.diagnostic(Level::Error, "argument_const", HashMap::new(), range);
+        }
+    }
+
+    // the `unless @static_env.declared_forward_args?` part of paren_args, for args_forward being token dot3_p
+    fn check_forwarded_args(&mut self, dot3_p: usize) {
+        if self.lexer.static_env().unwrap().has_declared_forward_args() { return; }
+
+        let mut arguments = HashMap::new();
+        arguments.insert("token", token_name(&Token::T_DOT3));
+        let range = self.tokens[dot3_p].1;
+        self.diagnostic(Level::Error, "unexpected_token", arguments, range);
+    }
+
+    // report the syntax which came with a later ruby than the target one, like Lexer::check_version
+    // NOTE it's still parsed as usual
+    fn check_version(&mut self, feature: &str, since: RubyVersion, range: Range) {
+        if self.options.version >= since { return; }
+
+        let mut arguments = HashMap::new();
+        arguments.insert("feature", String::from(feature));
+        arguments.insert("version", self.options.version.to_string());
+        self.diagnostic(Level::Error, "unavailable_in_version", arguments, range);
+    }
+
+    // NOTE from a later whitequark/parser, where endless def came with 3.0
+    //   def endless_method_name(name_t)
+    //     if !%w[=== == != <= >=].include?(name_t[0]) && name_t[0].end_with?('=')
+    //       diagnostic :error, :endless_setter, nil, name_t
+    //     end
+    //   end
+    fn check_endless_method_name(&mut self, name: &str, range: Range) {
+        if !["===", "==", "!=", "<=", ">="].contains(&name) && name.ends_with('=') {
+            self.diagnostic(Level::Error, "endless_setter", HashMap::new(), range);
+        }
+    }
+
+    // NOTE from a later whitequark/parser, where numbered parameters came with 2.7
+    //     def try_declare_numparam(node)
+    //       name = node.children[0]
+    //
+    //       if name =~ /\A_[1-9]\z/ && !static_env.declared?(name) && context.in_dynamic_block?
+    //         # definitely an implicit param
+    //         location = node.loc.expression
+    //
+    //         if max_numparam_stack.has_ordinary_params?
+    //           diagnostic :error, :ordinary_param_defined, nil, [nil, location]
+    //         end
+    //
+    //         raw_max_numparam_stack = max_numparam_stack.stack.dup
+    //         # ignore current block scope
+    //         raw_max_numparam_stack.pop
+    //
+    //         raw_max_numparam_stack.reverse_each do |outer_scope|
+    //           if outer_scope[:static]
+    //             # found an outer scope that can't have numparams
+    //             # like def/class/etc
+    //             break
+    //           else
+    //             outer_scope_has_numparams = outer_scope[:value] > 0
+    //
+    //             if outer_scope_has_numparams
+    //               diagnostic :error, :numparam_used_in_outer_scope, nil, [nil, location]
+    //             else
+    //               # for now it's ok, but an outer scope can also be a block
+    //               # like proc { _1; proc { proc { proc { _2 }} } }
+    //               # with numparams, so we need to continue
+    //             end
+    //           end
+    //         end
+    //
+    //         static_env.declare(name)
+    //         max_numparam_stack.register(name[1].to_i)
+    //
+    //         true
+    //       else
+    //         false
+    //       end
+    //     end
+    // 
+    // NOTE
+    // it's only the body of the innermost block, not a def or class within it, which is as deep in static_env as its scope,
+    // and an outer block is next to the one within it as long as it's one scope out, see MaxNumparamStack
+    fn try_declare_numparam(&mut self, name: &str, range: Range) -> bool {
+        if self.options.version < RubyVersion::Ruby27 { return false; }
+
+        let mut chars = name.chars();
+        let numparam = match (chars.next(), chars.next().and_then(|c| c.to_digit(10)), chars.next()) {
+            (Some('_'), Some(digit), None) if digit > 0 => digit as usize,
+            _ => { return false; }
+        };
+
+        let static_env_depth = {
+            let static_env = self.lexer.static_env().unwrap();
+            if static_env.has_declared(String::from(name)) { return false; }
+            static_env.depth()
+        };
+        let mut scopes = self.max_numparam_stack.stack().clone();
+        match scopes.pop() {
+            Some(ref scope) if scope.static_env_depth == static_env_depth => {},
+            _ => { return false; }
+        }
+
+        if self.max_numparam_stack.has_ordinary_params() {
+            self.diagnostic(Level::Error, "ordinary_param_defined", HashMap::new(), range);
+        }
+
+        let mut inner_depth = static_env_depth;
+        for outer_scope in scopes.iter().rev() {
+            if outer_scope.static_env_depth + 1 != inner_depth { break; }
+            if outer_scope.value > 0 {
+                self.diagnostic(Level::Error, "numparam_used_in_outer_scope", HashMap::new(), range);
+            }
+            inner_depth = outer_scope.static_env_depth;
+        }
+
+        self.update_static_env(|static_env| static_env.declare(name));
+        self.max_numparam_stack.register(numparam);
+
+        true
+    }
+
+    // NOTE
+    // primary takes the tails of method_call as far as they go, see p_primary,
+    // so for the rules starting with `primary_value call_op operation2` and the like, primary_value is a call ending with them,
+    // told by its last tokens, which gives the receiver, the token of call_op or tCOLON2, and the selector
+    fn trailing_call(&self, node: &Node, begin_p: usize) -> Option<(Node, Token, String)> {
+        let end_p = self.current_p;
+        if end_p < begin_p + 3 { return None; }
+
+        let t_dot = self.tokens[end_p - 2].0.clone();
+        match t_dot {
+            Token::T_DOT | Token::T_ANDDOT | Token::T_COLON2 => {},
+            _ => { return None; }
+        }
+        if !is_operation2(&self.tokens[end_p - 1].0) { return None; }
+
+        match *node {
+            Node::Send { receiver: Some(ref receiver), ref selector, ref args } if args.is_empty() => {
+                Some(((**receiver).clone(), t_dot, selector.clone()))
+            },
+            Node::CSend { ref receiver, ref selector, ref args } if args.is_empty() => {
+                Some(((**receiver).clone(), t_dot, selector.clone()))
+            },
+            Node::Const { scope: Some(ref scope), ref name } => {
+                Some(((**scope).clone(), t_dot, name.clone()))
+            },
+            _ => None
         }
     }
 
+    // the `last_arg.type == :block_pass` part of builder's block, for method_call getting the block beginning from token block_p
+    // 
+    // NOTE
+    // the block_pass is reported where its block_arg is, which is the last one ending before the block,
+    // the others ending there would be within it, like `&bar` of `fun(&foo(&bar)) {}`
+    fn check_block_and_blockarg(&mut self, method_call: &Node, block_p: usize) {
+        let has_block_pass = match *method_call {
+            Node::Send { ref args, .. } | Node::CSend { ref args, .. } => {
+                match args.last() { Some(&Node::BlockPass(_)) => true, _ => false }
+            },
+            _ => false
+        };
+        if !has_block_pass { return; }
+
+        let block_arg = self.block_arg_ends.iter()
+            .filter(|&(_, &end_p)| end_p <= block_p)
+            .map(|(&begin_p, &end_p)| (begin_p, end_p))
+            .max_by_key(|&(begin_p, end_p)| (end_p, block_p - begin_p));
+        if let Some((begin_p, end_p)) = block_arg {
+            let range = self.range_of_tokens(begin_p, end_p).unwrap();
+            self.diagnostic(Level::Error, "block_and_blockarg", HashMap::new(), range);
+        }
+    }
+
+    // builds the block for method_call from what brace_block and the like returned, which begins from token block_p
+    fn block_for(&mut self, method_call: Node, n_block: Node, block_p: usize) -> Node {
+        self.check_block_and_blockarg(&method_call, block_p);
+
+        let mut nodes = extract_nodes(n_block);
+        let n_body = nodes.pop().unwrap();
+        let n_args = nodes.pop().unwrap();
+        node::block(method_call, n_args, n_body)
+    }
+
+    // `@max_numparam_stack.push(static: false)` right after `@static_env.extend_dynamic`, see MaxNumparamStack
+    fn push_numparam_scope(&mut self) {
+        let static_env_depth = self.lexer.static_env().unwrap().depth();
+        self.max_numparam_stack.push(static_env_depth);
+    }
+
+    //   args = @max_numparam_stack.has_numparams? ? @builder.numargs(@max_numparam_stack.top) : val[1]
+    //   ...
+    //   @max_numparam_stack.pop
+    fn pop_numparam_scope(&mut self, args: Node) -> Node {
+        let args = if self.max_numparam_stack.has_numparams() {
+            node::numargs(self.max_numparam_stack.top().unwrap() as usize)
+        } else {
+            args
+        };
+        self.max_numparam_stack.pop();
+        args
+    }
+
     // get a new one if necessary
     fn current_token(&mut self) -> Option<Token> {
         // println!("PARSER current_token, current_p: {}", self.current_p);
 
-        if let Some(token) = self.tokens.get(self.current_p) {
+        let token = self.fetch_current_token();
+
+        let range = match self.tokens.get(self.current_p) {
+            Some(&(_, range)) => range,
+            None => {
+                let source_len = self.lexer.source_buffer().source().len();
+                Range::new(source_len, source_len)
+            }
+        };
+        if range.begin_pos >= self.furthest_token.1.begin_pos {
+            self.furthest_token = (token.clone(), range);
+        }
+
+        token
+    }
+
+    fn fetch_current_token(&mut self) -> Option<Token> {
+        if let Some(&(ref token, _)) = self.tokens.get(self.current_p) {
             return Some(token.clone());
         }
 
         // try to fetch a new one
-        if let Some(token) = self.lexer.advance() {
-            self.tokens.push(token.clone());
+        // NOTE
+        // a token lexed along with the previous one has the checkpoint after the previous one's,
+        // a new one is taken only before lexing, or after lexer has changed, which is where parser can rewind to
+        let lexer_checkpoint = match self.lexer_checkpoints.last() {
+            Some(Some(checkpoint)) if self.lexer.has_pending_tokens() && !self.is_lexer_changed => checkpoint.next(),
+            _ => self.lexer.checkpoint()
+        };
+        self.is_lexer_changed = false;
+        if let Some((token, range)) = self.lexer.advance() {
+            if token == Token::T_STRING_END {
+                if let Some(dedent_level) = self.lexer.dedent_level() {
+                    self.dedent_levels.insert(self.tokens.len(), dedent_level);
+                }
+            }
+
+            self.tokens.push((token.clone(), range));
+            self.lexer_checkpoints.push(Some(lexer_checkpoint));
             return Some(token);
         } else {
             return None;
         }
     }
 
+    // see dedent_levels
+    fn dedent_level_of_previous_token(&self) -> Option<usize> {
+        self.dedent_levels.get(&(self.current_p - 1)).cloned()
+    }
+
     // TODO handle no more token
     fn consume_current_token(&mut self) -> Token {
-        let token_to_consume = self.tokens.get(self.current_p).unwrap().clone();
+        let (token_to_consume, _) = self.tokens.get(self.current_p).unwrap().clone();
 
         self.current_p += 1;
 
-        // println!("PARSER comsume_current_token: {:?}", token_to_consume);
-        println!("consumed token {:?}, current stack: {:?}", token_to_consume, self.recursion_stack);
-
         return token_to_consume;
     }
 
@@ -108,18 +837,222 @@ impl Parser {
         return None;
     }
 
+    // backtrack to token p, like resetting current_p, but the tokens from there on are dropped,
+    // and lexer goes back to where it was before lexing them, to lex them again
+    // 
+    // NOTE
+    // resetting current_p is enough as long as an alternative didn't change lexer's state,
+    // otherwise the tokens lexed ahead of it may be the wrong ones
+    fn rewind(&mut self, p: usize) {
+        self.current_p = p;
+        self.drop_tokens_from(p);
+    }
+
+    fn drop_tokens_from(&mut self, p: usize) {
+        if p >= self.tokens.len() { return; }
+
+        let static_env_log_len = self.lexer.static_env().map(StaticEnv::log_len);
+        self.is_lexer_changed = true;
+        let lexer_checkpoint = self.lexer_checkpoints[p].as_ref().expect("can't rewind to a token before a committed one");
+        for diagnostic in self.lexer.rewind(lexer_checkpoint) {
+            if diagnostic.level >= Level::Error && !self.dropped_lexer_diagnostics.contains(&diagnostic) {
+                self.dropped_lexer_diagnostics.push(diagnostic);
+            }
+        }
+        self.tokens.truncate(p);
+        self.lexer_checkpoints.truncate(p);
+        self.dedent_levels.retain(|&token_p, _| token_p < p);
+        self.block_arg_ends.retain(|_, &mut end_p| end_p <= p);
+
+        if self.lexer.static_env().map(StaticEnv::log_len) == static_env_log_len {
+            self.primary_memo.retain(|_, &mut (_, _, lexed_len, _)| lexed_len <= p);
+        } else {
+            self.primary_memo.clear();
+        }
+    }
+
+    // the tokens before p are never dropped from now on, like the statements the outermost top_stmts has parsed,
+    // so their lexer checkpoints and what's kept along with them are let go,
+    // which keeps memory, and the retains of drop_tokens_from, from growing with the source
+    fn commit_tokens(&mut self, p: usize) {
+        if p <= self.committed_p { return; }
+
+        for lexer_checkpoint in &mut self.lexer_checkpoints[self.committed_p..p] {
+            *lexer_checkpoint = None;
+        }
+        self.committed_p = p;
+
+        self.primary_memo.retain(|&(memo_p, _), _| memo_p >= p);
+        self.dedent_levels.retain(|&token_p, _| token_p >= p);
+        self.block_arg_ends.retain(|&begin_p, _| begin_p >= p);
+    }
+
+    // like `@lexer.state = ...` in a rule,
+    // tokens after the current one must be lexed in that state, see rewind
+    fn set_lexer_state(&mut self, state: LexingState) {
+        self.update_lexer(false, |lexer| lexer.set_state(state));
+    }
+
+    // the mid-rule actions of ruby25.y on lexer, like `@lexer.cond.push(true)` or `@lexer.cmdarg = val[0]`
+    // 
+    // NOTE
+    // the tokens after such an action are to be lexed under the updated lexer,
+    // while we may have lexed them already for another alternative, so they're dropped and lexed again,
+    // and a rule giving up after such an action should `rewind` to before it instead of resetting current_p,
+    // so that lexer is back as it was
+    // 
+    // racc has read the lookahead token before some of these actions, like the one after call_args in command_args,
+    // then is_lookahead_read keeps that token as lexed before the update, as ruby25.y expects
+    fn update_lexer<F: FnOnce(&mut Lexer)>(&mut self, is_lookahead_read: bool, update: F) {
+        let mut p = self.current_p;
+        if is_lookahead_read && self.current_token().is_some() { p += 1; }
+
+        self.drop_tokens_from(p);
+        update(&mut self.lexer);
+        self.is_lexer_changed = true;
+    }
+
+    // `@static_env.declare ...`, `@static_env.extend_static` and so on in a rule,
+    // local variables are told apart from method calls by lexer, so the tokens after are lexed again, see update_lexer
+    fn update_static_env<F: FnOnce(&mut StaticEnv)>(&mut self, update: F) {
+        self.update_lexer(false, |lexer| update(lexer.static_env_mut().unwrap()));
+        self.primary_memo.clear();
+    }
+
+    // `result = @lexer.cmdarg.dup` followed by an update of cmdarg, like the mid-rule actions of brace_body,
+    // returning the dup, see update_lexer
+    // 
+    // NOTE
+    // lexer may have lexed ahead of current token for another alternative,
+    // so cmdarg is duplicated once lexer is back to where the action is
+    fn dup_cmdarg_and_update<F: FnOnce(&mut StackState)>(&mut self, is_lookahead_read: bool, update: F) -> StackState {
+        let mut cmdarg = StackState::new();
+        self.update_lexer(is_lookahead_read, |lexer| {
+            cmdarg = lexer.cmdarg.clone();
+            update(&mut lexer.cmdarg);
+        });
+        cmdarg
+    }
+
     // TODO
     fn recurse(&mut self, fn_name: &str) {
         let fn_name = String::from(fn_name);
         self.recursion_stack.push(fn_name.clone());
-        println!("recursed. stack: {:?}", self.recursion_stack);
+        self.rule_starts.push(self.current_p);
+        self.location_starts.push(self.locations.len());
+
+        let p = self.current_p;
+        self.lexer.trace(|| TraceEvent::RuleEnter { rule: fn_name, p });
     }
 
     // TODO NOTE
     // currently functions will only `decurse` when returning None
     fn decurse(&mut self) {
-        self.recursion_stack.pop();
-        println!("decursed. stack: {:?}", self.recursion_stack);
+        self.pop_rule(false);
+    }
+
+    // NOTE the located nodes a rule has produced but not taken are from the alternatives it gave up on
+    fn pop_rule(&mut self, matched: bool) {
+        let rule = self.recursion_stack.pop();
+        self.rule_starts.pop();
+        if let Some(location_start) = self.location_starts.pop() {
+            self.locations.truncate(location_start);
+        }
+
+        if let Some(rule) = rule {
+            let p = self.current_p;
+            self.lexer.trace(|| TraceEvent::RuleExit { rule, p, matched });
+        }
+    }
+
+    // decurse with the node produced by current rule, recording its location if necessary
+    fn decurse_with(&mut self, node: Node) -> Option<Node> {
+        let begin_p = *self.rule_starts.last().unwrap();
+        let located = self.locate_built(begin_p, &node);
+
+        self.pop_rule(true);
+        if let Some(located) = located {
+            self.locations.push(located);
+        }
+        Some(node)
+    }
+
+    // NOTE decurse_with does it for the node a rule returns,
+    // the ones built within a rule transformed into non-left-recursive form are recorded by the rule itself
+    fn record_location(&mut self, begin_p: usize, node: &Node) {
+        if let Some(located) = self.locate_built(begin_p, node) {
+            self.locations.push(located);
+        }
+    }
+
+    // the node just built by current rule from tokens[begin_p..current_p], located,
+    // its children are taken from the located nodes produced since the rule started
+    // 
+    // NOTE
+    // a node passed on as it is, like primary by arg, is the located node produced already,
+    // otherwise a child is the latest equal one within the node,
+    // since the alternatives given up on by current rule may have left some behind
+    fn locate_built(&mut self, begin_p: usize, node: &Node) -> Option<LocatedNode> {
+        if !self.record_locations { return None; }
+
+        // NOTE Nodes is only a list for the rules, like stmts, what's in it is taken out as it is
+        let location_start = *self.location_starts.last().unwrap();
+        let mut candidates: Vec<Option<LocatedNode>> = vec![];
+        for located in self.locations.split_off(location_start) {
+            match located.node {
+                Node::Nodes(_) => candidates.extend(located.children.into_iter().map(Some)),
+                _ => candidates.push(Some(located)),
+            }
+        }
+
+        let passed_on = candidates.iter().rposition(|candidate| match *candidate {
+            Some(ref located) => located.node == *node,
+            None => false
+        });
+        let located = match passed_on {
+            Some(i) => candidates[i].take(),
+            None => self.range_of_tokens(begin_p, self.current_p).map(|expression| {
+                let mut children = vec![];
+                // NOTE children are mostly produced in order, so the search goes on from where the last one was found
+                let mut cursor = candidates.len();
+                for child in located::children(node).into_iter().rev() {
+                    let is_child = |candidate: &Option<LocatedNode>| match *candidate {
+                        Some(ref located) => {
+                            located.expression.begin_pos >= expression.begin_pos && located.expression.end_pos <= expression.end_pos &&
+                                located.node == *child
+                        },
+                        None => false
+                    };
+
+                    let i = candidates[..cursor].iter().rposition(&is_child)
+                        .or_else(|| candidates[cursor..].iter().rposition(&is_child).map(|i| cursor + i));
+                    if let Some(i) = i {
+                        children.push(candidates[i].take().unwrap());
+                        cursor = i;
+                    }
+                }
+                children.sort_by_key(|child| child.expression.begin_pos);
+
+                LocatedNode { node: node.clone(), expression, children }
+            })
+        };
+
+        self.locations.extend(candidates.into_iter().flatten());
+
+        located
+    }
+
+    // range of tokens[begin_p..end_p], leaving out the terms around
+    fn range_of_tokens(&self, begin_p: usize, end_p: usize) -> Option<Range> {
+        let ranges: Vec<Range> = self.tokens[begin_p..end_p].iter()
+            .filter(|&&(ref token, _)| *token != Token::T_NL && *token != Token::T_SEMI)
+            .map(|&(_, range)| range)
+            .collect();
+
+        match (ranges.first(), ranges.last()) {
+            (Some(first), Some(last)) => Some(first.join(last)),
+            _ => None
+        }
     }
 
     // ===
@@ -130,7 +1063,7 @@ impl Parser {
         self.recurse("p_program");
         let p = self.current_p;
 
-        if let Some(n_top_compstmt) = self.p_top_compstmt() { self.decurse(); return Some(n_top_compstmt); }
+        if let Some(n_top_compstmt) = self.p_top_compstmt() { return self.decurse_with(n_top_compstmt); }
         self.current_p = p;
 
         self.decurse();
@@ -151,12 +1084,11 @@ impl Parser {
         if let Some(n_top_stmts) = self.p_top_stmts() {
             if let Some(n_opt_terms) = self.p_opt_terms() {
                 // branch: both top_stmts and opt_terms exists
-                self.decurse(); return Some( node::compstmt( n_top_stmts ) );
+                return self.decurse_with( node::compstmt( n_top_stmts ) );
             }
-            self.current_p = p;
 
             // branch: top_stmts exists and opt_temrs is none
-            self.decurse(); return Some( node::compstmt( n_top_stmts ) );
+            return self.decurse_with( node::compstmt( n_top_stmts ) );
         }
         self.current_p = p;
 
@@ -203,6 +1135,11 @@ impl Parser {
                 let p = self.current_p;
                 let mut matched = false;
 
+                // NOTE
+                // the outermost top_stmts, right within program and top_compstmt, never gives up a statement it has parsed,
+                // unlike the one of klBEGIN
+                if self.recursion_stack.len() == 3 { self.commit_tokens(p); }
+
                 if let Some(n_terms) = self.p_terms() {
                     if let Some(n_top_stmt) = self.p_top_stmt() {
                         matched = true;
@@ -216,8 +1153,7 @@ impl Parser {
                 }
             }
 
-            self.decurse();
-            return Some(Node::Nodes(nodes));
+            return self.decurse_with(Node::Nodes(nodes));
         }
         self.current_p = p;
 
@@ -236,13 +1172,11 @@ impl Parser {
         let p = self.current_p;
 
         if let Some(n_stmt) = self.p_stmt() {
-            self.decurse();
-            return Some(n_stmt);
+            return self.decurse_with(n_stmt);
         }
         self.current_p = p;
 
         // TODO | klBEGIN tLCURLY top_compstmt tRCURLY
-        panic!("UNIMPL");
 
         self.decurse();
         None
@@ -263,17 +1197,33 @@ impl Parser {
     //                           else_t,   else_,
     //                           ensure_t, ensure_)
     //             }
+    // TODO INCOMPLETE opt_rescue opt_else opt_ensure,
+    // begin_body of a compstmt alone is the compstmt
+    fn p_bodystmt(&mut self) -> Option<Node> {
+        self.recurse("p_bodystmt");
+        let p = self.current_p;
+
+        if let Some(n_compstmt) = self.p_compstmt() { return self.decurse_with(n_compstmt); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     // compstmt: stmts opt_terms
     //             {
     //               result = @builder.compstmt(val[0])
     //             }
+    // NOTE stmts never fails, being none is an empty Node::Nodes, then the result is Node::Null
     fn p_compstmt(&mut self) -> Option<Node> {
         self.recurse("p_compstmt");
         let p = self.current_p;
 
-        // TODO DUMMY
-        return self.p_stmt();
+        if let Some(n_stmts) = self.p_stmts() {
+            self.p_opt_terms();
+            return self.decurse_with(node::compstmt(n_stmts));
+        }
+        self.current_p = p;
 
         self.decurse();
         None
@@ -295,22 +1245,62 @@ impl Parser {
     //             {
     //               result = [ val[1] ]
     //             }
-    // TODO INCOMPLETE
-    // fn p_stmts(&mut self) -> Option<Node> {
-    //     if let Some(n_stmt) = self.p_stmt() { return Some(n_stmt); }
-    //     None
-    // }
+    // TODO handle branch: `error stmt`
+    // 
+    // NOTE transformed into non-recursive form, like top_stmts
+    //     none | stmt_or_begin | stmts terms stmt_or_begin
+    //                    ==>
+    //     [ stmt_or_begin ] [ terms stmt_or_begin ]*
+    // 
+    // NOTE returns Node::Nodes only
+    fn p_stmts(&mut self) -> Option<Node> {
+        self.recurse("p_stmts");
 
-    //    stmt_or_begin: stmt
-    //                 | klBEGIN tLCURLY top_compstmt tRCURLY
+        let mut nodes = vec![];
+
+        let p = self.current_p;
+        if let Some(n_stmt_or_begin) = self.p_stmt_or_begin() {
+            nodes.push(n_stmt_or_begin);
+        } else {
+            self.current_p = p;
+        }
+
+        loop {
+            let p = self.current_p;
+            let mut matched = false;
+
+            if let Some(_n_terms) = self.p_terms() {
+                if let Some(n_stmt_or_begin) = self.p_stmt_or_begin() {
+                    matched = true;
+                    nodes.push(n_stmt_or_begin);
+                }
+            }
+
+            if !matched {
+                self.current_p = p;
+                break;
+            }
+        }
+
+        self.decurse_with(Node::Nodes(nodes))
+    }
+
+    //    stmt_or_begin: stmt
+    //                 | klBEGIN tLCURLY top_compstmt tRCURLY
     //                     {
     //                       diagnostic :error, :begin_in_method, nil, val[0]
     //                     }
-    // TODO INCOMPLETE
-    // fn p_stmt_or_begin(&mut self) -> Option<Node> {
-    //     if let Some(n_stmt) = self.p_stmt() { return Some(n_stmt); }
-    //     None
-    // }
+    // TODO INCOMPLETE klBEGIN
+    fn p_stmt_or_begin(&mut self) -> Option<Node> {
+        self.recurse("p_stmt_or_begin");
+        let p = self.current_p;
+
+        if let Some(n_stmt) = self.p_stmt() { return self.decurse_with(n_stmt); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     // stmt: kALIAS fitem
     //         {
@@ -386,12 +1376,45 @@ impl Parser {
     //         }
     //     | expr 
     // TODO INCOMPLETE
+    // NOTE
+    // transformed into non-left-recursive form for the modifiers,
+    //     expr [ modifier expr_value ]*
+    // where modifier is one of kIF_MOD, kUNLESS_MOD, kWHILE_MOD and kUNTIL_MOD
     fn p_stmt(&mut self) -> Option<Node> {
         self.recurse("p_stmt");
         let p = self.current_p;
 
         // expr
-        if let Some(n_expr) = self.p_expr() { self.decurse(); return Some(n_expr); }
+        if let Some(mut n_stmt) = self.p_expr() {
+            loop {
+                let modifier_p = self.current_p;
+
+                if let Some(t_modifier) = self.current_token() {
+                    match t_modifier {
+                        Token::K_IF_MOD | Token::K_UNLESS_MOD | Token::K_WHILE_MOD | Token::K_UNTIL_MOD => {
+                            self.consume_current_token();
+
+                            if let Some(n_expr_value) = self.p_expr_value() {
+                                n_stmt = match t_modifier {
+                                    Token::K_IF_MOD => node::condition_mod(n_stmt, Node::Null, n_expr_value),
+                                    Token::K_UNLESS_MOD => node::condition_mod(Node::Null, n_stmt, n_expr_value),
+                                    _ => node::loop_mod(n_stmt, t_modifier, n_expr_value),
+                                };
+                                self.record_location(p, &n_stmt);
+
+                                continue;
+                            }
+                        },
+                        _ => {}
+                    }
+                }
+
+                self.current_p = modifier_p;
+                break;
+            }
+
+            return self.decurse_with(n_stmt);
+        }
         self.current_p = p;
 
         self.decurse();
@@ -480,7 +1503,10 @@ impl Parser {
         self.recurse("p_expr");
         let p = self.current_p;
 
-        if let Some(n_arg) = self.p_arg() { self.decurse(); return Some(n_arg); }
+        if let Some(n_command_call) = self.p_command_call() { return self.decurse_with(n_command_call); }
+        self.current_p = p;
+
+        if let Some(n_arg) = self.p_arg() { return self.decurse_with(n_arg); }
         self.current_p = p;
 
         self.decurse();
@@ -488,9 +1514,94 @@ impl Parser {
     }
 
     //       expr_value: expr
+    fn p_expr_value(&mut self) -> Option<Node> {
+        self.recurse("p_expr_value");
+        let p = self.current_p;
+
+        if let Some(n_expr) = self.p_expr() { return self.decurse_with(n_expr); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //     command_call: command
     //                 | block_command
+    // NOTE
+    // block_command and block_call, which start with command as well, are transformed into non-left-recursive form,
+    //     command [ do_block block_call_tail* [ dot_or_colon operation2 command_args ] ]
+    // where block_call_tail is what follows block_call in the recursive alternatives of block_call
+    fn p_command_call(&mut self) -> Option<Node> {
+        self.recurse("p_command_call");
+        let p = self.current_p;
+
+        if let Some(n_command) = self.p_command() {
+            let block_p = self.current_p;
+
+            //  block_call: command do_block
+            if let Some(n_do_block) = self.p_do_block() {
+                let mut n_block_call = self.block_for(n_command, n_do_block, block_p);
+                self.record_location(p, &n_block_call);
+
+                loop {
+                    let tail_p = self.current_p;
+
+                    let t_dot = match self.match_call_op() {
+                        Some(t_call_op) => Some(t_call_op),
+                        None => self.match_1_token(Token::T_COLON2)
+                    };
+                    if let Some(t_dot) = t_dot {
+                        if let Some(n_operation2) = self.p_operation2() {
+                            let operation2_end_p = self.current_p;
+                            let selector = extract_ident(n_operation2);
+
+                            //  block_call: block_call dot_or_colon operation2 command_args do_block
+                            //  block_command: block_call dot_or_colon operation2 command_args
+                            if let Some(n_command_args) = self.p_command_args() {
+                                let method_call = node::call_method(Some(n_block_call.clone()), Some(t_dot.clone()), Some(selector.clone()), extract_nodes(n_command_args));
+                                self.record_location(p, &method_call);
+
+                                let block_p = self.current_p;
+                                if let Some(n_do_block) = self.p_do_block() {
+                                    n_block_call = self.block_for(method_call, n_do_block, block_p);
+                                    self.record_location(p, &n_block_call);
+                                    continue;
+                                }
+                                self.current_p = block_p;
+
+                                return self.decurse_with(method_call);
+                            }
+                            self.current_p = operation2_end_p;
+
+                            //  block_call: block_call dot_or_colon operation2 opt_paren_args
+                            //            | block_call dot_or_colon operation2 opt_paren_args brace_block
+                            let args = match self.p_paren_args() {
+                                Some(n_paren_args) => extract_nodes(n_paren_args),
+                                None => { self.current_p = operation2_end_p; vec![] }
+                            };
+                            let method_call = node::call_method(Some(n_block_call), Some(t_dot), Some(selector), args);
+                            self.record_location(p, &method_call);
+                            n_block_call = self.match_brace_block(method_call);
+                            self.record_location(p, &n_block_call);
+                            continue;
+                        }
+                    }
+
+                    self.current_p = tail_p;
+                    break;
+                }
+
+                return self.decurse_with(n_block_call);
+            }
+            self.current_p = block_p;
+
+            return self.decurse_with(n_command);
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //    block_command: block_call
     //                 | block_call dot_or_colon operation2 command_args
@@ -505,6 +1616,16 @@ impl Parser {
     //                     }
 
     //            fcall: operation
+    fn p_fcall(&mut self) -> Option<Node> {
+        self.recurse("p_fcall");
+        let p = self.current_p;
+
+        if let Some(n_operation) = self.p_operation() { return self.decurse_with(n_operation); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //          command: fcall command_args =tLOWEST
     //                     {
@@ -573,6 +1694,36 @@ impl Parser {
     //                       result = @builder.keyword_cmd(:next, val[0],
     //                                   nil, val[1], nil)
     //                     }
+    // TODO INCOMPLETE cmd_brace_block and the keyword commands
+    fn p_command(&mut self) -> Option<Node> {
+        self.recurse("p_command");
+        let p = self.current_p;
+
+        //  command: fcall command_args =tLOWEST
+        if let Some(n_fcall) = self.p_fcall() {
+            if let Some(n_command_args) = self.p_command_args() {
+                let node = node::call_method(None, None, Some(extract_ident(n_fcall)), extract_nodes(n_command_args));
+                return self.decurse_with(node);
+            }
+        }
+        self.current_p = p;
+
+        //         | primary_value call_op operation2 command_args =tLOWEST
+        //         | primary_value tCOLON2 operation2 command_args =tLOWEST
+        // NOTE see trailing_call
+        if let Some(n_primary_value) = self.p_primary_value() {
+            if let Some((receiver, t_dot, selector)) = self.trailing_call(&n_primary_value, p) {
+                if let Some(n_command_args) = self.p_command_args() {
+                    let node = node::call_method(Some(receiver), Some(t_dot), Some(selector), extract_nodes(n_command_args));
+                    return self.decurse_with(node);
+                }
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //         mlhs: mlhs_basic
     //                 {
@@ -582,6 +1733,23 @@ impl Parser {
     //                 {
     //                   result = @builder.begin(val[0], val[1], val[2])
     //                 }
+    fn p_mlhs(&mut self) -> Option<Node> {
+        self.recurse("p_mlhs");
+        let p = self.current_p;
+
+        if let Some(n_mlhs_basic) = self.p_mlhs_basic() {
+            return self.decurse_with(node::multi_lhs(n_mlhs_basic));
+        }
+        self.current_p = p;
+
+        if let Some(n_mlhs_inner) = self.p_paren_mlhs_inner() {
+            return self.decurse_with(node::begin(n_mlhs_inner));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //   mlhs_inner: mlhs_basic
     //                 {
@@ -591,6 +1759,41 @@ impl Parser {
     //                 {
     //                   result = @builder.multi_lhs(val[0], val[1], val[2])
     //                 }
+    fn p_mlhs_inner(&mut self) -> Option<Node> {
+        self.recurse("p_mlhs_inner");
+        let p = self.current_p;
+
+        if let Some(n_mlhs_basic) = self.p_mlhs_basic() {
+            return self.decurse_with(node::multi_lhs(n_mlhs_basic));
+        }
+        self.current_p = p;
+
+        if let Some(n_mlhs_inner) = self.p_paren_mlhs_inner() {
+            return self.decurse_with(node::multi_lhs(n_mlhs_inner));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    // the `tLPAREN mlhs_inner rparen` of mlhs, mlhs_inner and mlhs_item
+    fn p_paren_mlhs_inner(&mut self) -> Option<Node> {
+        self.recurse("p_paren_mlhs_inner");
+        let p = self.current_p;
+
+        if let Some(_t_lparen) = self.match_1_token(Token::T_LPAREN) {
+            if let Some(n_mlhs_inner) = self.p_mlhs_inner() {
+                if let Some(_n_rparen) = self.p_rparen() {
+                    return self.decurse_with(n_mlhs_inner);
+                }
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //   mlhs_basic: mlhs_head
     //             | mlhs_head mlhs_item
@@ -638,12 +1841,63 @@ impl Parser {
     //                   result = [ @builder.splat(val[0]),
     //                              *val[2] ]
     //                 }
+    // NOTE returns Node::Nodes only
+    fn p_mlhs_basic(&mut self) -> Option<Node> {
+        self.recurse("p_mlhs_basic");
+        let p = self.current_p;
+
+        let mut nodes = match self.p_mlhs_head() {
+            Some(n_mlhs_head) => extract_nodes(n_mlhs_head),
+            None => vec![]
+        };
+
+        if !nodes.is_empty() {
+            if let Some(n_mlhs_item) = self.p_mlhs_item() {
+                nodes.push(n_mlhs_item);
+                return self.decurse_with(Node::Nodes(nodes));
+            }
+        }
+
+        // the `tSTAR [ mlhs_node ] [ tCOMMA mlhs_post ]` part
+        if let Some(_t_star) = self.match_1_token(Token::T_STAR) {
+            let star_p = self.current_p;
+            match self.p_mlhs_node() {
+                Some(n_mlhs_node) => nodes.push(node::splat(Some(n_mlhs_node))),
+                None => { self.current_p = star_p; nodes.push(node::splat(None)); }
+            }
+
+            if let Some(n_mlhs_post) = self.p_f_args_part(false, Parser::p_mlhs_post) {
+                nodes.append(&mut extract_nodes(n_mlhs_post));
+            }
+        }
+
+        if !nodes.is_empty() { return self.decurse_with(Node::Nodes(nodes)); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //    mlhs_item: mlhs_node
     //             | tLPAREN mlhs_inner rparen
     //                 {
     //                   result = @builder.begin(val[0], val[1], val[2])
     //                 }
+    fn p_mlhs_item(&mut self) -> Option<Node> {
+        self.recurse("p_mlhs_item");
+        let p = self.current_p;
+
+        if let Some(n_mlhs_inner) = self.p_paren_mlhs_inner() {
+            return self.decurse_with(node::begin(n_mlhs_inner));
+        }
+        self.current_p = p;
+
+        if let Some(n_mlhs_node) = self.p_mlhs_node() { return self.decurse_with(n_mlhs_node); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //    mlhs_head: mlhs_item tCOMMA
     //                 {
@@ -653,6 +1907,30 @@ impl Parser {
     //                 {
     //                   result = val[0] << val[1]
     //                 }
+    // NOTE returns Node::Nodes only
+    fn p_mlhs_head(&mut self) -> Option<Node> {
+        self.recurse("p_mlhs_head");
+
+        let mut nodes = vec![];
+        loop {
+            let p = self.current_p;
+
+            if let Some(n_mlhs_item) = self.p_mlhs_item() {
+                if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                    nodes.push(n_mlhs_item);
+                    continue;
+                }
+            }
+
+            self.current_p = p;
+            break;
+        }
+
+        if !nodes.is_empty() { return self.decurse_with(Node::Nodes(nodes)); }
+
+        self.decurse();
+        None
+    }
 
     //    mlhs_post: mlhs_item
     //                 {
@@ -662,6 +1940,35 @@ impl Parser {
     //                 {
     //                   result = val[0] << val[2]
     //                 }
+    // NOTE returns Node::Nodes only
+    fn p_mlhs_post(&mut self) -> Option<Node> {
+        self.recurse("p_mlhs_post");
+        let p = self.current_p;
+
+        if let Some(n_mlhs_item) = self.p_mlhs_item() {
+            let mut nodes = vec![n_mlhs_item];
+
+            loop {
+                let p = self.current_p;
+
+                if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                    if let Some(n_mlhs_item) = self.p_mlhs_item() {
+                        nodes.push(n_mlhs_item);
+                        continue;
+                    }
+                }
+
+                self.current_p = p;
+                break;
+            }
+
+            return self.decurse_with(Node::Nodes(nodes));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //    mlhs_node: user_variable
     //                 {
@@ -701,6 +2008,17 @@ impl Parser {
     //                 {
     //                   result = @builder.assignable(val[0])
     //                 }
+    // NOTE the same alternatives as lhs
+    fn p_mlhs_node(&mut self) -> Option<Node> {
+        self.recurse("p_mlhs_node");
+        let p = self.current_p;
+
+        if let Some(n_lhs) = self.p_lhs() { return self.decurse_with(n_lhs); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //  lhs: user_variable
     //         {
@@ -745,12 +2063,49 @@ impl Parser {
         self.recurse("p_lhs");
         let p = self.current_p;
 
+        //     | primary_value tLBRACK2 opt_call_args rbracket
+        //         {
+        //           result = @builder.index_asgn(val[0], val[1], val[2], val[3])
+        //         }
+        //     | primary_value call_op tIDENTIFIER
+        //     | primary_value tCOLON2 tIDENTIFIER
+        //     | primary_value call_op tCONSTANT
+        //         {
+        //           result = @builder.attr_asgn(val[0], val[1], val[2])
+        //         }
+        // NOTE
+        // see trailing_call, an index is told by its last token likewise,
+        // these go before user_variable, which would take the receiver of `foo.bar = 1` otherwise
+        // TODO casgn of primary_value tCOLON2 tCONSTANT
+        if let Some(n_primary_value) = self.p_primary_value() {
+            if let Some((receiver, t_dot, selector)) = self.trailing_call(&n_primary_value, p) {
+                match n_primary_value {
+                    Node::Send { .. } | Node::CSend { .. } => {
+                        return self.decurse_with(node::attr_asgn(receiver, t_dot, selector));
+                    },
+                    _ => {}
+                }
+            }
+
+            if self.tokens[self.current_p - 1].0 == Token::T_RBRACK {
+                if let Node::Send { receiver: Some(receiver), selector, args } = n_primary_value {
+                    if selector == "[]" {
+                        return self.decurse_with(node::index_asgn(*receiver, args));
+                    }
+                }
+            }
+        }
+        self.current_p = p;
+
         //  user_variable
         //         {
         //           result = @builder.assignable(val[0])
         //         }
+        // TODO casgn, only identifiers are assignable for now
         if let Some(n_user_variable) = self.p_user_variable() {
-            self.decurse(); return Some(node::assignable(n_user_variable));
+            if let Node::Ident(_) = n_user_variable {
+                return self.decurse_with(node::assignable(n_user_variable));
+            }
         }
         self.current_p = p;
 
@@ -763,6 +2118,30 @@ impl Parser {
     //                   diagnostic :error, :module_name_const, nil, val[0]
     //                 }
     //             | tCONSTANT
+    // NOTE returns Node::Ident
+    fn p_cname(&mut self) -> Option<Node> {
+        self.recurse("p_cname");
+        let p = self.current_p;
+
+        if let Some(Token::T_IDENTIFIER(name)) = self.current_token() {
+            self.consume_current_token();
+
+            let range = self.tokens[p].1;
+            self.diagnostic(Level::Error, "module_name_const", HashMap::new(), range);
+
+            return self.decurse_with(Node::Ident(name));
+        }
+        self.current_p = p;
+
+        if let Some(Token::T_CONSTANT(name)) = self.current_token() {
+            self.consume_current_token();
+            return self.decurse_with(Node::Ident(name));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //        cpath: tCOLON3 cname
     //                 {
@@ -776,10 +2155,69 @@ impl Parser {
     //                 {
     //                   result = @builder.const_fetch(val[0], val[1], val[2])
     //                 }
+    // NOTE
+    // primary takes `tCOLON2 tCONSTANT` as far as it goes, see p_primary,
+    // so a constant path is a primary_value being a Node::Const already,
+    // tCOLON3 and cname alone are left for what primary doesn't take, `::foo` and `foo`
+    fn p_cpath(&mut self) -> Option<Node> {
+        self.recurse("p_cpath");
+        let p = self.current_p;
+
+        if let Some(n_primary_value) = self.p_primary_value() {
+            let primary_value_end_p = self.current_p;
+
+            if let Some(_t_colon2) = self.match_1_token(Token::T_COLON2) {
+                if let Some(n_cname) = self.p_cname() {
+                    return self.decurse_with(node::const_fetch(n_primary_value, extract_ident(n_cname)));
+                }
+            }
+            self.current_p = primary_value_end_p;
+
+            if let Node::Const { .. } = n_primary_value {
+                return self.decurse_with(n_primary_value);
+            }
+        }
+        self.current_p = p;
+
+        if let Some(_t_colon3) = self.match_1_token(Token::T_COLON3) {
+            if let Some(n_cname) = self.p_cname() {
+                return self.decurse_with(node::const_global(extract_ident(n_cname)));
+            }
+        }
+        self.current_p = p;
+
+        if let Some(n_cname) = self.p_cname() {
+            return self.decurse_with(node::constant(extract_ident(n_cname)));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //        fname: tIDENTIFIER | tCONSTANT | tFID
     //             | op
     //             | reswords
+    // NOTE returns Node::Ident, the name being the source of the token, see is_op and is_resword
+    fn p_fname(&mut self) -> Option<Node> {
+        self.recurse("p_fname");
+        let p = self.current_p;
+
+        let is_fname = match self.current_token() {
+            Some(Token::T_IDENTIFIER(_)) | Some(Token::T_CONSTANT(_)) | Some(Token::T_FID(_)) => true,
+            Some(ref token) => is_op(token) || is_resword(token),
+            None => false
+        };
+        if is_fname {
+            self.consume_current_token();
+            let name = self.token_source(p);
+            return self.decurse_with(Node::Ident(name));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //         fsym: fname
     //                 {
@@ -830,10 +2268,20 @@ impl Parser {
         //         }
         if let Some(n_lhs) = self.p_lhs() {
             if let Some(t_eql) = self.match_1_token(Token::T_EQL) {
+                // NOTE
+                // builder's assignable declares the variable, when racc reduces lhs with tEQL as the lookahead,
+                // we only know it's an assignment once tEQL is matched, then the rhs already sees the variable
+                if let Node::LVasgn(ref name, _) = n_lhs {
+                    self.update_static_env(|static_env| static_env.declare(name));
+                }
+
                 if let Some(n_arg_rhs) = self.p_arg_rhs() {
-                    self.decurse(); return Some(node::assign(n_lhs, Token::T_EQL, n_arg_rhs));
+                    return self.decurse_with(node::assign(n_lhs, Token::T_EQL, n_arg_rhs));
                     // return Some(Node::Assign( box n_lhs, Token::T_EQL, box n_arg_rhs ));
                 }
+
+                // undeclare it
+                self.rewind(p);
             }
             // TODO else put back token
         }
@@ -1003,16 +2451,133 @@ impl Parser {
         //           result = @builder.ternary(val[0], val[1],
         //                                     val[2], val[4], val[5])
         //         }
+        // TODO INCOMPLETE the condition is a primary, until the operators above are parsed
+        if let Some(n_primary) = self.p_primary() {
+            if let Some(_t_eh) = self.match_1_token(Token::T_EH) {
+                if let Some(n_if_true) = self.p_arg() {
+                    self.match_1_token(Token::T_NL);
+
+                    if let Some(_t_colon) = self.match_1_token(Token::T_COLON) {
+                        if let Some(n_if_false) = self.p_arg() {
+                            return self.decurse_with(node::ternary(n_primary, n_if_true, n_if_false));
+                        }
+                    }
+                }
+            }
+        }
+        self.current_p = p;
         //     | primary
-        if let Some(n_primary) = self.p_primary() { self.decurse(); return Some(n_primary); }
+        if let Some(n_primary) = self.p_primary() { return self.decurse_with(n_primary); }
         self.current_p = p;
 
-        self.decurse();
-        None
-    }
-
-    //        relop: tGT | tLT | tGEQ | tLEQ
-
+        // NOTE from a later whitequark/parser's ruby30.y, where endless def came with 3.0
+        //     | defn_head f_opt_paren_args tEQL arg
+        //         {
+        //           def_t, (name_t, ctx) = val[0]
+        //           endless_method_name(name_t)
+        //
+        //           result = @builder.def_endless_method(def_t, name_t,
+        //                      val[1], val[2], val[3])
+        //
+        //           local_pop
+        //           @current_arg_stack.pop
+        //           @context.in_def = ctx.in_def
+        //         }
+        //     | defs_head f_opt_paren_args tEQL arg
+        //         {
+        //           def_t, recv, dot_t, (name_t, ctx) = val[0]
+        //           endless_method_name(name_t)
+        //
+        //           result = @builder.def_endless_singleton(def_t, recv, dot_t, name_t,
+        //                      val[1], val[2], val[3])
+        //
+        //           local_pop
+        //           @current_arg_stack.pop
+        //           @context.in_def = ctx.in_def
+        //         }
+        // NOTE
+        // defn_head and defs_head are `kDEF fname` and `kDEF singleton dot_or_colon fname` of primary,
+        // with the same actions, local_push being `@static_env.extend_static` and `@lexer.push_cmdarg`,
+        // and def_endless_method and def_endless_singleton build the same def and defs as def_method and def_singleton
+        if let Some(_t_def) = self.match_1_token(Token::K_DEF) {
+            let fname_p = self.current_p;
+            if let Some(n_fname) = self.p_fname() {
+                self.def_level += 1;
+                self.update_static_env(|static_env| static_env.extend_static());
+                self.update_lexer(false, |lexer| lexer.push_cmdarg());
+
+                if let Some(n_f_opt_paren_args) = self.p_f_opt_paren_args() {
+                    let eql_p = self.current_p;
+                    if let Some(_t_eql) = self.match_1_token(Token::T_EQL) {
+                        if let Some(n_arg) = self.p_arg() {
+                            self.update_lexer(false, |lexer| lexer.pop_cmdarg());
+                            self.update_static_env(|static_env| static_env.unextend());
+                            self.def_level -= 1;
+
+                            let name = extract_ident(n_fname);
+                            self.check_version("endless method definition", RubyVersion::Ruby30, self.tokens[eql_p].1);
+                            self.check_endless_method_name(&name, self.tokens[fname_p].1);
+
+                            return self.decurse_with(node::def_method(name, n_f_opt_paren_args, n_arg));
+                        }
+                    }
+                }
+
+                // NOTE rewinding brings back static_env and cmdarg as well
+                self.def_level -= 1;
+                self.rewind(p);
+            }
+        }
+        self.current_p = p;
+
+        if let Some(_t_def) = self.match_1_token(Token::K_DEF) {
+            if let Some(n_singleton) = self.p_singleton() {
+                let singleton_end_p = self.current_p;
+                if let Some(_n_dot_or_colon) = self.p_dot_or_colon() {
+                    self.set_lexer_state(state!("expr_fname"));
+
+                    let fname_p = self.current_p;
+                    if let Some(n_fname) = self.p_fname() {
+                        self.def_level += 1;
+                        self.update_static_env(|static_env| static_env.extend_static());
+                        self.update_lexer(false, |lexer| lexer.push_cmdarg());
+
+                        if let Some(n_f_opt_paren_args) = self.p_f_opt_paren_args() {
+                            let eql_p = self.current_p;
+                            if let Some(_t_eql) = self.match_1_token(Token::T_EQL) {
+                                if let Some(n_arg) = self.p_arg() {
+                                    self.update_lexer(false, |lexer| lexer.pop_cmdarg());
+                                    self.update_static_env(|static_env| static_env.unextend());
+                                    self.def_level -= 1;
+
+                                    let name = extract_ident(n_fname);
+                                    self.check_version("endless method definition", RubyVersion::Ruby30, self.tokens[eql_p].1);
+                                    self.check_endless_method_name(&name, self.tokens[fname_p].1);
+                                    if node::is_singleton_literal(&n_singleton) {
+                                        let range = self.range_of_tokens(p + 2, singleton_end_p - 1).unwrap();
+                                        self.diagnostic(Level::Error, "singleton_literal", HashMap::new(), range);
+                                    }
+
+                                    return self.decurse_with(node::def_singleton(n_singleton, name, n_f_opt_paren_args, n_arg));
+                                }
+                            }
+                        }
+
+                        self.def_level -= 1;
+                    }
+
+                    self.rewind(p);
+                }
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //        relop: tGT | tLT | tGEQ | tLEQ
+
     //     rel_expr: arg relop arg =tGT
     //                 {
     //                   result = @builder.binary_op(val[0], val[1], val[2])
@@ -1027,7 +2592,7 @@ impl Parser {
         self.recurse("p_arg_value");
         let p = self.current_p;
 
-        if let Some(n_arg) = self.p_arg() { self.decurse(); return Some(n_arg); }
+        if let Some(n_arg) = self.p_arg() { return self.decurse_with(n_arg); }
         self.current_p = p;
 
         self.decurse();
@@ -1052,11 +2617,11 @@ impl Parser {
 
         if let Some(n_args) = self.p_args() {
             if let Some(n_trailer) = self.p_trailer() {
-                self.decurse(); return Some(Node::Dummy);
+                return self.decurse_with(Node::Dummy);
             }
 
             // trailer being none
-            self.decurse(); return Some(n_args);
+            return self.decurse_with(n_args);
         }
         self.current_p = p;
 
@@ -1080,7 +2645,7 @@ impl Parser {
         let p = self.current_p;
 
         // TODO DUMMY
-        if let Some(n_primary) = self.p_primary() { self.decurse(); return Some(n_primary); }
+        if let Some(n_primary) = self.p_primary() { return self.decurse_with(n_primary); }
         self.current_p = p;
 
         self.decurse();
@@ -1091,6 +2656,73 @@ impl Parser {
     //                     {
     //                       result = val
     //                     }
+    // NOTE
+    // from a later whitequark/parser's ruby30.y, where `...` came with 2.7, and the arguments before it with 3.0,
+    //                 | tLPAREN2 args tCOMMA args_forward rparen
+    //                     {
+    //                       unless @static_env.declared_forward_args?
+    //                         diagnostic :error, :unexpected_token, { :token => 'tBDOT3' } , val[3]
+    //                       end
+    //
+    //                       result = [val[0], [*val[1], @builder.forwarded_args(val[3])], val[4]]
+    //                     }
+    //                 | tLPAREN2 args_forward rparen
+    //                     {
+    //                       unless @static_env.declared_forward_args?
+    //                         diagnostic :error, :unexpected_token, { :token => 'tBDOT3' } , val[1]
+    //                       end
+    //
+    //                       result = [val[0], [@builder.forwarded_args(val[1])], val[2]]
+    //                     }
+    // NOTE returns Node::Nodes of the args
+    fn p_paren_args(&mut self) -> Option<Node> {
+        self.recurse("p_paren_args");
+        let p = self.current_p;
+
+        if let Some(_t_lparen2) = self.match_1_token(Token::T_LPAREN2) {
+            if let Some(n_opt_call_args) = self.p_opt_call_args() {
+                if let Some(_n_rparen) = self.p_rparen() {
+                    return self.decurse_with(n_opt_call_args);
+                }
+            }
+        }
+        self.current_p = p;
+
+        if let Some(_t_lparen2) = self.match_1_token(Token::T_LPAREN2) {
+            if let Some(n_args) = self.p_args() {
+                if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                    let dot3_p = self.current_p;
+                    if let Some(_t_dot3) = self.match_1_token(Token::T_DOT3) {
+                        if let Some(_n_rparen) = self.p_rparen() {
+                            self.check_forwarded_args(dot3_p);
+                            self.check_version("argument forwarding after other arguments", RubyVersion::Ruby30, self.tokens[dot3_p].1);
+
+                            let mut nodes = extract_nodes(n_args);
+                            nodes.push(node::forwarded_args());
+                            return self.decurse_with(Node::Nodes(nodes));
+                        }
+                    }
+                }
+            }
+        }
+        self.current_p = p;
+
+        if let Some(_t_lparen2) = self.match_1_token(Token::T_LPAREN2) {
+            let dot3_p = self.current_p;
+            if let Some(_t_dot3) = self.match_1_token(Token::T_DOT3) {
+                if let Some(_n_rparen) = self.p_rparen() {
+                    self.check_forwarded_args(dot3_p);
+                    self.check_version("argument forwarding", RubyVersion::Ruby27, self.tokens[dot3_p].1);
+
+                    return self.decurse_with(Node::Nodes(vec![node::forwarded_args()]));
+                }
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //   opt_paren_args: # nothing
     //                     {
@@ -1112,6 +2744,28 @@ impl Parser {
     //                     {
     //                       result = [ @builder.associate(nil, val[0], nil) ]
     //                     }
+    // NOTE
+    // returns Node::Nodes, being empty for nothing,
+    // the trailing tCOMMA of `args tCOMMA` and the like is taken after call_args,
+    // which are those without it as long as they don't end with block_arg
+    fn p_opt_call_args(&mut self) -> Option<Node> {
+        self.recurse("p_opt_call_args");
+        let p = self.current_p;
+
+        if let Some(n_call_args) = self.p_call_args() {
+            let nodes = extract_nodes(n_call_args);
+
+            match nodes.last() {
+                Some(&Node::BlockPass(_)) => {},
+                _ => { self.match_1_token(Token::T_COMMA); }
+            }
+
+            return self.decurse_with(Node::Nodes(nodes));
+        }
+        self.current_p = p;
+
+        self.decurse_with(Node::Nodes(vec![]))
+    }
 
     //        call_args: command
     //                     {
@@ -1136,6 +2790,52 @@ impl Parser {
     //                     {
     //                       result =  [ val[0] ]
     //                     }
+    // NOTE returns Node::Nodes
+    fn p_call_args(&mut self) -> Option<Node> {
+        self.recurse("p_call_args");
+        let p = self.current_p;
+
+        if let Some(n_command) = self.p_command() { return self.decurse_with(Node::Nodes(vec![n_command])); }
+        self.current_p = p;
+
+        // NOTE args opt_block_arg and args tCOMMA assocs opt_block_arg together
+        if let Some(n_args) = self.p_args() {
+            let mut nodes = extract_nodes(n_args);
+
+            let args_end_p = self.current_p;
+            if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                if let Some(n_assocs) = self.p_assocs() {
+                    nodes.push(node::associate(extract_nodes(n_assocs)));
+                } else {
+                    self.current_p = args_end_p;
+                }
+            }
+
+            if let Some(n_opt_block_arg) = self.p_opt_block_arg() {
+                nodes.push(n_opt_block_arg);
+            }
+
+            return self.decurse_with(Node::Nodes(nodes));
+        }
+        self.current_p = p;
+
+        if let Some(n_assocs) = self.p_assocs() {
+            let mut nodes = vec![node::associate(extract_nodes(n_assocs))];
+
+            if let Some(n_opt_block_arg) = self.p_opt_block_arg() {
+                nodes.push(n_opt_block_arg);
+            }
+
+            return self.decurse_with(Node::Nodes(nodes));
+        }
+        self.current_p = p;
+
+        if let Some(n_block_arg) = self.p_block_arg() { return self.decurse_with(Node::Nodes(vec![n_block_arg])); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //     command_args:   {
     //                       result = @lexer.cmdarg.dup
@@ -1147,11 +2847,45 @@ impl Parser {
 
     //                       result = val[1]
     //                     }
+    // NOTE racc has read the first token of call_args before the first action, the lookahead to reduce fcall and the like
+    fn p_command_args(&mut self) -> Option<Node> {
+        self.recurse("p_command_args");
+        let p = self.current_p;
+
+        let cmdarg = self.lexer.cmdarg.clone();
+        self.update_lexer(true, |lexer| lexer.cmdarg.push(true));
+
+        if let Some(n_call_args) = self.p_call_args() {
+            self.update_lexer(true, |lexer| lexer.cmdarg = cmdarg);
+            return self.decurse_with(n_call_args);
+        }
+
+        self.rewind(p);
+        self.update_lexer(false, |lexer| lexer.cmdarg = cmdarg);
+
+        self.decurse();
+        None
+    }
 
     //        block_arg: tAMPER arg_value
     //                     {
     //                       result = @builder.block_pass(val[0], val[1])
     //                     }
+    fn p_block_arg(&mut self) -> Option<Node> {
+        self.recurse("p_block_arg");
+        let p = self.current_p;
+
+        if let Some(_t_amper) = self.match_1_token(Token::T_AMPER) {
+            if let Some(n_arg_value) = self.p_arg_value() {
+                self.block_arg_ends.insert(p, self.current_p);
+                return self.decurse_with(node::block_pass(n_arg_value));
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //    opt_block_arg: tCOMMA block_arg
     //                     {
@@ -1161,6 +2895,21 @@ impl Parser {
     //                     {
     //                       result = []
     //                     }
+    // NOTE returns the block_pass, being nothing handled by parent rule
+    fn p_opt_block_arg(&mut self) -> Option<Node> {
+        self.recurse("p_opt_block_arg");
+        let p = self.current_p;
+
+        if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+            if let Some(n_block_arg) = self.p_block_arg() {
+                return self.decurse_with(n_block_arg);
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
 
     // args: arg_value
@@ -1179,78 +2928,36 @@ impl Parser {
     //         {
     //           result = val[0] << @builder.splat(val[2], val[3])
     //         }
-    // TODO INCOMPLETE
-    // 
-    // NOTE transformed into non-recursive form
-    // 
-    // original grammar:
-    // as: a | * a | as , a | as , * a 
-    // 
-    // transformed:
-    // as:  a  [, a | , * a]
-    //    | *a [, a | , * a] 
-    // 
+    // NOTE
+    // transformed into non-left-recursive form,
+    //     [ tSTAR ] arg_value [ tCOMMA [ tSTAR ] arg_value ]*
+    // NOTE
+    // an arg_value followed by tASSOC is the key of assocs after args, like `fun(foo, :foo => 1)`,
+    // or the arg_value of mrhs before exc_var, so it's left out, racc decides it by the lookahead
     fn p_args(&mut self) -> Option<Node> {
         self.recurse("p_args");
         let p = self.current_p;
 
-        if let Some(n_arg_value) = self.p_arg_value() {
-            let mut nodes = vec![n_arg_value];
-
-            loop {
-                if let Some(t_comma) = self.match_1_token(Token::T_COMMA) {
-                    if let Some(n_arg_value) = self.p_arg_value() {
-                        nodes.push(n_arg_value);
-                        continue;
-                    }
-                    if let Some(t_star) = self.match_1_token(Token::T_STAR) {
-                        if let Some(n_arg_value) = self.p_arg_value() {
-                            panic!("n_arg_value t_star n_arg_value UNIMPL");
-                            // TODO handle builder.splat
-                            // nodes.push(n_arg_value);
-                            // continue;
-                        }
-                    }
-                    break;
-                } else {
-                    break;
-                }
-            }
-
-            self.decurse(); return Some( Node::Nodes( nodes ) );
-        }
-        self.current_p = p;
-
-        if let Some(t_star) = self.match_1_token(Token::T_STAR) {
-            if let Some(n_arg_value) = self.p_arg_value() {
-                // TODO handle builder.splat
-                // let nodes = vec![n_arg_value];
+        let mut nodes = vec![];
+        loop {
+            let item_p = self.current_p;
 
-                panic!("p_args t_star n_arg_value UNIMPL");
+            if !nodes.is_empty() && self.match_1_token(Token::T_COMMA).is_none() { break; }
 
-                // loop {
-                //     if let Some(t_comma) = self.match_1_token(Token::T_COMMA) {
-                //         if let Some(n_arg_value) = self.p_arg_value() {
-                //             nodes.push(n_arg_value);
-                //             continue;
-                //         }
-                //         if let Some(t_star) = self.match_1_token(Token::T_STAR) {
-                //             if let Some(n_arg_value) = self.p_arg_value() {
-                //                 panic!("n_arg_value t_star n_arg_value UNIMPL");
-                //                 // TODO handle builder.splat
-                //                 // nodes.push(n_arg_value);
-                //                 // continue;
-                //             }
-                //         }
-                //         break;
-                //     } else {
-                //         break;
-                //     }
-                // }
+            let n_item = if let Some(_t_star) = self.match_1_token(Token::T_STAR) {
+                self.p_arg_value().map(|n_arg_value| node::splat(Some(n_arg_value)))
+            } else {
+                self.p_arg_value()
+            };
 
-                // self.decurse(); return Some( Node::Nodes( nodes ) );
+            match n_item {
+                Some(_) if self.current_token() == Some(Token::T_ASSOC) => { self.current_p = item_p; break; },
+                Some(n_item) => { nodes.push(n_item); },
+                None => { self.current_p = item_p; break; }
             }
         }
+
+        if !nodes.is_empty() { return self.decurse_with(Node::Nodes(nodes)); }
         self.current_p = p;
 
         self.decurse();
@@ -1278,35 +2985,204 @@ impl Parser {
     //             }
 
     // TODO INCOMPLETE
+    // NOTE
+    // transformed into non-left-recursive form,
+    //     primary_head primary_tail*
+    // where primary_head is primary without the alternatives starting with primary_value,
+    // and primary_tail is what follows primary_value in them, see match_primary_tail
     fn p_primary(&mut self) -> Option<Node> {
+        // NOTE see primary_memo
+        let memo_key = (self.current_p, self.def_level);
+        if let Some(&(ref node, end_p, _, ref located)) = self.primary_memo.get(&memo_key) {
+            self.current_p = end_p;
+            if let Some(ref located) = *located {
+                self.locations.push(located.clone());
+            }
+            return Some(node.clone());
+        }
+
         self.recurse("p_primary");
         let p = self.current_p;
 
+        if let Some(mut n_primary) = self.p_primary_head() {
+            loop {
+                let tail_p = self.current_p;
+
+                if let Some(node) = self.match_primary_tail(&n_primary) {
+                    n_primary = node;
+                    self.record_location(p, &n_primary);
+
+                    //  primary: method_call brace_block
+                    let is_method_call = match n_primary {
+                        Node::Send { .. } | Node::CSend { .. } => true,
+                        _ => false
+                    };
+                    if is_method_call {
+                        n_primary = self.match_brace_block(n_primary);
+                        self.record_location(p, &n_primary);
+                    }
+
+                    continue;
+                }
+
+                self.current_p = tail_p;
+                break;
+            }
+
+            let (end_p, lexed_len) = (self.current_p, self.tokens.len());
+            let n_primary = self.decurse_with(n_primary);
+            let located = if self.record_locations { self.locations.last().cloned() } else { None };
+            self.primary_memo.insert(memo_key, (n_primary.clone().unwrap(), end_p, lexed_len, located));
+
+            return n_primary;
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    // primary_value tCOLON2 tCONSTANT of primary, and the alternatives of method_call starting with primary_value,
+    // building the node with n_primary being primary_value
+    fn match_primary_tail(&mut self, n_primary: &Node) -> Option<Node> {
+        let p = self.current_p;
+
+        //  method_call: primary_value call_op operation2 opt_paren_args
+        //             | primary_value call_op paren_args
+        if let Some(t_call_op) = self.match_call_op() {
+            if let Some(n_operation2) = self.p_operation2() {
+                let args_p = self.current_p;
+                let args = match self.p_paren_args() {
+                    Some(n_paren_args) => extract_nodes(n_paren_args),
+                    None => { self.current_p = args_p; vec![] }
+                };
+
+                return Some(node::call_method(Some(n_primary.clone()), Some(t_call_op), Some(extract_ident(n_operation2)), args));
+            }
+
+            if let Some(n_paren_args) = self.p_paren_args() {
+                return Some(node::call_method(Some(n_primary.clone()), Some(t_call_op), None, extract_nodes(n_paren_args)));
+            }
+        }
+        self.current_p = p;
+
+        if let Some(t_colon2) = self.match_1_token(Token::T_COLON2) {
+            let colon2_end_p = self.current_p;
+
+            //  method_call: primary_value tCOLON2 operation2 paren_args
+            if let Some(n_operation2) = self.p_operation2() {
+                if let Some(n_paren_args) = self.p_paren_args() {
+                    return Some(node::call_method(Some(n_primary.clone()), Some(t_colon2), Some(extract_ident(n_operation2)), extract_nodes(n_paren_args)));
+                }
+            }
+            self.current_p = colon2_end_p;
+
+            //  primary: primary_value tCOLON2 tCONSTANT
+            if let Some(Token::T_CONSTANT(name)) = self.current_token() {
+                self.consume_current_token();
+                return Some(node::const_fetch(n_primary.clone(), name));
+            }
+            self.current_p = colon2_end_p;
+
+            //  method_call: primary_value tCOLON2 operation3
+            if let Some(n_operation3) = self.p_operation3() {
+                return Some(node::call_method(Some(n_primary.clone()), Some(t_colon2), Some(extract_ident(n_operation3)), vec![]));
+            }
+            self.current_p = colon2_end_p;
+
+            //  method_call: primary_value tCOLON2 paren_args
+            if let Some(n_paren_args) = self.p_paren_args() {
+                return Some(node::call_method(Some(n_primary.clone()), Some(t_colon2), None, extract_nodes(n_paren_args)));
+            }
+        }
+        self.current_p = p;
+
+        //  method_call: primary_value tLBRACK2 opt_call_args rbracket
+        if let Some(_t_lbrack2) = self.match_1_token(Token::T_LBRACK2) {
+            if let Some(n_opt_call_args) = self.p_opt_call_args() {
+                if let Some(_n_rbracket) = self.p_rbracket() {
+                    return Some(node::index(n_primary.clone(), extract_nodes(n_opt_call_args)));
+                }
+            }
+        }
+        self.current_p = p;
+
+        None
+    }
+
+    // the block of method_call if brace_block follows, or method_call itself
+    fn match_brace_block(&mut self, method_call: Node) -> Node {
+        let p = self.current_p;
+
+        if let Some(n_brace_block) = self.p_brace_block() {
+            return self.block_for(method_call, n_brace_block, p);
+        }
+        self.current_p = p;
+
+        method_call
+    }
+
+    fn p_primary_head(&mut self) -> Option<Node> {
+        self.recurse("p_primary_head");
+        let p = self.current_p;
+
         //  primary: literal
-        if let Some(n_literal) = self.p_literal() { self.decurse(); return Some(n_literal); }
+        if let Some(n_literal) = self.p_literal() { return self.decurse_with(n_literal); }
         self.current_p = p;
         //         | strings
-        if let Some(n_strings) = self.p_strings() { self.decurse(); return Some(n_strings); }
+        if let Some(n_strings) = self.p_strings() { return self.decurse_with(n_strings); }
         self.current_p = p;
         //         | xstring
-        // if let Some(n_xstring) = self.p_xstring() { self.decurse(); return Some(n_xstring); }
+        if let Some(n_xstring) = self.p_xstring() { return self.decurse_with(n_xstring); }
+        self.current_p = p;
         //         | regexp
+        if let Some(n_regexp) = self.p_regexp() { return self.decurse_with(n_regexp); }
+        self.current_p = p;
         //         | words
-        if let Some(n_words) = self.p_words() { self.decurse(); return Some(n_words); }
+        if let Some(n_words) = self.p_words() { return self.decurse_with(n_words); }
         self.current_p = p;
         //         | qwords
-        if let Some(n_qwords) = self.p_qwords() { self.decurse(); return Some(n_qwords); }
+        if let Some(n_qwords) = self.p_qwords() { return self.decurse_with(n_qwords); }
         self.current_p = p;
         //         | symbols
         //         | qsymbols
         //         | var_ref
-        if let Some(n_var_ref) = self.p_var_ref() { self.decurse(); return Some(n_var_ref); }
+        // NOTE
+        // fcall paren_args of method_call, along with method_call brace_block, and fcall brace_block go first,
+        // var_ref would take the operation otherwise
+        if let Some(n_fcall) = self.p_fcall() {
+            let fcall_end_p = self.current_p;
+            let name = extract_ident(n_fcall);
+
+            if let Some(n_paren_args) = self.p_paren_args() {
+                let node = node::call_method(None, None, Some(name), extract_nodes(n_paren_args));
+                self.record_location(p, &node);
+                let node = self.match_brace_block(node);
+                return self.decurse_with(node);
+            }
+            self.current_p = fcall_end_p;
+
+            let method_call = node::call_method(None, None, Some(name), vec![]);
+            self.record_location(p, &method_call);
+            if let Some(n_brace_block) = self.p_brace_block() {
+                let node = self.block_for(method_call, n_brace_block, fcall_end_p);
+                return self.decurse_with(node);
+            }
+        }
+        self.current_p = p;
+
+        if let Some(n_var_ref) = self.p_var_ref() { return self.decurse_with(n_var_ref); }
         self.current_p = p;
         //         | backref
         //         | tFID
         //             {
         //               result = @builder.call_method(nil, nil, val[0])
         //             }
+        if let Some(Token::T_FID(name)) = self.current_token() {
+            self.consume_current_token();
+            return self.decurse_with(node::call_method(None, None, Some(name), vec![]));
+        }
+        self.current_p = p;
         //         | kBEGIN
         //             {
         //               result = @lexer.cmdarg.dup
@@ -1318,6 +3194,20 @@ impl Parser {
         // 
         //               result = @builder.begin_keyword(val[0], val[2], val[3])
         //             }
+        if let Some(_t_begin) = self.match_1_token(Token::K_BEGIN) {
+            let cmdarg = self.dup_cmdarg_and_update(false, |cmdarg| cmdarg.clear());
+
+            if let Some(n_bodystmt) = self.p_bodystmt() {
+                if let Some(_t_end) = self.match_1_token(Token::K_END) {
+                    self.update_lexer(false, |lexer| lexer.cmdarg = cmdarg);
+
+                    return self.decurse_with(node::begin_keyword(n_bodystmt));
+                }
+            }
+
+            self.rewind(p);
+        }
+        self.current_p = p;
         //         | tLPAREN_ARG
         //             {
         //               result = @lexer.cmdarg.dup
@@ -1333,6 +3223,24 @@ impl Parser {
         // 
         //               result = @builder.begin(val[0], val[2], val[4])
         //             }
+        if let Some(_t_lparen_arg) = self.match_1_token(Token::T_LPAREN_ARG) {
+            let cmdarg = self.lexer.cmdarg.clone();
+            self.update_lexer(false, |lexer| lexer.cmdarg.clear());
+
+            if let Some(n_stmt) = self.p_stmt() {
+                // NOTE racc has read the token after stmt to reduce it
+                self.update_lexer(true, |lexer| lexer.set_state(state!("expr_endarg")));
+
+                if let Some(_n_rparen) = self.p_rparen() {
+                    self.update_lexer(false, |lexer| lexer.cmdarg = cmdarg);
+                    return self.decurse_with(node::begin(n_stmt));
+                }
+            }
+
+            self.rewind(p + 1);
+            self.update_lexer(false, |lexer| lexer.cmdarg = cmdarg);
+        }
+        self.current_p = p;
         //         | tLPAREN_ARG
         //             {
         //               @lexer.state = :expr_endarg
@@ -1341,6 +3249,17 @@ impl Parser {
         //             {
         //               result = @builder.begin(val[0], nil, val[3])
         //             }
+        if let Some(_t_lparen_arg) = self.match_1_token(Token::T_LPAREN_ARG) {
+            self.update_lexer(true, |lexer| lexer.set_state(state!("expr_endarg")));
+
+            self.match_1_token(Token::T_NL);
+            if let Some(_t_rparen) = self.match_1_token(Token::T_RPAREN) {
+                return self.decurse_with(node::begin(Node::Null));
+            }
+
+            self.rewind(p + 1);
+        }
+        self.current_p = p;
         //         | tLPAREN compstmt tRPAREN
         //             {
         //               result = @builder.begin(val[0], val[1], val[2])
@@ -1349,10 +3268,18 @@ impl Parser {
         //             {
         //               result = @builder.const_fetch(val[0], val[1], val[2])
         //             }
+        // NOTE see p_primary
         //         | tCOLON3 tCONSTANT
         //             {
         //               result = @builder.const_global(val[0], val[1])
         //             }
+        if let Some(_t_colon3) = self.match_1_token(Token::T_COLON3) {
+            if let Some(Token::T_CONSTANT(name)) = self.current_token() {
+                self.consume_current_token();
+                return self.decurse_with(node::const_global(name));
+            }
+        }
+        self.current_p = p;
         //         | tLBRACK aref_args tRBRACK
         //             {
         //               result = @builder.array(val[0], val[1], val[2])
@@ -1361,7 +3288,7 @@ impl Parser {
             let p = self.current_p;
             // special rule for aref_args being `none`
             if let Some(t_rbrack) = self.match_1_token(Token::T_RBRACK) {
-                self.decurse(); return Some(Node::Array(vec![]));
+                return self.decurse_with(Node::Array(vec![]));
             }
             self.current_p = p;
 
@@ -1369,7 +3296,7 @@ impl Parser {
                 if let Some(t_rbrack) = self.match_1_token(Token::T_RBRACK) {
                     // TODO handle builder.array
                     if let Node::Nodes(nodes) = n_aref_args {
-                        self.decurse(); return Some(Node::Array(nodes));
+                        return self.decurse_with(Node::Array(nodes));
                     } else { panic!("cant extract nodes from n_aref_args"); }
                 }
             }
@@ -1384,8 +3311,7 @@ impl Parser {
             let p = self.current_p;
             // special rule for assoc_list being `none`
             if let Some(t_rcurly) = self.match_1_token(Token::T_RCURLY) {
-                self.decurse();
-                return Some(Node::Hash(vec![]));
+                return self.decurse_with(Node::Hash(vec![]));
             }
             self.current_p = p;
 
@@ -1393,8 +3319,7 @@ impl Parser {
                 if let Some(t_rcurly) = self.match_1_token(Token::T_RCURLY) {
                     let nodes = extract_nodes(n_assoc_list);
 
-                    self.decurse();
-                    return Some(Node::Hash(nodes));
+                    return self.decurse_with(Node::Hash(nodes));
                 }
             }
         }
@@ -1438,6 +3363,7 @@ impl Parser {
         //                               begin_t, args, body, end_t)
         //             }
         //         | method_call
+        // NOTE see var_ref above and p_primary
         //         | method_call brace_block
         //             {
         //               begin_t, args, body, end_t = val[1]
@@ -1452,6 +3378,13 @@ impl Parser {
         //               result      = @builder.block(lambda_call,
         //                               begin_t, args, body, end_t)
         //             }
+        if let Some(_t_lambda) = self.match_1_token(Token::T_LAMBDA) {
+            if let Some(n_lambda) = self.p_lambda() {
+                let node = self.block_for(node::call_lambda(), n_lambda, p);
+                return self.decurse_with(node);
+            }
+        }
+        self.current_p = p;
         //         | kIF expr_value then compstmt if_tail kEND
         //             {
         //               else_t, else_ = val[4]
@@ -1466,6 +3399,31 @@ impl Parser {
         //                                           else_,  else_t,
         //                                           val[3], val[5])
         //             }
+        if let Some(t_keyword) = self.current_token() {
+            if t_keyword == Token::K_IF || t_keyword == Token::K_UNLESS {
+                self.consume_current_token();
+
+                if let Some(n_expr_value) = self.p_expr_value() {
+                    if let Some(_n_then) = self.p_then() {
+                        if let Some(n_compstmt) = self.p_compstmt() {
+                            let n_else = if t_keyword == Token::K_IF { self.p_if_tail() } else { self.p_opt_else() };
+
+                            if let Some(n_else) = n_else {
+                                if let Some(_t_end) = self.match_1_token(Token::K_END) {
+                                    let node = if t_keyword == Token::K_IF {
+                                        node::condition(n_expr_value, n_compstmt, n_else)
+                                    } else {
+                                        node::condition(n_expr_value, n_else, n_compstmt)
+                                    };
+                                    return self.decurse_with(node);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        self.current_p = p;
         //         | kWHILE
         //             {
         //               @lexer.cond.push(true)
@@ -1492,6 +3450,28 @@ impl Parser {
         //               result = @builder.loop(:until, val[0], val[2], val[3],
         //                                      val[5], val[6])
         //             }
+        if let Some(t_keyword) = self.current_token() {
+            if t_keyword == Token::K_WHILE || t_keyword == Token::K_UNTIL {
+                self.consume_current_token();
+                self.update_lexer(false, |lexer| lexer.cond.push(true));
+
+                if let Some(n_expr_value) = self.p_expr_value() {
+                    if let Some(_n_do) = self.p_do() {
+                        self.update_lexer(false, |lexer| { lexer.cond.pop(); });
+
+                        if let Some(n_compstmt) = self.p_compstmt() {
+                            if let Some(_t_end) = self.match_1_token(Token::K_END) {
+                                return self.decurse_with(node::loop_(t_keyword, n_expr_value, n_compstmt));
+                            }
+                        }
+                    }
+                }
+
+                // NOTE rewinding brings back cond as well
+                self.rewind(p);
+            }
+        }
+        self.current_p = p;
         //         | kCASE expr_value opt_terms case_body kEND
         //             {
         //               *when_bodies, (else_t, else_body) = *val[3]
@@ -1508,9 +3488,53 @@ impl Parser {
         //                                      when_bodies, else_t, else_body,
         //                                      val[3])
         //             }
-        //         | kFOR for_var kIN
+        // NOTE from a later whitequark/parser's ruby27.y, where pattern matching came with 2.7
+        //         | kCASE expr_value opt_terms p_case_body kEND
         //             {
-        //               @lexer.cond.push(true)
+        //               *in_bodies, (else_t, else_body) = *val[3]
+        // 
+        //               result = @builder.case_match(val[0], val[1],
+        //                                            in_bodies, else_t, else_body,
+        //                                            val[4])
+        //             }
+        if let Some(_t_case) = self.match_1_token(Token::K_CASE) {
+            let expr_p = self.current_p;
+            let n_expr_value = self.p_expr_value();
+            if n_expr_value.is_none() { self.current_p = expr_p; }
+            self.p_opt_terms();
+            let case_body_p = self.current_p;
+
+            if let Some(n_case_body) = self.p_case_body() {
+                if let Some(_t_end) = self.match_1_token(Token::K_END) {
+                    let mut when_bodies = extract_nodes(n_case_body);
+                    let else_body = when_bodies.pop().unwrap();
+                    let node = node::case(n_expr_value.unwrap_or(Node::Null), when_bodies, else_body);
+                    return self.decurse_with(node);
+                }
+            }
+            self.current_p = case_body_p;
+
+            if let Some(n_expr_value) = n_expr_value {
+                if let Some(n_p_case_body) = self.p_p_case_body() {
+                    if let Some(_t_end) = self.match_1_token(Token::K_END) {
+                        let mut in_bodies = extract_nodes(n_p_case_body);
+                        let (else_t, else_body) = match in_bodies.pop().unwrap() {
+                            Node::Nodes(mut else_) => (Some(Token::K_ELSE), else_.pop().unwrap()),
+                            _ => (None, Node::Null)
+                        };
+                        let node = node::case_match(n_expr_value, in_bodies, else_t, else_body);
+                        return self.decurse_with(node);
+                    }
+                }
+            }
+
+            // NOTE p_p_case_body may have updated lexer
+            self.rewind(p);
+        }
+        self.current_p = p;
+        //         | kFOR for_var kIN
+        //             {
+        //               @lexer.cond.push(true)
         //             }
         //             expr_value do
         //             {
@@ -1522,6 +3546,32 @@ impl Parser {
         //                                     val[2], val[4],
         //                                     val[5], val[7], val[8])
         //             }
+        if let Some(_t_for) = self.match_1_token(Token::K_FOR) {
+            if let Some(n_for_var) = self.p_for_var() {
+                if let Some(_t_in) = self.match_1_token(Token::K_IN) {
+                    // NOTE declared by assignable, like the lhs of p_arg
+                    for name in assigned_lvars(&n_for_var) {
+                        self.update_static_env(|static_env| static_env.declare(&name));
+                    }
+                    self.update_lexer(false, |lexer| lexer.cond.push(true));
+
+                    if let Some(n_expr_value) = self.p_expr_value() {
+                        if let Some(_n_do) = self.p_do() {
+                            self.update_lexer(false, |lexer| { lexer.cond.pop(); });
+
+                            if let Some(n_compstmt) = self.p_compstmt() {
+                                if let Some(_t_end) = self.match_1_token(Token::K_END) {
+                                    return self.decurse_with(node::for_(n_for_var, n_expr_value, n_compstmt));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            self.rewind(p);
+        }
+        self.current_p = p;
         //         | kCLASS cpath superclass
         //             {
         //               @static_env.extend_static
@@ -1541,6 +3591,32 @@ impl Parser {
         //               @lexer.pop_cmdarg
         //               @static_env.unextend
         //             }
+        if let Some(_t_class) = self.match_1_token(Token::K_CLASS) {
+            if let Some(n_cpath) = self.p_cpath() {
+                if let Some(n_superclass) = self.p_superclass() {
+                    self.update_static_env(|static_env| static_env.extend_static());
+                    self.update_lexer(false, |lexer| lexer.push_cmdarg());
+
+                    if let Some(n_bodystmt) = self.p_bodystmt() {
+                        if let Some(_t_end) = self.match_1_token(Token::K_END) {
+                            if self.in_def() {
+                                let range = self.tokens[p].1;
+                                self.diagnostic(Level::Error, "class_in_def", HashMap::new(), range);
+                            }
+
+                            self.update_lexer(false, |lexer| lexer.pop_cmdarg());
+                            self.update_static_env(|static_env| static_env.unextend());
+
+                            return self.decurse_with(node::def_class(n_cpath, n_superclass, n_bodystmt));
+                        }
+                    }
+                }
+            }
+
+            // NOTE rewinding brings back static_env and cmdarg as well
+            self.rewind(p);
+        }
+        self.current_p = p;
         //         | kCLASS tLSHFT expr term
         //             {
         //               result = @def_level
@@ -1559,6 +3635,35 @@ impl Parser {
         // 
         //               @def_level = val[4]
         //             }
+        if let Some(_t_class) = self.match_1_token(Token::K_CLASS) {
+            if let Some(_t_lshft) = self.match_1_token(Token::T_LSHFT) {
+                if let Some(n_expr) = self.p_expr() {
+                    if let Some(_n_term) = self.p_term() {
+                        let def_level = self.def_level;
+                        self.def_level = 0;
+
+                        self.update_static_env(|static_env| static_env.extend_static());
+                        self.update_lexer(false, |lexer| lexer.push_cmdarg());
+
+                        if let Some(n_bodystmt) = self.p_bodystmt() {
+                            if let Some(_t_end) = self.match_1_token(Token::K_END) {
+                                self.update_lexer(false, |lexer| lexer.pop_cmdarg());
+                                self.update_static_env(|static_env| static_env.unextend());
+
+                                self.def_level = def_level;
+
+                                return self.decurse_with(node::def_sclass(n_expr, n_bodystmt));
+                            }
+                        }
+
+                        self.def_level = def_level;
+                    }
+                }
+            }
+
+            self.rewind(p);
+        }
+        self.current_p = p;
         //         | kMODULE cpath
         //             {
         //               @static_env.extend_static
@@ -1576,6 +3681,29 @@ impl Parser {
         //               @lexer.pop_cmdarg
         //               @static_env.unextend
         //             }
+        if let Some(_t_module) = self.match_1_token(Token::K_MODULE) {
+            if let Some(n_cpath) = self.p_cpath() {
+                self.update_static_env(|static_env| static_env.extend_static());
+                self.update_lexer(false, |lexer| lexer.push_cmdarg());
+
+                if let Some(n_bodystmt) = self.p_bodystmt() {
+                    if let Some(_t_end) = self.match_1_token(Token::K_END) {
+                        if self.in_def() {
+                            let range = self.tokens[p].1;
+                            self.diagnostic(Level::Error, "module_in_def", HashMap::new(), range);
+                        }
+
+                        self.update_lexer(false, |lexer| lexer.pop_cmdarg());
+                        self.update_static_env(|static_env| static_env.unextend());
+
+                        return self.decurse_with(node::def_module(n_cpath, n_bodystmt));
+                    }
+                }
+            }
+
+            self.rewind(p);
+        }
+        self.current_p = p;
         //         | kDEF fname
         //             {
         //               @def_level += 1
@@ -1610,6 +3738,70 @@ impl Parser {
         //               @static_env.unextend
         //               @def_level -= 1
         //             }
+        if let Some(_t_def) = self.match_1_token(Token::K_DEF) {
+            if let Some(n_fname) = self.p_fname() {
+                self.def_level += 1;
+                self.update_static_env(|static_env| static_env.extend_static());
+                self.update_lexer(false, |lexer| lexer.push_cmdarg());
+
+                if let Some(n_f_arglist) = self.p_f_arglist() {
+                    if let Some(n_bodystmt) = self.p_bodystmt() {
+                        if let Some(_t_end) = self.match_1_token(Token::K_END) {
+                            self.update_lexer(false, |lexer| lexer.pop_cmdarg());
+                            self.update_static_env(|static_env| static_env.unextend());
+                            self.def_level -= 1;
+
+                            return self.decurse_with(node::def_method(extract_ident(n_fname), n_f_arglist, n_bodystmt));
+                        }
+                    }
+                }
+
+                // NOTE rewinding brings back static_env and cmdarg as well
+                self.def_level -= 1;
+                self.rewind(p);
+            }
+        }
+        self.current_p = p;
+
+        if let Some(_t_def) = self.match_1_token(Token::K_DEF) {
+            if let Some(n_singleton) = self.p_singleton() {
+                let singleton_end_p = self.current_p;
+                if let Some(_n_dot_or_colon) = self.p_dot_or_colon() {
+                    self.set_lexer_state(state!("expr_fname"));
+
+                    if let Some(n_fname) = self.p_fname() {
+                        self.def_level += 1;
+                        self.update_static_env(|static_env| static_env.extend_static());
+                        self.update_lexer(false, |lexer| lexer.push_cmdarg());
+
+                        if let Some(n_f_arglist) = self.p_f_arglist() {
+                            if let Some(n_bodystmt) = self.p_bodystmt() {
+                                if let Some(_t_end) = self.match_1_token(Token::K_END) {
+                                    self.update_lexer(false, |lexer| lexer.pop_cmdarg());
+                                    self.update_static_env(|static_env| static_env.unextend());
+                                    self.def_level -= 1;
+
+                                    // see def_singleton
+                                    // NOTE a literal is only possible within tLPAREN2 and rparen, leave them out
+                                    if node::is_singleton_literal(&n_singleton) {
+                                        let range = self.range_of_tokens(p + 2, singleton_end_p - 1).unwrap();
+                                        self.diagnostic(Level::Error, "singleton_literal", HashMap::new(), range);
+                                    }
+
+                                    return self.decurse_with(node::def_singleton(n_singleton, extract_ident(n_fname), n_f_arglist, n_bodystmt));
+                                }
+                            }
+                        }
+
+                        self.def_level -= 1;
+                    }
+
+                    self.rewind(p);
+                }
+            }
+        }
+        self.current_p = p;
+
         //         | kBREAK
         //             {
         //               result = @builder.keyword_cmd(:break, val[0])
@@ -1632,6 +3824,16 @@ impl Parser {
     }
 
     //    primary_value: primary
+    fn p_primary_value(&mut self) -> Option<Node> {
+        self.recurse("p_primary_value");
+        let p = self.current_p;
+
+        if let Some(n_primary) = self.p_primary() { return self.decurse_with(n_primary); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //             then: term
     //                 | kTHEN
@@ -1639,9 +3841,38 @@ impl Parser {
     //                     {
     //                       result = val[1]
     //                     }
+    fn p_then(&mut self) -> Option<Node> {
+        self.recurse("p_then");
+        let p = self.current_p;
+
+        if let Some(_n_term) = self.p_term() {
+            self.match_1_token(Token::K_THEN);
+            return self.decurse_with(Node::Dummy);
+        }
+        self.current_p = p;
+
+        if let Some(_t_then) = self.match_1_token(Token::K_THEN) { return self.decurse_with(Node::Dummy); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //               do: term
     //                 | kDO_COND
+    fn p_do(&mut self) -> Option<Node> {
+        self.recurse("p_do");
+        let p = self.current_p;
+
+        if let Some(_n_term) = self.p_term() { return self.decurse_with(Node::Dummy); }
+        self.current_p = p;
+
+        if let Some(_t_do_cond) = self.match_1_token(Token::K_DO_COND) { return self.decurse_with(Node::Dummy); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //          if_tail: opt_else
     //                 | kELSIF expr_value then compstmt if_tail
@@ -1653,15 +3884,65 @@ impl Parser {
     //                                                     else_,  nil),
     //                                ]
     //                     }
+    // NOTE returns the else branch, Node::Null for none
+    fn p_if_tail(&mut self) -> Option<Node> {
+        self.recurse("p_if_tail");
+        let p = self.current_p;
+
+        if let Some(_t_elsif) = self.match_1_token(Token::K_ELSIF) {
+            if let Some(n_expr_value) = self.p_expr_value() {
+                if let Some(_n_then) = self.p_then() {
+                    if let Some(n_compstmt) = self.p_compstmt() {
+                        if let Some(n_if_tail) = self.p_if_tail() {
+                            return self.decurse_with(node::condition(n_expr_value, n_compstmt, n_if_tail));
+                        }
+                    }
+                }
+            }
+        }
+        self.current_p = p;
+
+        if let Some(n_opt_else) = self.p_opt_else() { return self.decurse_with(n_opt_else); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //         opt_else: none
     //                 | kELSE compstmt
     //                     {
     //                       result = val
     //                     }
+    // NOTE returns the compstmt, Node::Null for none
+    fn p_opt_else(&mut self) -> Option<Node> {
+        self.recurse("p_opt_else");
+        let p = self.current_p;
+
+        if let Some(_t_else) = self.match_1_token(Token::K_ELSE) {
+            if let Some(n_compstmt) = self.p_compstmt() { return self.decurse_with(n_compstmt); }
+        }
+        self.current_p = p;
+
+        self.decurse_with(Node::Null)
+    }
 
     //          for_var: lhs
     //                 | mlhs
+    // NOTE mlhs goes first, lhs would take the first one of `a, b` otherwise
+    fn p_for_var(&mut self) -> Option<Node> {
+        self.recurse("p_for_var");
+        let p = self.current_p;
+
+        if let Some(n_mlhs) = self.p_mlhs() { return self.decurse_with(n_mlhs); }
+        self.current_p = p;
+
+        if let Some(n_lhs) = self.p_lhs() { return self.decurse_with(n_lhs); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //           f_marg: f_norm_arg
     //                     {
@@ -1671,6 +3952,30 @@ impl Parser {
     //                     {
     //                       result = @builder.multi_lhs(val[0], val[1], val[2])
     //                     }
+    fn p_f_marg(&mut self) -> Option<Node> {
+        self.recurse("p_f_marg");
+        let p = self.current_p;
+
+        if let Some(n_f_norm_arg) = self.p_f_norm_arg() {
+            return self.decurse_with(node::arg(extract_ident(n_f_norm_arg)));
+        }
+        self.current_p = p;
+
+        if let Some(_t_lparen) = self.match_1_token(Token::T_LPAREN) {
+            if let Some(n_f_margs) = self.p_f_margs() {
+                if let Some(_n_rparen) = self.p_rparen() {
+                    return self.decurse_with(node::multi_lhs(n_f_margs));
+                }
+            }
+
+            // NOTE undeclare the ones f_margs declared
+            self.rewind(p);
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //      f_marg_list: f_marg
     //                     {
@@ -1680,6 +3985,39 @@ impl Parser {
     //                     {
     //                       result = val[0] << val[2]
     //                     }
+    // NOTE
+    // transformed into non-recursive form,
+    //     f_marg [ tCOMMA f_marg ]*
+    // 
+    // NOTE returns Node::Nodes only
+    fn p_f_marg_list(&mut self) -> Option<Node> {
+        self.recurse("p_f_marg_list");
+        let p = self.current_p;
+
+        if let Some(n_f_marg) = self.p_f_marg() {
+            let mut nodes = vec![n_f_marg];
+
+            loop {
+                let p = self.current_p;
+
+                if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                    if let Some(n_f_marg) = self.p_f_marg() {
+                        nodes.push(n_f_marg);
+                        continue;
+                    }
+                }
+
+                self.current_p = p;
+                break;
+            }
+
+            return self.decurse_with(Node::Nodes(nodes));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //          f_margs: f_marg_list
     //                 | f_marg_list tCOMMA tSTAR f_norm_arg
@@ -1722,6 +4060,56 @@ impl Parser {
     //                       result = [ @builder.restarg(val[0]),
     //                                  *val[2] ]
     //                     }
+    // NOTE
+    // transformed like f_args,
+    //     [ f_marg_list ] [ tCOMMA tSTAR [ f_norm_arg ] [ tCOMMA f_marg_list ] ]
+    // with at least one of them
+    // 
+    // NOTE returns Node::Nodes only
+    fn p_f_margs(&mut self) -> Option<Node> {
+        self.recurse("p_f_margs");
+        let p = self.current_p;
+
+        let mut nodes = vec![];
+
+        if let Some(n_f_marg_list) = self.p_f_args_part(true, Parser::p_f_marg_list) {
+            nodes.append(&mut extract_nodes(n_f_marg_list));
+        }
+        if let Some(n_f_marg_rest) = self.p_f_args_part(nodes.is_empty(), Parser::p_f_marg_rest) {
+            nodes.append(&mut extract_nodes(n_f_marg_rest));
+        }
+
+        if !nodes.is_empty() { return self.decurse_with(Node::Nodes(nodes)); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    // the `tSTAR [ f_norm_arg ] [ tCOMMA f_marg_list ]` part of f_margs
+    // 
+    // NOTE returns Node::Nodes only
+    fn p_f_marg_rest(&mut self) -> Option<Node> {
+        self.recurse("p_f_marg_rest");
+        let p = self.current_p;
+
+        if let Some(_t_star) = self.match_1_token(Token::T_STAR) {
+            let mut nodes = match self.p_f_norm_arg() {
+                Some(n_f_norm_arg) => vec![node::restarg(Some(extract_ident(n_f_norm_arg)))],
+                None => vec![node::restarg(None)]
+            };
+
+            if let Some(n_f_marg_list) = self.p_f_args_part(false, Parser::p_f_marg_list) {
+                nodes.append(&mut extract_nodes(n_f_marg_list));
+            }
+
+            return self.decurse_with(Node::Nodes(nodes));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //  block_args_tail: f_block_kwarg tCOMMA f_kwrest opt_f_block_arg
     //                     {
@@ -1749,6 +4137,35 @@ impl Parser {
     //                     {
     //                       result = []
     //                     }
+    // NOTE
+    // transformed like args_tail, opt_f_block_arg included,
+    //     [ f_block_kwarg ] [ tCOMMA f_kwrest ] [ tCOMMA f_block_arg ]
+    // with at least one of them
+    // 
+    // NOTE returns Node::Nodes only, opt_block_args_tail being none is handled by block_param
+    fn p_block_args_tail(&mut self) -> Option<Node> {
+        self.recurse("p_block_args_tail");
+        let p = self.current_p;
+
+        let mut nodes = vec![];
+
+        if let Some(n_f_block_kwarg) = self.p_f_args_part(true, Parser::p_f_block_kwarg) {
+            nodes.append(&mut extract_nodes(n_f_block_kwarg));
+        }
+        if let Some(n_f_kwrest) = self.p_f_args_part(nodes.is_empty(), Parser::p_f_kwrest) {
+            nodes.append(&mut extract_nodes(n_f_kwrest));
+        }
+        if let Some(n_f_block_arg) = self.p_f_args_part(nodes.is_empty(), Parser::p_f_block_arg) {
+            nodes.push(n_f_block_arg);
+        }
+
+        if !nodes.is_empty() { return self.decurse_with(Node::Nodes(nodes)); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
 
     //      block_param: f_arg tCOMMA f_block_optarg tCOMMA f_rest_arg              opt_block_args_tail
     //                     {
@@ -1836,6 +4253,66 @@ impl Parser {
     //                                   concat(val[3])
     //                     }
     //                 |                                                                block_args_tail
+    // NOTE
+    // transformed like f_args,
+    //     [ f_arg ] [ f_block_optarg ] [ f_rest_arg ] [ f_arg ] [ block_args_tail ]
+    // with at least one of them, where the 2nd f_arg only comes after f_block_optarg or f_rest_arg,
+    // and the trailing tCOMMA of `f_arg tCOMMA` only comes after the 1st f_arg alone
+    // 
+    // NOTE returns Node::Nodes only
+    fn p_block_param(&mut self) -> Option<Node> {
+        self.recurse("p_block_param");
+        let p = self.current_p;
+
+        let mut nodes = vec![];
+
+        if let Some(n_f_arg) = self.p_f_args_part(true, Parser::p_f_arg) {
+            nodes.append(&mut extract_nodes(n_f_arg));
+        }
+        let f_arg_len = nodes.len();
+
+        let mut has_optarg_or_rest_arg = false;
+        if let Some(n_f_block_optarg) = self.p_f_args_part(nodes.is_empty(), Parser::p_f_block_optarg) {
+            has_optarg_or_rest_arg = true;
+            nodes.append(&mut extract_nodes(n_f_block_optarg));
+        }
+        if let Some(n_f_rest_arg) = self.p_f_args_part(nodes.is_empty(), Parser::p_f_rest_arg) {
+            has_optarg_or_rest_arg = true;
+            nodes.append(&mut extract_nodes(n_f_rest_arg));
+        }
+        if has_optarg_or_rest_arg {
+            if let Some(n_f_arg) = self.p_f_args_part(false, Parser::p_f_arg) {
+                nodes.append(&mut extract_nodes(n_f_arg));
+            }
+        }
+
+        if let Some(n_block_args_tail) = self.p_f_args_part(nodes.is_empty(), Parser::p_block_args_tail) {
+            nodes.append(&mut extract_nodes(n_block_args_tail));
+        }
+
+        if nodes.is_empty() {
+            self.current_p = p;
+
+            self.decurse();
+            return None;
+        }
+
+        if nodes.len() == f_arg_len {
+            //  block_param: f_arg tCOMMA
+            if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                return self.decurse_with(Node::Nodes(nodes));
+            }
+
+            //  block_param: f_arg opt_block_args_tail
+            if nodes.len() == 1 {
+                let n_procarg0 = node::procarg0(nodes.pop().unwrap());
+                return self.decurse_with(Node::Nodes(vec![n_procarg0]));
+            }
+        }
+
+        self.decurse_with(Node::Nodes(nodes))
+    }
+
 
     //  opt_block_param: # nothing
     //                     {
@@ -1845,6 +4322,21 @@ impl Parser {
     //                     {
     //                       @lexer.state = :expr_value
     //                     }
+    fn p_opt_block_param(&mut self) -> Option<Node> {
+        self.recurse("p_opt_block_param");
+        let p = self.current_p;
+
+        if let Some(n_block_param_def) = self.p_block_param_def() {
+            self.set_lexer_state(state!("expr_value"));
+            // NOTE `@max_numparam_stack.has_ordinary_params!` of a later whitequark/parser's block_param_def
+            self.max_numparam_stack.set_has_ordinary_params();
+            return self.decurse_with(n_block_param_def);
+        }
+        self.current_p = p;
+
+        self.decurse_with(node::args(Node::Nodes(vec![])))
+    }
+
 
     //  block_param_def: tPIPE opt_bv_decl tPIPE
     //                     {
@@ -1858,6 +4350,41 @@ impl Parser {
     //                     {
     //                       result = @builder.args(val[0], val[1].concat(val[2]), val[3])
     //                     }
+    // NOTE the alternatives starting with tPIPE together, with block_param being optional
+    fn p_block_param_def(&mut self) -> Option<Node> {
+        self.recurse("p_block_param_def");
+        let p = self.current_p;
+
+        if let Some(_t_pipe) = self.match_1_token(Token::T_PIPE) {
+            let mut nodes = vec![];
+
+            if let Some(n_block_param) = self.p_block_param() {
+                nodes.append(&mut extract_nodes(n_block_param));
+            }
+            if let Some(n_opt_bv_decl) = self.p_opt_bv_decl() {
+                nodes.append(&mut extract_nodes(n_opt_bv_decl));
+            }
+
+            if let Some(_t_pipe) = self.match_1_token(Token::T_PIPE) {
+                self.check_duplicate_args(p);
+
+                return self.decurse_with(node::args(Node::Nodes(nodes)));
+            }
+
+            // NOTE undeclare the ones block_param and opt_bv_decl declared
+            self.rewind(p);
+        }
+        self.current_p = p;
+
+        if let Some(_t_orop) = self.match_1_token(Token::T_OROP) {
+            return self.decurse_with(node::args(Node::Nodes(vec![])));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
 
     //      opt_bv_decl: opt_nl
     //                     {
@@ -1867,15 +4394,67 @@ impl Parser {
     //                     {
     //                       result = val[2]
     //                     }
+    // NOTE returns Node::Nodes only, being an empty one for nothing
+    fn p_opt_bv_decl(&mut self) -> Option<Node> {
+        self.recurse("p_opt_bv_decl");
 
-    //         bv_decls: bvar
-    //                     {
-    //                       result = [ val[0] ]
-    //                     }
-    //                 | bv_decls tCOMMA bvar
+        self.match_1_token(Token::T_NL);
+        let p = self.current_p;
+
+        if let Some(_t_semi) = self.match_1_token(Token::T_SEMI) {
+            if let Some(n_bv_decls) = self.p_bv_decls() {
+                self.match_1_token(Token::T_NL);
+                return self.decurse_with(n_bv_decls);
+            }
+        }
+        self.current_p = p;
+
+        self.decurse_with(Node::Nodes(vec![]))
+    }
+
+
+    //         bv_decls: bvar
+    //                     {
+    //                       result = [ val[0] ]
+    //                     }
+    //                 | bv_decls tCOMMA bvar
     //                     {
     //                       result = val[0] << val[2]
     //                     }
+    // NOTE
+    // transformed into non-recursive form,
+    //     bvar [ tCOMMA bvar ]*
+    // 
+    // NOTE returns Node::Nodes only
+    fn p_bv_decls(&mut self) -> Option<Node> {
+        self.recurse("p_bv_decls");
+        let p = self.current_p;
+
+        if let Some(n_bvar) = self.p_bvar() {
+            let mut nodes = vec![n_bvar];
+
+            loop {
+                let p = self.current_p;
+
+                if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                    if let Some(n_bvar) = self.p_bvar() {
+                        nodes.push(n_bvar);
+                        continue;
+                    }
+                }
+
+                self.current_p = p;
+                break;
+            }
+
+            return self.decurse_with(Node::Nodes(nodes));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
 
     //             bvar: tIDENTIFIER
     //                     {
@@ -1883,6 +4462,30 @@ impl Parser {
     //                       result = @builder.shadowarg(val[0])
     //                     }
     //                 | f_bad_arg
+    fn p_bvar(&mut self) -> Option<Node> {
+        self.recurse("p_bvar");
+        let p = self.current_p;
+
+        if let Some(Token::T_IDENTIFIER(name)) = self.current_token() {
+            self.consume_current_token();
+
+            let range = self.tokens[p].1;
+            self.declare_arg(&name, range);
+
+            return self.decurse_with(node::shadowarg(name));
+        }
+        self.current_p = p;
+
+        // NOTE whitequark passes the token of f_bad_arg on to shadowarg as well
+        if let Some(n_f_bad_arg) = self.p_f_bad_arg() {
+            return self.decurse_with(node::shadowarg(extract_ident(n_f_bad_arg)));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
 
     //           lambda:   {
     //                       @static_env.extend_dynamic
@@ -1901,6 +4504,38 @@ impl Parser {
 
     //                       @static_env.unextend
     //                     }
+    // NOTE returns Node::Nodes of the args and the body, see block_for
+    fn p_lambda(&mut self) -> Option<Node> {
+        self.recurse("p_lambda");
+        let p = self.current_p;
+
+        self.update_static_env(|static_env| static_env.extend_dynamic());
+        self.push_numparam_scope();
+
+        if let Some(n_f_larglist) = self.p_f_larglist() {
+            // NOTE racc has read the first token of lambda_body to reduce f_larglist
+            let cmdarg = self.dup_cmdarg_and_update(true, |cmdarg| cmdarg.clear());
+
+            if let Some(n_lambda_body) = self.p_lambda_body() {
+                self.update_lexer(false, |lexer| {
+                    lexer.cmdarg = cmdarg;
+                    lexer.cmdarg.lexpop();
+                });
+                self.update_static_env(|static_env| static_env.unextend());
+                let n_f_larglist = self.pop_numparam_scope(n_f_larglist);
+
+                return self.decurse_with(Node::Nodes(vec![n_f_larglist, n_lambda_body]));
+            }
+        }
+
+        // NOTE rewinding brings back static_env and cmdarg as well
+        self.max_numparam_stack.pop();
+        self.rewind(p);
+
+        self.decurse();
+        None
+    }
+
 
     //      f_larglist: tLPAREN2 f_args opt_bv_decl tRPAREN
     //                     {
@@ -1910,6 +4545,46 @@ impl Parser {
     //                     {
     //                       result = @builder.args(nil, val[0], nil)
     //                     }
+    fn p_f_larglist(&mut self) -> Option<Node> {
+        self.recurse("p_f_larglist");
+        let p = self.current_p;
+
+        if let Some(_t_lparen2) = self.match_1_token(Token::T_LPAREN2) {
+            if let Some(n_f_args) = self.p_f_args() {
+                let mut nodes = extract_nodes(n_f_args);
+                if let Some(n_opt_bv_decl) = self.p_opt_bv_decl() {
+                    nodes.append(&mut extract_nodes(n_opt_bv_decl));
+                }
+
+                if let Some(_t_rparen) = self.match_1_token(Token::T_RPAREN) {
+                    self.check_duplicate_args(p);
+                    // NOTE `@max_numparam_stack.has_ordinary_params!` of a later whitequark/parser
+                    self.max_numparam_stack.set_has_ordinary_params();
+
+                    return self.decurse_with(node::args(Node::Nodes(nodes)));
+                }
+            }
+
+            // NOTE undeclare the ones f_args and opt_bv_decl declared
+            self.rewind(p);
+        }
+        self.current_p = p;
+
+        if let Some(n_f_args) = self.p_f_args() {
+            self.check_duplicate_args(p);
+            // NOTE `@max_numparam_stack.has_ordinary_params! if val[0].any?` of a later whitequark/parser
+            if self.current_p > p {
+                self.max_numparam_stack.set_has_ordinary_params();
+            }
+
+            return self.decurse_with(node::args(n_f_args));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
 
     //      lambda_body: tLAMBEG compstmt tRCURLY
     //                     {
@@ -1919,11 +4594,59 @@ impl Parser {
     //                     {
     //                       result = [ val[0], val[1], val[2] ]
     //                     }
+    // NOTE returns the body only
+    fn p_lambda_body(&mut self) -> Option<Node> {
+        self.recurse("p_lambda_body");
+        let p = self.current_p;
+
+        if let Some(_t_lambeg) = self.match_1_token(Token::T_LAMBEG) {
+            if let Some(n_compstmt) = self.p_compstmt() {
+                if let Some(_t_rcurly) = self.match_1_token(Token::T_RCURLY) {
+                    return self.decurse_with(n_compstmt);
+                }
+            }
+        }
+        self.current_p = p;
+
+        if let Some(_t_do_lambda) = self.match_1_token(Token::K_DO_LAMBDA) {
+            if let Some(n_compstmt) = self.p_compstmt() {
+                if let Some(_t_end) = self.match_1_token(Token::K_END) {
+                    return self.decurse_with(n_compstmt);
+                }
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
 
     //         do_block: kDO_BLOCK do_body kEND
     //                     {
     //                       result = [ val[0], *val[1], val[2] ]
     //                     }
+    // NOTE returns Node::Nodes of the args and the body, see block_for
+    fn p_do_block(&mut self) -> Option<Node> {
+        self.recurse("p_do_block");
+        let p = self.current_p;
+
+        if let Some(_t_do_block) = self.match_1_token(Token::K_DO_BLOCK) {
+            if let Some(n_do_body) = self.p_do_body() {
+                if let Some(_t_end) = self.match_1_token(Token::K_END) {
+                    return self.decurse_with(n_do_body);
+                }
+            }
+
+            // NOTE do_body has updated static_env and cmdarg
+            self.rewind(p);
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
 
     //       block_call: command do_block
     //                     {
@@ -1956,6 +4679,7 @@ impl Parser {
     //                       result      = @builder.block(method_call,
     //                                       begin_t, args, body, end_t)
     //                     }
+    // NOTE see p_command_call
 
     //      method_call: fcall paren_args
     //                     {
@@ -2014,6 +4738,38 @@ impl Parser {
     //                     {
     //                       result = [ val[0], *val[1], val[2] ]
     //                     }
+    // NOTE returns Node::Nodes of the args and the body, see block_for
+    fn p_brace_block(&mut self) -> Option<Node> {
+        self.recurse("p_brace_block");
+        let p = self.current_p;
+
+        if let Some(_t_lcurly) = self.match_1_token(Token::T_LCURLY) {
+            if let Some(n_brace_body) = self.p_brace_body() {
+                if let Some(_t_rcurly) = self.match_1_token(Token::T_RCURLY) {
+                    return self.decurse_with(n_brace_body);
+                }
+            }
+
+            // NOTE brace_body has updated static_env and cmdarg
+            self.rewind(p);
+        }
+        self.current_p = p;
+
+        if let Some(_t_do) = self.match_1_token(Token::K_DO) {
+            if let Some(n_do_body) = self.p_do_body() {
+                if let Some(_t_end) = self.match_1_token(Token::K_END) {
+                    return self.decurse_with(n_do_body);
+                }
+            }
+
+            self.rewind(p);
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
 
     //       brace_body:   {
     //                       @static_env.extend_dynamic
@@ -2030,6 +4786,36 @@ impl Parser {
     //                       @lexer.cmdarg = val[1]
     //                       @lexer.cmdarg.pop
     //                     }
+    // NOTE
+    // racc has read tRCURLY to reduce compstmt, which is lexed before the last action, see update_lexer,
+    // static_env doesn't matter to tRCURLY, it's unextended first so that tRCURLY isn't lexed again afterwards,
+    // since lexing tRCURLY lexpops cmdarg
+    // 
+    // NOTE
+    // a later whitequark/parser keeps a scope for numbered parameters along with the one of static_env,
+    // and the args are numargs if the body has used them, see pop_numparam_scope
+    // 
+    // NOTE returns Node::Nodes of the args and the body
+    fn p_brace_body(&mut self) -> Option<Node> {
+        self.recurse("p_brace_body");
+
+        self.update_static_env(|static_env| static_env.extend_dynamic());
+        self.push_numparam_scope();
+        let cmdarg = self.dup_cmdarg_and_update(false, |cmdarg| cmdarg.clear());
+
+        let n_opt_block_param = self.p_opt_block_param().unwrap();
+        let n_compstmt = self.p_compstmt().unwrap();
+        let n_opt_block_param = self.pop_numparam_scope(n_opt_block_param);
+
+        self.update_static_env(|static_env| static_env.unextend());
+        self.update_lexer(true, |lexer| {
+            lexer.cmdarg = cmdarg;
+            lexer.cmdarg.pop();
+        });
+
+        self.decurse_with(Node::Nodes(vec![n_opt_block_param, n_compstmt]))
+    }
+
 
     //          do_body:   {
     //                       @static_env.extend_dynamic
@@ -2045,112 +4831,211 @@ impl Parser {
     //                       @static_env.unextend
     //                       @lexer.cmdarg = val[1]
     //                     }
+    // NOTE numbered parameters are taken like brace_body
+    // 
+    // NOTE returns Node::Nodes of the args and the body
+    fn p_do_body(&mut self) -> Option<Node> {
+        self.recurse("p_do_body");
+
+        self.update_static_env(|static_env| static_env.extend_dynamic());
+        self.push_numparam_scope();
+        let cmdarg = self.dup_cmdarg_and_update(false, |cmdarg| cmdarg.clear());
+
+        let n_opt_block_param = self.p_opt_block_param().unwrap();
+        let n_bodystmt = self.p_bodystmt().unwrap();
+        let n_opt_block_param = self.pop_numparam_scope(n_opt_block_param);
+
+        self.update_static_env(|static_env| static_env.unextend());
+        self.update_lexer(true, |lexer| lexer.cmdarg = cmdarg);
+
+        self.decurse_with(Node::Nodes(vec![n_opt_block_param, n_bodystmt]))
+    }
+
 
     //        case_body: kWHEN args then compstmt cases
     //                     {
     //                       result = [ @builder.when(val[0], val[1], val[2], val[3]),
     //                                  *val[4] ]
     //                     }
-
+    // 
     //            cases: opt_else
     //                     {
     //                       result = [ val[0] ]
     //                     }
     //                 | case_body
+    // NOTE
+    // transformed into non-recursive form,
+    //     [ kWHEN args then compstmt ]+ opt_else
+    // returns the whens followed by the else, Node::Null for none
+    fn p_case_body(&mut self) -> Option<Node> {
+        self.recurse("p_case_body");
+        let p = self.current_p;
 
-    //       opt_rescue: kRESCUE exc_list exc_var then compstmt opt_rescue
-    //                     {
-    //                       assoc_t, exc_var = val[2]
+        let mut nodes = vec![];
+        loop {
+            let when_p = self.current_p;
+
+            if let Some(_t_when) = self.match_1_token(Token::K_WHEN) {
+                if let Some(n_args) = self.p_args() {
+                    if let Some(_n_then) = self.p_then() {
+                        if let Some(n_compstmt) = self.p_compstmt() {
+                            let node = node::when(n_args, n_compstmt);
+                            self.record_location(when_p, &node);
+                            nodes.push(node);
+                            continue;
+                        }
+                    }
+                }
+            }
+            self.current_p = when_p;
+            break;
+        }
 
-    //                       if val[1]
-    //                         exc_list = @builder.array(nil, val[1], nil)
-    //                       end
+        if !nodes.is_empty() {
+            if let Some(n_opt_else) = self.p_opt_else() {
+                nodes.push(n_opt_else);
+                return self.decurse_with(Node::Nodes(nodes));
+            }
+        }
+        self.current_p = p;
 
-    //                       result = [ @builder.rescue_body(val[0],
-    //                                       exc_list, assoc_t, exc_var,
-    //                                       val[3], val[4]),
-    //                                  *val[5] ]
+        self.decurse();
+        None
+    }
+
+    // NOTE
+    // from a later whitequark/parser's ruby27.y, where pattern matching came with 2.7,
+    // the rules are named after the ones there, which begin with p_ already, like p_p_case_body for p_case_body
+    // 
+    //      p_case_body: kIN
+    //                     {
+    //                       @lexer.state = :expr_beg
+    //                       @lexer.command_start = false
+    //                       @pattern_variables.push
+    //                       @pattern_hash_keys.push
+    // 
+    //                       result = @lexer.in_kwarg
+    //                       @lexer.in_kwarg = true
     //                     }
-    //                 |
+    //                     p_top_expr then
     //                     {
-    //                       result = []
+    //                       @lexer.in_kwarg = val[1]
     //                     }
-
-    //         exc_list: arg_value
+    //                     compstmt p_cases
     //                     {
-    //                       result = [ val[0] ]
+    //                       result = [ @builder.in_pattern(val[0], *val[2], val[3], val[5]),
+    //                                  *val[6] ]
     //                     }
-    //                 | mrhs
-    //                 | none
-
-    //          exc_var: tASSOC lhs
+    // 
+    //          p_cases: opt_else
     //                     {
-    //                       result = [ val[0], val[1] ]
+    //                       result = [ val[0] ]
     //                     }
-    //                 | none
-
-    //       opt_ensure: kENSURE compstmt
+    //                 | p_case_body
     //                     {
-    //                       result = [ val[0], val[1] ]
+    //                       result = val[0]
     //                     }
-    //                 | none
-
-    //  literal: numeric
-    //         | symbol
-    //         | dsym
-    fn p_literal(&mut self) -> Option<Node> {
-        self.recurse("p_literal");
+    // NOTE
+    // transformed into non-recursive form like case_body,
+    // returns the in_patterns followed by the else, Node::Nodes of its compstmt for `else`, Node::Null for none,
+    // which case_match tells an empty else from a missing one by
+    // 
+    // lexer has no command_start,
+    // and the pattern variables and hash keys are remembered by token, see check_duplicate_pattern_variables
+    fn p_p_case_body(&mut self) -> Option<Node> {
+        self.recurse("p_p_case_body");
         let p = self.current_p;
 
-        if let Some(n_numeric) = self.p_numeric() { self.decurse(); return Some(n_numeric); }
-        if let Some(n_symbol) = self.p_symbol() { self.decurse(); return Some(n_symbol); }
-        if let Some(n_dsym) = self.p_dsym() { self.decurse(); return Some(n_dsym); }
+        let mut nodes = vec![];
+        loop {
+            let in_p = self.current_p;
 
-        self.decurse();
-        None
-    }
+            if let Some(_t_in) = self.match_1_token(Token::K_IN) {
+                let range = self.tokens[in_p].1;
+                self.check_version("pattern matching", RubyVersion::Ruby27, range);
 
-    //  strings: string
-    //             {
-    //               result = @builder.string_compose(nil, val[0], nil)
-    //             }
-    fn p_strings(&mut self) -> Option<Node> {
-        self.recurse("p_strings");
-        let p = self.current_p;
+                let in_kwarg = self.lexer.in_kwarg();
+                self.update_lexer(false, |lexer| {
+                    lexer.set_state(state!("expr_beg"));
+                    lexer.set_in_kwarg(true);
+                });
 
-        if let Some(n_string) = self.p_string() {
-            self.decurse(); return Some(node::string_compose(n_string));
+                if let Some(n_p_top_expr) = self.p_p_top_expr() {
+                    if let Some(_n_then) = self.p_then() {
+                        self.update_lexer(false, |lexer| lexer.set_in_kwarg(in_kwarg));
+
+                        if let Some(n_compstmt) = self.p_compstmt() {
+                            let mut pattern_and_guard = extract_nodes(n_p_top_expr);
+                            let n_guard = pattern_and_guard.pop().unwrap();
+                            let n_pattern = pattern_and_guard.pop().unwrap();
+
+                            let node = node::in_pattern(n_pattern, n_guard, n_compstmt);
+                            self.record_location(in_p, &node);
+                            nodes.push(node);
+                            continue;
+                        }
+                    }
+                }
+
+                // NOTE rewinding brings back in_kwarg as well
+                self.rewind(in_p);
+            }
+            self.current_p = in_p;
+            break;
+        }
+
+        if !nodes.is_empty() {
+            let has_else = self.current_token() == Some(Token::K_ELSE);
+            if let Some(n_opt_else) = self.p_opt_else() {
+                nodes.push(if has_else { Node::Nodes(vec![n_opt_else]) } else { n_opt_else });
+                return self.decurse_with(Node::Nodes(nodes));
+            }
         }
+        self.current_p = p;
 
         self.decurse();
         None
     }
 
-    //   string: string1
-    //             {
-    //               result = [ val[0] ]
-    //             }
-    //         | string string1
-    //             {
-    //               result = val[0] << val[1]
-    //             }
-    // NOTE transformed into non-recursive form
-    fn p_string(&mut self) -> Option<Node> {
-        self.recurse("p_string");
+    //       p_top_expr: p_top_expr_body
+    //                     {
+    //                       result = [ val[0], nil ]
+    //                     }
+    //                 | p_top_expr_body kIF_MOD expr_value
+    //                     {
+    //                       result = [ val[0], @builder.if_guard(val[1], val[2]) ]
+    //                     }
+    //                 | p_top_expr_body kUNLESS_MOD expr_value
+    //                     {
+    //                       result = [ val[0], @builder.unless_guard(val[1], val[2]) ]
+    //                     }
+    // NOTE returns the pattern and the guard, Node::Null for none
+    fn p_p_top_expr(&mut self) -> Option<Node> {
+        self.recurse("p_p_top_expr");
         let p = self.current_p;
 
-        if let Some(n_string1) = self.p_string1() {
-            let mut string1s = vec![n_string1];
-
-            loop {
-                if let Some(n_string1) = self.p_string1() {
-                    string1s.push(n_string1);
-                } else {
-                    break;
+        if let Some(n_p_top_expr_body) = self.p_p_top_expr_body() {
+            self.check_duplicate_pattern_variables(p);
+            let guard_p = self.current_p;
+
+            if let Some(t_keyword) = self.current_token() {
+                if t_keyword == Token::K_IF_MOD || t_keyword == Token::K_UNLESS_MOD {
+                    self.consume_current_token();
+
+                    if let Some(n_expr_value) = self.p_expr_value() {
+                        let n_guard = if t_keyword == Token::K_IF_MOD {
+                            node::if_guard(n_expr_value)
+                        } else {
+                            node::unless_guard(n_expr_value)
+                        };
+                        self.record_location(guard_p, &n_guard);
+                        return self.decurse_with(Node::Nodes(vec![n_p_top_expr_body, n_guard]));
+                    }
                 }
             }
+            self.current_p = guard_p;
 
-            self.decurse(); return Some(Node::Nodes(string1s));
+            return self.decurse_with(Node::Nodes(vec![n_p_top_expr_body, Node::Null]));
         }
         self.current_p = p;
 
@@ -2158,11 +5043,1089 @@ impl Parser {
         None
     }
 
-    // TODO INCOMPLETE
-    fn p_string1(&mut self) -> Option<Node> {
-        self.recurse("p_string1");
-        let p = self.current_p;
-
+    //  p_top_expr_body: p_expr
+    //                 | p_expr tCOMMA
+    //                     {
+    //                       # array patterns that end with comma
+    //                       # like 1, 2,
+    //                       # must be emitted as `array_pattern_with_tail`
+    //                       item = @builder.match_with_trailing_comma(val[0], val[1])
+    //                       result = @builder.array_pattern(nil, [ item ], nil)
+    //                     }
+    //                 | p_expr tCOMMA p_args
+    //                     {
+    //                       result = @builder.array_pattern(nil, [val[0]].concat(val[2]), nil)
+    //                     }
+    //                 | p_args_tail
+    //                     {
+    //                       result = @builder.array_pattern(nil, val[0], nil)
+    //                     }
+    //                 | p_kwargs
+    //                     {
+    //                       result = @builder.hash_pattern(nil, val[0], nil)
+    //                     }
+    // NOTE from a later whitequark/parser's ruby30.y
+    //                 | p_find
+    //                     {
+    //                       result = @builder.find_pattern(nil, val[0], nil)
+    //                     }
+    fn p_p_top_expr_body(&mut self) -> Option<Node> {
+        self.recurse("p_p_top_expr_body");
+        let p = self.current_p;
+
+        if let Some(n_p_expr) = self.p_p_expr() {
+            let expr_end_p = self.current_p;
+
+            if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                let args_p = self.current_p;
+                if let Some(n_p_args) = self.p_p_args() {
+                    let mut items = vec![n_p_expr];
+                    items.extend(extract_nodes(n_p_args));
+                    return self.decurse_with(node::array_pattern(Node::Nodes(items)));
+                }
+                self.current_p = args_p;
+
+                let item = node::match_with_trailing_comma(n_p_expr);
+                self.record_location(p, &item);
+                return self.decurse_with(node::array_pattern(Node::Nodes(vec![item])));
+            }
+            self.current_p = expr_end_p;
+
+            return self.decurse_with(n_p_expr);
+        }
+        self.current_p = p;
+
+        if let Some(n_p_find) = self.p_p_find() { return self.decurse_with(node::find_pattern(n_p_find)); }
+        self.current_p = p;
+
+        if let Some(n_p_args_tail) = self.p_p_args_tail() { return self.decurse_with(node::array_pattern(n_p_args_tail)); }
+        self.current_p = p;
+
+        if let Some(n_p_kwargs) = self.p_p_kwargs() { return self.decurse_with(node::hash_pattern(n_p_kwargs)); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //           p_expr: p_as
+    // 
+    //             p_as: p_expr tASSOC p_variable
+    //                     {
+    //                       result = @builder.match_as(val[0], val[1], val[2])
+    //                     }
+    //                 | p_alt
+    // NOTE transformed into non-left-recursive form, p_alt [ tASSOC p_variable ]*
+    fn p_p_expr(&mut self) -> Option<Node> {
+        self.recurse("p_p_expr");
+        let p = self.current_p;
+
+        if let Some(mut n_p_expr) = self.p_p_alt() {
+            loop {
+                let assoc_p = self.current_p;
+
+                if let Some(_t_assoc) = self.match_1_token(Token::T_ASSOC) {
+                    if let Some(n_p_variable) = self.p_p_variable() {
+                        n_p_expr = node::match_as(n_p_expr, n_p_variable);
+                        self.record_location(p, &n_p_expr);
+                        continue;
+                    }
+                }
+                self.current_p = assoc_p;
+                break;
+            }
+
+            return self.decurse_with(n_p_expr);
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //            p_alt: p_alt tPIPE p_expr_basic
+    //                     {
+    //                       result = @builder.match_alt(val[0], val[1], val[2])
+    //                     }
+    //                 | p_expr_basic
+    // NOTE transformed into non-left-recursive form, p_expr_basic [ tPIPE p_expr_basic ]*
+    fn p_p_alt(&mut self) -> Option<Node> {
+        self.recurse("p_p_alt");
+        let p = self.current_p;
+
+        if let Some(mut n_p_alt) = self.p_p_expr_basic() {
+            loop {
+                let pipe_p = self.current_p;
+
+                if let Some(_t_pipe) = self.match_1_token(Token::T_PIPE) {
+                    if let Some(n_p_expr_basic) = self.p_p_expr_basic() {
+                        n_p_alt = node::match_alt(n_p_alt, n_p_expr_basic);
+                        self.record_location(p, &n_p_alt);
+                        continue;
+                    }
+                }
+                self.current_p = pipe_p;
+                break;
+            }
+
+            return self.decurse_with(n_p_alt);
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //     p_expr_basic: p_value
+    //                 | p_const p_lparen p_args rparen
+    //                     {
+    //                       @pattern_hash_keys.pop
+    //                       pattern = @builder.array_pattern(nil, val[2], nil)
+    //                       result = @builder.const_pattern(val[0], val[1], pattern, val[3])
+    //                     }
+    //                 | p_const p_lparen p_kwargs rparen
+    //                     {
+    //                       @pattern_hash_keys.pop
+    //                       pattern = @builder.hash_pattern(nil, val[2], nil)
+    //                       result = @builder.const_pattern(val[0], val[1], pattern, val[3])
+    //                     }
+    //                 | p_const tLPAREN2 rparen
+    //                     {
+    //                       pattern = @builder.array_pattern(val[1], nil, val[2])
+    //                       result = @builder.const_pattern(val[0], val[1], pattern, val[2])
+    //                     }
+    //                 | p_const p_lbracket p_args rbracket
+    //                 | p_const p_lbracket p_kwargs rbracket
+    //                 | p_const tLBRACK2 rbracket
+    //                 | tLBRACK
+    //                     {
+    //                       @pattern_hash_keys.push
+    //                     }
+    //                     p_args rbracket
+    //                     {
+    //                       @pattern_hash_keys.pop
+    //                       result = @builder.array_pattern(val[0], val[2], val[3])
+    //                     }
+    //                 | tLBRACK rbracket
+    //                     {
+    //                       result = @builder.array_pattern(val[0], [], val[1])
+    //                     }
+    //                 | tLBRACE
+    //                     {
+    //                       @pattern_hash_keys.push
+    //                       result = @lexer.in_kwarg
+    //                       @lexer.in_kwarg = false
+    //                     }
+    //                     p_kwargs rbrace
+    //                     {
+    //                       @pattern_hash_keys.pop
+    //                       @lexer.in_kwarg = val[1]
+    //                       result = @builder.hash_pattern(val[0], val[2], val[3])
+    //                     }
+    //                 | tLBRACE rbrace
+    //                     {
+    //                       result = @builder.hash_pattern(val[0], [], val[1])
+    //                     }
+    //                 | tLPAREN
+    //                     {
+    //                       @pattern_hash_keys.push
+    //                     }
+    //                     p_expr rparen
+    //                     {
+    //                       @pattern_hash_keys.pop
+    //                       result = @builder.begin(val[0], val[2], val[3])
+    //                     }
+    // NOTE from a later whitequark/parser's ruby30.y, p_find within the brackets of the ones taking p_args as well
+    //                 | p_const p_lparen p_find rparen
+    //                 | p_const p_lbracket p_find rbracket
+    //                 | tLBRACK p_find rbracket
+    // NOTE p_lparen and p_lbracket are tLPAREN2 and tLBRACK2 with `@pattern_hash_keys.push`, see p_p_kwargs
+    fn p_p_expr_basic(&mut self) -> Option<Node> {
+        self.recurse("p_p_expr_basic");
+        let p = self.current_p;
+
+        if let Some(n_p_const) = self.p_p_const() {
+            if let Some(t_ldelim) = self.current_token() {
+                if t_ldelim == Token::T_LPAREN2 || t_ldelim == Token::T_LBRACK2 {
+                    self.consume_current_token();
+
+                    let n_pattern = self.match_bracketed_pattern(true);
+                    let n_rdelim = if t_ldelim == Token::T_LPAREN2 { self.p_rparen() } else { self.p_rbracket() };
+                    if n_rdelim.is_some() {
+                        return self.decurse_with(node::const_pattern(n_p_const, n_pattern));
+                    }
+                }
+            }
+        }
+        self.current_p = p;
+
+        if let Some(_t_lbrack) = self.match_1_token(Token::T_LBRACK) {
+            let n_pattern = self.match_bracketed_pattern(false);
+            if let Some(_t_rbracket) = self.p_rbracket() { return self.decurse_with(n_pattern); }
+        }
+        self.current_p = p;
+
+        if let Some(_t_lbrace) = self.match_1_token(Token::T_LBRACE) {
+            let in_kwarg = self.lexer.in_kwarg();
+            self.update_lexer(false, |lexer| lexer.set_in_kwarg(false));
+
+            let kwargs_p = self.current_p;
+            let n_p_kwargs = match self.p_p_kwargs() {
+                Some(n_p_kwargs) => n_p_kwargs,
+                None => { self.current_p = kwargs_p; Node::Nodes(vec![]) }
+            };
+            if let Some(_n_rbrace) = self.p_rbrace() {
+                self.update_lexer(false, |lexer| lexer.set_in_kwarg(in_kwarg));
+                return self.decurse_with(node::hash_pattern(n_p_kwargs));
+            }
+
+            // NOTE rewinding brings back in_kwarg as well
+            self.rewind(p);
+        }
+        self.current_p = p;
+
+        if let Some(_t_lparen) = self.match_1_token(Token::T_LPAREN) {
+            if let Some(n_p_expr) = self.p_p_expr() {
+                if let Some(_n_rparen) = self.p_rparen() { return self.decurse_with(node::begin(n_p_expr)); }
+            }
+        }
+        self.current_p = p;
+
+        if let Some(n_p_value) = self.p_p_value() { return self.decurse_with(n_p_value); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    // p_args, p_find or p_kwargs between the brackets or parentheses of p_expr_basic, as the pattern,
+    // an empty array pattern for nothing, like `Foo()` and `[]`
+    fn match_bracketed_pattern(&mut self, is_kwargs_allowed: bool) -> Node {
+        let p = self.current_p;
+
+        if let Some(n_p_find) = self.p_p_find() {
+            let node = node::find_pattern(n_p_find);
+            self.record_location(p, &node);
+            return node;
+        }
+        self.current_p = p;
+
+        if let Some(n_p_args) = self.p_p_args() {
+            let node = node::array_pattern(n_p_args);
+            self.record_location(p, &node);
+            return node;
+        }
+        self.current_p = p;
+
+        if is_kwargs_allowed {
+            if let Some(n_p_kwargs) = self.p_p_kwargs() {
+                let node = node::hash_pattern(n_p_kwargs);
+                self.record_location(p, &node);
+                return node;
+            }
+            self.current_p = p;
+        }
+
+        node::array_pattern(Node::Nodes(vec![]))
+    }
+
+    //           p_args: p_expr
+    //                     {
+    //                       result = [ val[0] ]
+    //                     }
+    //                 | p_args_head
+    //                     {
+    //                       result = val[0]
+    //                     }
+    //                 | p_args_head p_arg
+    //                     {
+    //                       result = [ *val[0], val[1] ]
+    //                     }
+    //                 | p_args_head tSTAR tIDENTIFIER
+    //                     {
+    //                       match_rest = @builder.match_rest(val[1], val[2])
+    //                       result = [ *val[0], match_rest ]
+    //                     }
+    //                 | p_args_head tSTAR tIDENTIFIER tCOMMA p_args_post
+    //                     {
+    //                       match_rest = @builder.match_rest(val[1], val[2])
+    //                       result = [ *val[0], match_rest, *val[4] ]
+    //                     }
+    //                 | p_args_head tSTAR
+    //                     {
+    //                       result = [ *val[0], @builder.match_rest(val[1]) ]
+    //                     }
+    //                 | p_args_head tSTAR tCOMMA p_args_post
+    //                     {
+    //                       result = [ *val[0], @builder.match_rest(val[1]), *val[3] ]
+    //                     }
+    //                 | p_args_tail
+    // 
+    //      p_args_head: p_arg tCOMMA
+    //                     {
+    //                       # array patterns that end with comma
+    //                       # like [1, 2,]
+    //                       # must be emitted as `array_pattern_with_tail`
+    //                       item = @builder.match_with_trailing_comma(val[0], val[1])
+    //                       result = [ item ]
+    //                     }
+    //                 | p_args_head p_arg tCOMMA
+    //                     {
+    //                       # array patterns that end with comma
+    //                       # like [1, 2,]
+    //                       # must be emitted as `array_pattern_with_tail`
+    //                       last_item = @builder.match_with_trailing_comma(val[1], val[2])
+    //                       result = [ *val[0], last_item ]
+    //                     }
+    // 
+    //            p_arg: p_expr
+    // NOTE
+    // transformed into non-left-recursive form,
+    //     p_args_tail | p_arg [ tCOMMA p_arg ]* [ tCOMMA [ p_args_tail ] ]
+    // where the tSTAR alternatives after p_args_head are p_args_tail, and a p_arg followed by tCOMMA is of p_args_head
+    fn p_p_args(&mut self) -> Option<Node> {
+        self.recurse("p_p_args");
+        let p = self.current_p;
+
+        if let Some(n_p_args_tail) = self.p_p_args_tail() { return self.decurse_with(n_p_args_tail); }
+        self.current_p = p;
+
+        let mut nodes = vec![];
+        loop {
+            let item_p = self.current_p;
+
+            if let Some(n_p_arg) = self.p_p_expr() {
+                let arg_end_p = self.current_p;
+
+                if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                    let item = node::match_with_trailing_comma(n_p_arg);
+                    self.record_location(item_p, &item);
+                    nodes.push(item);
+
+                    let tail_p = self.current_p;
+                    if let Some(n_p_args_tail) = self.p_p_args_tail() {
+                        nodes.extend(extract_nodes(n_p_args_tail));
+                        break;
+                    }
+                    self.current_p = tail_p;
+
+                    continue;
+                }
+                self.current_p = arg_end_p;
+
+                nodes.push(n_p_arg);
+                break;
+            }
+            self.current_p = item_p;
+            break;
+        }
+
+        if !nodes.is_empty() { return self.decurse_with(Node::Nodes(nodes)); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //      p_args_tail: tSTAR tIDENTIFIER
+    //                     {
+    //                       match_rest = @builder.match_rest(val[0], val[1])
+    //                       result = [ match_rest ]
+    //                     }
+    //                 | tSTAR tIDENTIFIER tCOMMA p_args_post
+    //                     {
+    //                       match_rest = @builder.match_rest(val[0], val[1])
+    //                       result = [ match_rest, *val[3] ]
+    //                     }
+    //                 | tSTAR
+    //                     {
+    //                       match_rest = @builder.match_rest(val[0])
+    //                       result = [ match_rest ]
+    //                     }
+    //                 | tSTAR tCOMMA p_args_post
+    //                     {
+    //                       match_rest = @builder.match_rest(val[0])
+    //                       result = [ match_rest, *val[2] ]
+    //                     }
+    // NOTE the tSTAR with or without tIDENTIFIER is p_rest of a later whitequark/parser, see p_p_rest
+    fn p_p_args_tail(&mut self) -> Option<Node> {
+        self.recurse("p_p_args_tail");
+        let p = self.current_p;
+
+        if let Some(n_p_rest) = self.p_p_rest() {
+            let rest_end_p = self.current_p;
+
+            if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                if let Some(n_p_args_post) = self.p_p_args_post() {
+                    let mut nodes = vec![n_p_rest];
+                    nodes.extend(extract_nodes(n_p_args_post));
+                    return self.decurse_with(Node::Nodes(nodes));
+                }
+            }
+            self.current_p = rest_end_p;
+
+            return self.decurse_with(Node::Nodes(vec![n_p_rest]));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    // NOTE from a later whitequark/parser's ruby30.y, where find pattern came with 3.0
+    //           p_find: p_rest tCOMMA p_args_post tCOMMA p_rest
+    //                     {
+    //                       result = [ val[0], *val[2], val[4] ]
+    //                     }
+    fn p_p_find(&mut self) -> Option<Node> {
+        self.recurse("p_p_find");
+        let p = self.current_p;
+
+        if let Some(n_p_rest_0) = self.p_p_rest() {
+            if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                if let Some(n_p_args_post) = self.p_p_args_post() {
+                    if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                        if let Some(n_p_rest_4) = self.p_p_rest() {
+                            let range = self.range_of_tokens(p, self.current_p).unwrap();
+                            self.check_version("find pattern", RubyVersion::Ruby30, range);
+
+                            let mut nodes = vec![n_p_rest_0];
+                            nodes.extend(extract_nodes(n_p_args_post));
+                            nodes.push(n_p_rest_4);
+                            return self.decurse_with(Node::Nodes(nodes));
+                        }
+                    }
+                }
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    // NOTE from a later whitequark/parser's ruby30.y
+    //           p_rest: tSTAR tIDENTIFIER
+    //                     {
+    //                       result = @builder.match_rest(val[0], val[1])
+    //                     }
+    //                 | tSTAR
+    //                     {
+    //                       result = @builder.match_rest(val[0])
+    //                     }
+    fn p_p_rest(&mut self) -> Option<Node> {
+        self.recurse("p_p_rest");
+        let p = self.current_p;
+
+        if let Some(_t_star) = self.match_1_token(Token::T_STAR) {
+            let name_p = self.current_p;
+
+            if let Some(Token::T_IDENTIFIER(name)) = self.current_token() {
+                self.consume_current_token();
+
+                let range = self.tokens[name_p].1;
+                self.declare_pattern_variable(&name, range);
+
+                return self.decurse_with(node::match_rest(Some(name)));
+            }
+            self.current_p = name_p;
+
+            return self.decurse_with(node::match_rest(None));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //      p_args_post: p_arg
+    //                     {
+    //                       result = [ val[0] ]
+    //                     }
+    //                 | p_args_post tCOMMA p_arg
+    //                     {
+    //                       result = [ *val[0], val[2] ]
+    //                     }
+    // NOTE transformed into non-left-recursive form, p_arg [ tCOMMA p_arg ]*
+    fn p_p_args_post(&mut self) -> Option<Node> {
+        self.recurse("p_p_args_post");
+        let p = self.current_p;
+
+        let mut nodes = vec![];
+        loop {
+            let item_p = self.current_p;
+
+            if !nodes.is_empty() && self.match_1_token(Token::T_COMMA).is_none() { break; }
+
+            match self.p_p_expr() {
+                Some(n_p_arg) => { nodes.push(n_p_arg); },
+                None => { self.current_p = item_p; break; }
+            }
+        }
+
+        if !nodes.is_empty() { return self.decurse_with(Node::Nodes(nodes)); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //         p_kwargs: p_kwarg tCOMMA p_kwrest
+    //                     {
+    //                       result = [ *val[0], *val[2] ]
+    //                     }
+    //                 | p_kwarg
+    //                     {
+    //                       result = val[0]
+    //                     }
+    //                 | p_kwarg tCOMMA
+    //                     {
+    //                       result = val[0]
+    //                     }
+    //                 | p_kwrest
+    //                     {
+    //                       result = val[0]
+    //                     }
+    //                 | p_kwarg tCOMMA p_kwnorest
+    //                     {
+    //                       result = [ *val[0], *val[2] ]
+    //                     }
+    //                 | p_kwnorest
+    //                     {
+    //                       result = [ *val[0] ]
+    //                     }
+    // 
+    //          p_kwarg: p_kw
+    //                     {
+    //                       result = [ val[0] ]
+    //                     }
+    //                 | p_kwarg tCOMMA p_kw
+    //                     {
+    //                       result = [ *val[0], val[2] ]
+    //                     }
+    // NOTE
+    // transformed into non-left-recursive form,
+    //     [ p_kw tCOMMA ]* ( p_kwrest | p_kwnorest | p_kw ) | p_kw [ tCOMMA p_kw ]* tCOMMA
+    // 
+    // the keys are checked here, instead of by a @pattern_hash_keys pushed with the brackets around
+    fn p_p_kwargs(&mut self) -> Option<Node> {
+        self.recurse("p_p_kwargs");
+        let p = self.current_p;
+
+        let mut nodes = vec![];
+        loop {
+            if let Some(n_p_kwrest) = self.p_p_kwnorest().or_else(|| self.p_p_kwrest()) {
+                nodes.push(n_p_kwrest);
+                break;
+            }
+
+            if let Some(n_p_kw) = self.p_p_kw() {
+                nodes.push(n_p_kw);
+                if self.match_1_token(Token::T_COMMA).is_some() { continue; }
+            }
+            break;
+        }
+
+        if !nodes.is_empty() {
+            self.check_duplicate_pattern_hash_keys(p);
+            return self.decurse_with(Node::Nodes(nodes));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //             p_kw: p_kw_label p_expr
+    //                     {
+    //                       result = @builder.match_pair(*val[0], val[1])
+    //                     }
+    //                 | p_kw_label
+    //                     {
+    //                       result = @builder.match_label(*val[0])
+    //                     }
+    // 
+    //       p_kw_label: tLABEL
+    //                     {
+    //                       result = [:label, val[0]]
+    //                     }
+    //                 | tSTRING_BEG string_contents tLABEL_END
+    //                     {
+    //                       result = [:quoted, [val[0], val[1], val[2]]]
+    //                     }
+    // TODO INCOMPLETE quoted labels, which assoc lacks as well
+    fn p_p_kw(&mut self) -> Option<Node> {
+        self.recurse("p_p_kw");
+        let p = self.current_p;
+
+        if let Some(Token::T_LABLE(key)) = self.current_token() {
+            self.consume_current_token();
+
+            let range = self.tokens[p].1;
+            self.declare_pattern_hash_key(&key, range);
+
+            let value_p = self.current_p;
+            if let Some(n_p_expr) = self.p_p_expr() {
+                return self.decurse_with(node::match_pair(key, n_p_expr));
+            }
+            self.current_p = value_p;
+
+            // NOTE match_hash_var, the name leaves out the colon of the label
+            let name_range = Range::new(range.begin_pos, range.end_pos - 1);
+            self.declare_pattern_variable(&key, name_range);
+
+            return self.decurse_with(node::match_label(key));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //         p_kwrest: kwrest_mark tIDENTIFIER
+    //                     {
+    //                       result = [ @builder.match_rest(val[0], val[1]) ]
+    //                     }
+    //                 | kwrest_mark
+    //                     {
+    //                       result = [ @builder.match_rest(val[0], nil) ]
+    //                     }
+    // NOTE returns the match_rest, instead of a list of it
+    fn p_p_kwrest(&mut self) -> Option<Node> {
+        self.recurse("p_p_kwrest");
+        let p = self.current_p;
+
+        if let Some(_t_kwrest_mark) = self.match_kwrest_mark() {
+            let name_p = self.current_p;
+
+            if let Some(Token::T_IDENTIFIER(name)) = self.current_token() {
+                self.consume_current_token();
+
+                let range = self.tokens[name_p].1;
+                self.declare_pattern_variable(&name, range);
+
+                return self.decurse_with(node::match_rest(Some(name)));
+            }
+            self.current_p = name_p;
+
+            return self.decurse_with(node::match_rest(None));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //       p_kwnorest: kwrest_mark kNIL
+    //                     {
+    //                       result = [ @builder.match_nil_pattern(val[0], val[1]) ]
+    //                     }
+    // NOTE returns the match_nil_pattern, instead of a list of it
+    fn p_p_kwnorest(&mut self) -> Option<Node> {
+        self.recurse("p_p_kwnorest");
+        let p = self.current_p;
+
+        if let Some(_t_kwrest_mark) = self.match_kwrest_mark() {
+            if let Some(_t_nil) = self.match_1_token(Token::K_NIL) {
+                return self.decurse_with(node::match_nil_pattern());
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //      kwrest_mark: tPOW | tDSTAR
+    fn match_kwrest_mark(&mut self) -> Option<Token> {
+        if let Some(t_pow) = self.match_1_token(Token::T_POW) { return Some(t_pow); }
+        if let Some(t_dstar) = self.match_1_token(Token::T_DSTAR) { return Some(t_dstar); }
+        None
+    }
+
+    //          p_value: p_primitive
+    //                 | p_primitive tDOT2 p_primitive
+    //                     {
+    //                       result = @builder.range_inclusive(val[0], val[1], val[2])
+    //                     }
+    //                 | p_primitive tDOT3 p_primitive
+    //                     {
+    //                       result = @builder.range_exclusive(val[0], val[1], val[2])
+    //                     }
+    //                 | p_primitive tDOT2
+    //                     {
+    //                       result = @builder.range_inclusive(val[0], val[1], nil)
+    //                     }
+    //                 | p_primitive tDOT3
+    //                     {
+    //                       result = @builder.range_exclusive(val[0], val[1], nil)
+    //                     }
+    //                 | p_variable
+    //                 | p_var_ref
+    //                 | p_const
+    //                 | tBDOT2 p_primitive
+    //                     {
+    //                       result = @builder.range_inclusive(nil, val[0], val[1])
+    //                     }
+    //                 | tBDOT3 p_primitive
+    //                     {
+    //                       result = @builder.range_exclusive(nil, val[0], val[1])
+    //                     }
+    // NOTE from a later whitequark/parser's ruby31.y, where pinning an expression came with 3.1
+    //                 | p_expr_ref
+    // NOTE lexer has tDOT2 and tDOT3 for tBDOT2 and tBDOT3
+    fn p_p_value(&mut self) -> Option<Node> {
+        self.recurse("p_p_value");
+        let p = self.current_p;
+
+        if let Some(n_p_primitive) = self.p_p_primitive() {
+            let primitive_end_p = self.current_p;
+
+            if let Some(t_dot) = self.current_token() {
+                if t_dot == Token::T_DOT2 || t_dot == Token::T_DOT3 {
+                    self.consume_current_token();
+
+                    let rhs_p = self.current_p;
+                    let n_rhs = match self.p_p_primitive() {
+                        Some(n_p_primitive) => n_p_primitive,
+                        None => { self.current_p = rhs_p; Node::Null }
+                    };
+                    let node = if t_dot == Token::T_DOT2 {
+                        node::range_inclusive(n_p_primitive, n_rhs)
+                    } else {
+                        node::range_exclusive(n_p_primitive, n_rhs)
+                    };
+                    return self.decurse_with(node);
+                }
+            }
+            self.current_p = primitive_end_p;
+
+            return self.decurse_with(n_p_primitive);
+        }
+        self.current_p = p;
+
+        if let Some(n_p_variable) = self.p_p_variable() { return self.decurse_with(n_p_variable); }
+        self.current_p = p;
+
+        if let Some(n_p_var_ref) = self.p_p_var_ref() { return self.decurse_with(n_p_var_ref); }
+        self.current_p = p;
+
+        if let Some(n_p_expr_ref) = self.p_p_expr_ref() { return self.decurse_with(n_p_expr_ref); }
+        self.current_p = p;
+
+        if let Some(n_p_const) = self.p_p_const() { return self.decurse_with(n_p_const); }
+        self.current_p = p;
+
+        if let Some(t_bdot) = self.current_token() {
+            if t_bdot == Token::T_DOT2 || t_bdot == Token::T_DOT3 {
+                self.consume_current_token();
+
+                if let Some(n_p_primitive) = self.p_p_primitive() {
+                    let node = if t_bdot == Token::T_DOT2 {
+                        node::range_inclusive(Node::Null, n_p_primitive)
+                    } else {
+                        node::range_exclusive(Node::Null, n_p_primitive)
+                    };
+                    return self.decurse_with(node);
+                }
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //      p_primitive: literal
+    //                 | strings
+    //                 | xstring
+    //                 | regexp
+    //                 | words
+    //                 | qwords
+    //                 | symbols
+    //                 | qsymbols
+    //                 | keyword_variable
+    //                     {
+    //                       result = @builder.accessible(val[0])
+    //                     }
+    //                 | tLAMBDA lambda
+    //                     {
+    //                       lambda_call = @builder.call_lambda(val[0])
+    // 
+    //                       args, (begin_t, body, end_t) = val[1]
+    //                       result      = @builder.block(lambda_call,
+    //                                       begin_t, args, body, end_t)
+    //                     }
+    // TODO INCOMPLETE symbols and qsymbols, which primary lacks as well
+    fn p_p_primitive(&mut self) -> Option<Node> {
+        self.recurse("p_p_primitive");
+        let p = self.current_p;
+
+        if let Some(n_literal) = self.p_literal() { return self.decurse_with(n_literal); }
+        self.current_p = p;
+
+        if let Some(n_strings) = self.p_strings() { return self.decurse_with(n_strings); }
+        self.current_p = p;
+
+        if let Some(n_xstring) = self.p_xstring() { return self.decurse_with(n_xstring); }
+        self.current_p = p;
+
+        if let Some(n_regexp) = self.p_regexp() { return self.decurse_with(n_regexp); }
+        self.current_p = p;
+
+        if let Some(n_words) = self.p_words() { return self.decurse_with(n_words); }
+        self.current_p = p;
+
+        if let Some(n_qwords) = self.p_qwords() { return self.decurse_with(n_qwords); }
+        self.current_p = p;
+
+        if let Some(n_keyword_variable) = self.p_keyword_variable() {
+            let node = node::accessible(n_keyword_variable, self.lexer.static_env().unwrap());
+            return self.decurse_with(node);
+        }
+        self.current_p = p;
+
+        if let Some(_t_lambda) = self.match_1_token(Token::T_LAMBDA) {
+            if let Some(n_lambda) = self.p_lambda() {
+                let node = self.block_for(node::call_lambda(), n_lambda, p);
+                return self.decurse_with(node);
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //       p_variable: tIDENTIFIER
+    //                     {
+    //                       result = @builder.assignable(@builder.match_var(val[0]))
+    //                     }
+    // NOTE declared by declare_pattern_variable, which is what assignable would do
+    fn p_p_variable(&mut self) -> Option<Node> {
+        self.recurse("p_p_variable");
+        let p = self.current_p;
+
+        if let Some(Token::T_IDENTIFIER(name)) = self.current_token() {
+            self.consume_current_token();
+
+            let range = self.tokens[p].1;
+            self.declare_pattern_variable(&name, range);
+
+            return self.decurse_with(node::match_var(name));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //        p_var_ref: tCARET tIDENTIFIER
+    //                     {
+    //                       name = val[1][0]
+    //                       lvar = @builder.accessible(@builder.ident(val[1]))
+    //                       result = @builder.pin(val[0], lvar)
+    //                     }
+    fn p_p_var_ref(&mut self) -> Option<Node> {
+        self.recurse("p_p_var_ref");
+        let p = self.current_p;
+
+        if let Some(_t_caret) = self.match_1_token(Token::T_CARET) {
+            if let Some(Token::T_IDENTIFIER(name)) = self.current_token() {
+                self.consume_current_token();
+
+                let lvar = node::accessible(Node::Ident(name), self.lexer.static_env().unwrap());
+                return self.decurse_with(node::pin(lvar));
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    // NOTE from a later whitequark/parser's ruby31.y, where pinning an expression came with 3.1
+    //       p_expr_ref: tCARET tLPAREN expr_value rparen
+    //                     {
+    //                       expr = @builder.begin(val[1], val[2], val[3])
+    //                       result = @builder.pin(val[0], expr)
+    //                     }
+    fn p_p_expr_ref(&mut self) -> Option<Node> {
+        self.recurse("p_p_expr_ref");
+        let p = self.current_p;
+
+        if let Some(_t_caret) = self.match_1_token(Token::T_CARET) {
+            if let Some(_t_lparen) = self.match_1_token(Token::T_LPAREN) {
+                if let Some(n_expr_value) = self.p_expr_value() {
+                    if let Some(_n_rparen) = self.p_rparen() {
+                        let range = self.range_of_tokens(p, self.current_p).unwrap();
+                        self.check_version("pinning an expression", RubyVersion::Ruby31, range);
+
+                        return self.decurse_with(node::pin(node::begin(n_expr_value)));
+                    }
+                }
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //          p_const: tCOLON3 cname
+    //                     {
+    //                       result = @builder.const_global(val[0], val[1])
+    //                     }
+    //                 | p_const tCOLON2 cname
+    //                     {
+    //                       result = @builder.const_fetch(val[0], val[1], val[2])
+    //                     }
+    //                 | tCONSTANT
+    //                     {
+    //                       result = @builder.const(val[0])
+    //                     }
+    // NOTE transformed into non-left-recursive form, ( tCOLON3 cname | tCONSTANT ) [ tCOLON2 cname ]*
+    fn p_p_const(&mut self) -> Option<Node> {
+        self.recurse("p_p_const");
+        let p = self.current_p;
+
+        let n_p_const = if let Some(_t_colon3) = self.match_1_token(Token::T_COLON3) {
+            self.p_cname().map(|n_cname| node::const_global(extract_ident(n_cname)))
+        } else if let Some(Token::T_CONSTANT(name)) = self.current_token() {
+            self.consume_current_token();
+            Some(node::constant(name))
+        } else {
+            None
+        };
+
+        if let Some(mut n_p_const) = n_p_const {
+            loop {
+                let colon2_p = self.current_p;
+
+                if let Some(_t_colon2) = self.match_1_token(Token::T_COLON2) {
+                    if let Some(n_cname) = self.p_cname() {
+                        n_p_const = node::const_fetch(n_p_const, extract_ident(n_cname));
+                        self.record_location(p, &n_p_const);
+                        continue;
+                    }
+                }
+                self.current_p = colon2_p;
+                break;
+            }
+
+            return self.decurse_with(n_p_const);
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    //       opt_rescue: kRESCUE exc_list exc_var then compstmt opt_rescue
+    //                     {
+    //                       assoc_t, exc_var = val[2]
+
+    //                       if val[1]
+    //                         exc_list = @builder.array(nil, val[1], nil)
+    //                       end
+
+    //                       result = [ @builder.rescue_body(val[0],
+    //                                       exc_list, assoc_t, exc_var,
+    //                                       val[3], val[4]),
+    //                                  *val[5] ]
+    //                     }
+    //                 |
+    //                     {
+    //                       result = []
+    //                     }
+
+    //         exc_list: arg_value
+    //                     {
+    //                       result = [ val[0] ]
+    //                     }
+    //                 | mrhs
+    //                 | none
+
+    //          exc_var: tASSOC lhs
+    //                     {
+    //                       result = [ val[0], val[1] ]
+    //                     }
+    //                 | none
+
+    //       opt_ensure: kENSURE compstmt
+    //                     {
+    //                       result = [ val[0], val[1] ]
+    //                     }
+    //                 | none
+
+    //  literal: numeric
+    //         | symbol
+    //         | dsym
+    fn p_literal(&mut self) -> Option<Node> {
+        self.recurse("p_literal");
+        let p = self.current_p;
+
+        if let Some(n_numeric) = self.p_numeric() { return self.decurse_with(n_numeric); }
+        if let Some(n_symbol) = self.p_symbol() { return self.decurse_with(n_symbol); }
+        if let Some(n_dsym) = self.p_dsym() { return self.decurse_with(n_dsym); }
+
+        self.decurse();
+        None
+    }
+
+    //  strings: string
+    //             {
+    //               result = @builder.string_compose(nil, val[0], nil)
+    //             }
+    fn p_strings(&mut self) -> Option<Node> {
+        self.recurse("p_strings");
+        let p = self.current_p;
+
+        if let Some(n_string) = self.p_string() {
+            return self.decurse_with(node::string_compose(n_string));
+        }
+
+        self.decurse();
+        None
+    }
+
+    //   string: string1
+    //             {
+    //               result = [ val[0] ]
+    //             }
+    //         | string string1
+    //             {
+    //               result = val[0] << val[1]
+    //             }
+    // NOTE transformed into non-recursive form
+    fn p_string(&mut self) -> Option<Node> {
+        self.recurse("p_string");
+        let p = self.current_p;
+
+        if let Some(n_string1) = self.p_string1() {
+            let mut string1s = vec![n_string1];
+
+            loop {
+                if let Some(n_string1) = self.p_string1() {
+                    string1s.push(n_string1);
+                } else {
+                    break;
+                }
+            }
+
+            return self.decurse_with(Node::Nodes(string1s));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    // TODO INCOMPLETE
+    fn p_string1(&mut self) -> Option<Node> {
+        self.recurse("p_string1");
+        let p = self.current_p;
+
         //  string1: tSTRING_BEG string_contents tSTRING_END
         //             {
         //               string = @builder.string_compose(val[0], val[1], val[2])
@@ -2171,10 +6134,9 @@ impl Parser {
         if let Some(t_string_beg) = self.match_1_token(Token::T_STRING_BEG) {
             if let Some(n_string_contents) = self.p_string_contents() {
                 if let Some(t_string_end) = self.match_1_token(Token::T_STRING_END) {
-                    //   string = @builder.string_compose(val[0], val[1], val[2])
-                    //   result = @builder.dedent_string(string, @lexer.dedent_level)
-                    // TODO DUMMY
-                    self.decurse(); return Some(node::string_compose(n_string_contents));
+                    let string = node::string_compose(n_string_contents);
+                    let dedent_level = self.dedent_level_of_previous_token();
+                    return self.decurse_with(node::dedent_string(string, dedent_level));
                 }
             }
         }
@@ -2187,7 +6149,7 @@ impl Parser {
         //             }
         if let Some(Token::T_STRING(token_string)) = self.current_token() {
             self.consume_current_token();
-            self.decurse(); return Some( Node::Str( token_string ) );
+            return self.decurse_with( Node::Str( token_string ) );
         }
         self.current_p = p;
 
@@ -2195,7 +6157,11 @@ impl Parser {
         //             {
         //               result = @builder.character(val[0])
         //             }
-        // TODO
+        if let Some(Token::T_CHARACTER(_)) = self.current_token() {
+            let t_character = self.consume_current_token();
+            return self.decurse_with( node::character(t_character) );
+        }
+        self.current_p = p;
 
         self.decurse();
         None
@@ -2206,20 +6172,61 @@ impl Parser {
     //                   string = @builder.xstring_compose(val[0], val[1], val[2])
     //                   result = @builder.dedent_string(string, @lexer.dedent_level)
     //                 }
-    // fn p_xstring(&mut self) -> Option<Node> {
-    //     if let Token::T_XSTRING_BEG = self.current_token() {
-    //         self.consume_current_token();
-    //         panic!("p_xstring UNIMPL");
-    //     }
-    // 
-    //     None
-    // }
+    fn p_xstring(&mut self) -> Option<Node> {
+        self.recurse("p_xstring");
+        let p = self.current_p;
+
+        if let Some(t_xstring_beg) = self.match_1_token(Token::T_XSTRING_BEG) {
+            if let Some(n_xstring_contents) = self.p_xstring_contents() {
+                if let Some(t_string_end) = self.match_1_token(Token::T_STRING_END) {
+                    let string = node::xstring_compose(n_xstring_contents);
+                    let dedent_level = self.dedent_level_of_previous_token();
+                    return self.decurse_with(node::dedent_string(string, dedent_level));
+                }
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //       regexp: tREGEXP_BEG regexp_contents tSTRING_END tREGEXP_OPT
     //                 {
     //                   opts   = @builder.regexp_options(val[3])
     //                   result = @builder.regexp_compose(val[0], val[1], val[2], opts)
     //                 }
+    fn p_regexp(&mut self) -> Option<Node> {
+        self.recurse("p_regexp");
+        let p = self.current_p;
+
+        if let Some(t_regexp_beg) = self.match_1_token(Token::T_REGEXP_BEG) {
+            if let Some(n_regexp_contents) = self.p_regexp_contents() {
+                if let Some(t_string_end) = self.match_1_token(Token::T_STRING_END) {
+                    if let Some(Token::T_REGEXP_OPT(_)) = self.current_token() {
+                        let t_regexp_opt = self.consume_current_token();
+
+                        let opts = node::regexp_options(t_regexp_opt);
+                        let n_regexp = node::regexp_compose(n_regexp_contents, opts);
+
+                        // see regexp_compose
+                        if let Err(message) = node::static_regexp_node(&n_regexp) {
+                            let range = self.tokens[p].1.join(&self.tokens[self.current_p - 2].1);
+                            let mut arguments = HashMap::new();
+                            arguments.insert("message", message);
+                            self.diagnostic(Level::Error, "invalid_regexp", arguments, range);
+                        }
+
+                        return self.decurse_with(n_regexp);
+                    }
+                }
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //        words: tWORDS_BEG word_list tSTRING_END
     //                 {
@@ -2232,12 +6239,12 @@ impl Parser {
         if let Some(t_words_beg) = self.match_1_token(Token::T_WORDS_BEG) {
             // handle word_list being none
             if let Some(t_string_end) = self.match_1_token(Token::T_STRING_END) {
-                self.decurse(); return Some(Node::Array(vec![]));
+                return self.decurse_with(Node::Array(vec![]));
             }
 
             if let Some(n_word_list) = self.p_word_list() {
                 if let Some(t_string_end) = self.match_1_token(Token::T_STRING_END) {
-                    if let Node::Nodes(nodes) = n_word_list { self.decurse(); return Some(Node::Array(nodes)); }
+                    if let Node::Nodes(nodes) = n_word_list { return self.decurse_with(Node::Array(nodes)); }
                 }
             }
         }
@@ -2258,17 +6265,24 @@ impl Parser {
     // NOTE transformed into non-recursive form
     fn p_word_list(&mut self) -> Option<Node> {
         self.recurse("p_word_list");
-        let p = self.current_p;
 
-        if let Some(n_word) = self.p_word() {
-            let mut n_words = vec![n_word];
-            loop { if let Some(n_word) = self.p_word() { n_words.push(n_word); } else { break; } }
-            self.decurse(); return Some(Node::Nodes(n_words));
+        let mut n_words = vec![];
+        loop {
+            let p = self.current_p;
+
+            if let Some(n_word) = self.p_word() {
+                if let Some(_t_space) = self.match_1_token(Token::T_SPACE) {
+                    n_words.push(node::word(n_word));
+                    continue;
+                }
+            }
+
+            self.current_p = p;
+            break;
         }
-        self.current_p = p;
 
         self.decurse();
-        None
+        Some(Node::Nodes(n_words))
     }
 
     //         word: string_content
@@ -2288,7 +6302,7 @@ impl Parser {
             let mut n_words = vec![n_string_content];
             // TODO properly handle node children
             loop { if let Some(n_string_content) = self.p_string_content() { n_words.push(n_string_content); } else { break; } }
-            self.decurse(); return Some(Node::Nodes(n_words));
+            return self.decurse_with(Node::Nodes(n_words));
         }
         self.current_p = p;
 
@@ -2322,13 +6336,13 @@ impl Parser {
             // handle qword_list being `none`
             if let Some(_t_string_end) = self.match_1_token(Token::T_STRING_END) {
                 // TODO builder.words_compose
-                self.decurse(); return Some(Node::Array(vec![]));
+                return self.decurse_with(Node::Array(vec![]));
             }
 
             if let Some(qword_list) = self.p_qword_list() {
                 if let Some(_t_string_end) = self.match_1_token(Token::T_STRING_END) {
                     // TODO builder.words_compose
-                    self.decurse(); return Some(Node::Array(extract_nodes(qword_list)));
+                    return self.decurse_with(Node::Array(extract_nodes(qword_list)));
                 }
             }
         }
@@ -2373,7 +6387,7 @@ impl Parser {
                 }
 
                 // TODO builder.string_internal
-                self.decurse(); return Some(Node::Nodes(nodes));
+                return self.decurse_with(Node::Nodes(nodes));
             }
         }
         self.current_p = p;
@@ -2402,25 +6416,13 @@ impl Parser {
     // NOTE transformed to non-recursive
     fn p_string_contents(&mut self) -> Option<Node> {
         self.recurse("p_string_contents");
-        let p = self.current_p;
-
-        if let Some(n_string_content) = self.p_string_content() {
-            let mut string_contents = vec![n_string_content];
-
-            loop {
-                if let Some(n_string_content)  = self.p_string_content() {
-                    string_contents.push(n_string_content);
-                } else {
-                    break;
-                }
-            }
 
-            self.decurse(); return Some(Node::Nodes(string_contents));
+        let mut string_contents = vec![];
+        while let Some(n_string_content) = self.p_string_content() {
+            string_contents.push(n_string_content);
         }
-        self.current_p = p;
 
-        self.decurse();
-        None
+        return self.decurse_with(Node::Nodes(string_contents));
     }
 
     // xstring_contents: # nothing
@@ -2431,21 +6433,16 @@ impl Parser {
     //                     {
     //                     result = val[0] << val[1]
     //                     }
-    // TODO INCOMPLETE DUMMY
+    // NOTE transformed to non-recursive
     fn p_xstring_contents(&mut self) -> Option<Node> {
         self.recurse("p_xstring_contents");
-        let p = self.current_p;
-
-        // NOTE transformed to non-recursive
-        if let Some(n_string_content) = self.p_string_content() {
-            // TODO handle list
 
-            self.decurse(); return Some(n_string_content);
+        let mut xstring_contents = vec![];
+        while let Some(n_string_content) = self.p_string_content() {
+            xstring_contents.push(n_string_content);
         }
-        self.current_p = p;
 
-        self.decurse();
-        None
+        return self.decurse_with(Node::Nodes(xstring_contents));
     }
 
     // regexp_contents: # nothing
@@ -2456,6 +6453,17 @@ impl Parser {
     //                     {
     //                       result = val[0] << val[1]
     //                     }
+    // NOTE transformed to non-recursive
+    fn p_regexp_contents(&mut self) -> Option<Node> {
+        self.recurse("p_regexp_contents");
+
+        let mut regexp_contents = vec![];
+        while let Some(n_string_content) = self.p_string_content() {
+            regexp_contents.push(n_string_content);
+        }
+
+        return self.decurse_with(Node::Nodes(regexp_contents));
+    }
 
     // TODO INCOMPLETE
     fn p_string_content(&mut self) -> Option<Node> {
@@ -2468,7 +6476,7 @@ impl Parser {
         //                     }
         if let Some(Token::T_STRING_CONTENT(t_string_content_value)) = self.current_token() {
             self.consume_current_token();
-            self.decurse(); return Some(Node::Str(t_string_content_value));
+            return self.decurse_with(Node::Str(t_string_content_value));
         }
         self.current_p = p;
 
@@ -2499,8 +6507,7 @@ impl Parser {
                     self.lexer.cond.lexpop();
                     self.lexer.cmdarg.lexpop();
 
-                    // TODO builder.begin
-                    panic!("WIP");
+                    return self.decurse_with(node::begin(n_compstmt));
                 }
             }
         }
@@ -2533,7 +6540,7 @@ impl Parser {
         self.recurse("p_numeric");
         let p = self.current_p;
 
-        if let Some(n_simple_numeric) = self.p_simple_numeric() { self.decurse(); return Some(n_simple_numeric); }
+        if let Some(n_simple_numeric) = self.p_simple_numeric() { return self.decurse_with(n_simple_numeric); }
         self.current_p = p;
 
         //         | tUNARY_NUM simple_numeric =tLOWEST
@@ -2549,7 +6556,7 @@ impl Parser {
         if let Some(Token::T_UNARY_NUM(_)) = self.current_token() {
             let t_unary_num = self.consume_current_token();
             if let Some(n_simple_numeric) = self.p_simple_numeric() {
-                self.decurse(); return Some(node::unary_num(t_unary_num, n_simple_numeric));
+                return self.decurse_with(node::unary_num(t_unary_num, n_simple_numeric));
             }
         }
         self.current_p = p;
@@ -2570,9 +6577,9 @@ impl Parser {
         if let Some(Token::T_SYMBOL(symbol_string)) = self.current_token() {
             let _t_symbol = self.consume_current_token();
 
-            self.lexer.set_state(state!("expr_endarg"));
+            self.set_lexer_state(state!("expr_endarg"));
 
-            self.decurse(); return Some(Node::Sym(symbol_string));
+            return self.decurse_with(Node::Sym(symbol_string));
         }
         self.current_p = p;
 
@@ -2592,10 +6599,10 @@ impl Parser {
         if let Some(t_symbeg) = self.match_1_token(Token::T_SYMBEG) {
             if let Some(n_xstring_contents) = self.p_xstring_contents() {
                 if let Some(t_string_end) = self.match_1_token(Token::T_STRING_END) {
-                    self.lexer.set_state(state!("expr_endarg"));
+                    self.set_lexer_state(state!("expr_endarg"));
                     // TODO DUMMY
                     // self.decurse(); return Some(node::symbol_compose(t_symbeg, n_xstring_contents, t_string_end));
-                    if let Node::Str(str_value) = n_xstring_contents { self.decurse(); return Some(Node::Sym(str_value)); }
+                    if let Node::Str(str_value) = node::string_compose(n_xstring_contents) { return self.decurse_with(Node::Sym(str_value)); }
                 }
             }
         }
@@ -2645,14 +6652,19 @@ impl Parser {
         self.recurse("p_simple_numeric");
 
         match self.current_token() {
-            Some(Token::T_INTEGER(i)) => {
-                self.lexer.set_state(state!("expr_end"));
+            Some(Token::T_INTEGER(_)) |
+            Some(Token::T_BIG_INTEGER(_)) |
+            Some(Token::T_FLOAT(_)) |
+            Some(Token::T_RATIONAL(_)) |
+            Some(Token::T_BIG_RATIONAL(_)) |
+            Some(Token::T_IMAGINARY(_)) => {
+                let t_numeric = self.consume_current_token();
 
-                self.consume_current_token();
+                self.set_lexer_state(state!("expr_end"));
 
-                self.decurse(); return Some(Node::Int(i));
+                return self.decurse_with(node::numeric(t_numeric));
             },
-            _ => { return None; }
+            _ => { self.decurse(); return None; }
         }
     }
 
@@ -2686,7 +6698,13 @@ impl Parser {
 
         if let Some(Token::T_IDENTIFIER(t_id_value)) = current_token {
             self.consume_current_token();
-            self.decurse(); return Some(Node::Ident(t_id_value));
+            return self.decurse_with(Node::Ident(t_id_value));
+        }
+        self.current_p = p;
+
+        if let Some(Token::T_CONSTANT(name)) = self.current_token() {
+            self.consume_current_token();
+            return self.decurse_with(node::constant(name));
         }
         self.current_p = p;
 
@@ -2703,28 +6721,28 @@ impl Parser {
         //                     {
         //                       result = @builder.nil(val[0])
         //                     }
-        if let Some(_) = self.match_1_token(Token::K_NIL) { self.decurse(); return Some(Node::Nil); }
+        if let Some(_) = self.match_1_token(Token::K_NIL) { return self.decurse_with(Node::Nil); }
         self.current_p = p;
 
         //                 | kSELF
         //                     {
         //                       result = @builder.self(val[0])
         //                     }
-        if let Some(_) = self.match_1_token(Token::K_SELF) { self.decurse(); return Some(Node::NSelf); }
+        if let Some(_) = self.match_1_token(Token::K_SELF) { return self.decurse_with(Node::NSelf); }
         self.current_p = p;
 
         //                 | kTRUE
         //                     {
         //                       result = @builder.true(val[0])
         //                     }
-        if let Some(_) = self.match_1_token(Token::K_TRUE) { self.decurse(); return Some(Node::True); }
+        if let Some(_) = self.match_1_token(Token::K_TRUE) { return self.decurse_with(Node::True); }
         self.current_p = p;
 
         //                 | kFALSE
         //                     {
         //                       result = @builder.false(val[0])
         //                     }
-        if let Some(_) = self.match_1_token(Token::K_FALSE) { self.decurse(); return Some(Node::False); }
+        if let Some(_) = self.match_1_token(Token::K_FALSE) { return self.decurse_with(Node::False); }
         self.current_p = p;
 
         //                 | k__FILE__
@@ -2755,10 +6773,24 @@ impl Parser {
         self.recurse("p_var_ref");
         let p = self.current_p;
 
-        if let Some(n_user_variable) = self.p_user_variable() { self.decurse(); return Some(node::accessible(n_user_variable)); }
+        if let Some(n_user_variable) = self.p_user_variable() {
+            // NOTE a later whitequark/parser tries it in accessible, numbered parameters being no declared variables
+            if let Node::Ident(ref name) = n_user_variable {
+                let range = self.tokens[p].1;
+                if self.try_declare_numparam(name, range) {
+                    return self.decurse_with(Node::LVar(name.clone()));
+                }
+            }
+
+            let node = node::accessible(n_user_variable, self.lexer.static_env().unwrap());
+            return self.decurse_with(node);
+        }
         self.current_p = p;
 
-        if let Some(n_keyword_variable) = self.p_keyword_variable() { self.decurse(); return Some(node::accessible(n_keyword_variable)); }
+        if let Some(n_keyword_variable) = self.p_keyword_variable() {
+            let node = node::accessible(n_keyword_variable, self.lexer.static_env().unwrap());
+            return self.decurse_with(node);
+        }
         self.current_p = p;
 
         self.decurse();
@@ -2795,6 +6827,25 @@ impl Parser {
     //                     {
     //                       result = nil
     //                     }
+    // NOTE returns Node::Null for nothing
+    fn p_superclass(&mut self) -> Option<Node> {
+        self.recurse("p_superclass");
+        let p = self.current_p;
+
+        if let Some(_t_lt) = self.match_1_token(Token::T_LT) {
+            self.set_lexer_state(state!("expr_value"));
+
+            if let Some(n_expr_value) = self.p_expr_value() {
+                if let Some(_n_term) = self.p_term() {
+                    return self.decurse_with(n_expr_value);
+                }
+            }
+            self.rewind(p);
+        }
+        self.current_p = p;
+
+        self.decurse_with(Node::Null)
+    }
 
     //        f_arglist: tLPAREN2 f_args rparen
     //                     {
@@ -2811,6 +6862,129 @@ impl Parser {
     //                       @lexer.in_kwarg = val[0]
     //                       result = @builder.args(nil, val[1], nil)
     //                     }
+    // NOTE the first alternative is f_paren_args, which takes `...` as well
+    fn p_f_arglist(&mut self) -> Option<Node> {
+        self.recurse("p_f_arglist");
+        let p = self.current_p;
+
+        if let Some(n_f_paren_args) = self.p_f_paren_args() { return self.decurse_with(n_f_paren_args); }
+        self.current_p = p;
+
+        // NOTE racc has read the first token of f_args by then, to tell the alternatives apart
+        let in_kwarg = self.lexer.in_kwarg();
+        self.update_lexer(true, |lexer| lexer.set_in_kwarg(true));
+
+        if let Some(n_f_args) = self.p_f_args() {
+            if let Some(_n_term) = self.p_term() {
+                self.update_lexer(false, |lexer| lexer.set_in_kwarg(in_kwarg));
+                self.check_duplicate_args(p);
+
+                return self.decurse_with(node::args(n_f_args));
+            }
+        }
+        self.rewind(p);
+
+        self.decurse();
+        None
+    }
+
+    // NOTE from a later whitequark/parser's ruby30.y, where `...` came with 2.7, and the arguments before it with 3.0
+    // f_opt_paren_args: f_paren_args
+    //                 | none
+    //                     {
+    //                       result = @builder.args(nil, [], nil)
+    //                     }
+    fn p_f_opt_paren_args(&mut self) -> Option<Node> {
+        self.recurse("p_f_opt_paren_args");
+        let p = self.current_p;
+
+        if let Some(n_f_paren_args) = self.p_f_paren_args() { return self.decurse_with(n_f_paren_args); }
+        self.current_p = p;
+
+        self.decurse_with(node::args(Node::Nodes(vec![])))
+    }
+
+    //     f_paren_args: tLPAREN2 f_args rparen
+    //                     {
+    //                       result = @builder.args(val[0], val[1], val[2])
+    //
+    //                       @lexer.state = :expr_value
+    //                     }
+    //                 | tLPAREN2 f_arg tCOMMA args_forward rparen
+    //                     {
+    //                       args = [ *val[1], @builder.forward_arg(val[3]) ]
+    //                       result = @builder.args(val[0], args, val[4])
+    //
+    //                       @static_env.declare_forward_args
+    //                     }
+    //                 | tLPAREN2 args_forward rparen
+    //                     {
+    //                       result = @builder.forward_only_args(val[0], val[1], val[2])
+    //                       @static_env.declare_forward_args
+    //
+    //                       @lexer.state = :expr_value
+    //                     }
+    //
+    //     args_forward: tBDOT3
+    // NOTE lexer has tDOT3 for tBDOT3, and forward_only_args builds args of forward_arg, see p_paren_args for the call
+    fn p_f_paren_args(&mut self) -> Option<Node> {
+        self.recurse("p_f_paren_args");
+        let p = self.current_p;
+
+        if let Some(_t_lparen2) = self.match_1_token(Token::T_LPAREN2) {
+            if let Some(n_f_args) = self.p_f_args() {
+                if let Some(_n_rparen) = self.p_rparen() {
+                    self.check_duplicate_args(p);
+                    self.set_lexer_state(state!("expr_value"));
+
+                    return self.decurse_with(node::args(n_f_args));
+                }
+            }
+
+            // NOTE undeclare the ones f_args declared
+            self.rewind(p);
+        }
+        self.current_p = p;
+
+        if let Some(_t_lparen2) = self.match_1_token(Token::T_LPAREN2) {
+            if let Some(n_f_arg) = self.p_f_arg() {
+                if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                    let dot3_p = self.current_p;
+                    if let Some(_t_dot3) = self.match_1_token(Token::T_DOT3) {
+                        if let Some(_n_rparen) = self.p_rparen() {
+                            self.check_duplicate_args(p);
+                            self.check_version("argument forwarding after other arguments", RubyVersion::Ruby30, self.tokens[dot3_p].1);
+                            self.update_static_env(|static_env| static_env.declare_forward_args());
+
+                            let mut nodes = extract_nodes(n_f_arg);
+                            nodes.push(node::forward_arg());
+                            return self.decurse_with(node::args(Node::Nodes(nodes)));
+                        }
+                    }
+                }
+            }
+
+            self.rewind(p);
+        }
+        self.current_p = p;
+
+        if let Some(_t_lparen2) = self.match_1_token(Token::T_LPAREN2) {
+            let dot3_p = self.current_p;
+            if let Some(_t_dot3) = self.match_1_token(Token::T_DOT3) {
+                if let Some(_n_rparen) = self.p_rparen() {
+                    self.check_version("argument forwarding", RubyVersion::Ruby27, self.tokens[dot3_p].1);
+                    self.update_static_env(|static_env| static_env.declare_forward_args());
+                    self.set_lexer_state(state!("expr_value"));
+
+                    return self.decurse_with(node::args(Node::Nodes(vec![node::forward_arg()])));
+                }
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //        args_tail: f_kwarg tCOMMA f_kwrest opt_f_block_arg
     //                     {
@@ -2837,6 +7011,34 @@ impl Parser {
     //                     {
     //                       result = []
     //                     }
+    // NOTE
+    // transformed like f_args, opt_f_block_arg included,
+    //     [ f_kwarg ] [ tCOMMA f_kwrest ] [ tCOMMA f_block_arg ]
+    // with at least one of them
+    // 
+    // NOTE returns Node::Nodes only, opt_args_tail being none is handled by f_args
+    fn p_args_tail(&mut self) -> Option<Node> {
+        self.recurse("p_args_tail");
+        let p = self.current_p;
+
+        let mut nodes = vec![];
+
+        if let Some(n_f_kwarg) = self.p_f_args_part(true, Parser::p_f_kwarg) {
+            nodes.append(&mut extract_nodes(n_f_kwarg));
+        }
+        if let Some(n_f_kwrest) = self.p_f_args_part(nodes.is_empty(), Parser::p_f_kwrest) {
+            nodes.append(&mut extract_nodes(n_f_kwrest));
+        }
+        if let Some(n_f_block_arg) = self.p_f_args_part(nodes.is_empty(), Parser::p_f_block_arg) {
+            nodes.push(n_f_block_arg);
+        }
+
+        if !nodes.is_empty() { return self.decurse_with(Node::Nodes(nodes)); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //           f_args: f_arg tCOMMA f_optarg tCOMMA f_rest_arg              opt_args_tail
     //                     {
@@ -2927,6 +7129,55 @@ impl Parser {
     //                     {
     //                       result = []
     //                     }
+    // NOTE
+    // transformed into a sequence of optional parts, separated by tCOMMA,
+    //     [ f_arg ] [ f_optarg ] [ f_rest_arg ] [ f_arg ] [ args_tail ]
+    // where the 2nd f_arg only comes after f_optarg or f_rest_arg,
+    // the parts start with tokens of their own, since f_arg stops before an f_opt, see p_f_arg
+    // 
+    // NOTE returns Node::Nodes only, being none is an empty one
+    fn p_f_args(&mut self) -> Option<Node> {
+        self.recurse("p_f_args");
+
+        let mut nodes = vec![];
+
+        if let Some(n_f_arg) = self.p_f_args_part(true, Parser::p_f_arg) {
+            nodes.append(&mut extract_nodes(n_f_arg));
+        }
+
+        let mut has_optarg_or_rest_arg = false;
+        if let Some(n_f_optarg) = self.p_f_args_part(nodes.is_empty(), Parser::p_f_optarg) {
+            has_optarg_or_rest_arg = true;
+            nodes.append(&mut extract_nodes(n_f_optarg));
+        }
+        if let Some(n_f_rest_arg) = self.p_f_args_part(nodes.is_empty(), Parser::p_f_rest_arg) {
+            has_optarg_or_rest_arg = true;
+            nodes.append(&mut extract_nodes(n_f_rest_arg));
+        }
+        if has_optarg_or_rest_arg {
+            if let Some(n_f_arg) = self.p_f_args_part(false, Parser::p_f_arg) {
+                nodes.append(&mut extract_nodes(n_f_arg));
+            }
+        }
+
+        if let Some(n_args_tail) = self.p_f_args_part(nodes.is_empty(), Parser::p_args_tail) {
+            nodes.append(&mut extract_nodes(n_args_tail));
+        }
+
+        self.decurse_with(Node::Nodes(nodes))
+    }
+
+    // a part of f_args and the like, after a tCOMMA unless it's the first one
+    fn p_f_args_part(&mut self, is_first: bool, rule: fn(&mut Parser) -> Option<Node>) -> Option<Node> {
+        let p = self.current_p;
+
+        if is_first || self.match_1_token(Token::T_COMMA).is_some() {
+            if let Some(node) = rule(self) { return Some(node); }
+        }
+        self.current_p = p;
+
+        None
+    }
 
     //        f_bad_arg: tCONSTANT
     //                     {
@@ -2944,6 +7195,32 @@ impl Parser {
     //                     {
     //                       diagnostic :error, :argument_cvar, nil, val[0]
     //                     }
+    // TODO tCVAR, which isn't lexed yet
+    // NOTE returns Node::Ident after reporting, so that the rest is parsed as usual
+    fn p_f_bad_arg(&mut self) -> Option<Node> {
+        self.recurse("p_f_bad_arg");
+        let p = self.current_p;
+
+        let reason = match self.current_token() {
+            Some(Token::T_CONSTANT(_)) => Some("argument_const"),
+            Some(Token::T_IVAR(_)) => Some("argument_ivar"),
+            Some(Token::T_GVAR(_)) => Some("argument_gvar"),
+            _ => None
+        };
+        if let Some(reason) = reason {
+            self.consume_current_token();
+
+            let range = self.tokens[p].1;
+            self.diagnostic(Level::Error, reason, HashMap::new(), range);
+
+            let name = self.token_source(p);
+            return self.decurse_with(Node::Ident(name));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //       f_norm_arg: f_bad_arg
     //                 | tIDENTIFIER
@@ -2952,6 +7229,27 @@ impl Parser {
 
     //                       result = val[0]
     //                     }
+    // NOTE returns Node::Ident
+    fn p_f_norm_arg(&mut self) -> Option<Node> {
+        self.recurse("p_f_norm_arg");
+        let p = self.current_p;
+
+        if let Some(n_f_bad_arg) = self.p_f_bad_arg() { return self.decurse_with(n_f_bad_arg); }
+        self.current_p = p;
+
+        if let Some(Token::T_IDENTIFIER(name)) = self.current_token() {
+            self.consume_current_token();
+
+            let range = self.tokens[p].1;
+            self.declare_arg(&name, range);
+
+            return self.decurse_with(Node::Ident(name));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //       f_arg_asgn: f_norm_arg
     //                     {
@@ -2966,6 +7264,31 @@ impl Parser {
     //                     {
     //                       result = @builder.multi_lhs(val[0], val[1], val[2])
     //                     }
+    // NOTE f_arg_asgn is f_norm_arg
+    fn p_f_arg_item(&mut self) -> Option<Node> {
+        self.recurse("p_f_arg_item");
+        let p = self.current_p;
+
+        if let Some(n_f_arg_asgn) = self.p_f_norm_arg() {
+            return self.decurse_with(node::arg(extract_ident(n_f_arg_asgn)));
+        }
+        self.current_p = p;
+
+        if let Some(_t_lparen) = self.match_1_token(Token::T_LPAREN) {
+            if let Some(n_f_margs) = self.p_f_margs() {
+                if let Some(_n_rparen) = self.p_rparen() {
+                    return self.decurse_with(node::multi_lhs(n_f_margs));
+                }
+            }
+
+            // NOTE undeclare the ones f_margs declared
+            self.rewind(p);
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //            f_arg: f_arg_item
     //                     {
@@ -2975,6 +7298,60 @@ impl Parser {
     //                     {
     //                       result = val[0] << val[2]
     //                     }
+    // NOTE
+    // transformed into non-recursive form,
+    //     f_arg_item [ tCOMMA f_arg_item ]*
+    // stopping before an f_opt, which starts with f_arg_asgn as well, see is_f_opt_ahead
+    // 
+    // NOTE returns Node::Nodes only
+    fn p_f_arg(&mut self) -> Option<Node> {
+        self.recurse("p_f_arg");
+        let p = self.current_p;
+
+        if !self.is_f_opt_ahead() {
+            if let Some(n_f_arg_item) = self.p_f_arg_item() {
+                let mut nodes = vec![n_f_arg_item];
+
+                loop {
+                    let p = self.current_p;
+
+                    if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                        if !self.is_f_opt_ahead() {
+                            if let Some(n_f_arg_item) = self.p_f_arg_item() {
+                                nodes.push(n_f_arg_item);
+                                continue;
+                            }
+                        }
+                    }
+
+                    self.current_p = p;
+                    break;
+                }
+
+                return self.decurse_with(Node::Nodes(nodes));
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    // `tIDENTIFIER tEQL`, looked ahead without consuming
+    fn is_f_opt_ahead(&mut self) -> bool {
+        let p = self.current_p;
+
+        let is_f_opt_ahead = match self.current_token() {
+            Some(Token::T_IDENTIFIER(_)) => {
+                self.current_p += 1;
+                self.current_token() == Some(Token::T_EQL)
+            },
+            _ => false
+        };
+        self.current_p = p;
+
+        is_f_opt_ahead
+    }
 
     //          f_label: tLABEL
     //                     {
@@ -2984,6 +7361,28 @@ impl Parser {
 
     //                       result = val[0]
     //                     }
+    // NOTE returns Node::Ident
+    fn p_f_label(&mut self) -> Option<Node> {
+        self.recurse("p_f_label");
+        let p = self.current_p;
+
+        if let Some(Token::T_LABLE(name)) = self.current_token() {
+            self.consume_current_token();
+
+            let label_range = self.tokens[p].1;
+            self.check_kwarg_name(&name, label_range);
+
+            // NOTE the name without the colon, like kwarg_map of whitequark
+            let range = Range::new(label_range.begin_pos, label_range.end_pos - 1);
+            self.declare_arg(&name, range);
+
+            return self.decurse_with(Node::Ident(name));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //             f_kw: f_label arg_value
     //                     {
@@ -2993,6 +7392,25 @@ impl Parser {
     //                     {
     //                       result = @builder.kwarg(val[0])
     //                     }
+    fn p_f_kw(&mut self) -> Option<Node> {
+        self.recurse("p_f_kw");
+        let p = self.current_p;
+
+        if let Some(n_f_label) = self.p_f_label() {
+            let label_end_p = self.current_p;
+
+            if let Some(n_arg_value) = self.p_arg_value() {
+                return self.decurse_with(node::kwoptarg(extract_ident(n_f_label), n_arg_value));
+            }
+            self.current_p = label_end_p;
+
+            return self.decurse_with(node::kwarg(extract_ident(n_f_label)));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //       f_block_kw: f_label primary_value
     //                     {
@@ -3002,6 +7420,26 @@ impl Parser {
     //                     {
     //                       result = @builder.kwarg(val[0])
     //                     }
+    fn p_f_block_kw(&mut self) -> Option<Node> {
+        self.recurse("p_f_block_kw");
+        let p = self.current_p;
+
+        if let Some(n_f_label) = self.p_f_label() {
+            let label_end_p = self.current_p;
+
+            if let Some(n_primary_value) = self.p_primary_value() {
+                return self.decurse_with(node::kwoptarg(extract_ident(n_f_label), n_primary_value));
+            }
+            self.current_p = label_end_p;
+
+            return self.decurse_with(node::kwarg(extract_ident(n_f_label)));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
 
     //    f_block_kwarg: f_block_kw
     //                     {
@@ -3011,6 +7449,40 @@ impl Parser {
     //                     {
     //                       result = val[0] << val[2]
     //                     }
+    // NOTE
+    // transformed into non-recursive form,
+    //     f_block_kw [ tCOMMA f_block_kw ]*
+    // 
+    // NOTE returns Node::Nodes only
+    fn p_f_block_kwarg(&mut self) -> Option<Node> {
+        self.recurse("p_f_block_kwarg");
+        let p = self.current_p;
+
+        if let Some(n_f_block_kw) = self.p_f_block_kw() {
+            let mut nodes = vec![n_f_block_kw];
+
+            loop {
+                let p = self.current_p;
+
+                if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                    if let Some(n_f_block_kw) = self.p_f_block_kw() {
+                        nodes.push(n_f_block_kw);
+                        continue;
+                    }
+                }
+
+                self.current_p = p;
+                break;
+            }
+
+            return self.decurse_with(Node::Nodes(nodes));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
 
     //          f_kwarg: f_kw
     //                     {
@@ -3020,6 +7492,39 @@ impl Parser {
     //                     {
     //                       result = val[0] << val[2]
     //                     }
+    // NOTE
+    // transformed into non-recursive form,
+    //     f_kw [ tCOMMA f_kw ]*
+    // 
+    // NOTE returns Node::Nodes only
+    fn p_f_kwarg(&mut self) -> Option<Node> {
+        self.recurse("p_f_kwarg");
+        let p = self.current_p;
+
+        if let Some(n_f_kw) = self.p_f_kw() {
+            let mut nodes = vec![n_f_kw];
+
+            loop {
+                let p = self.current_p;
+
+                if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                    if let Some(n_f_kw) = self.p_f_kw() {
+                        nodes.push(n_f_kw);
+                        continue;
+                    }
+                }
+
+                self.current_p = p;
+                break;
+            }
+
+            return self.decurse_with(Node::Nodes(nodes));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //      kwrest_mark: tPOW | tDSTAR
 
@@ -3033,16 +7538,87 @@ impl Parser {
     //                     {
     //                       result = [ @builder.kwrestarg(val[0]) ]
     //                     }
+    // NOTE returns Node::Nodes only
+    fn p_f_kwrest(&mut self) -> Option<Node> {
+        self.recurse("p_f_kwrest");
+        let p = self.current_p;
+
+        let is_kwrest_mark = match self.current_token() {
+            Some(Token::T_POW) | Some(Token::T_DSTAR) => true,
+            _ => false
+        };
+        if is_kwrest_mark {
+            self.consume_current_token();
+
+            let name_p = self.current_p;
+            if let Some(Token::T_IDENTIFIER(name)) = self.current_token() {
+                self.consume_current_token();
+
+                let range = self.tokens[name_p].1;
+                self.declare_arg(&name, range);
+
+                return self.decurse_with(Node::Nodes(vec![node::kwrestarg(Some(name))]));
+            }
+            self.current_p = name_p;
+
+            return self.decurse_with(Node::Nodes(vec![node::kwrestarg(None)]));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //            f_opt: f_arg_asgn tEQL arg_value
     //                     {
     //                       result = @builder.optarg(val[0], val[1], val[2])
     //                     }
+    // NOTE f_arg_asgn is f_norm_arg
+    fn p_f_opt(&mut self) -> Option<Node> {
+        self.recurse("p_f_opt");
+        let p = self.current_p;
+
+        if let Some(n_f_arg_asgn) = self.p_f_norm_arg() {
+            if let Some(_t_eql) = self.match_1_token(Token::T_EQL) {
+                if let Some(n_arg_value) = self.p_arg_value() {
+                    return self.decurse_with(node::optarg(extract_ident(n_f_arg_asgn), n_arg_value));
+                }
+            }
+
+            // undeclare it
+            self.rewind(p);
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //      f_block_opt: f_arg_asgn tEQL primary_value
     //                     {
     //                       result = @builder.optarg(val[0], val[1], val[2])
     //                     }
+    // NOTE f_arg_asgn is f_norm_arg
+    fn p_f_block_opt(&mut self) -> Option<Node> {
+        self.recurse("p_f_block_opt");
+        let p = self.current_p;
+
+        if let Some(n_f_arg_asgn) = self.p_f_norm_arg() {
+            if let Some(_t_eql) = self.match_1_token(Token::T_EQL) {
+                if let Some(n_primary_value) = self.p_primary_value() {
+                    return self.decurse_with(node::optarg(extract_ident(n_f_arg_asgn), n_primary_value));
+                }
+            }
+
+            // undeclare it
+            self.rewind(p);
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
 
     //   f_block_optarg: f_block_opt
     //                     {
@@ -3052,6 +7628,42 @@ impl Parser {
     //                     {
     //                       result = val[0] << val[2]
     //                     }
+    // NOTE
+    // transformed into non-recursive form,
+    //     f_block_opt [ tCOMMA f_block_opt ]*
+    // 
+    // NOTE returns Node::Nodes only
+    fn p_f_block_optarg(&mut self) -> Option<Node> {
+        self.recurse("p_f_block_optarg");
+        let p = self.current_p;
+
+        if let Some(n_f_block_opt) = self.p_f_block_opt() {
+            let mut nodes = vec![n_f_block_opt];
+
+            loop {
+                let p = self.current_p;
+
+                if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                    if self.is_f_opt_ahead() {
+                        if let Some(n_f_block_opt) = self.p_f_block_opt() {
+                            nodes.push(n_f_block_opt);
+                            continue;
+                        }
+                    }
+                }
+
+                self.current_p = p;
+                break;
+            }
+
+            return self.decurse_with(Node::Nodes(nodes));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
 
     //         f_optarg: f_opt
     //                     {
@@ -3061,6 +7673,41 @@ impl Parser {
     //                     {
     //                       result = val[0] << val[2]
     //                     }
+    // NOTE
+    // transformed into non-recursive form,
+    //     f_opt [ tCOMMA f_opt ]*
+    // 
+    // NOTE returns Node::Nodes only
+    fn p_f_optarg(&mut self) -> Option<Node> {
+        self.recurse("p_f_optarg");
+        let p = self.current_p;
+
+        if let Some(n_f_opt) = self.p_f_opt() {
+            let mut nodes = vec![n_f_opt];
+
+            loop {
+                let p = self.current_p;
+
+                if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                    if self.is_f_opt_ahead() {
+                        if let Some(n_f_opt) = self.p_f_opt() {
+                            nodes.push(n_f_opt);
+                            continue;
+                        }
+                    }
+                }
+
+                self.current_p = p;
+                break;
+            }
+
+            return self.decurse_with(Node::Nodes(nodes));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //     restarg_mark: tSTAR2 | tSTAR
 
@@ -3074,6 +7721,36 @@ impl Parser {
     //                     {
     //                       result = [ @builder.restarg(val[0]) ]
     //                     }
+    // NOTE returns Node::Nodes only
+    fn p_f_rest_arg(&mut self) -> Option<Node> {
+        self.recurse("p_f_rest_arg");
+        let p = self.current_p;
+
+        let is_restarg_mark = match self.current_token() {
+            Some(Token::T_STAR2) | Some(Token::T_STAR) => true,
+            _ => false
+        };
+        if is_restarg_mark {
+            self.consume_current_token();
+
+            let name_p = self.current_p;
+            if let Some(Token::T_IDENTIFIER(name)) = self.current_token() {
+                self.consume_current_token();
+
+                let range = self.tokens[name_p].1;
+                self.declare_arg(&name, range);
+
+                return self.decurse_with(Node::Nodes(vec![node::restarg(Some(name))]));
+            }
+            self.current_p = name_p;
+
+            return self.decurse_with(Node::Nodes(vec![node::restarg(None)]));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //      blkarg_mark: tAMPER2 | tAMPER
 
@@ -3083,6 +7760,32 @@ impl Parser {
     // 
     //                       result = @builder.blockarg(val[0], val[1])
     //                     }
+    fn p_f_block_arg(&mut self) -> Option<Node> {
+        self.recurse("p_f_block_arg");
+        let p = self.current_p;
+
+        let is_blkarg_mark = match self.current_token() {
+            Some(Token::T_AMPER2) | Some(Token::T_AMPER) => true,
+            _ => false
+        };
+        if is_blkarg_mark {
+            self.consume_current_token();
+
+            let name_p = self.current_p;
+            if let Some(Token::T_IDENTIFIER(name)) = self.current_token() {
+                self.consume_current_token();
+
+                let range = self.tokens[name_p].1;
+                self.declare_arg(&name, range);
+
+                return self.decurse_with(node::blockarg(name));
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //  opt_f_block_arg: tCOMMA f_block_arg
     //                     {
@@ -3098,6 +7801,25 @@ impl Parser {
     //                     {
     //                       result = val[1]
     //                     }
+    fn p_singleton(&mut self) -> Option<Node> {
+        self.recurse("p_singleton");
+        let p = self.current_p;
+
+        if let Some(n_var_ref) = self.p_var_ref() { return self.decurse_with(n_var_ref); }
+        self.current_p = p;
+
+        if let Some(_t_lparen2) = self.match_1_token(Token::T_LPAREN2) {
+            if let Some(n_expr) = self.p_expr() {
+                if let Some(_n_rparen) = self.p_rparen() {
+                    return self.decurse_with(n_expr);
+                }
+            }
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
 
     //       assoc_list: # nothing
     //                     {
@@ -3112,15 +7834,13 @@ impl Parser {
         if let Some(n_assocs) = self.p_assocs() {
             let p = self.current_p;
             if let Some(n_trailer) = self.p_trailer() {
-                self.decurse();
                 // well i guess the default result is this...
-                return Some(n_assocs);
+                return self.decurse_with(n_assocs);
             }
             self.current_p = p;
 
             // handle trailer being none
-            self.decurse();
-            return Some(n_assocs);
+            return self.decurse_with(n_assocs);
         }
         self.current_p = p;
 
@@ -3146,15 +7866,20 @@ impl Parser {
             let mut nodes = vec![n_assoc];
 
             loop {
-                if let Some(n_assoc) = self.p_assoc() {
-                    nodes.push(n_assoc);
-                } else {
-                    break;
+                let assoc_p = self.current_p;
+
+                if let Some(_t_comma) = self.match_1_token(Token::T_COMMA) {
+                    if let Some(n_assoc) = self.p_assoc() {
+                        nodes.push(n_assoc);
+                        continue;
+                    }
                 }
+
+                self.current_p = assoc_p;
+                break;
             }
 
-            self.decurse();
-            return Some(Node::Nodes(nodes));
+            return self.decurse_with(Node::Nodes(nodes));
         }
         self.current_p = p;
 
@@ -3186,13 +7911,44 @@ impl Parser {
         if let Some(n_arg_value_0) = self.p_arg_value() {
             if let Some(t_assoc) = self.match_1_token(Token::T_ASSOC) {
                 if let Some(n_arg_value_2) = self.p_arg_value() {
-                    self.decurse();
-                    return Some(Node::Pair {key: box n_arg_value_0, value: box n_arg_value_2 });
+                    return self.decurse_with(node::pair(n_arg_value_0, n_arg_value_2));
                 }
             }
         }
         self.current_p = p;
 
+        if let Some(Token::T_LABLE(key)) = self.current_token() {
+            self.consume_current_token();
+
+            if let Some(n_arg_value) = self.p_arg_value() {
+                return self.decurse_with(node::pair_keyword(key, n_arg_value));
+            }
+        }
+        self.current_p = p;
+
+        // NOTE from a later whitequark/parser's ruby31.y, where `{x:}` came with 3.1
+        //                 | tLABEL
+        //                     {
+        //                       result = @builder.pair_label(val[0])
+        //                     }
+        if let Some(Token::T_LABLE(key)) = self.current_token() {
+            self.consume_current_token();
+
+            let range = self.tokens[p].1;
+            self.check_version("hash value omission", RubyVersion::Ruby31, range);
+
+            let node = node::pair_label(key, self.lexer.static_env().unwrap());
+            return self.decurse_with(node);
+        }
+        self.current_p = p;
+
+        if let Some(_t_dstar) = self.match_1_token(Token::T_DSTAR) {
+            if let Some(n_arg_value) = self.p_arg_value() {
+                return self.decurse_with(node::kwsplat(n_arg_value));
+            }
+        }
+        self.current_p = p;
+
         self.decurse();
         None
     }
@@ -3200,6 +7956,38 @@ impl Parser {
     //        operation: tIDENTIFIER | tCONSTANT | tFID
     //       operation2: tIDENTIFIER | tCONSTANT | tFID | op
     //       operation3: tIDENTIFIER | tFID | op
+    // NOTE return Node::Ident, the name being the source of the token, like p_fname
+    fn p_operation(&mut self) -> Option<Node> {
+        self.p_operation_by("p_operation", is_operation)
+    }
+
+    fn p_operation2(&mut self) -> Option<Node> {
+        self.p_operation_by("p_operation2", is_operation2)
+    }
+
+    fn p_operation3(&mut self) -> Option<Node> {
+        self.p_operation_by("p_operation3", is_operation3)
+    }
+
+    fn p_operation_by(&mut self, rule: &str, is_operation: fn(&Token) -> bool) -> Option<Node> {
+        self.recurse(rule);
+        let p = self.current_p;
+
+        let matched = match self.current_token() {
+            Some(ref token) => is_operation(token),
+            None => false
+        };
+        if matched {
+            self.consume_current_token();
+            let name = self.token_source(p);
+            return self.decurse_with(Node::Ident(name));
+        }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
     //     dot_or_colon: call_op | tCOLON2
     //          call_op: tDOT
     //                     {
@@ -3209,6 +7997,30 @@ impl Parser {
     //                     {
     //                       result = [:anddot, val[0][1]]
     //                     }
+    // TODO call_op, returns Node::Dummy for now
+    fn p_dot_or_colon(&mut self) -> Option<Node> {
+        self.recurse("p_dot_or_colon");
+        let p = self.current_p;
+
+        if let Some(_t_dot) = self.match_1_token(Token::T_DOT) { return self.decurse_with(Node::Dummy); }
+        self.current_p = p;
+
+        if let Some(_t_anddot) = self.match_1_token(Token::T_ANDDOT) { return self.decurse_with(Node::Dummy); }
+        self.current_p = p;
+
+        if let Some(_t_colon2) = self.match_1_token(Token::T_COLON2) { return self.decurse_with(Node::Dummy); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    // NOTE call_op as its token, which tells call_method a csend from a send
+    fn match_call_op(&mut self) -> Option<Token> {
+        if let Some(t_dot) = self.match_1_token(Token::T_DOT) { return Some(t_dot); }
+        if let Some(t_anddot) = self.match_1_token(Token::T_ANDDOT) { return Some(t_anddot); }
+        None
+    }
 
     //        opt_terms:  | terms
     // NOTE the null branch will be handled in parent node
@@ -3217,7 +8029,7 @@ impl Parser {
         let p = self.current_p;
 
         if let Some(n_terms) = self.p_terms() {
-            self.decurse(); return Some(n_terms);
+            return self.decurse_with(n_terms);
         }
         self.current_p = p;
 
@@ -3230,11 +8042,51 @@ impl Parser {
     //                     {
     //                       result = val[1]
     //                     }
+    fn p_rparen(&mut self) -> Option<Node> {
+        self.recurse("p_rparen");
+        let p = self.current_p;
+
+        self.match_1_token(Token::T_NL);
+        if let Some(_t_rparen) = self.match_1_token(Token::T_RPAREN) { return self.decurse_with(Node::Dummy); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
+    // NOTE from a later whitequark/parser, for the hash patterns of p_expr_basic
+    //           rbrace: opt_nl tRCURLY
+    //                     {
+    //                       result = val[1]
+    //                     }
+    fn p_rbrace(&mut self) -> Option<Node> {
+        self.recurse("p_rbrace");
+        let p = self.current_p;
+
+        self.match_1_token(Token::T_NL);
+        if let Some(_t_rcurly) = self.match_1_token(Token::T_RCURLY) { return self.decurse_with(Node::Dummy); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
     //         rbracket: opt_nl tRBRACK
     //                     {
     //                       result = val[1]
     //                     }
-    // 
+    fn p_rbracket(&mut self) -> Option<Node> {
+        self.recurse("p_rbracket");
+        let p = self.current_p;
+
+        self.match_1_token(Token::T_NL);
+        if let Some(_t_rbrack) = self.match_1_token(Token::T_RBRACK) { return self.decurse_with(Node::Dummy); }
+        self.current_p = p;
+
+        self.decurse();
+        None
+    }
+
     //          trailer:  | tNL | tCOMMA
     // TODO handle option none
     // NOTE consume token
@@ -3242,10 +8094,10 @@ impl Parser {
         self.recurse("p_trailer");
         let p = self.current_p;
 
-        if let Some(t_nl) = self.match_1_token(Token::T_NL) { self.decurse(); return Some(Node::Dummy); }
+        if let Some(t_nl) = self.match_1_token(Token::T_NL) { return self.decurse_with(Node::Dummy); }
         self.current_p = p;
 
-        if let Some(t_tomma) = self.match_1_token(Token::T_COMMA) { self.decurse(); return Some(Node::Dummy); }
+        if let Some(t_tomma) = self.match_1_token(Token::T_COMMA) { return self.decurse_with(Node::Dummy); }
         self.current_p = p;
 
         self.decurse();
@@ -3263,12 +8115,12 @@ impl Parser {
         let p = self.current_p;
 
         if let Some(t_semi) = self.match_1_token(Token::T_SEMI) {
-            self.decurse(); return Some(Node::Dummy);
+            return self.decurse_with(Node::Dummy);
         }
         self.current_p = p;
 
         if let Some(t_nl) = self.match_1_token(Token::T_NL) {
-            self.decurse(); return Some(Node::Dummy);
+            return self.decurse_with(Node::Dummy);
         }
         self.current_p = p;
 
@@ -3302,7 +8154,7 @@ impl Parser {
                     break;
                 }
             }
-            self.decurse(); return Some(Node::Nodes(n_terms));
+            return self.decurse_with(Node::Nodes(n_terms));
         }
         self.current_p = p;
 
@@ -1,8 +1,25 @@
+use num_rational::Ratio;
+use num_bigint::BigInt;
+
+use source::range::Range;
+
 pub type TokenString = String;
 
+// token along with its begin/end offsets in source
+// like whitequark's [ :tIDENTIFIER, [ "foo", range ] ], the value lives in Token itself
+pub type TokenWithRange = (Token, Range);
+
 #[derive( Debug, Clone, PartialEq )]
 pub enum Token {
     T_INTEGER( isize ),
+    // integer which doesn't fit in isize
+    T_BIG_INTEGER( BigInt ),
+    T_FLOAT( f64 ),
+    T_RATIONAL( Ratio<isize> ),
+    // rational whose numerator or denominator doesn't fit in isize
+    T_BIG_RATIONAL( Ratio<BigInt> ),
+    // Complex(0, value), value being one of T_INTEGER, T_BIG_INTEGER, T_FLOAT, T_RATIONAL and T_BIG_RATIONAL
+    T_IMAGINARY( Box<Token> ),
     T_GVAR( TokenString ),
     T_CONSTANT( TokenString ),
     T_IDENTIFIER( TokenString ),
@@ -14,7 +31,9 @@ pub enum Token {
     T_SYMBOL( TokenString ),
 
     T_STRING( TokenString ),
+    T_CHARACTER( TokenString ),
     T_STRING_CONTENT( TokenString ),
+    T_REGEXP_OPT( TokenString ),
 
     T_STRING_BEG,
     T_STRING_END,
@@ -139,4 +158,7 @@ pub enum Token {
     T_UMINUS,
     T_UPLUS,
     T_SPACE,
+
+    // NOTE not in whitequark/parser, the unexpected char emitted by an error-tolerant lexer, see Lexer::set_error_tolerant
+    T_ERROR( TokenString ),
 }
@@ -1,44 +1,39 @@
-// TODO
-// move into parser
+// NOTE
+// the lexer holds it like whitequark's `@lexer.static_env = @static_env`, the parser updates it through Parser::update_lexer,
+// every change is logged, so that the lexer's checkpoint keeps how long the log was instead of a copy,
+// and rewinding undoes the changes after that, see Lexer::checkpoint
 
-// module Parser
-
-//   class StaticEnvironment
-//     def extend_static
-//       @stack.push(@variables)
-//       @variables = Set[]
-
-//       self
-//     end
-
-//     def extend_dynamic
-//       @stack.push(@variables)
-//       @variables = @variables.dup
-
-//       self
-//     end
-
-//     def unextend
-//       @variables = @stack.pop
+use std::collections::HashSet;
+use std::mem;
 
-//       self
-//     end
+// NOTE it's a constant name, so no local variable takes it
+const FORWARD_ARGS: &str = "FORWARD_ARGS";
 
-//     def declare(name)
-//       @variables.add(name.to_sym)
+// module Parser
 
-//       self
+//   class StaticEnvironment
+//     def initialize
+//       reset
 //     end
-//   end
-// end
-
-use std::collections::HashSet;
 
+//     def reset
 //       @variables = Set[]
 //       @stack     = []
+//     end
+#[derive(Debug, Clone, PartialEq)]
 pub struct StaticEnv {
     variables: HashSet<String>,
     stack: Vec<HashSet<String>>,
+    // the changes so far, see rewind
+    log: Vec<Change>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Change {
+    Extended,
+    // the variables of the scope left
+    Unextended(HashSet<String>),
+    Declared(String),
 }
 
 impl StaticEnv {
@@ -46,13 +41,110 @@ impl StaticEnv {
         StaticEnv {
             variables: HashSet::new(),
             stack: vec![],
+            log: vec![],
         }
     }
 
+    //     def extend_static
+    //       @stack.push(@variables)
+    //       @variables = Set[]
+    //
+    //       self
+    //     end
+    pub fn extend_static(&mut self) {
+        let variables = mem::take(&mut self.variables);
+        self.stack.push(variables);
+        self.log.push(Change::Extended);
+    }
+
+    //     def extend_dynamic
+    //       @stack.push(@variables)
+    //       @variables = @variables.dup
+    //
+    //       self
+    //     end
+    pub fn extend_dynamic(&mut self) {
+        let variables = self.variables.clone();
+        self.stack.push(variables);
+        self.log.push(Change::Extended);
+    }
+
+    //     def unextend
+    //       @variables = @stack.pop
+    //
+    //       self
+    //     end
+    pub fn unextend(&mut self) {
+        let variables = self.stack.pop().expect("StaticEnv::unextend without a scope to leave");
+        let inner_variables = mem::replace(&mut self.variables, variables);
+        self.log.push(Change::Unextended(inner_variables));
+    }
+
+    //     def declare(name)
+    //       @variables.add(name.to_sym)
+    //
+    //       self
+    //     end
+    pub fn declare(&mut self, name: &str) {
+        if self.has_declared(String::from(name)) { return; }
+        self.variables.insert(String::from(name));
+        self.log.push(Change::Declared(String::from(name)));
+    }
+
     //     def declared?(name)
     //       @variables.include?(name.to_sym)
     //     end
     pub fn has_declared(&self, name: String) -> bool {
         self.variables.contains(&name)
     }
+
+    // NOTE from a later whitequark/parser, where `...` came with 2.7
+    //     FORWARD_ARGS = :FORWARD_ARGS
+    //
+    //     def declare_forward_args
+    //       declare(FORWARD_ARGS)
+    //
+    //       self
+    //     end
+    pub fn declare_forward_args(&mut self) {
+        self.declare(FORWARD_ARGS);
+    }
+
+    //     def declared_forward_args?
+    //       declared?(FORWARD_ARGS)
+    //     end
+    pub fn has_declared_forward_args(&self) -> bool {
+        self.has_declared(String::from(FORWARD_ARGS))
+    }
+
+    // how many scopes the current one is within, see MaxNumparamStack
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    // how many changes there have been, to rewind to later
+    pub fn log_len(&self) -> usize {
+        self.log.len()
+    }
+
+    // undo the changes made after the log was log_len long
+    pub fn rewind(&mut self, log_len: usize) {
+        while self.log.len() > log_len {
+            match self.log.pop().unwrap() {
+                Change::Extended => {
+                    self.variables = self.stack.pop().unwrap();
+                },
+                Change::Unextended(inner_variables) => {
+                    let variables = mem::replace(&mut self.variables, inner_variables);
+                    self.stack.push(variables);
+                },
+                Change::Declared(name) => {
+                    self.variables.remove(&name);
+                },
+            }
+        }
+    }
 }
+
+//   end
+// end
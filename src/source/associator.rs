@@ -0,0 +1,317 @@
+// based on
+// https://github.com/whitequark/parser/blob/2a73841d6da04a5ab9bd270561165fd766722d43/lib/parser/source/comment/associator.rb
+
+use regex::Regex;
+
+use source::buffer::{SourceBuffer, ENCODING_RE};
+use source::comment::Comment;
+use ast::located::LocatedNode;
+use ast::node::Node;
+
+lazy_static! {
+    static ref ENCODING_COMMENT_RE: Regex = Regex::new(ENCODING_RE).unwrap();
+}
+
+// module Parser
+//   module Source
+//
+//     ##
+//     # A processor which associates AST nodes with comments based on their
+//     # location in source code. It may be used, for example, to implement
+//     # rdoc-style processing.
+//     #
+//     # @example
+//     #   require 'parser/current'
+//     #
+//     #   ast, comments = Parser::CurrentRuby.parse_with_comments(<<-CODE)
+//     #   # Class stuff
+//     #   class Foo
+//     #     # Attr stuff
+//     #     # @see bar
+//     #     attr_accessor :foo
+//     #   end
+//     #   CODE
+//     #
+//     #   p Parser::Source::Comment.associate(ast, comments)
+//     #   # => {
+//     #   #   (class (const nil :Foo) ...) =>
+//     #   #     [#<Parser::Source::Comment (string):1:1 "# Class stuff">],
+//     #   #   (send nil :attr_accessor (sym :foo)) =>
+//     #   #     [#<Parser::Source::Comment (string):3:3 "# Attr stuff">,
+//     #   #      #<Parser::Source::Comment (string):4:3 "# @see bar">]
+//     #   # }
+//     #
+//     # @see {associate}
+//     #
+//     # @!attribute skip_directives
+//     #  Skip file processing directives disguised as comments.
+//     #  Namely:
+//     #
+//     #    * Shebang line,
+//     #    * Magic encoding comment.
+//     #
+//     #  @return [Boolean]
+//     #
+//     # @api public
+//     #
+//     class Comment::Associator
+//       attr_accessor :skip_directives
+pub struct Associator<'a, 'c> {
+    ast: &'a LocatedNode,
+    comments: &'c [Comment],
+    source_buffer: &'c SourceBuffer,
+
+    pub skip_directives: bool,
+
+    mapping: Vec<Association<'a>>,
+    comment_num: usize,
+}
+
+// NOTE
+// whitequark maps each node to a flat list of comments, we keep
+// the comments before the node apart from the ones inside or after it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Association<'a> {
+    pub node: &'a LocatedNode,
+    pub leading: Vec<Comment>,
+    pub trailing: Vec<Comment>,
+}
+
+impl<'a, 'c> Associator<'a, 'c> {
+    //       ##
+    //       # @param [Parser::AST::Node] ast
+    //       # @param [Array(Parser::Source::Comment)] comments
+    //       def initialize(ast, comments)
+    //         @ast         = ast
+    //         @comments    = comments
+    //
+    //         @skip_directives = true
+    //       end
+    pub fn new(ast: &'a LocatedNode, comments: &'c [Comment], source_buffer: &'c SourceBuffer) -> Associator<'a, 'c> {
+        Associator {
+            ast,
+            comments,
+            source_buffer,
+
+            skip_directives: true,
+
+            mapping: vec![],
+            comment_num: 0,
+        }
+    }
+
+    //       ##
+    //       # Compute a mapping between AST nodes and comments.
+    //       #
+    //       # A comment belongs to a certain node if it begins after end
+    //       # of the previous node (if one exists) and ends before beginning of
+    //       # the current node.
+    //       #
+    //       # This rule is unambiguous and produces the result
+    //       # one could reasonably expect; for example, this code
+    //       #
+    //       #     # foo
+    //       #     hoge # bar
+    //       #       + fuga
+    //       #
+    //       # will result in the following association:
+    //       #
+    //       #     {
+    //       #       (send (lvar :hoge) :+ (lvar :fuga)) =>
+    //       #         [#<Parser::Source::Comment (string):2:1 "# foo">],
+    //       #       (lvar :fuga) =>
+    //       #         [#<Parser::Source::Comment (string):3:8 "# bar">]
+    //       #     }
+    //       #
+    //       # @return [Hash(Parser::AST::Node, Array(Parser::Source::Comment))]
+    //       #
+    //       def associate
+    //         @mapping     = Hash.new { |h, k| h[k] = [] }
+    //         @comment_num = -1
+    //         advance_comment
+    //
+    //         advance_through_directives if @skip_directives
+    //
+    //         visit(@ast) if @ast
+    //
+    //         @mapping
+    //       end
+    //
+    // NOTE nodes are listed in the order they got their first comment
+    pub fn associate(&mut self) -> Vec<Association<'a>> {
+        self.mapping = vec![];
+        self.comment_num = 0;
+
+        if self.skip_directives { self.advance_through_directives(); }
+
+        let ast = self.ast;
+        self.visit(ast);
+
+        self.mapping.clone()
+    }
+
+    // NOTE
+    // this version of whitequark/parser associates a comment with the next node only,
+    // visiting and the trailing comments follow the later versions, like
+    //
+    //       def visit(node)
+    //         process_leading_comments(node)
+    //
+    //         node.children.each do |child|
+    //           next unless child.is_a?(AST::Node) && child.loc && child.loc.expression
+    //
+    //           visit(child)
+    //         end
+    //
+    //         process_trailing_comments(node)
+    //       end
+    fn visit(&mut self, node: &'a LocatedNode) {
+        self.process_leading_comments(node);
+
+        for child in &node.children {
+            self.visit(child);
+        }
+
+        self.process_trailing_comments(node);
+    }
+
+    //       def process_leading_comments(node)
+    //         return if node.type == :begin
+    //         while current_comment_before?(node)
+    //           associate_and_advance_comment(node)
+    //         end
+    //       end
+    fn process_leading_comments(&mut self, node: &'a LocatedNode) {
+        if let Node::Begin(_) = node.node { return; }
+
+        while self.current_comment_before(node) {
+            self.associate_and_advance_comment(node, true);
+        }
+    }
+
+    //       def process_trailing_comments(node)
+    //         while current_comment_before_end?(node)
+    //           associate_and_advance_comment(node)
+    //         end
+    //
+    //         while current_comment_decorates?(node)
+    //           associate_and_advance_comment(node)
+    //         end
+    //       end
+    fn process_trailing_comments(&mut self, node: &'a LocatedNode) {
+        while self.current_comment_before_end(node) {
+            self.associate_and_advance_comment(node, false);
+        }
+
+        while self.current_comment_decorates(node) {
+            self.associate_and_advance_comment(node, false);
+        }
+    }
+
+    //       def advance_comment
+    //         @comment_num += 1
+    //         @current_comment = @comments[@comment_num]
+    //       end
+    fn advance_comment(&mut self) {
+        self.comment_num += 1;
+    }
+
+    fn current_comment(&self) -> Option<&'c Comment> {
+        self.comments.get(self.comment_num)
+    }
+
+    //       def current_comment_before?(node)
+    //         return false if !@current_comment
+    //         comment_loc = @current_comment.location.expression
+    //         node_loc = node.location.expression
+    //         comment_loc.end_pos <= node_loc.begin_pos
+    //       end
+    fn current_comment_before(&self, node: &LocatedNode) -> bool {
+        match self.current_comment() {
+            Some(comment) => comment.range.end_pos <= node.expression.begin_pos,
+            None => false
+        }
+    }
+
+    //       def current_comment_before_end?(node)
+    //         return false if !@current_comment
+    //         comment_loc = @current_comment.location.expression
+    //         node_loc = node.location.expression
+    //         comment_loc.end_pos <= node_loc.end_pos
+    //       end
+    fn current_comment_before_end(&self, node: &LocatedNode) -> bool {
+        match self.current_comment() {
+            Some(comment) => comment.range.end_pos <= node.expression.end_pos,
+            None => false
+        }
+    }
+
+    //       def current_comment_decorates?(node)
+    //         return false if !@current_comment
+    //         @current_comment.location.line == node.location.last_line
+    //       end
+    // NOTE
+    // only when there's nothing but separators between the node and the comment,
+    // otherwise in `foo; bar # baz` the comment would go to `foo`
+    fn current_comment_decorates(&self, node: &LocatedNode) -> bool {
+        match self.current_comment() {
+            Some(comment) => {
+                if comment.range.begin_pos < node.expression.end_pos { return false; }
+
+                let (comment_line, _) = self.source_buffer.decompose_position(comment.range.begin_pos);
+                let (node_last_line, _) = self.source_buffer.decompose_position(node.expression.end_pos);
+
+                let is_separated = self.source_buffer.source().get(node.expression.end_pos..comment.range.begin_pos)
+                    .map_or(false, |between| between.chars().all(|c| c == ' ' || c == '\t' || c == ',' || c == ';'));
+
+                comment_line == node_last_line && is_separated
+            },
+            None => false
+        }
+    }
+
+    //       def associate_and_advance_comment(node)
+    //         @mapping[node] << @current_comment
+    //         advance_comment
+    //       end
+    fn associate_and_advance_comment(&mut self, node: &'a LocatedNode, is_leading: bool) {
+        let comment = self.current_comment().unwrap().clone();
+
+        let index = match self.mapping.iter().position(|association| association.node as *const LocatedNode == node as *const LocatedNode) {
+            Some(index) => index,
+            None => {
+                self.mapping.push(Association { node, leading: vec![], trailing: vec![] });
+                self.mapping.len() - 1
+            }
+        };
+
+        if is_leading {
+            self.mapping[index].leading.push(comment);
+        } else {
+            self.mapping[index].trailing.push(comment);
+        }
+
+        self.advance_comment();
+    }
+
+    //       def advance_through_directives
+    //         # Skip shebang.
+    //         if @current_comment && @current_comment.text =~ /^#!/
+    //           advance_comment
+    //         end
+    //
+    //         # Skip encoding line.
+    //         if @current_comment && @current_comment.text =~ Buffer::ENCODING_RE
+    //           advance_comment
+    //         end
+    //       end
+    fn advance_through_directives(&mut self) {
+        if let Some(comment) = self.current_comment() {
+            if comment.text.starts_with("#!") { self.advance_comment(); }
+        }
+
+        if let Some(comment) = self.current_comment() {
+            if ENCODING_COMMENT_RE.is_match(&comment.text) { self.advance_comment(); }
+        }
+    }
+}
@@ -0,0 +1,338 @@
+// based on
+// https://github.com/whitequark/parser/blob/2a73841d6da04a5ab9bd270561165fd766722d43/lib/parser/source/buffer.rb
+
+// NOTE
+// offsets here are byte offsets into the source, like the ones of the lexer,
+// so a range always slices the source on char boundaries
+
+use std::collections::HashMap;
+
+use regex::bytes::Regex;
+
+use source::range::Range;
+use source::encoding::Encoding;
+use diagnostic::{Diagnostic, Level};
+
+//       ENCODING_RE =
+//         /[\s#](en)?coding\s*[:=]\s*
+//           (
+//             # Special-case: there's a UTF8-MAC encoding.
+//             (utf8-mac)
+//           |
+//             # Chew the suffix; it's there for emacs compat.
+//             ([A-Za-z0-9_-]+?)(-unix|-dos|-mac)
+//           |
+//             ([A-Za-z0-9_-]+)
+//           )
+//         /x
+pub const ENCODING_RE: &str = r"[\s#](en)?coding\s*[:=]\s*((utf8-mac)|([A-Za-z0-9_-]+?)(-unix|-dos|-mac)|([A-Za-z0-9_-]+))";
+
+lazy_static! {
+    static ref ENCODING_BYTES_RE: Regex = Regex::new(ENCODING_RE).unwrap();
+}
+
+// module Parser
+//   module Source
+//     class Buffer
+//       attr_reader :name, :first_line
+#[derive(Debug, Clone)]
+pub struct SourceBuffer {
+    pub name: String,
+    pub first_line: usize,
+    // NOTE the source is always kept in UTF-8, this is the encoding it was decoded from
+    pub encoding: Encoding,
+
+    source: String,
+
+    // offset of the first byte of every line
+    line_begins: Vec<usize>,
+
+    // the input as it was given, if it had any `\r\n`, along with where each `\n` of the source turned from one is,
+    // see original_position
+    input: Option<String>,
+    crlf_positions: Vec<usize>,
+}
+
+impl SourceBuffer {
+    //       def initialize(name, first_line = 1)
+    //         @name        = name.to_s
+    //         @source      = nil
+    //         @first_line  = first_line
+    //
+    //         @lines       = nil
+    //         @line_begins = nil
+    //       end
+    //
+    //       def source=(input)
+    //         ...
+    //         @source = input.gsub("\r\n".freeze, "\n".freeze).freeze
+    //       end
+    pub fn new(name: &str, input: String) -> SourceBuffer {
+        let source = input.replace("\r\n", "\n");
+
+        let mut line_begins = vec![0];
+        for (index, byte) in source.bytes().enumerate() {
+            if byte == b'\n' { line_begins.push(index + 1); }
+        }
+
+        let (input, crlf_positions) = if source.len() == input.len() {
+            (None, vec![])
+        } else {
+            let crlf_positions = input.match_indices("\r\n").enumerate()
+                .map(|(crlfs_before, (index, _))| index - crlfs_before)
+                .collect();
+            (Some(input), crlf_positions)
+        };
+
+        SourceBuffer {
+            name: String::from(name),
+            first_line: 1,
+            encoding: Encoding::Utf8,
+            source,
+            line_begins,
+            input,
+            crlf_positions,
+        }
+    }
+
+    //       def self.recognize_encoding(string)
+    //         return if string.empty?
+    //
+    //         # extract the first two lines in an efficient way
+    //         string =~ /\A(.*)\n?(.*\n)?/
+    //         first_line, second_line = $1, $2
+    //
+    //         if first_line =~ /\A\xef\xbb\xbf/ # BOM
+    //           return Encoding::UTF_8
+    //         elsif first_line[0, 2] == '#!'.freeze
+    //           encoding_line = second_line
+    //         else
+    //           encoding_line = first_line
+    //         end
+    //
+    //         return nil if encoding_line.nil? || encoding_line[0] != '#'
+    //
+    //         if (result = ENCODING_RE.match(encoding_line))
+    //           Encoding.find(result[3] || result[4] || result[6])
+    //         else
+    //           nil
+    //         end
+    //       end
+    //
+    // NOTE returns the name and its range instead, the name may be unknown to us
+    pub fn recognize_encoding(source: &[u8]) -> Option<(String, Range)> {
+        if source.is_empty() { return None; }
+
+        let first_line_end = source.iter().position(|byte| *byte == b'\n').unwrap_or(source.len());
+        let first_line = &source[..first_line_end];
+
+        if first_line.starts_with(b"\xef\xbb\xbf") {
+            return Some((String::from("UTF-8"), Range::new(0, 3)));
+        }
+
+        let (encoding_line_begin, encoding_line) = if first_line.starts_with(b"#!") {
+            if first_line_end == source.len() { return None; }
+
+            let second_line_begin = first_line_end + 1;
+            let second_line_end = source[second_line_begin..].iter().position(|byte| *byte == b'\n')
+                .map_or(source.len(), |index| second_line_begin + index);
+            (second_line_begin, &source[second_line_begin..second_line_end])
+        } else {
+            (0, first_line)
+        };
+
+        if !encoding_line.starts_with(b"#") { return None; }
+
+        let captures = ENCODING_BYTES_RE.captures(encoding_line)?;
+        let name = captures.get(3).or(captures.get(4)).or(captures.get(6)).unwrap();
+
+        Some((
+            String::from_utf8_lossy(name.as_bytes()).into_owned(),
+            Range::new(encoding_line_begin + name.start(), encoding_line_begin + name.end())
+        ))
+    }
+
+    //       def self.reencode_string(input)
+    //         original_encoding = input.encoding
+    //         detected_encoding = recognize_encoding(input.force_encoding(Encoding::BINARY))
+    //
+    //         if detected_encoding.nil?
+    //           input.force_encoding(original_encoding)
+    //         elsif detected_encoding == Encoding::BINARY
+    //           input
+    //         else
+    //           input.
+    //             force_encoding(detected_encoding).
+    //             encode(Encoding::UTF_8)
+    //         end
+    //       end
+    //
+    //       def source=(input)
+    //         input = input.dup if input.frozen?
+    //         input = self.class.reencode_string(input)
+    //
+    //         unless input.valid_encoding?
+    //           raise EncodingError, "invalid byte sequence in #{input.encoding.name}"
+    //         end
+    //
+    //         self.raw_source = input
+    //       end
+    //
+    // NOTE
+    // for sources which are not in UTF-8, or not known to be valid,
+    // whitequark raises where we return a fatal diagnostic, the range is in bytes of the input
+    pub fn from_bytes(name: &str, source: Vec<u8>) -> Result<SourceBuffer, Diagnostic> {
+        let encoding = match SourceBuffer::recognize_encoding(&source) {
+            None => Encoding::Utf8,
+            Some((encoding_name, range)) => {
+                match Encoding::find(&encoding_name) {
+                    Some(encoding) => encoding,
+                    None => {
+                        let mut arguments = HashMap::new();
+                        arguments.insert("name", encoding_name);
+                        return Err(Diagnostic::new(Level::Fatal, "unknown_encoding", arguments, range));
+                    }
+                }
+            }
+        };
+
+        match encoding.decode(&source) {
+            Ok(decoded_source) => {
+                let mut source_buffer = SourceBuffer::new(name, decoded_source);
+                source_buffer.encoding = encoding;
+                Ok(source_buffer)
+            },
+            Err(position) => {
+                let mut arguments = HashMap::new();
+                arguments.insert("encoding", String::from(encoding.name()));
+                Err(Diagnostic::new(Level::Fatal, "invalid_byte_sequence", arguments, Range::new(position, position + 1)))
+            }
+        }
+    }
+
+    //       def source
+    //         if @source.nil?
+    //           raise RuntimeError, 'Cannot extract source from uninitialized Source::Buffer'
+    //         end
+    //
+    //         @source
+    //       end
+    pub fn source(&self) -> &String {
+        &self.source
+    }
+
+    // NOTE
+    // the source has `\r\n` turned into `\n`, while ruby reads the data after `__END__` as it was,
+    // these are the input as it was given and where a position of the source is in it
+    pub fn original_source(&self) -> &String {
+        self.input.as_ref().unwrap_or(&self.source)
+    }
+
+    pub fn original_position(&self, position: usize) -> usize {
+        let crlfs_before = match self.crlf_positions.binary_search(&position) {
+            Ok(index) | Err(index) => index
+        };
+        position + crlfs_before
+    }
+
+    //       def decompose_position(position)
+    //         line_index = line_index_for_position(position)
+    //         line_begin = line_begins[line_index]
+    //
+    //         [ @first_line + line_index , position - line_begin ]
+    //       end
+    //
+    // NOTE both line and column are 1-based here
+    pub fn decompose_position(&self, position: usize) -> (usize, usize) {
+        let line_index = self.line_index_for_position(position);
+        let line_begin = self.line_begins[line_index];
+
+        ( self.first_line + line_index, position - line_begin + 1 )
+    }
+
+    //       def line_for_position(position)
+    //         line_index_for_position(position) + @first_line
+    //       end
+    pub fn line_for_position(&self, position: usize) -> usize {
+        self.line_index_for_position(position) + self.first_line
+    }
+
+    //       def column_for_position(position)
+    //         line_index = line_index_for_position(position)
+    //         position - line_begins[line_index]
+    //       end
+    // NOTE 1-based, see decompose_position
+    pub fn column_for_position(&self, position: usize) -> usize {
+        self.decompose_position(position).1
+    }
+
+    //       def source_lines
+    //         @lines ||= begin
+    //           lines = @source.lines.to_a
+    //           lines << ''.dup if @source.end_with?("\n".freeze)
+    //
+    //           lines.each do |line|
+    //             line.chomp!("\n".freeze)
+    //             line.freeze
+    //           end
+    //
+    //           lines.freeze
+    //         end
+    //       end
+    pub fn source_lines(&self) -> Vec<String> {
+        self.source.split('\n').map(String::from).collect()
+    }
+
+    //       def source_line(lineno)
+    //         source_lines.fetch(lineno - @first_line).dup
+    //       end
+    pub fn source_line(&self, lineno: usize) -> String {
+        let range = self.line_range(lineno);
+
+        String::from(&self.source[range.begin_pos..range.end_pos])
+    }
+
+    //       def line_range(lineno)
+    //         index = lineno - @first_line + 1
+    //         if index <= 0 || index > line_begins.size
+    //           raise IndexError, "Parser::Source::Buffer: range for line " \
+    //             "#{lineno} requested, valid line numbers are #{@first_line}.." \
+    //             "#{@first_line + line_begins.size - 1}"
+    //         elsif index == line_begins.size
+    //           Range.new(self, line_begins[-index], @source.size)
+    //         else
+    //           Range.new(self, line_begins[-index], line_begins[-index - 1] - 1)
+    //         end
+    //       end
+    //
+    // NOTE the range doesn't include the trailing newline
+    pub fn line_range(&self, lineno: usize) -> Range {
+        if lineno < self.first_line || lineno - self.first_line >= self.line_begins.len() {
+            panic!("SourceBuffer: range for line {} requested, valid line numbers are {}..{}",
+                lineno, self.first_line, self.first_line + self.line_begins.len() - 1);
+        }
+
+        let index = lineno - self.first_line;
+        let begin_pos = self.line_begins[index];
+        let end_pos = match self.line_begins.get(index + 1) {
+            Some(next_line_begin) => next_line_begin - 1,
+            None => self.source.len()
+        };
+
+        Range::new(begin_pos, end_pos)
+    }
+
+    //       def line_index_for_position(position)
+    //         @line_index_for_position[position] || begin
+    //           index = bsearch(line_begins, position) - 1
+    //           @line_index_for_position[position] = index unless @line_index_for_position.frozen?
+    //           index
+    //         end
+    //       end
+    fn line_index_for_position(&self, position: usize) -> usize {
+        match self.line_begins.binary_search(&position) {
+            Ok(index) => index,
+            Err(index) => index - 1,
+        }
+    }
+}
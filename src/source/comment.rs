@@ -0,0 +1,105 @@
+// based on
+// https://github.com/whitequark/parser/blob/2a73841d6da04a5ab9bd270561165fd766722d43/lib/parser/source/comment.rb
+
+use source::range::Range;
+use source::buffer::SourceBuffer;
+use source::associator::{Associator, Association};
+use ast::located::LocatedNode;
+
+// module Parser
+//   module Source
+//     ##
+//     # A comment in the source code.
+//     #
+//     # @!attribute [r] text
+//     #  @return [String]
+//     #
+//     # @!attribute [r] location
+//     #  @return [Parser::Source::Map]
+//     #
+//     # @api public
+//     #
+//     class Comment
+//       attr_reader  :text
+//
+//       attr_reader  :location
+//       alias_method :loc, :location
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub range: Range,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentType {
+    Inline,
+    Document,
+}
+
+impl Comment {
+    //       def initialize(range)
+    //         @location = Parser::Source::Map.new(range)
+    //         @text     = range.source.freeze
+    //
+    //         freeze
+    //       end
+    pub fn new(range: Range, text: String) -> Comment {
+        Comment { range, text }
+    }
+
+    //       def self.associate(ast, comments)
+    //         associator = Associator.new(ast, comments)
+    //         associator.associate
+    //       end
+    //
+    // NOTE our nodes don't carry locations, see Parser::locate
+    pub fn associate<'a>(ast: &'a LocatedNode, comments: &[Comment], source_buffer: &SourceBuffer) -> Vec<Association<'a>> {
+        let mut associator = Associator::new(ast, comments, source_buffer);
+        associator.associate()
+    }
+
+    //       ##
+    //       # Type of this comment.
+    //       #
+    //       #   * Inline comments correspond to `:inline`:
+    //       #
+    //       #         # whatever
+    //       #
+    //       #   * Block comments correspond to `:document`:
+    //       #
+    //       #         =begin
+    //       #         hi i am a document
+    //       #         =end
+    //       #
+    //       # @return [Symbol]
+    //       #
+    //       def type
+    //         if text.start_with?("#".freeze)
+    //           :inline
+    //         elsif text.start_with?("=begin".freeze)
+    //           :document
+    //         end
+    //       end
+    // NOTE the lexer emits only these two kinds
+    pub fn comment_type(&self) -> CommentType {
+        if self.text.starts_with("=begin") {
+            CommentType::Document
+        } else {
+            CommentType::Inline
+        }
+    }
+
+    //       def inline?
+    //         type == :inline
+    //       end
+    pub fn is_inline(&self) -> bool {
+        self.comment_type() == CommentType::Inline
+    }
+
+    //       def document?
+    //         type == :document
+    //       end
+    pub fn is_document(&self) -> bool {
+        self.comment_type() == CommentType::Document
+    }
+}
@@ -0,0 +1,82 @@
+// NOTE
+// whitequark leaves encodings to Ruby, which knows every one of them,
+// we only know how to decode the usual ones of Ruby sources into UTF-8, see SourceBuffer::from_bytes
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    UsAscii,
+    // ASCII-8BIT
+    Binary,
+    Iso8859_1,
+    Windows1252,
+}
+
+// Windows-1252 differs from ISO-8859-1 only in 0x80..0x9F, 0 for the unassigned ones
+const WINDOWS_1252_HIGH: [u32; 32] = [
+    0x20AC, 0,      0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021,
+    0x02C6, 0x2030, 0x0160, 0x2039, 0x0152, 0,      0x017D, 0,
+    0,      0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014,
+    0x02DC, 0x2122, 0x0161, 0x203A, 0x0153, 0,      0x017E, 0x0178,
+];
+
+impl Encoding {
+    // like Encoding.find, names are case-insensitive
+    pub fn find(name: &str) -> Option<Encoding> {
+        match name.to_lowercase().as_str() {
+            "utf-8" | "cp65001" |
+            "utf8-mac" | "utf-8-mac" | "utf-8-hfs" => Some(Encoding::Utf8),
+            "us-ascii" | "ascii" | "ansi_x3.4-1968" | "646" => Some(Encoding::UsAscii),
+            "ascii-8bit" | "binary" => Some(Encoding::Binary),
+            "iso-8859-1" | "iso8859-1" => Some(Encoding::Iso8859_1),
+            "windows-1252" | "cp1252" => Some(Encoding::Windows1252),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::UsAscii => "US-ASCII",
+            Encoding::Binary => "ASCII-8BIT",
+            Encoding::Iso8859_1 => "ISO-8859-1",
+            Encoding::Windows1252 => "Windows-1252",
+        }
+    }
+
+    // decode into UTF-8, or the offset of the first invalid byte
+    //
+    // NOTE
+    // ASCII-8BIT is decoded like ISO-8859-1, so every byte survives as a char,
+    // string literals will hold the chars instead of the raw bytes
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, usize> {
+        match *self {
+            Encoding::Utf8 => {
+                String::from_utf8(bytes.to_vec()).map_err(|error| error.utf8_error().valid_up_to())
+            },
+            Encoding::UsAscii => {
+                match bytes.iter().position(|byte| *byte >= 0x80) {
+                    Some(position) => Err(position),
+                    None => Ok(bytes.iter().map(|byte| *byte as char).collect())
+                }
+            },
+            Encoding::Binary | Encoding::Iso8859_1 => {
+                Ok(bytes.iter().map(|byte| *byte as char).collect())
+            },
+            Encoding::Windows1252 => {
+                let mut string = String::with_capacity(bytes.len());
+                for (position, byte) in bytes.iter().enumerate() {
+                    let codepoint = match *byte {
+                        0x80..=0x9F => WINDOWS_1252_HIGH[(*byte - 0x80) as usize],
+                        byte => byte as u32,
+                    };
+                    match ::std::char::from_u32(codepoint) {
+                        Some(character) if codepoint != 0 || *byte == 0 => string.push(character),
+                        _ => { return Err(position); }
+                    }
+                }
+                Ok(string)
+            },
+        }
+    }
+}
@@ -0,0 +1,158 @@
+// NOTE
+// whitequark leaves magic comments to Ruby except the encoding one, see SourceBuffer::recognize_encoding,
+// the rules here follow parse.y: the encoding comment lives on the first line (the second after a shebang),
+// frozen_string_literal is ignored after any tokens, warn_indent may appear on any line of its own
+
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use source::range::Range;
+use source::buffer::{SourceBuffer, ENCODING_RE};
+use source::comment::Comment;
+use diagnostic::{Diagnostic, Level};
+
+lazy_static! {
+    static ref ENCODING_COMMENT_RE: Regex = Regex::new(ENCODING_RE).unwrap();
+    static ref EMACS_RE: Regex = Regex::new(r"-\*-(.*?)-\*-").unwrap();
+    static ref PAIR_RE: Regex = Regex::new(r"^\s*([\w-]+)\s*:\s*([^\s;]+)\s*$").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MagicCommentKind {
+    Encoding,
+    FrozenStringLiteral,
+    WarnIndent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MagicComment {
+    pub kind: MagicCommentKind,
+    pub value: String,
+    // range of the value
+    pub range: Range,
+}
+
+impl MagicComment {
+    // the value of frozen_string_literal and warn_indent, case-insensitive like ruby
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.value.to_lowercase().as_str() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None
+        }
+    }
+}
+
+// magic comments among comments, in source order
+//
+// first_token_pos is the position of the first token, if any
+pub fn recognize(comments: &[Comment], first_token_pos: Option<usize>, source_buffer: &SourceBuffer, diagnostics: &mut Vec<Diagnostic>) -> Vec<MagicComment> {
+    let mut magic_comments = vec![];
+
+    for comment in comments {
+        if !comment.is_inline() || !is_on_own_line(comment, source_buffer) { continue; }
+
+        let is_before_tokens = match first_token_pos {
+            Some(first_token_pos) => comment.range.end_pos <= first_token_pos,
+            None => true
+        };
+
+        if is_before_tokens && is_encoding_line(comment, source_buffer) {
+            if let Some(captures) = ENCODING_COMMENT_RE.captures(&comment.text) {
+                let name = captures.get(3).or(captures.get(4)).or(captures.get(6)).unwrap();
+                magic_comments.push(MagicComment {
+                    kind: MagicCommentKind::Encoding,
+                    value: String::from(name.as_str()),
+                    range: Range::new(comment.range.begin_pos + name.start(), comment.range.begin_pos + name.end()),
+                });
+            }
+        }
+
+        for (key, value, range) in magic_pairs(comment) {
+            let kind = match key.as_str() {
+                "frozen_string_literal" => MagicCommentKind::FrozenStringLiteral,
+                "warn_indent" => MagicCommentKind::WarnIndent,
+                _ => { continue; }
+            };
+
+            // NOTE ruby warns and ignores it
+            if kind == MagicCommentKind::FrozenStringLiteral && !is_before_tokens {
+                let mut arguments = HashMap::new();
+                arguments.insert("name", key.clone());
+                diagnostics.push(Diagnostic::new(Level::Warning, "magic_comment_ignored", arguments, comment.range));
+                continue;
+            }
+
+            let magic_comment = MagicComment { kind, value, range };
+
+            // NOTE ruby warns and ignores it as well
+            if magic_comment.as_bool().is_none() {
+                let mut arguments = HashMap::new();
+                arguments.insert("name", key.clone());
+                arguments.insert("value", magic_comment.value.clone());
+                diagnostics.push(Diagnostic::new(Level::Warning, "invalid_magic_value", arguments, range));
+                continue;
+            }
+
+            magic_comments.push(magic_comment);
+        }
+    }
+
+    magic_comments
+}
+
+// nothing but whitespaces before the comment on its line
+fn is_on_own_line(comment: &Comment, source_buffer: &SourceBuffer) -> bool {
+    let (lineno, _) = source_buffer.decompose_position(comment.range.begin_pos);
+    let line_begin = source_buffer.line_range(lineno).begin_pos;
+
+    source_buffer.source().get(line_begin..comment.range.begin_pos)
+        .map_or(false, |before| before.chars().all(|c| c == ' ' || c == '\t'))
+}
+
+// the first line, or the second one after a shebang
+fn is_encoding_line(comment: &Comment, source_buffer: &SourceBuffer) -> bool {
+    let (lineno, _) = source_buffer.decompose_position(comment.range.begin_pos);
+
+    if lineno == source_buffer.first_line { return true; }
+
+    lineno == source_buffer.first_line + 1 && source_buffer.source().starts_with("#!")
+}
+
+// `key: value` pairs of either `# key: value` or the emacs style `# -*- key: value; key: value -*-`,
+// with keys normalized like `frozen_string_literal`
+fn magic_pairs(comment: &Comment) -> Vec<(String, String, Range)> {
+    let text = &comment.text;
+    let mut pairs = vec![];
+
+    let mut parts: Vec<(usize, &str)> = vec![];
+    match EMACS_RE.captures(text) {
+        Some(captures) => {
+            let inner = captures.get(1).unwrap();
+            let mut part_begin = inner.start();
+            for part in inner.as_str().split(';') {
+                parts.push((part_begin, part));
+                part_begin += part.len() + 1;
+            }
+        },
+        None => {
+            parts.push((1, &text[1..]));
+        }
+    }
+
+    for (part_begin, part) in parts {
+        if let Some(captures) = PAIR_RE.captures(part) {
+            let key = captures.get(1).unwrap().as_str().to_lowercase().replace('-', "_");
+            let value = captures.get(2).unwrap();
+            let range = Range::new(
+                comment.range.begin_pos + part_begin + value.start(),
+                comment.range.begin_pos + part_begin + value.end()
+            );
+
+            pairs.push((key, String::from(value.as_str()), range));
+        }
+    }
+
+    pairs
+}
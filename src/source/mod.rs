@@ -0,0 +1,6 @@
+pub mod range;
+pub mod buffer;
+pub mod encoding;
+pub mod comment;
+pub mod associator;
+pub mod magic_comment;
@@ -0,0 +1,50 @@
+// based on
+// https://github.com/whitequark/parser/blob/2a73841d6da04a5ab9bd270561165fd766722d43/lib/parser/source/range.rb
+
+// module Parser
+//   module Source
+//     class Range
+//       attr_reader :source_buffer
+//       attr_reader :begin_pos, :end_pos
+// 
+// NOTE
+// unlike the original one, a range doesn't refer to its source buffer,
+// it's just a pair of offsets, `end_pos` being exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Range {
+    pub begin_pos: usize,
+    pub end_pos: usize,
+}
+
+impl Range {
+    //       def initialize(source_buffer, begin_pos, end_pos)
+    //         if end_pos < begin_pos
+    //           raise ArgumentError, 'Parser::Source::Range: end_pos must not be less than begin_pos'
+    //         end
+    pub fn new(begin_pos: usize, end_pos: usize) -> Range {
+        if end_pos < begin_pos {
+            panic!("Range: end_pos must not be less than begin_pos");
+        }
+
+        Range { begin_pos, end_pos }
+    }
+
+    //       def size
+    //         @end_pos - @begin_pos
+    //       end
+    pub fn size(&self) -> usize {
+        self.end_pos - self.begin_pos
+    }
+
+    //       def join(other)
+    //         Range.new(@source_buffer,
+    //             [@begin_pos, other.begin_pos].min,
+    //             [@end_pos,   other.end_pos].max)
+    //       end
+    pub fn join(&self, other: &Range) -> Range {
+        Range::new(
+            if self.begin_pos < other.begin_pos { self.begin_pos } else { other.begin_pos },
+            if self.end_pos > other.end_pos { self.end_pos } else { other.end_pos }
+        )
+    }
+}
@@ -0,0 +1,28 @@
+// NOTE
+// the lexer and the parser used to println! whatever they were doing,
+// now they hand these events to a tracer instead, if one is set, see Parser::set_tracer and Lexer::set_tracer
+
+use std::rc::Rc;
+
+use lexer::lexing_state::LexingState;
+use parser::token::Token;
+use source::range::Range;
+use diagnostic::Diagnostic;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEvent {
+    // lexer switched to another machine
+    StateTransition { from: LexingState, to: LexingState },
+    // the action chosen by longest match in current machine, with its pattern and the slice it matched
+    ActionMatched { state: LexingState, pattern: String, slice: String, range: Range },
+    TokenEmitted { token: Token, range: Range },
+    Diagnostic(Diagnostic),
+
+    // a parser rule started or finished at the token position p,
+    // matched tells if the rule produced a node or gave up
+    RuleEnter { rule: String, p: usize },
+    RuleExit { rule: String, p: usize, matched: bool },
+}
+
+// NOTE an Rc, so the parser and its lexer can share one
+pub type Tracer = Rc<Fn(&TraceEvent)>;
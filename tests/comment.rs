@@ -0,0 +1,150 @@
+// based on
+// https://github.com/whitequark/parser/blob/2a73841d6da04a5ab9bd270561165fd766722d43/test/test_source_comment.rb
+// https://github.com/whitequark/parser/blob/2a73841d6da04a5ab9bd270561165fd766722d43/test/test_source_comment_associator.rb
+
+extern crate ruby_on_rust;
+
+use ruby_on_rust::parser::parser::Parser;
+use ruby_on_rust::ast::node::Node;
+use ruby_on_rust::source::range::Range;
+use ruby_on_rust::source::comment::{Comment, CommentType};
+use ruby_on_rust::source::associator::Associator;
+
+// helpers
+
+// parse and associate, returning (node, expression, leading texts, trailing texts) of every association
+macro_rules! associate {
+    ($content:expr) => {{
+        let mut parser = Parser::new(String::from($content));
+        let (node, comments) = parser.parse_with_comments().unwrap();
+        let located = parser.locate(&node).unwrap();
+
+        Comment::associate(&located, &comments, parser.source_buffer()).iter().map(|association| {
+            (
+                association.node.node.clone(),
+                association.node.expression,
+                association.leading.iter().map(|comment| comment.text.clone()).collect::<Vec<String>>(),
+                association.trailing.iter().map(|comment| comment.text.clone()).collect::<Vec<String>>(),
+            )
+        }).collect::<Vec<_>>()
+    }};
+}
+
+fn texts(texts: &[&str]) -> Vec<String> {
+    texts.iter().map(|text| String::from(*text)).collect()
+}
+
+#[test]
+fn comment_type() {
+    let mut parser = Parser::new(String::from("# foo\n=begin\nbar\n=end\nbaz"));
+    let (node, comments) = parser.parse_with_comments().unwrap();
+
+    assert_eq!(node, Node::Send { receiver: None, selector: String::from("baz"), args: vec![] });
+
+    assert_eq!(comments.len(), 2);
+    assert_eq!(comments[0].range, Range::new(0, 5));
+    assert_eq!(comments[0].comment_type(), CommentType::Inline);
+    assert!(comments[0].is_inline());
+    assert_eq!(comments[1].range, Range::new(6, 22));
+    assert_eq!(comments[1].comment_type(), CommentType::Document);
+    assert!(comments[1].is_document());
+}
+
+#[test]
+fn locate() {
+    let mut parser = Parser::new(String::from("a = [1, 2]\nb"));
+    let (node, _) = parser.parse_with_comments().unwrap();
+    let located = parser.locate(&node).unwrap();
+
+    assert_eq!(located.expression, Range::new(0, 12));
+    assert_eq!(located.children[0].expression, Range::new(0, 10));
+    assert_eq!(located.children[0].children[0].expression, Range::new(4, 10));
+    assert_eq!(located.children[0].children[0].children[0].expression, Range::new(5, 6));
+    assert_eq!(located.children[0].children[0].children[1].expression, Range::new(8, 9));
+    assert_eq!(located.children[1].expression, Range::new(11, 12));
+}
+
+#[test]
+fn locate_modifier() {
+    let mut parser = Parser::new(String::from("bar if foo"));
+    let (node, _) = parser.parse_with_comments().unwrap();
+    let located = parser.locate(&node).unwrap();
+
+    assert_eq!(located.expression, Range::new(0, 10));
+    assert_eq!(located.children[0].expression, Range::new(0, 3));
+    assert_eq!(located.children[1].expression, Range::new(7, 10));
+}
+
+// an equal node elsewhere isn't taken for the child
+#[test]
+fn locate_equal_nodes() {
+    let mut parser = Parser::new(String::from("a = 1 if 1 # c"));
+    let (node, _) = parser.parse_with_comments().unwrap();
+    let located = parser.locate(&node).unwrap();
+
+    assert_eq!(located.expression, Range::new(0, 10));
+    assert_eq!(located.children[0].expression, Range::new(0, 5));
+    assert_eq!(located.children[0].children[0].expression, Range::new(4, 5));
+    assert_eq!(located.children[1].expression, Range::new(9, 10));
+}
+
+#[test]
+fn associate() {
+    let associations = associate!("# foo\na = 1 # bar\n# baz\nb = [1, 2] # arr\n# tail");
+
+    assert_eq!(associations.len(), 4);
+
+    assert_eq!(associations[0].1, Range::new(6, 11));
+    assert_eq!(associations[0].2, texts(&["# foo"]));
+    assert!(associations[0].3.is_empty());
+
+    // like whitequark, a decorating comment goes to the last node on its line
+    assert_eq!(associations[1].0, Node::Int(1));
+    assert!(associations[1].2.is_empty());
+    assert_eq!(associations[1].3, texts(&["# bar"]));
+
+    assert_eq!(associations[2].1, Range::new(24, 34));
+    assert_eq!(associations[2].2, texts(&["# baz"]));
+
+    assert_eq!(associations[3].1, Range::new(28, 34));
+    assert_eq!(associations[3].3, texts(&["# arr"]));
+}
+
+// the comment after `foo; foo` decorates the second one
+#[test]
+fn associate_same_line() {
+    let associations = associate!("foo; foo # second\n");
+
+    assert_eq!(associations.len(), 1);
+    assert_eq!(associations[0].1, Range::new(5, 8));
+    assert_eq!(associations[0].3, texts(&["# second"]));
+}
+
+#[test]
+fn associate_document() {
+    let associations = associate!("=begin\ndoc\n=end\nfoo");
+
+    assert_eq!(associations.len(), 1);
+    assert_eq!(associations[0].0, Node::Send { receiver: None, selector: String::from("foo"), args: vec![] });
+    assert_eq!(associations[0].2, texts(&["=begin\ndoc\n=end\n"]));
+}
+
+// shebang and magic encoding comments are skipped unless asked not to
+#[test]
+fn associate_directives() {
+    let associations = associate!("#!/usr/bin/env ruby\n# encoding: utf-8\n# foo\nfoo\n");
+
+    assert_eq!(associations.len(), 1);
+    assert_eq!(associations[0].2, texts(&["# foo"]));
+
+    let mut parser = Parser::new(String::from("#!/usr/bin/env ruby\nfoo\n"));
+    let (node, comments) = parser.parse_with_comments().unwrap();
+    let located = parser.locate(&node).unwrap();
+
+    let mut associator = Associator::new(&located, &comments, parser.source_buffer());
+    associator.skip_directives = false;
+    let associations = associator.associate();
+
+    assert_eq!(associations.len(), 1);
+    assert_eq!(associations[0].leading[0].text, "#!/usr/bin/env ruby");
+}
@@ -0,0 +1,85 @@
+// based on
+// https://github.com/whitequark/parser/blob/2a73841d6da04a5ab9bd270561165fd766722d43/test/test_diagnostic.rb
+
+extern crate ruby_on_rust;
+
+use std::collections::HashMap;
+
+use ruby_on_rust::diagnostic::{Diagnostic, Level};
+use ruby_on_rust::source::buffer::SourceBuffer;
+use ruby_on_rust::source::range::Range;
+
+//   def test_message
+//     diag = Parser::Diagnostic.new(:error, :not_static_cpath_in_class,
+//                                   { :name => 'x' }, @range1)
+//     ...
+//   end
+#[test]
+fn message() {
+    let mut arguments = HashMap::new();
+    arguments.insert("character", String::from("$"));
+    let diagnostic = Diagnostic::new(Level::Fatal, "unexpected", arguments, Range::new(0, 1));
+
+    assert_eq!(diagnostic.message(), "unexpected `$'");
+}
+
+//   def test_render
+//     location = Parser::Source::Range.new(@buffer, 21, 23)
+//     highlights = [...]
+// 
+//     diag  = Parser::Diagnostic.new(:error, :unexpected_token, { :token => 'ident' },
+//                                    location, highlights)
+//     assert_equal([
+//       "(string):1:22: error: unexpected token ident",
+//       "(string):1: if (this is some bad code + bugs)",
+//       "(string):1:     ~~~~       ^~             ~~ "
+//     ],
+//     diag.render)
+//   end
+// 
+// NOTE highlights are not supported
+#[test]
+fn render() {
+    let buffer = SourceBuffer::new("(string)", String::from("if (this is some bad code + bugs)\nnil"));
+
+    let mut arguments = HashMap::new();
+    arguments.insert("token", String::from("ident"));
+    let diagnostic = Diagnostic::new(Level::Error, "unexpected_token", arguments, Range::new(21, 23));
+
+    assert_eq!(diagnostic.render(&buffer), vec![
+        "(string):1:22: error: unexpected token ident",
+        "(string):1: if (this is some bad code + bugs)",
+        "(string):1:                      ^~",
+    ]);
+}
+
+#[test]
+fn render_at_end_of_line() {
+    let buffer = SourceBuffer::new("(string)", String::from("nil\n[1,"));
+
+    let mut arguments = HashMap::new();
+    arguments.insert("token", String::from("$end"));
+    let diagnostic = Diagnostic::new(Level::Error, "unexpected_token", arguments, Range::new(7, 7));
+
+    assert_eq!(diagnostic.render(&buffer), vec![
+        "(string):2:4: error: unexpected token $end",
+        "(string):2: [1,",
+        "(string):2:    ^",
+    ]);
+}
+
+// ranges are in bytes, the highlight lines up with chars
+#[test]
+fn render_utf8() {
+    let buffer = SourceBuffer::new("(string)", String::from("café + ほげ"));
+
+    let mut arguments = HashMap::new();
+    arguments.insert("token", String::from("ident"));
+    let diagnostic = Diagnostic::new(Level::Error, "unexpected_token", arguments, Range::new(8, 14));
+
+    assert_eq!(diagnostic.render(&buffer), vec![
+        "(string):1:9: error: unexpected token ident",
+        "(string):1: café + ほげ",
+        "(string):1:        ^~",
+    ]);
+}
@@ -0,0 +1,217 @@
+// based on
+// https://github.com/whitequark/parser/blob/2a73841d6da04a5ab9bd270561165fd766722d43/test/test_encoding.rb
+
+extern crate ruby_on_rust;
+
+use ruby_on_rust::parser::parser::Parser;
+use ruby_on_rust::ast::node::Node;
+use ruby_on_rust::source::buffer::SourceBuffer;
+use ruby_on_rust::source::encoding::Encoding;
+use ruby_on_rust::source::range::Range;
+use ruby_on_rust::source::magic_comment::MagicCommentKind;
+use ruby_on_rust::diagnostic::Level;
+
+// helpers
+
+//   def recognize(string)
+//     Parser::Source::Buffer.recognize_encoding(string)
+//   end
+fn recognize(source: &[u8]) -> Option<String> {
+    SourceBuffer::recognize_encoding(source).map(|(name, _)| name)
+}
+
+//   def test_default
+//     assert_nil recognize('foobar')
+//   end
+#[test]
+fn default() {
+    assert_eq!(recognize(b"foobar"), None);
+}
+
+//   def test_bom
+//     assert_equal Encoding::UTF_8, recognize("\xef\xbb\xbf\nfoobar")
+//   end
+#[test]
+fn bom() {
+    assert_eq!(recognize(b"\xef\xbb\xbf\nfoobar"), Some(String::from("UTF-8")));
+}
+
+//   def test_magic_comment
+//     assert_equal Encoding::KOI8_R, recognize("# coding:koi8-r\nfoobar")
+//   end
+#[test]
+fn magic_comment() {
+    assert_eq!(recognize(b"# coding:koi8-r\nfoobar"), Some(String::from("koi8-r")));
+}
+
+//   def test_shebang
+//     assert_equal Encoding::KOI8_R, recognize("#!/bin/foo\n# coding:koi8-r\nfoobar")
+//   end
+#[test]
+fn shebang() {
+    assert_eq!(recognize(b"#!/bin/foo\n# coding:koi8-r\nfoobar"), Some(String::from("koi8-r")));
+}
+
+//   def test_case
+//     assert_equal Encoding::KOI8_R, recognize("# coding:KoI8-r\nfoobar")
+//   end
+#[test]
+fn case() {
+    assert_eq!(recognize(b"# coding:KoI8-r\nfoobar"), Some(String::from("KoI8-r")));
+    assert_eq!(Encoding::find("Iso-8859-1"), Some(Encoding::Iso8859_1));
+}
+
+//   def test_space
+//     assert_equal Encoding::KOI8_R, recognize("# coding : koi8-r\nfoobar")
+//   end
+#[test]
+fn space() {
+    assert_eq!(recognize(b"# coding : koi8-r\nfoobar"), Some(String::from("koi8-r")));
+}
+
+//   def test_empty
+//     assert_nil recognize('')
+//   end
+#[test]
+fn empty() {
+    assert_eq!(recognize(b""), None);
+}
+
+//   def test_no_comment
+//     assert_nil recognize(%{require 'cane' unless RUBY_VERSION =~ /^1\.8/})
+//   end
+#[test]
+fn no_comment() {
+    assert_eq!(recognize(b"require 'cane' unless RUBY_VERSION =~ /^1\\.8/"), None);
+}
+
+//   def test_adjacent
+//     assert_nil recognize('# codingkoi8-r')
+//     assert_nil recognize('# coding koi8-r')
+//   end
+#[test]
+fn adjacent() {
+    assert_eq!(recognize(b"# codingkoi8-r"), None);
+    assert_eq!(recognize(b"# coding koi8-r"), None);
+}
+
+//   def test_utf8_mac
+//     assert_equal Encoding::UTF8_MAC, recognize('# coding: utf8-mac')
+//   end
+#[test]
+fn utf8_mac() {
+    assert_eq!(recognize(b"# coding: utf8-mac"), Some(String::from("utf8-mac")));
+}
+
+//   def test_suffix
+//     assert_equal Encoding::UTF_8, recognize('# coding: utf-8-dos')
+//     assert_equal Encoding::UTF_8, recognize('# coding: utf-8-unix')
+//     assert_equal Encoding::UTF_8, recognize('# coding: utf-8-mac')
+//
+//     assert_raises(ArgumentError) do
+//       assert_nil recognize('# coding: utf-8-dicks')
+//     end
+//   end
+#[test]
+fn suffix() {
+    assert_eq!(recognize(b"# coding: utf-8-dos"), Some(String::from("utf-8")));
+    assert_eq!(recognize(b"# coding: utf-8-unix"), Some(String::from("utf-8")));
+    assert_eq!(recognize(b"# coding: utf-8-mac"), Some(String::from("utf-8")));
+
+    let diagnostic = SourceBuffer::from_bytes("(string)", b"# coding: utf-8-dicks".to_vec()).unwrap_err();
+    assert_eq!(diagnostic.reason, "unknown_encoding");
+    assert_eq!(diagnostic.range, Range::new(10, 21));
+}
+
+// a Latin-1 source, "é" being 0xe9
+#[test]
+fn from_bytes_latin1() {
+    let mut parser = Parser::from_bytes(b"# encoding: iso-8859-1\n'caf\xe9'".to_vec()).unwrap();
+
+    assert_eq!(parser.source_buffer().encoding, Encoding::Iso8859_1);
+    assert_eq!(parser.source_buffer().source(), "# encoding: iso-8859-1\n'café'");
+    assert_eq!(parser.parse().unwrap(), Node::Str(String::from("café")));
+}
+
+#[test]
+fn from_bytes_windows_1252() {
+    let source_buffer = SourceBuffer::from_bytes("(string)", b"# -*- coding: cp1252 -*-\n'\x80'".to_vec()).unwrap();
+
+    assert_eq!(source_buffer.encoding, Encoding::Windows1252);
+    assert_eq!(source_buffer.source(), "# -*- coding: cp1252 -*-\n'€'");
+
+    let diagnostic = SourceBuffer::from_bytes("(string)", b"# coding: cp1252\n'\x81'".to_vec()).unwrap_err();
+    assert_eq!(diagnostic.reason, "invalid_byte_sequence");
+    assert_eq!(diagnostic.range, Range::new(18, 19));
+}
+
+#[test]
+fn from_bytes_binary() {
+    let source_buffer = SourceBuffer::from_bytes("(string)", b"# encoding: binary\n'\xff'".to_vec()).unwrap();
+
+    assert_eq!(source_buffer.encoding, Encoding::Binary);
+    assert_eq!(source_buffer.source(), "# encoding: binary\n'\u{ff}'");
+}
+
+// bytes which don't match the declared or the default encoding
+#[test]
+fn from_bytes_misdeclared() {
+    let diagnostic = Parser::from_bytes(b"'caf\xe9'".to_vec()).err().unwrap();
+    assert_eq!(diagnostic.level, Level::Fatal);
+    assert_eq!(diagnostic.reason, "invalid_byte_sequence");
+    assert_eq!(diagnostic.arguments.get("encoding"), Some(&String::from("UTF-8")));
+    assert_eq!(diagnostic.range, Range::new(4, 5));
+
+    let diagnostic = Parser::from_bytes(b"# coding: us-ascii\n'caf\xc3\xa9'".to_vec()).err().unwrap();
+    assert_eq!(diagnostic.reason, "invalid_byte_sequence");
+    assert_eq!(diagnostic.arguments.get("encoding"), Some(&String::from("US-ASCII")));
+    assert_eq!(diagnostic.range, Range::new(23, 24));
+}
+
+#[test]
+fn bom_skipped() {
+    let mut parser = Parser::from_bytes(b"\xef\xbb\xbfnil".to_vec()).unwrap();
+    assert_eq!(parser.parse().unwrap(), Node::Nil);
+}
+
+#[test]
+fn magic_comments() {
+    let mut parser = Parser::new(String::from("#!/usr/bin/env ruby\n# encoding: utf-8\n# frozen_string_literal: true\n# -*- warn-indent: false -*-\nnil"));
+    parser.parse().unwrap();
+
+    let magic_comments = parser.magic_comments();
+    assert_eq!(magic_comments.len(), 3);
+
+    assert_eq!(magic_comments[0].kind, MagicCommentKind::Encoding);
+    assert_eq!(magic_comments[0].value, "utf-8");
+    assert_eq!(magic_comments[0].range, Range::new(32, 37));
+
+    assert_eq!(magic_comments[1].kind, MagicCommentKind::FrozenStringLiteral);
+    assert_eq!(magic_comments[1].as_bool(), Some(true));
+    assert_eq!(magic_comments[1].range, Range::new(63, 67));
+
+    assert_eq!(magic_comments[2].kind, MagicCommentKind::WarnIndent);
+    assert_eq!(magic_comments[2].as_bool(), Some(false));
+}
+
+#[test]
+fn magic_comments_ignored() {
+    // not on the first line
+    let mut parser = Parser::new(String::from("# foo\n# encoding: utf-8\nnil"));
+    parser.parse().unwrap();
+    assert!(parser.magic_comments().is_empty());
+
+    // after any tokens
+    let mut parser = Parser::new(String::from("nil\n# frozen_string_literal: true\n"));
+    parser.parse().unwrap();
+    assert!(parser.magic_comments().is_empty());
+    assert_eq!(parser.diagnostics()[0].level, Level::Warning);
+    assert_eq!(parser.diagnostics()[0].reason, "magic_comment_ignored");
+
+    // invalid value
+    let mut parser = Parser::new(String::from("# frozen_string_literal: yes\nnil"));
+    parser.parse().unwrap();
+    assert!(parser.magic_comments().is_empty());
+    assert_eq!(parser.diagnostics()[0].reason, "invalid_magic_value");
+    assert_eq!(parser.diagnostics()[0].range, Range::new(25, 28));
+}
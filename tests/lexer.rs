@@ -1,12 +1,59 @@
 // based on 
 // https://github.com/whitequark/parser/blob/2a73841d6da04a5ab9bd270561165fd766722d43/test/test_lexer.rb
 
-// TODO assert macro helper
-
 extern crate ruby_on_rust;
 
-use ruby_on_rust::lexer::Lexer;
+use std::io::{self, Read};
+
+use ruby_on_rust::lexer::{Lexer, tokenize};
+use ruby_on_rust::lexer::lexing_state::LexingState;
+use ruby_on_rust::lexer::dedenter::Dedenter;
 use ruby_on_rust::parser::token::{Token, TokenString};
+use ruby_on_rust::parser::options::RubyVersion;
+use ruby_on_rust::source::range::Range;
+use ruby_on_rust::shared::static_env::StaticEnv;
+use ruby_on_rust::diagnostic::Level;
+
+extern crate num_rational;
+extern crate num_bigint;
+use num_rational::Ratio;
+use num_bigint::BigInt;
+
+// assert the next token emitted by lexer, along with its range
+// like whitequark's `:tRBRACK, "]", [0, 1]`
+macro_rules! assert_next_token {
+    ($lexer:expr, $token:expr, [$begin_pos:expr, $end_pos:expr]) => {
+        assert_eq!($lexer.advance().unwrap(), ($token, Range::new($begin_pos, $end_pos)));
+    };
+}
+
+// a reader handing out at most chunk_len bytes at a time, to cut tokens and chars in half
+struct ChunkedReader {
+    bytes: Vec<u8>,
+    pos: usize,
+    chunk_len: usize,
+}
+
+impl ChunkedReader {
+    fn new(source: &[u8], chunk_len: usize) -> ChunkedReader {
+        ChunkedReader { bytes: source.to_vec(), pos: 0, chunk_len }
+    }
+}
+
+impl Read for ChunkedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = *[self.chunk_len, buf.len(), self.bytes.len() - self.pos].iter().min().unwrap();
+        buf[..len].copy_from_slice(&self.bytes[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+fn lex_all(lexer: &mut Lexer) -> Vec<(Token, Range)> {
+    let mut tokens = vec![];
+    while let Some(token) = lexer.advance() { tokens.push(token); }
+    tokens
+}
 
 #[test]
 fn identifier() {
@@ -14,8 +61,7 @@ fn identifier() {
 
     let mut lexer = Lexer::new(content);
 
-    let token = lexer.advance().unwrap();
-    assert_eq!(token, Token::T_IDENTIFIER(TokenString::from("identifier")));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("identifier")), [0, 10]);
 }
 
 //   def test_string_double_interp
@@ -52,21 +98,20 @@ fn string_double_interp() {
 
     let mut lexer = Lexer::new(content);
 
-    assert_eq!(lexer.advance().unwrap(), Token::T_STRING_BEG);
-    assert_eq!(lexer.advance().unwrap(), Token::T_STRING_CONTENT(TokenString::from("blah #x a ")));
-    assert_eq!(lexer.advance().unwrap(), Token::T_STRING_DVAR);
-    assert_eq!(lexer.advance().unwrap(), Token::T_IVAR(TokenString::from("@a")));
-    assert_eq!(lexer.advance().unwrap(), Token::T_STRING_CONTENT(TokenString::from(" b ")));
-    assert_eq!(lexer.advance().unwrap(), Token::T_STRING_DVAR);
-    assert_eq!(lexer.advance().unwrap(), Token::T_GVAR(TokenString::from("$b")));
-    assert_eq!(lexer.advance().unwrap(), Token::T_STRING_CONTENT(TokenString::from(" c ")));
-    assert_eq!(lexer.advance().unwrap(), Token::T_STRING_DBEG);
-    assert_eq!(lexer.advance().unwrap(), Token::T_INTEGER(3));
-    // // assert_eq!(lexer.advance().unwrap(), Token::T_RCURLY); // -> T_STRING_DEND
-    // assert_eq!(lexer.advance().unwrap(), Token::T_STRING_DEND);
-    // assert_eq!(lexer.advance().unwrap(), Token::T_STRING_CONTENT(TokenString::from(" # ")));
-    // assert_eq!(lexer.advance().unwrap(), Token::T_STRING_END);
-    // // TODO assert must be empty, impl in helper macro
+    assert_next_token!(lexer, Token::T_STRING_BEG, [0, 1]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("blah #x a ")), [1, 11]);
+    assert_next_token!(lexer, Token::T_STRING_DVAR, [11, 12]);
+    assert_next_token!(lexer, Token::T_IVAR(TokenString::from("@a")), [12, 14]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from(" b ")), [14, 17]);
+    assert_next_token!(lexer, Token::T_STRING_DVAR, [17, 18]);
+    assert_next_token!(lexer, Token::T_GVAR(TokenString::from("$b")), [18, 20]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from(" c ")), [20, 23]);
+    assert_next_token!(lexer, Token::T_STRING_DBEG, [23, 25]);
+    assert_next_token!(lexer, Token::T_INTEGER(3), [25, 26]);
+    assert_next_token!(lexer, Token::T_STRING_DEND, [26, 27]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from(" # ")), [27, 30]);
+    assert_next_token!(lexer, Token::T_STRING_END, [30, 31]);
+    // TODO assert must be empty, impl in helper macro
 }
 
 
@@ -77,7 +122,7 @@ fn string_double_interp() {
 fn rbarcket() {
     let content = String::from("]");
     let mut lexer = Lexer::new(content);
-    assert_eq!(lexer.advance().unwrap(), Token::T_RBRACK);
+    assert_next_token!(lexer, Token::T_RBRACK, [0, 1]);
 }
 
 //   def test_rcurly
@@ -87,5 +132,1148 @@ fn rbarcket() {
 fn rcurly() {
     let content = String::from("}");
     let mut lexer = Lexer::new(content);
-    assert_eq!(lexer.advance().unwrap(), Token::T_RCURLY);
+    assert_next_token!(lexer, Token::T_RCURLY, [0, 1]);
+}
+
+//   def test_string_single
+//     assert_scanned "'string'", :tSTRING, "string", [0, 8]
+//   end
+#[test]
+fn string_single() {
+    let content = String::from("'string'");
+    let mut lexer = Lexer::new(content);
+    assert_next_token!(lexer, Token::T_STRING(TokenString::from("string")), [0, 8]);
+}
+
+//   def test_string_pct_q
+//     assert_scanned("%q[s1 s2]",
+//                    :tSTRING_BEG,     "%q[",   [0, 3],
+//                    :tSTRING_CONTENT, "s1 s2", [3, 8],
+//                    :tSTRING_END,     "]",     [8, 9])
+//   end
+#[test]
+fn string_pct_q() {
+    let content = String::from("%q[s1 s2]");
+    let mut lexer = Lexer::new(content);
+    assert_next_token!(lexer, Token::T_STRING_BEG, [0, 3]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("s1 s2")), [3, 8]);
+    assert_next_token!(lexer, Token::T_STRING_END, [8, 9]);
+}
+
+//   def test_string_single_escape_chars
+//     assert_scanned("'s\\tri\\ng'",
+//                    :tSTRING, "s\\tri\\ng", [0, 10])
+//   end
+#[test]
+fn string_single_escape_chars() {
+    let mut lexer = Lexer::new(String::from("'s\\tri\\ng\\''"));
+    assert_next_token!(lexer, Token::T_STRING(TokenString::from("s\\tri\\ng'")), [0, 12]);
+}
+
+//   def test_string_double_escape_chars
+//     assert_scanned("\"s\\tri\\ng\"",
+//                    :tSTRING, "s\tri\ng", [0, 10])
+//   end
+#[test]
+fn string_double_escape_chars() {
+    let mut lexer = Lexer::new(String::from("\"s\\tri\\ng\""));
+    assert_next_token!(lexer, Token::T_STRING(TokenString::from("s\tri\ng")), [0, 10]);
+
+    let mut lexer = Lexer::new(String::from("\"\\a\\b\\e\\f\\r\\s\\v\\\\\\q\\\"\""));
+    assert_next_token!(lexer, Token::T_STRING(TokenString::from("\x07\x08\x1b\x0c\r \x0b\\q\"")), [0, 22]);
+}
+
+//   def test_string_double_escape_octal
+//     assert_scanned("\"n = \\101\\102\\103\"",
+//                    :tSTRING, "n = ABC", [0, 18])
+//   end
+// 
+//   def test_string_double_escape_hex
+//     assert_scanned("\"n = \\x61\\x62\\x63\"",
+//                    :tSTRING, "n = abc", [0, 18])
+//   end
+// 
+// NOTE bytes make up utf-8 chars together, like "\xe2\x98\xba"
+#[test]
+fn string_double_escape_octal_hex() {
+    let mut lexer = Lexer::new(String::from("\"n = \\101\\102\\103\""));
+    assert_next_token!(lexer, Token::T_STRING(TokenString::from("n = ABC")), [0, 18]);
+
+    let mut lexer = Lexer::new(String::from("\"n = \\x61\\x62\\x63\""));
+    assert_next_token!(lexer, Token::T_STRING(TokenString::from("n = abc")), [0, 18]);
+
+    let mut lexer = Lexer::new(String::from("\"\\0\\xe2\\x98\\xba\""));
+    assert_next_token!(lexer, Token::T_STRING(TokenString::from("\0\u{263a}")), [0, 16]);
+}
+
+// \u with 4 digits, and codepoint lists in \u{}
+#[test]
+fn string_double_escape_unicode() {
+    let mut lexer = Lexer::new(String::from("\"\\u263a\\u{ 1F600 20\t1f600 }\""));
+    assert_next_token!(lexer, Token::T_STRING(TokenString::from("\u{263a}\u{1F600} \u{1F600}")), [0, 28]);
+}
+
+// \C-, \c and \M-, \M- makes a single byte, only tested where bytes add up to a utf-8 char
+#[test]
+fn string_double_escape_ctrl_meta() {
+    let mut lexer = Lexer::new(String::from("\"\\C-a\\ca\\C-\\t\\c?\""));
+    assert_next_token!(lexer, Token::T_STRING(TokenString::from("\x01\x01\x09\x7f")), [0, 17]);
+
+    // \xe1 \x81 \x81
+    let mut lexer = Lexer::new(String::from("\"\\M-a\\M-\\C-a\\c\\M-a\""));
+    assert_next_token!(lexer, Token::T_STRING(TokenString::from("\u{1041}")), [0, 19]);
+    assert!(lexer.diagnostics.is_empty());
+}
+
+//   def test_string_pct_w_backslash
+//     assert_scanned("%w[foo\\ bar]",
+//                    :tQWORDS_BEG,     "%w[",     [0, 3],
+//                    :tSTRING_CONTENT, "foo bar", [3, 11],
+//                    :tSPACE,          nil,       [11, 11],
+//                    :tSTRING_END,     "]",       [11, 12])
+//   end
+#[test]
+fn string_pct_w_backslash() {
+    let mut lexer = Lexer::new(String::from("%w[foo\\ bar]"));
+    assert_next_token!(lexer, Token::T_QWORDS_BEG, [0, 3]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("foo bar")), [3, 11]);
+    assert_next_token!(lexer, Token::T_SPACE, [11, 11]);
+    assert_next_token!(lexer, Token::T_STRING_END, [11, 12]);
+
+    let mut lexer = Lexer::new(String::from("%W[foo\\tbar]"));
+    assert_next_token!(lexer, Token::T_WORDS_BEG, [0, 3]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("foo\tbar")), [3, 11]);
+}
+
+//   def test_regexp_escape_chars
+//     assert_scanned('/u\\tmp/',
+//                    :tREGEXP_BEG,     "/",      [0, 1],
+//                    :tSTRING_CONTENT, "u\\tmp", [1, 6],
+//                    :tSTRING_END,     "/",      [6, 7],
+//                    :tREGEXP_OPT,     "",       [7, 7])
+//   end
+// 
+// NOTE an escaped delimiter loses its backslash, unless it's a metacharacter
+#[test]
+fn regexp_escape_chars() {
+    let mut lexer = Lexer::new(String::from("/u\\tmp/"));
+    assert_next_token!(lexer, Token::T_REGEXP_BEG, [0, 1]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("u\\tmp")), [1, 6]);
+    assert_next_token!(lexer, Token::T_STRING_END, [6, 7]);
+
+    let mut lexer = Lexer::new(String::from("/\\//"));
+    assert_next_token!(lexer, Token::T_REGEXP_BEG, [0, 1]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("/")), [1, 3]);
+
+    let mut lexer = Lexer::new(String::from("%r{\\}\\/}"));
+    assert_next_token!(lexer, Token::T_REGEXP_BEG, [0, 3]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("\\}\\/")), [3, 7]);
+}
+
+#[test]
+fn escape_errors() {
+    let cases = vec![
+        ("\"\\x\"",        "invalid_hex_escape",     Range::new(1, 3)),
+        ("\"\\u12\"",      "invalid_unicode_escape", Range::new(1, 5)),
+        ("\"\\u{}\"",      "invalid_unicode_escape", Range::new(1, 5)),
+        ("\"\\u{123z}\"",  "unterminated_unicode",   Range::new(8, 9)),
+        ("\"\\u{12",       "unterminated_unicode",   Range::new(5, 6)),
+        ("\"\\Cx\"",       "invalid_escape",         Range::new(1, 4)),
+        ("\"\\M-\\Cx\"",   "invalid_escape",         Range::new(1, 7)),
+        ("\"\\",           "escape_eof",             Range::new(1, 2)),
+    ];
+
+    for (content, reason, range) in cases {
+        let mut lexer = Lexer::new(String::from(content));
+        while let Some(_) = lexer.advance() {}
+
+        let diagnostic = lexer.fatal_diagnostic().unwrap();
+        assert_eq!(diagnostic.reason, reason);
+        assert_eq!(diagnostic.range, range);
+    }
+
+    let mut lexer = Lexer::new(String::from("\"\\u{110000}\""));
+    while let Some(_) = lexer.advance() {}
+    assert_eq!(lexer.diagnostics[0].level, Level::Error);
+    assert_eq!(lexer.diagnostics[0].reason, "unicode_point_too_large");
+    assert_eq!(lexer.diagnostics[0].range, Range::new(4, 10));
+}
+
+// character literals, with or without escapes
+#[test]
+fn question() {
+    let cases = vec![
+        ("?a",           "a",          [0, 2]),
+        ("?\\s",         " ",          [0, 3]),
+        ("?\\n",         "\n",         [0, 3]),
+        ("?\\t",         "\t",         [0, 3]),
+        ("?\\C-a",       "\x01",       [0, 5]),
+        ("?\\u{1F600}",  "\u{1F600}",  [0, 10]),
+    ];
+
+    for (content, value, [begin_pos, end_pos]) in cases {
+        let mut lexer = Lexer::new(String::from(content));
+        assert_next_token!(lexer, Token::T_CHARACTER(TokenString::from(value)), [begin_pos, end_pos]);
+    }
+
+    // ternary when followed by an identifier, or multiple codepoints
+    let mut lexer = Lexer::new(String::from("?ab"));
+    assert_next_token!(lexer, Token::T_EH, [0, 1]);
+    let mut lexer = Lexer::new(String::from("?\\u{1 2}"));
+    assert_next_token!(lexer, Token::T_EH, [0, 1]);
+}
+
+//   def test_question_eof
+//     assert_scanned "?", :tEH, "?", [0, 1]  # ruby 18
+//     ...
+//     refute_scanned "?"
+//   end
+#[test]
+fn question_eof() {
+    let content = String::from("?");
+    let mut lexer = Lexer::new(content);
+    assert_eq!(lexer.advance(), None);
+
+    let diagnostic = lexer.fatal_diagnostic().unwrap();
+    assert_eq!(diagnostic.level, Level::Fatal);
+    assert_eq!(diagnostic.reason, "incomplete_escape");
+    assert_eq!(diagnostic.range, Range::new(0, 1));
+}
+
+#[test]
+fn label_end() {
+    let mut lexer = Lexer::new(String::from("{'q': 1}"));
+    lexer.advance();
+    assert_next_token!(lexer, Token::T_STRING_BEG, [1, 2]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("q")), [2, 3]);
+    assert_next_token!(lexer, Token::T_LABEL_END, [3, 5]);
+    assert_next_token!(lexer, Token::T_INTEGER(1), [6, 7]);
+
+    // NOTE a quoted label may end the source
+    let mut lexer = Lexer::new(String::from("{'q':"));
+    lexer.advance();
+    lexer.advance();
+    lexer.advance();
+    assert_next_token!(lexer, Token::T_LABEL_END, [3, 5]);
+    assert_eq!(lexer.advance(), None);
+}
+
+//   def test_float
+//     assert_scanned "1.0", :tFLOAT, 1.0, [0, 3]
+//   end
+#[test]
+fn float() {
+    let content = String::from("1.0");
+    let mut lexer = Lexer::new(content);
+    assert_next_token!(lexer, Token::T_FLOAT(1.0), [0, 3]);
+}
+
+//   def test_float_e_neg
+//     assert_scanned "1e-10", :tFLOAT, 1e-10, [0, 5]
+//   end
+#[test]
+fn float_e_neg() {
+    let content = String::from("1e-10");
+    let mut lexer = Lexer::new(content);
+    assert_next_token!(lexer, Token::T_FLOAT(1e-10), [0, 5]);
+}
+
+// float with rational and imaginary suffixes, same as int_suffix below
+#[test]
+fn float_suffix() {
+    let mut lexer = Lexer::new(String::from("1.0r"));
+    assert_next_token!(lexer, Token::T_RATIONAL(Ratio::from_integer(1)), [0, 4]);
+
+    let mut lexer = Lexer::new(String::from("1.5ri"));
+    assert_next_token!(lexer, Token::T_IMAGINARY(Box::new(Token::T_RATIONAL(Ratio::new(3, 2)))), [0, 5]);
+
+    let mut lexer = Lexer::new(String::from("1e1i"));
+    assert_next_token!(lexer, Token::T_IMAGINARY(Box::new(Token::T_FLOAT(10.0))), [0, 4]);
+}
+
+//   def test_int_suffix
+//     assert_scanned("42r",  :tRATIONAL,  Rational(42),           [0, 3])
+//     assert_scanned("42i",  :tIMAGINARY, Complex(0, 42),         [0, 3])
+//     assert_scanned("42ri", :tIMAGINARY, Complex(0, Rational(42)), [0, 4])
+//   end
+#[test]
+fn int_suffix() {
+    let mut lexer = Lexer::new(String::from("42r"));
+    assert_next_token!(lexer, Token::T_RATIONAL(Ratio::from_integer(42)), [0, 3]);
+
+    let mut lexer = Lexer::new(String::from("42i"));
+    assert_next_token!(lexer, Token::T_IMAGINARY(Box::new(Token::T_INTEGER(42))), [0, 3]);
+
+    let mut lexer = Lexer::new(String::from("42ri"));
+    assert_next_token!(lexer, Token::T_IMAGINARY(Box::new(Token::T_RATIONAL(Ratio::from_integer(42)))), [0, 4]);
+}
+
+//   def test_integer_hex
+//     assert_scanned "0x2a", :tINTEGER, 42, [0, 4]
+//   end
+//
+//   def test_integer_bin
+//     assert_scanned "0b101010", :tINTEGER, 42, [0, 8]
+//   end
+//
+//   def test_integer_oct
+//     assert_scanned "052", :tINTEGER, 42, [0, 3]
+//   end
+//
+//   def test_integer_oct_O
+//     assert_scanned "0O52", :tINTEGER, 42, [0, 4]
+//   end
+//
+//   def test_integer_dec_d
+//     assert_scanned "0d42", :tINTEGER, 42, [0, 4]
+//   end
+//
+//   def test_integer_zero
+//     assert_scanned "0", :tINTEGER, 0, [0, 1]
+//   end
+#[test]
+fn integer_bases() {
+    let mut lexer = Lexer::new(String::from("0x2a"));
+    assert_next_token!(lexer, Token::T_INTEGER(42), [0, 4]);
+
+    let mut lexer = Lexer::new(String::from("0b101010"));
+    assert_next_token!(lexer, Token::T_INTEGER(42), [0, 8]);
+
+    let mut lexer = Lexer::new(String::from("052"));
+    assert_next_token!(lexer, Token::T_INTEGER(42), [0, 3]);
+
+    let mut lexer = Lexer::new(String::from("0O52"));
+    assert_next_token!(lexer, Token::T_INTEGER(42), [0, 4]);
+
+    let mut lexer = Lexer::new(String::from("0d42"));
+    assert_next_token!(lexer, Token::T_INTEGER(42), [0, 4]);
+
+    let mut lexer = Lexer::new(String::from("0"));
+    assert_next_token!(lexer, Token::T_INTEGER(0), [0, 1]);
+}
+
+//   def test_integer_underscore
+//     assert_scanned "4_2", :tINTEGER, 42, [0, 3]
+//   end
+#[test]
+fn integer_underscore() {
+    let mut lexer = Lexer::new(String::from("4_2"));
+    assert_next_token!(lexer, Token::T_INTEGER(42), [0, 3]);
+
+    let mut lexer = Lexer::new(String::from("0x2_a"));
+    assert_next_token!(lexer, Token::T_INTEGER(42), [0, 5]);
+}
+
+//   def test_integer_trailing
+//     refute_scanned "1_",
+//                    :error, :trailing_in_number, { :character => '_'.freeze }, [1, 2]
+//   end
+//
+//   def test_integer_oct_bad_range
+//     refute_scanned "08",
+//                    :error, :invalid_octal, nil, [1, 2]
+//   end
+//
+//   def test_integer_hex_bad_none
+//     refute_scanned "0x ",
+//                    :error, :empty_numeric, nil, [0, 2]
+//   end
+#[test]
+fn integer_errors() {
+    let mut lexer = Lexer::new(String::from("1_"));
+    lexer.advance();
+    assert_eq!(lexer.diagnostics[0].level, Level::Error);
+    assert_eq!(lexer.diagnostics[0].reason, "trailing_in_number");
+    assert_eq!(lexer.diagnostics[0].range, Range::new(1, 2));
+
+    let mut lexer = Lexer::new(String::from("08"));
+    lexer.advance();
+    assert_eq!(lexer.diagnostics[0].reason, "invalid_octal");
+    assert_eq!(lexer.diagnostics[0].range, Range::new(1, 2));
+
+    let mut lexer = Lexer::new(String::from("0x "));
+    lexer.advance();
+    assert_eq!(lexer.diagnostics[0].reason, "empty_numeric");
+    assert_eq!(lexer.diagnostics[0].range, Range::new(0, 2));
+}
+
+// integers which don't fit in isize
+#[test]
+fn integer_big() {
+    let mut lexer = Lexer::new(String::from("0xffffffffffffffffff"));
+    assert_next_token!(lexer, Token::T_BIG_INTEGER(BigInt::parse_bytes(b"ffffffffffffffffff", 16).unwrap()), [0, 20]);
+}
+
+//   def test_heredoc_backtick
+//     assert_scanned("a = <<`EOF`\n  blah blah\nEOF\n\n",
+//                    :tIDENTIFIER,     "a",             [0, 1],
+//                    :tEQL,            "=",             [2, 3],
+//                    :tXSTRING_BEG,    "<<`",           [4, 11],
+//                    :tSTRING_CONTENT, "  blah blah\n", [12, 24],
+//                    :tSTRING_END,     "EOF",           [24, 27],
+//                    :tNL,             nil,             [11, 12])
+//   end
+#[test]
+fn heredoc_backtick() {
+    let mut lexer = Lexer::new(String::from("a = <<`EOF`\n  blah blah\nEOF\n\n"));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("a")), [0, 1]);
+    assert_next_token!(lexer, Token::T_EQL, [2, 3]);
+    assert_next_token!(lexer, Token::T_XSTRING_BEG, [4, 11]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("  blah blah\n")), [12, 24]);
+    assert_next_token!(lexer, Token::T_STRING_END, [24, 27]);
+    assert_next_token!(lexer, Token::T_NL, [11, 12]);
+}
+
+//   def test_heredoc_double
+//     assert_scanned("a = <<\"EOF\"\n  blah blah\nEOF\n\n",
+//                    :tIDENTIFIER,     "a",             [0, 1],
+//                    :tEQL,            "=",             [2, 3],
+//                    :tSTRING_BEG,     "<<\"",          [4, 11],
+//                    :tSTRING_CONTENT, "  blah blah\n", [12, 24],
+//                    :tSTRING_END,     "EOF",           [24, 27],
+//                    :tNL,             nil,             [11, 12])
+//   end
+#[test]
+fn heredoc_double() {
+    let mut lexer = Lexer::new(String::from("a = <<\"EOF\"\n  blah blah\nEOF\n\n"));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("a")), [0, 1]);
+    assert_next_token!(lexer, Token::T_EQL, [2, 3]);
+    assert_next_token!(lexer, Token::T_STRING_BEG, [4, 11]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("  blah blah\n")), [12, 24]);
+    assert_next_token!(lexer, Token::T_STRING_END, [24, 27]);
+    assert_next_token!(lexer, Token::T_NL, [11, 12]);
+}
+
+// heredoc bodies are emitted line by line, the closing line may be indented with <<-
+#[test]
+fn heredoc_dash() {
+    let mut lexer = Lexer::new(String::from("a = <<-EOF\nblah\nblah\n  EOF\n\n"));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("a")), [0, 1]);
+    assert_next_token!(lexer, Token::T_EQL, [2, 3]);
+    assert_next_token!(lexer, Token::T_STRING_BEG, [4, 10]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("blah\n")), [11, 16]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("blah\n")), [16, 21]);
+    assert_next_token!(lexer, Token::T_STRING_END, [21, 26]);
+    assert_next_token!(lexer, Token::T_NL, [10, 11]);
+}
+
+// bodies of heredocs opened on the same line follow each other
+#[test]
+fn heredoc_two_on_one_line() {
+    let mut lexer = Lexer::new(String::from("<<A + <<B\na\nA\nb\nB\n"));
+    assert_next_token!(lexer, Token::T_STRING_BEG, [0, 3]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("a\n")), [10, 12]);
+    assert_next_token!(lexer, Token::T_STRING_END, [12, 13]);
+    assert_next_token!(lexer, Token::T_PLUS, [4, 5]);
+    assert_next_token!(lexer, Token::T_STRING_BEG, [6, 9]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("b\n")), [14, 16]);
+    assert_next_token!(lexer, Token::T_STRING_END, [16, 17]);
+    assert_next_token!(lexer, Token::T_NL, [9, 10]);
+}
+
+// the dedent level of <<~ is available once the heredoc is closed
+#[test]
+fn heredoc_squiggly() {
+    let mut lexer = Lexer::new(String::from("<<~E\n    x\n  y\nE\n"));
+    assert_next_token!(lexer, Token::T_STRING_BEG, [0, 4]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("    x\n")), [5, 11]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("  y\n")), [11, 15]);
+    assert_next_token!(lexer, Token::T_STRING_END, [15, 16]);
+    assert_eq!(lexer.dedent_level(), Some(2));
+    assert_eq!(lexer.dedent_level(), None);
+}
+
+// an escaped newline is in the middle of a chunk, the line after it isn't dedented
+#[test]
+fn dedenter_escaped_newline() {
+    let mut lexer = Lexer::new(String::from("<<~E\n  a\\n  b\n  c\nE\n"));
+    assert_next_token!(lexer, Token::T_STRING_BEG, [0, 4]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("  a\n  b\n")), [5, 14]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("  c\n")), [14, 18]);
+    assert_next_token!(lexer, Token::T_STRING_END, [18, 19]);
+
+    let mut dedenter = Dedenter::new(lexer.dedent_level().unwrap());
+    assert_eq!(dedenter.dedent("  a\n  b\n"), "a\n  b\n");
+    assert_eq!(dedenter.dedent("  c\n"), "c\n");
+}
+
+#[test]
+fn heredoc_eof() {
+    let mut lexer = Lexer::new(String::from("<<E\nfoo\n"));
+    while let Some(_) = lexer.advance() {}
+
+    let diagnostic = lexer.fatal_diagnostic().unwrap();
+    assert_eq!(diagnostic.reason, "string_eof");
+    assert_eq!(diagnostic.range, Range::new(0, 1));
+}
+
+//   def test_regexp
+//     assert_scanned("/regexp/",
+//                    :tREGEXP_BEG,     "/",      [0, 1],
+//                    :tSTRING_CONTENT, "regexp", [1, 7],
+//                    :tSTRING_END,     "/",      [7, 8],
+//                    :tREGEXP_OPT,     "",       [8, 8])
+//   end
+#[test]
+fn regexp() {
+    let mut lexer = Lexer::new(String::from("/regexp/"));
+    assert_next_token!(lexer, Token::T_REGEXP_BEG, [0, 1]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("regexp")), [1, 7]);
+    assert_next_token!(lexer, Token::T_STRING_END, [7, 8]);
+    assert_next_token!(lexer, Token::T_REGEXP_OPT(TokenString::from("")), [8, 8]);
+}
+
+//   def test_regexp_nm
+//     assert_scanned("/.*/nm",
+//                    :tREGEXP_BEG,     "/",  [0, 1],
+//                    :tSTRING_CONTENT, ".*", [1, 3],
+//                    :tSTRING_END,     "/",  [3, 4],
+//                    :tREGEXP_OPT,     "nm", [4, 6])
+//   end
+#[test]
+fn regexp_nm() {
+    let mut lexer = Lexer::new(String::from("/.*/nm"));
+    assert_next_token!(lexer, Token::T_REGEXP_BEG, [0, 1]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from(".*")), [1, 3]);
+    assert_next_token!(lexer, Token::T_STRING_END, [3, 4]);
+    assert_next_token!(lexer, Token::T_REGEXP_OPT(TokenString::from("nm")), [4, 6]);
+}
+
+//   def test_regexp_bad
+//     refute_scanned("/.*/xyz",
+//                    :error, :regexp_options, {:options => "yz"}, [4, 7])
+//   end
+#[test]
+fn regexp_bad() {
+    let mut lexer = Lexer::new(String::from("/.*/xyz"));
+    while let Some(_) = lexer.advance() {}
+
+    assert_eq!(lexer.diagnostics[0].level, Level::Error);
+    assert_eq!(lexer.diagnostics[0].reason, "regexp_options");
+    assert_eq!(lexer.diagnostics[0].arguments.get("options"), Some(&String::from("yz")));
+    assert_eq!(lexer.diagnostics[0].range, Range::new(4, 7));
+}
+
+// regexp options are empty when followed by something else
+#[test]
+fn regexp_pct_r() {
+    let mut lexer = Lexer::new(String::from("%r{a} + 1"));
+    assert_next_token!(lexer, Token::T_REGEXP_BEG, [0, 3]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("a")), [3, 4]);
+    assert_next_token!(lexer, Token::T_STRING_END, [4, 5]);
+    assert_next_token!(lexer, Token::T_REGEXP_OPT(TokenString::from("")), [5, 5]);
+    assert_next_token!(lexer, Token::T_PLUS, [6, 7]);
+}
+
+//   def test_comment
+//     assert_scanned("1 # one\n# two\n2",
+//                    :tINTEGER, 1,   [0, 1],
+//                    :tNL,      nil, [7, 8],
+//                    :tINTEGER, 2,   [14, 15])
+//
+//     assert_equal 2, @lex.comments.length
+//     assert_equal '# one', @lex.comments[0].text
+//     assert_equal '# two', @lex.comments[1].text
+//   end
+#[test]
+fn comment() {
+    let mut lexer = Lexer::new(String::from("1 # one\n# two\n2"));
+    assert_next_token!(lexer, Token::T_INTEGER(1), [0, 1]);
+    assert_next_token!(lexer, Token::T_NL, [7, 8]);
+    assert_next_token!(lexer, Token::T_INTEGER(2), [14, 15]);
+
+    assert_eq!(lexer.comments.len(), 2);
+    assert_eq!(lexer.comments[0].text, "# one");
+    assert_eq!(lexer.comments[0].range, Range::new(2, 7));
+    assert_eq!(lexer.comments[1].text, "# two");
+    assert_eq!(lexer.comments[1].range, Range::new(8, 13));
+}
+
+//   def test_comment_begin
+//     assert_scanned("=begin\nblah\nblah\n=end\n42",
+//                    :tINTEGER, 42, [22, 24])
+//     assert_equal 1, @lex.comments.length
+//     assert_equal "=begin\nblah\nblah\n=end\n", @lex.comments[0].text
+//   end
+#[test]
+fn comment_begin() {
+    let mut lexer = Lexer::new(String::from("=begin\nblah\nblah\n=end\n42"));
+    assert_next_token!(lexer, Token::T_INTEGER(42), [22, 24]);
+
+    assert_eq!(lexer.comments.len(), 1);
+    assert_eq!(lexer.comments[0].text, "=begin\nblah\nblah\n=end\n");
+}
+
+//   def test_comment_begin_space
+//     assert_scanned("=begin blah\nblah\n=end\n")
+//     assert_equal "=begin blah\nblah\n=end\n", @lex.comments[0].text
+//   end
+//
+//   def test_comment_end_space_and_text
+//     assert_scanned("=begin blah\nblah\n=end blab\n")
+//     assert_equal "=begin blah\nblah\n=end blab\n", @lex.comments[0].text
+//   end
+#[test]
+fn comment_begin_space() {
+    let mut lexer = Lexer::new(String::from("=begin blah\nblah\n=end\n"));
+    assert_eq!(lexer.advance(), None);
+    assert_eq!(lexer.comments[0].text, "=begin blah\nblah\n=end\n");
+
+    let mut lexer = Lexer::new(String::from("=begin blah\nblah\n=end blab\n"));
+    assert_eq!(lexer.advance(), None);
+    assert_eq!(lexer.comments[0].text, "=begin blah\nblah\n=end blab\n");
+}
+
+//   def test_comment_eos
+//     assert_scanned("# comment")
+//   end
+#[test]
+fn comment_eos() {
+    let mut lexer = Lexer::new(String::from("# comment"));
+    assert_eq!(lexer.advance(), None);
+    assert_eq!(lexer.comments[0].text, "# comment");
+    assert_eq!(lexer.comments[0].range, Range::new(0, 9));
+}
+
+// an embedded document without =end
+#[test]
+fn comment_begin_eof() {
+    let mut lexer = Lexer::new(String::from("=begin\nfoo"));
+    while let Some(_) = lexer.advance() {}
+
+    let diagnostic = lexer.fatal_diagnostic().unwrap();
+    assert_eq!(diagnostic.reason, "embedded_document");
+    assert_eq!(diagnostic.range, Range::new(0, 6));
+    assert!(lexer.comments.is_empty());
+}
+
+// positions are byte offsets, multibyte chars shift neither the following tokens nor their ranges
+#[test]
+fn identifier_utf8() {
+    let content = "café = 1\nÜber";
+    let mut lexer = Lexer::new(String::from(content));
+
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("café")), [0, 5]);
+    assert_next_token!(lexer, Token::T_EQL, [6, 7]);
+    assert_next_token!(lexer, Token::T_INTEGER(1), [8, 9]);
+    assert_next_token!(lexer, Token::T_NL, [9, 10]);
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("Über")), [10, 15]);
+
+    assert_eq!(&content[0..5], "café");
+    assert_eq!(&content[10..15], "Über");
+}
+
+#[test]
+fn string_utf8() {
+    let mut lexer = Lexer::new(String::from("\"日本語 #{ほげ}\""));
+
+    assert_next_token!(lexer, Token::T_STRING_BEG, [0, 1]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("日本語 ")), [1, 11]);
+    assert_next_token!(lexer, Token::T_STRING_DBEG, [11, 13]);
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("ほげ")), [13, 19]);
+    assert_next_token!(lexer, Token::T_STRING_DEND, [19, 20]);
+    assert_next_token!(lexer, Token::T_STRING_END, [20, 21]);
+}
+
+#[test]
+fn symbol_and_comment_utf8() {
+    let mut lexer = Lexer::new(String::from(":🍣 # 🍺\n?é"));
+
+    assert_next_token!(lexer, Token::T_SYMBOL(TokenString::from("🍣")), [0, 5]);
+    assert_next_token!(lexer, Token::T_NL, [12, 13]);
+    assert_next_token!(lexer, Token::T_CHARACTER(TokenString::from("é")), [13, 16]);
+
+    assert_eq!(lexer.comments[0].text, "# 🍺");
+    assert_eq!(lexer.comments[0].range, Range::new(6, 12));
+}
+
+#[test]
+fn symbol_ambiguous_suffix() {
+    let mut lexer = Lexer::new(String::from("{:a=>1}"));
+    lexer.advance();
+    assert_next_token!(lexer, Token::T_SYMBOL(TokenString::from("a")), [1, 3]);
+    assert_next_token!(lexer, Token::T_ASSOC, [3, 5]);
+
+    let mut lexer = Lexer::new(String::from(":a==b"));
+    assert_next_token!(lexer, Token::T_SYMBOL(TokenString::from("a")), [0, 2]);
+    assert_next_token!(lexer, Token::T_EQ, [2, 4]);
+
+    let mut lexer = Lexer::new(String::from(":a==>b"));
+    assert_next_token!(lexer, Token::T_SYMBOL(TokenString::from("a=")), [0, 3]);
+    assert_next_token!(lexer, Token::T_ASSOC, [3, 5]);
+
+    let mut lexer = Lexer::new(String::from(":a===b"));
+    assert_next_token!(lexer, Token::T_SYMBOL(TokenString::from("a")), [0, 2]);
+    assert_next_token!(lexer, Token::T_EQQ, [2, 5]);
+
+    let mut lexer = Lexer::new(String::from(":a?"));
+    assert_next_token!(lexer, Token::T_SYMBOL(TokenString::from("a?")), [0, 3]);
+}
+
+#[test]
+fn reader_chunk_boundaries() {
+    let content = "foo = <<-EOS\n  héllo #{1}\n  world\n  EOS\n\"bar #{baz} qux\"\n# cömment\n=begin\nstuff\n=end\n[1, 2.5, :sym, ?a]\n";
+    let expected_tokens = lex_all(&mut Lexer::new(String::from(content)));
+
+    for chunk_len in 1..5 {
+        let mut lexer = Lexer::from_reader(ChunkedReader::new(content.as_bytes(), chunk_len));
+
+        assert_eq!(lex_all(&mut lexer), expected_tokens);
+        assert!(lexer.diagnostics.is_empty());
+        assert_eq!(lexer.comments.len(), 2);
+        assert_eq!(lexer.comments[1].text, "=begin\nstuff\n=end\n");
+    }
+}
+
+// only the part of source lexer may still look at is held
+#[test]
+fn reader_bounded_memory() {
+    let mut content = String::new();
+    for i in 0..5000 {
+        content.push_str(&format!("foo{} = [{}, :bar] # ç\n\"qux #{{baz}}\"\n", i, i));
+    }
+    let expected_tokens = lex_all(&mut Lexer::new(content.clone()));
+
+    let mut lexer = Lexer::from_reader(ChunkedReader::new(content.as_bytes(), 1000));
+    let mut tokens = vec![];
+    while let Some(token) = lexer.advance() {
+        tokens.push(token);
+        assert!(lexer.buffered_len() < 32 * 1024);
+    }
+
+    assert!(content.len() > 32 * 1024 * 4);
+    assert_eq!(tokens, expected_tokens);
+}
+
+#[test]
+fn reader_invalid_utf8() {
+    let mut lexer = Lexer::from_reader(ChunkedReader::new(b"foo\nb\xffar", 2));
+
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("foo")), [0, 3]);
+    assert_next_token!(lexer, Token::T_NL, [3, 4]);
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("b")), [4, 5]);
+    assert_eq!(lexer.advance(), None);
+
+    let diagnostic = lexer.fatal_diagnostic().unwrap();
+    assert_eq!(diagnostic.reason, "invalid_byte_sequence");
+    assert_eq!(diagnostic.range, Range::new(5, 6));
+}
+
+#[test]
+fn iterator() {
+    let lexer = Lexer::new(String::from("foo = 1"));
+
+    assert_eq!(lexer.map(|(token, _)| token).collect::<Vec<_>>(), vec![
+        Token::T_IDENTIFIER(TokenString::from("foo")),
+        Token::T_EQL,
+        Token::T_INTEGER(1),
+    ]);
+}
+
+#[test]
+fn tokenize_source() {
+    let (tokens, diagnostics) = tokenize(String::from("foo \u{1} bar"));
+    assert_eq!(tokens, vec![(Token::T_IDENTIFIER(TokenString::from("foo")), Range::new(0, 3))]);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].level, Level::Fatal);
+    assert_eq!(diagnostics[0].reason, "unexpected");
+}
+
+#[test]
+fn error_tolerant() {
+    let mut lexer = Lexer::new(String::from("a = 1 ` 2\nfoo)\u{7f}"));
+    lexer.set_error_tolerant(true);
+
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("a")), [0, 1]);
+    assert_next_token!(lexer, Token::T_EQL, [2, 3]);
+    assert_next_token!(lexer, Token::T_INTEGER(1), [4, 5]);
+    assert_next_token!(lexer, Token::T_ERROR(TokenString::from("`")), [6, 7]);
+    assert_next_token!(lexer, Token::T_INTEGER(2), [8, 9]);
+    assert_next_token!(lexer, Token::T_NL, [9, 10]);
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("foo")), [10, 13]);
+    assert_next_token!(lexer, Token::T_RPAREN, [13, 14]);
+    assert_next_token!(lexer, Token::T_ERROR(TokenString::from("\u{7f}")), [14, 15]);
+    assert_eq!(lexer.advance(), None);
+
+    assert!(lexer.fatal_diagnostic().is_none());
+    assert_eq!(lexer.diagnostics.len(), 2);
+    assert_eq!(lexer.diagnostics[0].level, Level::Error);
+    assert_eq!(lexer.diagnostics[0].reason, "unexpected");
+    assert_eq!(lexer.diagnostics[0].range, Range::new(6, 7));
+}
+
+// the same source lexed again under another state
+#[test]
+fn rewind_to_checkpoint() {
+    let mut lexer = Lexer::new(String::from("foo /2/"));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("foo")), [0, 3]);
+
+    let checkpoint = lexer.checkpoint();
+    assert_next_token!(lexer, Token::T_REGEXP_BEG, [4, 5]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("2")), [5, 6]);
+
+    lexer.rewind(&checkpoint);
+    lexer.set_state(LexingState::ExprEnd);
+    assert_next_token!(lexer, Token::T_DIVIDE, [4, 5]);
+    assert_next_token!(lexer, Token::T_INTEGER(2), [5, 6]);
+    assert_next_token!(lexer, Token::T_DIVIDE, [6, 7]);
+}
+
+// literals, comments and diagnostics lexed after the checkpoint are forgotten
+#[test]
+fn rewind_literals_and_comments() {
+    let mut lexer = Lexer::new(String::from("\"foo #{bar}\" # baz\n0_"));
+    let checkpoint = lexer.checkpoint();
+    let tokens = lex_all(&mut lexer);
+    assert_eq!(lexer.comments.len(), 1);
+    assert_eq!(lexer.diagnostics.len(), 1);
+
+    for p in 0..3 {
+        lexer.rewind(&checkpoint);
+        for _ in 0..p { lexer.advance(); }
+
+        let checkpoint = lexer.checkpoint();
+        lexer.advance();
+        lexer.rewind(&checkpoint);
+
+        assert_eq!(lex_all(&mut lexer), tokens[p..].to_vec());
+        assert_eq!(lexer.comments.len(), 1);
+        assert_eq!(lexer.diagnostics.len(), 1);
+    }
+}
+
+// what the parser does for `kWHILE { @lexer.cond.push(true) } expr_value do { @lexer.cond.pop }`
+#[test]
+fn do_cond() {
+    let mut lexer = Lexer::new(String::from("while x do 1 end"));
+    assert_next_token!(lexer, Token::K_WHILE, [0, 5]);
+    lexer.cond.push(true);
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("x")), [6, 7]);
+    assert_next_token!(lexer, Token::K_DO_COND, [8, 10]);
+    lexer.cond.pop();
+
+    let (tokens, _) = tokenize(String::from("while x do 1 end"));
+    assert_eq!(tokens[2], (Token::K_DO, Range::new(8, 10)));
+}
+
+// what the parser does for command_args, and push_cmdarg/pop_cmdarg around a lambda body
+#[test]
+fn do_block() {
+    let mut lexer = Lexer::new(String::from("foo bar do end"));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("foo")), [0, 3]);
+    let checkpoint = lexer.checkpoint();
+
+    lexer.cmdarg.push(true);
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("bar")), [4, 7]);
+    assert_next_token!(lexer, Token::K_DO_BLOCK, [8, 10]);
+
+    lexer.rewind(&checkpoint);
+    lexer.cmdarg.push(true);
+    lexer.push_cmdarg();
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("bar")), [4, 7]);
+    assert_next_token!(lexer, Token::K_DO, [8, 10]);
+
+    lexer.rewind(&checkpoint);
+    lexer.cmdarg.push(true);
+    lexer.push_cmdarg();
+    lexer.pop_cmdarg();
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("bar")), [4, 7]);
+    assert_next_token!(lexer, Token::K_DO_BLOCK, [8, 10]);
+}
+
+//   def test_static_env
+//     env = Parser::StaticEnvironment.new
+//     env.declare "a"
+//
+//     @lex.static_env = env
+//     assert_scanned("a [42]",
+//                    :tIDENTIFIER, "a",  [0, 1],
+//                    :tLBRACK2,    "[",  [2, 3],
+//                    :tINTEGER,    42,   [3, 5],
+//                    :tRBRACK,     "]",  [5, 6])
+//   end
+#[test]
+fn static_env() {
+    let mut env = StaticEnv::new();
+    env.declare("a");
+
+    let mut lexer = Lexer::new(String::from("a [42]"));
+    lexer.set_static_env(Some(env));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("a")), [0, 1]);
+    assert_next_token!(lexer, Token::T_LBRACK2, [2, 3]);
+    assert_next_token!(lexer, Token::T_INTEGER(42), [3, 5]);
+    assert_next_token!(lexer, Token::T_RBRACK, [5, 6]);
+}
+
+// a local variable is followed by a binary operator, a method by an argument, as in MRI
+#[test]
+fn static_env_local_or_method() {
+    let lex_with_foo = |source: &str, is_declared: bool| {
+        let mut lexer = Lexer::new(String::from(source));
+        let mut env = StaticEnv::new();
+        if is_declared { env.declare("foo"); }
+        lexer.set_static_env(Some(env));
+        lex_all(&mut lexer).into_iter().map(|(token, _)| token).collect::<Vec<_>>()
+    };
+
+    assert_eq!(lex_with_foo("foo -1", true)[1], Token::T_MINUS);
+    assert_eq!(lex_with_foo("foo -1", false)[1], Token::T_UNARY_NUM(TokenString::from("-")));
+
+    assert_eq!(lex_with_foo("foo [1]", true)[1], Token::T_LBRACK2);
+    assert_eq!(lex_with_foo("foo [1]", false)[1], Token::T_LBRACK);
+
+    assert_eq!(lex_with_foo("foo /x/", true)[1], Token::T_DIVIDE);
+    assert_eq!(lex_with_foo("foo /x/", false)[1], Token::T_REGEXP_BEG);
+}
+
+// declared after the checkpoint, undeclared by rewinding
+#[test]
+fn static_env_rewind() {
+    let mut lexer = Lexer::new(String::from("foo /2/"));
+    lexer.set_static_env(Some(StaticEnv::new()));
+    let checkpoint = lexer.checkpoint();
+
+    lexer.static_env_mut().unwrap().declare("foo");
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("foo")), [0, 3]);
+    assert_next_token!(lexer, Token::T_DIVIDE, [4, 5]);
+
+    lexer.rewind(&checkpoint);
+    assert!(!lexer.static_env().unwrap().has_declared(String::from("foo")));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("foo")), [0, 3]);
+    assert_next_token!(lexer, Token::T_REGEXP_BEG, [4, 5]);
+}
+
+// scopes entered and left after the checkpoint are undone by rewinding as well
+#[test]
+fn static_env_rewind_scopes() {
+    let mut env = StaticEnv::new();
+    env.declare("foo");
+    env.extend_dynamic();
+    env.declare("bar");
+
+    let mut lexer = Lexer::new(String::from("foo /2/"));
+    lexer.set_static_env(Some(env));
+    let checkpoint = lexer.checkpoint();
+
+    {
+        let env = lexer.static_env_mut().unwrap();
+        env.unextend();
+        env.extend_static();
+        env.declare("baz");
+    }
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("foo")), [0, 3]);
+    assert_next_token!(lexer, Token::T_REGEXP_BEG, [4, 5]);
+
+    lexer.rewind(&checkpoint);
+    {
+        let env = lexer.static_env().unwrap();
+        assert_eq!(env.depth(), 1);
+        assert!(env.has_declared(String::from("foo")));
+        assert!(env.has_declared(String::from("bar")));
+        assert!(!env.has_declared(String::from("baz")));
+    }
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("foo")), [0, 3]);
+    assert_next_token!(lexer, Token::T_DIVIDE, [4, 5]);
+
+    lexer.static_env_mut().unwrap().unextend();
+    assert!(!lexer.static_env().unwrap().has_declared(String::from("bar")));
+}
+
+// still lexed, along with an error
+#[test]
+fn unavailable_in_version() {
+    let mut lexer = Lexer::new(String::from("foo&.bar"));
+    lexer.set_version(RubyVersion::Ruby22);
+    assert_eq!(lex_all(&mut lexer)[1], (Token::T_ANDDOT, Range::new(3, 5)));
+    assert_eq!(lexer.diagnostics.len(), 1);
+    assert_eq!(lexer.diagnostics[0].level, Level::Error);
+    assert_eq!(lexer.diagnostics[0].message(), "safe navigation operator is not available in Ruby 2.2");
+    assert_eq!(lexer.diagnostics[0].range, Range::new(3, 5));
+
+    let mut lexer = Lexer::new(String::from("foo&.bar"));
+    lexer.set_version(RubyVersion::Ruby23);
+    lex_all(&mut lexer);
+    assert!(lexer.diagnostics.is_empty());
+}
+
+// nothing is lexed after it, lexer keeps where the data starts
+#[test]
+fn __end__() {
+    let mut lexer = Lexer::new(String::from("__END__\n"));
+    assert_eq!(lexer.advance(), None);
+    assert_eq!(lexer.data_s(), Some(8));
+
+    let mut lexer = Lexer::new(String::from("__END__"));
+    assert_eq!(lexer.advance(), None);
+    assert_eq!(lexer.data_s(), Some(7));
+
+    let mut lexer = Lexer::new(String::from("__END__foo"));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("__END__foo")), [0, 10]);
+    assert_eq!(lexer.data_s(), None);
+
+    let mut lexer = Lexer::new(String::from("nil\n__END__\nfoo bar\n"));
+    assert_next_token!(lexer, Token::K_NIL, [0, 3]);
+    assert_next_token!(lexer, Token::T_NL, [3, 4]);
+    assert_eq!(lexer.advance(), None);
+    assert_eq!(lexer.advance(), None);
+    assert_eq!(lexer.data_s(), Some(12));
+}
+
+// only at the very beginning of a line, outside of heredocs and embedded documents
+#[test]
+fn __end___ignored() {
+    let mut lexer = Lexer::new(String::from(" __END__\n"));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("__END__")), [1, 8]);
+    assert_eq!(lexer.data_s(), None);
+
+    let mut lexer = Lexer::new(String::from("<<E\n__END__\nE\n"));
+    assert_next_token!(lexer, Token::T_STRING_BEG, [0, 3]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("__END__\n")), [4, 12]);
+    assert_next_token!(lexer, Token::T_STRING_END, [12, 13]);
+    assert_next_token!(lexer, Token::T_NL, [3, 4]);
+    assert_eq!(lexer.advance(), None);
+    assert_eq!(lexer.data_s(), None);
+
+    let mut lexer = Lexer::new(String::from("=begin\n__END__\n=end\nnil"));
+    assert_next_token!(lexer, Token::K_NIL, [20, 23]);
+    assert_eq!(lexer.data_s(), None);
+}
+
+#[test]
+fn __end___crlf() {
+    // a source buffer turns `\r\n` into `\n`, a stream doesn't
+    let mut lexer = Lexer::from_reader(ChunkedReader::new(b"nil\n__END__\r\ndata", 5));
+    assert_next_token!(lexer, Token::K_NIL, [0, 3]);
+    assert_next_token!(lexer, Token::T_NL, [3, 4]);
+    assert_eq!(lexer.advance(), None);
+    assert_eq!(lexer.data_s(), Some(13));
+
+    let mut lexer = Lexer::new(String::from("__END__\r\ndata"));
+    assert_eq!(lexer.advance(), None);
+    assert_eq!(lexer.data_s(), Some(8));
+}
+
+// operator method names, kept whole however they start
+#[test]
+fn def_fname_operators() {
+    let mut lexer = Lexer::new(String::from("def []=(k, v)"));
+    assert_next_token!(lexer, Token::K_DEF, [0, 3]);
+    assert_next_token!(lexer, Token::T_ASET, [4, 7]);
+    assert_next_token!(lexer, Token::T_LPAREN2, [7, 8]);
+
+    let mut lexer = Lexer::new(String::from("def <=>(o)"));
+    assert_next_token!(lexer, Token::K_DEF, [0, 3]);
+    assert_next_token!(lexer, Token::T_CMP, [4, 7]);
+    assert_next_token!(lexer, Token::T_LPAREN2, [7, 8]);
+
+    let mut lexer = Lexer::new(String::from("def self.**(o)"));
+    assert_next_token!(lexer, Token::K_DEF, [0, 3]);
+    assert_next_token!(lexer, Token::K_SELF, [4, 8]);
+    assert_next_token!(lexer, Token::T_DOT, [8, 9]);
+    assert_next_token!(lexer, Token::T_POW, [9, 11]);
+}
+
+#[test]
+fn longest_operator() {
+    let mut lexer = Lexer::new(String::from("def f(*, **)"));
+    assert_next_token!(lexer, Token::K_DEF, [0, 3]);
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("f")), [4, 5]);
+    assert_next_token!(lexer, Token::T_LPAREN2, [5, 6]);
+    assert_next_token!(lexer, Token::T_STAR, [6, 7]);
+    assert_next_token!(lexer, Token::T_COMMA, [7, 8]);
+    assert_next_token!(lexer, Token::T_DSTAR, [9, 11]);
+
+    let mut lexer = Lexer::new(String::from("1...2"));
+    assert_next_token!(lexer, Token::T_INTEGER(1), [0, 1]);
+    assert_next_token!(lexer, Token::T_DOT3, [1, 4]);
+    assert_next_token!(lexer, Token::T_INTEGER(2), [4, 5]);
+}
+
+#[test]
+fn defined() {
+    let mut lexer = Lexer::new(String::from("defined? a"));
+    assert_next_token!(lexer, Token::K_DEFINED, [0, 8]);
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("a")), [9, 10]);
+}
+
+#[test]
+fn constant() {
+    let mut lexer = Lexer::new(String::from("Foo.bar"));
+    assert_next_token!(lexer, Token::T_CONSTANT(TokenString::from("Foo")), [0, 3]);
+    assert_next_token!(lexer, Token::T_DOT, [3, 4]);
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("bar")), [4, 7]);
+
+    let mut lexer = Lexer::new(String::from("(\"foo\")"));
+    assert_next_token!(lexer, Token::T_LPAREN, [0, 1]);
+    assert_next_token!(lexer, Token::T_STRING(TokenString::from("foo")), [1, 6]);
+    assert_next_token!(lexer, Token::T_RPAREN, [6, 7]);
+}
+
+// the closing line ends the source, nothing of it is lexed again
+#[test]
+fn heredoc_closing_at_eof() {
+    let mut lexer = Lexer::new(String::from("<<E\nfoo\nE"));
+    assert_next_token!(lexer, Token::T_STRING_BEG, [0, 3]);
+    assert_next_token!(lexer, Token::T_STRING_CONTENT(TokenString::from("foo\n")), [4, 8]);
+    assert_next_token!(lexer, Token::T_STRING_END, [8, 9]);
+    assert_next_token!(lexer, Token::T_NL, [3, 4]);
+    assert_eq!(lexer.advance(), None);
+}
+
+#[test]
+fn fid() {
+    let mut lexer = Lexer::new(String::from("foo.bar!"));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("foo")), [0, 3]);
+    assert_next_token!(lexer, Token::T_DOT, [3, 4]);
+    assert_next_token!(lexer, Token::T_FID(TokenString::from("bar!")), [4, 8]);
+
+    // NOTE whitequark keeps tFID for the name without its suffix here
+    let mut lexer = Lexer::new(String::from("foo.bar!=1"));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("foo")), [0, 3]);
+    assert_next_token!(lexer, Token::T_DOT, [3, 4]);
+    assert_next_token!(lexer, Token::T_FID(TokenString::from("bar")), [4, 7]);
+    assert_next_token!(lexer, Token::T_NEQ, [7, 9]);
+
+    let mut lexer = Lexer::new(String::from("foo!=1"));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("foo")), [0, 3]);
+    assert_next_token!(lexer, Token::T_NEQ, [3, 5]);
+
+    let mut lexer = Lexer::new(String::from("fun!(1)"));
+    assert_next_token!(lexer, Token::T_FID(TokenString::from("fun!")), [0, 4]);
+    assert_next_token!(lexer, Token::T_LPAREN2, [4, 5]);
+}
+
+// `(` right after a method name opens its args, with a space before it opens a parenthesized arg
+#[test]
+fn call_lparen() {
+    let mut lexer = Lexer::new(String::from("foo.bar(1)"));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("foo")), [0, 3]);
+    assert_next_token!(lexer, Token::T_DOT, [3, 4]);
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("bar")), [4, 7]);
+    assert_next_token!(lexer, Token::T_LPAREN2, [7, 8]);
+
+    let mut lexer = Lexer::new(String::from("fun (1)"));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("fun")), [0, 3]);
+    assert_next_token!(lexer, Token::T_LPAREN_ARG, [4, 5]);
+}
+
+// `{` after a method name without parentheses opens its block
+#[test]
+fn cmd_lcurly() {
+    let mut lexer = Lexer::new(String::from("fun {"));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("fun")), [0, 3]);
+    assert_next_token!(lexer, Token::T_LCURLY, [4, 5]);
+
+    let mut lexer = Lexer::new(String::from("-> a {"));
+    assert_next_token!(lexer, Token::T_LAMBDA, [0, 2]);
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("a")), [3, 4]);
+    assert_next_token!(lexer, Token::T_LAMBEG, [5, 6]);
+}
+
+// an arg starting with `do` isn't kDO
+#[test]
+fn cmd_do_prefix() {
+    let mut lexer = Lexer::new(String::from("foo double"));
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("foo")), [0, 3]);
+    assert_next_token!(lexer, Token::T_IDENTIFIER(TokenString::from("double")), [4, 10]);
 }
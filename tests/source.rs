@@ -0,0 +1,74 @@
+// based on
+// https://github.com/whitequark/parser/blob/2a73841d6da04a5ab9bd270561165fd766722d43/test/test_source_buffer.rb
+
+extern crate ruby_on_rust;
+
+use ruby_on_rust::source::buffer::SourceBuffer;
+use ruby_on_rust::source::range::Range;
+
+//   def test_decompose_position
+//     @buffer.source = "1\nfoo\nbar"
+// 
+//     assert_equal [1, 0], @buffer.decompose_position(0)
+//     assert_equal [1, 1], @buffer.decompose_position(1)
+//     assert_equal [2, 0], @buffer.decompose_position(2)
+//     assert_equal [3, 1], @buffer.decompose_position(7)
+//     assert_equal [3, 3], @buffer.decompose_position(9)
+//   end
+// 
+// NOTE columns are 1-based here
+#[test]
+fn decompose_position() {
+    let buffer = SourceBuffer::new("(string)", String::from("1\nfoo\nbar"));
+
+    assert_eq!(buffer.decompose_position(0), (1, 1));
+    assert_eq!(buffer.decompose_position(1), (1, 2));
+    assert_eq!(buffer.decompose_position(2), (2, 1));
+    assert_eq!(buffer.decompose_position(7), (3, 2));
+    assert_eq!(buffer.decompose_position(9), (3, 4));
+}
+
+//   def test_source_line
+//     @buffer.source = "1\nfoo\nbar"
+// 
+//     assert_equal '1', @buffer.source_line(1)
+//     assert_equal 'foo', @buffer.source_line(2)
+//   end
+#[test]
+fn source_line() {
+    let buffer = SourceBuffer::new("(string)", String::from("1\nfoo\nbar"));
+
+    assert_eq!(buffer.source_line(1), "1");
+    assert_eq!(buffer.source_line(2), "foo");
+    assert_eq!(buffer.source_line(3), "bar");
+    assert_eq!(buffer.line_range(2), Range::new(2, 5));
+}
+
+//   def test_source_lines
+//     @buffer.source = "1\nfoo\nbar\n"
+// 
+//     assert_equal ['1', 'foo', 'bar', ''], @buffer.source_lines
+//   end
+#[test]
+fn source_lines() {
+    let buffer = SourceBuffer::new("(string)", String::from("1\nfoo\nbar\n"));
+
+    assert_eq!(buffer.source_lines(), vec!["1", "foo", "bar", ""]);
+}
+
+//   def test_source_setter_crlf
+//     @buffer.source = "foo\r\nbar"
+//     assert_equal "foo\nbar", @buffer.source
+//   end
+#[test]
+fn source_crlf() {
+    let buffer = SourceBuffer::new("(string)", String::from("foo\r\nbar"));
+
+    assert_eq!(buffer.source(), "foo\nbar");
+    assert_eq!(buffer.decompose_position(4), (2, 1));
+    assert_eq!(buffer.source_line(1), "foo");
+
+    assert_eq!(buffer.original_source(), "foo\r\nbar");
+    assert_eq!(buffer.original_position(3), 3);
+    assert_eq!(buffer.original_position(4), 5);
+}
@@ -0,0 +1,92 @@
+// based on
+// https://github.com/whitequark/parser/blob/2a73841d6da04a5ab9bd270561165fd766722d43/test/test_static_environment.rb
+
+extern crate ruby_on_rust;
+
+use ruby_on_rust::shared::static_env::StaticEnv;
+
+//   def test_declare
+//     refute @env.declared?(:foo)
+//
+//     @env.declare :foo
+//     assert @env.declared?(:foo)
+//   end
+#[test]
+fn declare() {
+    let mut env = StaticEnv::new();
+    assert!(!env.has_declared(String::from("foo")));
+
+    env.declare("foo");
+    assert!(env.has_declared(String::from("foo")));
+}
+
+//   def test_extend_static
+//     @env.declare :foo
+//     @env.extend_static
+//     @env.declare :bar
+//
+//     refute @env.declared?(:foo)
+//     assert @env.declared?(:bar)
+//   end
+#[test]
+fn extend_static() {
+    let mut env = StaticEnv::new();
+    env.declare("foo");
+    env.extend_static();
+    env.declare("bar");
+
+    assert!(!env.has_declared(String::from("foo")));
+    assert!(env.has_declared(String::from("bar")));
+}
+
+//   def test_extend_dynamic
+//     @env.declare :foo
+//     @env.extend_dynamic
+//     @env.declare :bar
+//
+//     assert @env.declared?(:foo)
+//     assert @env.declared?(:bar)
+//   end
+#[test]
+fn extend_dynamic() {
+    let mut env = StaticEnv::new();
+    env.declare("foo");
+    env.extend_dynamic();
+    env.declare("bar");
+
+    assert!(env.has_declared(String::from("foo")));
+    assert!(env.has_declared(String::from("bar")));
+}
+
+//   def test_unextend
+//     @env.declare :foo
+//     @env.extend_dynamic
+//     @env.declare :bar
+//     @env.unextend
+//
+//     assert @env.declared?(:foo)
+//     refute @env.declared?(:bar)
+//   end
+#[test]
+fn unextend() {
+    let mut env = StaticEnv::new();
+    env.declare("foo");
+    env.extend_dynamic();
+    env.declare("bar");
+    env.unextend();
+
+    assert!(env.has_declared(String::from("foo")));
+    assert!(!env.has_declared(String::from("bar")));
+}
+
+// a clone taken before, like the lexer's checkpoint, keeps what was declared then
+#[test]
+fn clone_unaffected() {
+    let mut env = StaticEnv::new();
+    env.declare("foo");
+    let cloned = env.clone();
+    env.declare("bar");
+
+    assert!(cloned.has_declared(String::from("foo")));
+    assert!(!cloned.has_declared(String::from("bar")));
+}
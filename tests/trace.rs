@@ -0,0 +1,66 @@
+extern crate ruby_on_rust;
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use ruby_on_rust::lexer::Lexer;
+use ruby_on_rust::parser::parser::Parser;
+use ruby_on_rust::parser::token::Token;
+use ruby_on_rust::source::range::Range;
+use ruby_on_rust::trace::TraceEvent;
+
+// helpers
+
+// a tracer collecting every event
+fn collecting_tracer() -> (Rc<RefCell<Vec<TraceEvent>>>, Rc<Fn(&TraceEvent)>) {
+    let events = Rc::new(RefCell::new(vec![]));
+    let events_in_tracer = events.clone();
+    (events, Rc::new(move |event: &TraceEvent| events_in_tracer.borrow_mut().push(event.clone())))
+}
+
+#[test]
+fn lexer_events() {
+    let (events, tracer) = collecting_tracer();
+
+    let mut lexer = Lexer::new(String::from("nil"));
+    lexer.set_tracer(tracer);
+    assert_eq!(lexer.advance(), Some((Token::K_NIL, Range::new(0, 3))));
+
+    let events = events.borrow();
+
+    assert!(events.iter().any(|event| match *event {
+        TraceEvent::ActionMatched { ref slice, range, .. } => slice == "nil" && range == Range::new(0, 3),
+        _ => false
+    }));
+    assert!(events.iter().any(|event| match *event {
+        TraceEvent::StateTransition { .. } => true,
+        _ => false
+    }));
+    assert_eq!(
+        events.iter().filter(|event| match **event { TraceEvent::TokenEmitted { .. } => true, _ => false }).collect::<Vec<_>>(),
+        vec![&TraceEvent::TokenEmitted { token: Token::K_NIL, range: Range::new(0, 3) }]
+    );
+}
+
+#[test]
+fn parser_events() {
+    let (events, tracer) = collecting_tracer();
+
+    let mut parser = Parser::new(String::from("nil"));
+    parser.set_tracer(tracer);
+    parser.parse().unwrap();
+
+    let events = events.borrow();
+
+    // every rule entered exits once
+    let enters = events.iter().filter(|event| match **event { TraceEvent::RuleEnter { .. } => true, _ => false }).count();
+    let exits = events.iter().filter(|event| match **event { TraceEvent::RuleExit { .. } => true, _ => false }).count();
+    assert!(enters > 0);
+    assert_eq!(enters, exits);
+
+    assert_eq!(events.first(), Some(&TraceEvent::RuleEnter { rule: String::from("p_program"), p: 0 }));
+    assert_eq!(
+        events.iter().rev().find(|event| match **event { TraceEvent::RuleExit { .. } => true, _ => false }),
+        Some(&TraceEvent::RuleExit { rule: String::from("p_program"), p: 1, matched: true })
+    );
+}